@@ -0,0 +1,5 @@
+-- Add migration script here
+ALTER TABLE sessions
+    ADD COLUMN user_agent TEXT,
+    ADD COLUMN ip_address VARCHAR(45),
+    ADD COLUMN last_seen_at TIMESTAMP NOT NULL DEFAULT NOW();
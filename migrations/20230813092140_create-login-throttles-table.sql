@@ -0,0 +1,21 @@
+-- Add migration script here
+-- Failed logins per account (by email) and per IP address
+CREATE TABLE login_throttles (
+    scope VARCHAR(16) NOT NULL,
+    identifier VARCHAR(255) NOT NULL,
+    failed_attempts INT NOT NULL,
+    last_failed_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    locked_until TIMESTAMP,
+    PRIMARY KEY (scope, identifier)
+);
+
+CREATE TABLE lockout_events (
+    id UUID PRIMARY KEY,
+    scope VARCHAR(16) NOT NULL,
+    identifier VARCHAR(255) NOT NULL,
+    failed_attempts INT NOT NULL,
+    locked_until TIMESTAMP NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX lockout_events_created_at_idx ON lockout_events (created_at);
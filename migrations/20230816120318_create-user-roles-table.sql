@@ -0,0 +1,10 @@
+-- Add migration script here
+-- Users without any row here are regular users. The first admin has to be granted by hand:
+-- INSERT INTO user_roles (user_id, role) VALUES ('<user id>', 'admin');
+CREATE TABLE user_roles (
+    user_id UUID NOT NULL,
+    role VARCHAR(32) NOT NULL CHECK (role IN ('moderator', 'admin')),
+    granted_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, role),
+    FOREIGN KEY (user_id) REFERENCES users (id)
+);
@@ -0,0 +1,5 @@
+-- Add migration script here
+ALTER TABLE users ADD COLUMN updated_at TIMESTAMP NOT NULL DEFAULT NOW();
+
+-- Emails are checked before they are saved, this catches the requests that race each other
+CREATE UNIQUE INDEX users_email_key ON users (email);
@@ -0,0 +1,14 @@
+-- Add migration script here
+CREATE TABLE username_history (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL,
+    username VARCHAR(255) NOT NULL,
+    changed_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    -- Nobody else can take the old username before this
+    reserved_until TIMESTAMP NOT NULL,
+    FOREIGN KEY (user_id) REFERENCES users (id)
+);
+
+CREATE INDEX username_history_username_idx ON username_history (username, changed_at);
+
+CREATE UNIQUE INDEX users_username_key ON users (username);
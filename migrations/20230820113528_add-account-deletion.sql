@@ -0,0 +1,62 @@
+-- Add migration script here
+-- Deactivated accounts can be restored until purge_after, then they are purged for good
+ALTER TABLE users
+    ADD COLUMN deactivated_at TIMESTAMP,
+    ADD COLUMN purge_after TIMESTAMP;
+
+CREATE INDEX users_purge_after_idx ON users (purge_after) WHERE purge_after IS NOT NULL;
+
+-- A purged user's posts, likes and follows go with them
+ALTER TABLE posts
+    DROP CONSTRAINT fk_author,
+    ADD CONSTRAINT fk_author FOREIGN KEY (author) REFERENCES users (id) ON DELETE CASCADE;
+ALTER TABLE likes
+    DROP CONSTRAINT likes_user_id_fkey,
+    ADD CONSTRAINT likes_user_id_fkey FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE,
+    DROP CONSTRAINT likes_post_id_fkey,
+    ADD CONSTRAINT likes_post_id_fkey FOREIGN KEY (post_id) REFERENCES posts (id) ON DELETE CASCADE;
+ALTER TABLE users_followers
+    DROP CONSTRAINT users_followers_user_id_fkey,
+    ADD CONSTRAINT users_followers_user_id_fkey FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE,
+    DROP CONSTRAINT users_followers_follower_id_fkey,
+    ADD CONSTRAINT users_followers_follower_id_fkey FOREIGN KEY (follower_id) REFERENCES users (id) ON DELETE CASCADE;
+
+-- Comments that others replied to stay in their threads without an author
+ALTER TABLE comments
+    ALTER COLUMN user_id DROP NOT NULL,
+    DROP CONSTRAINT comments_user_id_fkey,
+    ADD CONSTRAINT comments_user_id_fkey FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE SET NULL,
+    DROP CONSTRAINT comments_post_id_fkey,
+    ADD CONSTRAINT comments_post_id_fkey FOREIGN KEY (post_id) REFERENCES posts (id) ON DELETE CASCADE;
+
+-- Account data
+ALTER TABLE sessions
+    DROP CONSTRAINT sessions_user_id_fkey,
+    ADD CONSTRAINT sessions_user_id_fkey FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE;
+ALTER TABLE refresh_tokens
+    DROP CONSTRAINT refresh_tokens_session_id_fkey,
+    ADD CONSTRAINT refresh_tokens_session_id_fkey FOREIGN KEY (session_id) REFERENCES sessions (id) ON DELETE CASCADE;
+ALTER TABLE password_reset_tokens
+    DROP CONSTRAINT password_reset_tokens_user_id_fkey,
+    ADD CONSTRAINT password_reset_tokens_user_id_fkey FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE;
+ALTER TABLE email_verification_tokens
+    DROP CONSTRAINT email_verification_tokens_user_id_fkey,
+    ADD CONSTRAINT email_verification_tokens_user_id_fkey FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE;
+ALTER TABLE user_totp
+    DROP CONSTRAINT user_totp_user_id_fkey,
+    ADD CONSTRAINT user_totp_user_id_fkey FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE;
+ALTER TABLE totp_recovery_codes
+    DROP CONSTRAINT totp_recovery_codes_user_id_fkey,
+    ADD CONSTRAINT totp_recovery_codes_user_id_fkey FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE;
+ALTER TABLE user_identities
+    DROP CONSTRAINT user_identities_user_id_fkey,
+    ADD CONSTRAINT user_identities_user_id_fkey FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE;
+ALTER TABLE user_roles
+    DROP CONSTRAINT user_roles_user_id_fkey,
+    ADD CONSTRAINT user_roles_user_id_fkey FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE;
+ALTER TABLE api_keys
+    DROP CONSTRAINT api_keys_user_id_fkey,
+    ADD CONSTRAINT api_keys_user_id_fkey FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE;
+ALTER TABLE username_history
+    DROP CONSTRAINT username_history_user_id_fkey,
+    ADD CONSTRAINT username_history_user_id_fkey FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE;
@@ -0,0 +1,3 @@
+-- Add migration script here
+-- Email addresses are private unless the user chooses to show them on their profile
+ALTER TABLE users ADD COLUMN show_email BOOLEAN NOT NULL DEFAULT FALSE;
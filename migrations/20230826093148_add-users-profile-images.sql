@@ -0,0 +1,3 @@
+-- Add migration script here
+ALTER TABLE users ADD COLUMN avatar_id UUID;
+ALTER TABLE users ADD COLUMN cover_id UUID;
@@ -0,0 +1,17 @@
+-- Add migration script here
+CREATE TABLE post_media (
+    id UUID PRIMARY KEY,
+    uploader_id UUID NOT NULL,
+    -- Uploads stay unattached until they are used in a post
+    post_id UUID,
+    position INT,
+    alt_text TEXT,
+    width INT NOT NULL,
+    height INT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    FOREIGN KEY (uploader_id) REFERENCES users (id) ON DELETE CASCADE,
+    FOREIGN KEY (post_id) REFERENCES posts (id) ON DELETE CASCADE
+);
+
+CREATE INDEX post_media_post_id_idx ON post_media (post_id, position);
+CREATE INDEX post_media_unattached_idx ON post_media (created_at) WHERE post_id IS NULL;
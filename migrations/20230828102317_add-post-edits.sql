@@ -0,0 +1,16 @@
+-- Add migration script here
+ALTER TABLE posts ADD COLUMN edited_at TIMESTAMP;
+
+-- Every version of a post before it was edited
+CREATE TABLE post_edits (
+    id UUID PRIMARY KEY,
+    post_id UUID NOT NULL,
+    title VARCHAR(255) NOT NULL,
+    location VARCHAR(255) NOT NULL,
+    content TEXT NOT NULL,
+    -- When this version was replaced
+    edited_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    FOREIGN KEY (post_id) REFERENCES posts (id) ON DELETE CASCADE
+);
+
+CREATE INDEX post_edits_post_id_idx ON post_edits (post_id, edited_at);
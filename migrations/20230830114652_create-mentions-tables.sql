@@ -0,0 +1,35 @@
+-- Add migration script here
+-- One row for every `@username` in a post's content, offsets are in characters
+CREATE TABLE post_mentions (
+    post_id UUID NOT NULL,
+    user_id UUID NOT NULL,
+    start_offset INTEGER NOT NULL,
+    end_offset INTEGER NOT NULL,
+    PRIMARY KEY (post_id, start_offset),
+    FOREIGN KEY (post_id) REFERENCES posts (id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE
+);
+
+CREATE INDEX post_mentions_user_id_idx ON post_mentions (user_id);
+
+-- Users who removed themselves from a post, so that editing the post doesn't tag them again
+CREATE TABLE post_mention_removals (
+    post_id UUID NOT NULL,
+    user_id UUID NOT NULL,
+    removed_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (post_id, user_id),
+    FOREIGN KEY (post_id) REFERENCES posts (id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE
+);
+
+CREATE TABLE comment_mentions (
+    comment_id UUID NOT NULL,
+    user_id UUID NOT NULL,
+    start_offset INTEGER NOT NULL,
+    end_offset INTEGER NOT NULL,
+    PRIMARY KEY (comment_id, start_offset),
+    FOREIGN KEY (comment_id) REFERENCES comments (id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE
+);
+
+CREATE INDEX comment_mentions_user_id_idx ON comment_mentions (user_id);
@@ -0,0 +1,29 @@
+-- Add migration script here
+ALTER TABLE posts
+    ADD COLUMN latitude DOUBLE PRECISION,
+    ADD COLUMN longitude DOUBLE PRECISION,
+    -- ISO 3166-1 alpha-2, uppercase
+    ADD COLUMN country_code CHAR(2),
+    ADD CONSTRAINT posts_coordinates_check CHECK (
+        (latitude IS NULL AND longitude IS NULL)
+        OR (
+            latitude BETWEEN -90 AND 90
+            AND longitude BETWEEN -180 AND 180
+        )
+    );
+
+-- Nearby and map searches first narrow posts down to a bounding box
+CREATE INDEX posts_coordinates_idx ON posts (latitude, longitude) WHERE latitude IS NOT NULL;
+
+-- Haversine distance in kilometres between two points, on a sphere with the Earth's mean radius
+CREATE FUNCTION great_circle_distance_km(
+    lat1 DOUBLE PRECISION,
+    lon1 DOUBLE PRECISION,
+    lat2 DOUBLE PRECISION,
+    lon2 DOUBLE PRECISION
+) RETURNS DOUBLE PRECISION AS $$
+    SELECT 2 * 6371.0088 * ASIN(LEAST(1, SQRT(
+        POWER(SIN(RADIANS(lat2 - lat1) / 2), 2)
+        + COS(RADIANS(lat1)) * COS(RADIANS(lat2)) * POWER(SIN(RADIANS(lon2 - lon1) / 2), 2)
+    )))
+$$ LANGUAGE SQL IMMUTABLE STRICT;
@@ -0,0 +1,58 @@
+-- Add migration script here
+-- Trimmed, lowercased and with runs of whitespace collapsed
+CREATE FUNCTION normalize_place_name(name TEXT) RETURNS TEXT AS $$
+    SELECT LOWER(REGEXP_REPLACE(TRIM(name), '\s+', ' ', 'g'))
+$$ LANGUAGE SQL IMMUTABLE STRICT;
+
+CREATE TABLE places (
+    id UUID PRIMARY KEY,
+    name VARCHAR(255) NOT NULL,
+    -- `normalize_place_name` of the name, places are told apart by it and their country
+    normalized_name VARCHAR(255) NOT NULL,
+    -- ISO 3166-1 alpha-2, uppercase
+    country_code CHAR(2),
+    latitude DOUBLE PRECISION,
+    longitude DOUBLE PRECISION,
+    category VARCHAR(20),
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    CHECK (
+        (latitude IS NULL AND longitude IS NULL)
+        OR (
+            latitude BETWEEN -90 AND 90
+            AND longitude BETWEEN -180 AND 180
+        )
+    ),
+    CHECK (
+        category IN (
+            'city', 'landmark', 'nature', 'beach', 'mountain', 'restaurant', 'accommodation',
+            'other'
+        )
+    )
+);
+
+CREATE UNIQUE INDEX places_name_country_idx ON places (normalized_name, COALESCE(country_code, ''));
+-- Autocomplete matches the start of the name
+CREATE INDEX places_normalized_name_prefix_idx ON places (normalized_name text_pattern_ops);
+
+ALTER TABLE posts
+    ADD COLUMN place_id UUID REFERENCES places (id) ON DELETE SET NULL;
+
+CREATE INDEX posts_place_id_idx ON posts (place_id, created_at);
+
+-- Every distinct location already posted becomes a place, taking the spelling it was first
+-- posted with, the country most of its posts gave and the centre of their coordinates
+INSERT INTO places (id, name, normalized_name, country_code, latitude, longitude)
+SELECT
+    gen_random_uuid(),
+    (ARRAY_AGG(REGEXP_REPLACE(TRIM(location), '\s+', ' ', 'g') ORDER BY created_at))[1],
+    normalize_place_name(location),
+    MODE() WITHIN GROUP (ORDER BY country_code),
+    AVG(latitude),
+    AVG(longitude)
+FROM posts
+GROUP BY normalize_place_name(location);
+
+UPDATE posts
+SET place_id = places.id
+FROM places
+WHERE places.normalized_name = normalize_place_name(posts.location);
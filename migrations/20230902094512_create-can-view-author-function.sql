@@ -0,0 +1,27 @@
+-- Add migration script here
+-- Whether the viewer may see what the author posts: the author's account is active and
+-- either public, the viewer's own or followed by the viewer, and neither of them has
+-- blocked the other. `viewer` is NULL for requests without a signed in user.
+CREATE FUNCTION can_view_author(author UUID, viewer UUID) RETURNS BOOLEAN AS $$
+    SELECT EXISTS (
+        SELECT 1
+        FROM users
+        WHERE users.id = author
+            AND users.deactivated_at IS NULL
+            AND (
+                NOT users.is_private
+                OR users.id = viewer
+                OR EXISTS (
+                    SELECT 1
+                    FROM users_followers
+                    WHERE user_id = author AND follower_id = viewer
+                )
+            )
+    )
+    AND NOT EXISTS (
+        SELECT 1
+        FROM user_blocks
+        WHERE (blocker_id = viewer AND blocked_id = author)
+            OR (blocker_id = author AND blocked_id = viewer)
+    )
+$$ LANGUAGE SQL STABLE;
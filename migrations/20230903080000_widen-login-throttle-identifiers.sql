@@ -0,0 +1,4 @@
+-- Add migration script here
+-- Login attempts are throttled by whatever email was sent, which isn't length checked
+ALTER TABLE login_throttles ALTER COLUMN identifier TYPE TEXT;
+ALTER TABLE lockout_events ALTER COLUMN identifier TYPE TEXT;
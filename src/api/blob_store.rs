@@ -0,0 +1,94 @@
+use std::{
+    io::ErrorKind,
+    path::{Component, Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::{anyhow, Context};
+use async_trait::async_trait;
+
+use super::configuration::{BlobStorage, MediaSettings};
+
+/// Stores binary objects such as uploaded images under slash-separated keys
+///
+/// The implementation is picked from `MediaSettings` at startup and shared with the
+/// routes as `Data<dyn BlobStore>`.
+#[async_trait]
+pub trait BlobStore: Send + Sync {
+    async fn put(&self, key: &str, bytes: Vec<u8>) -> anyhow::Result<()>;
+
+    /// Returns `None` if there is nothing stored under the key
+    async fn get(&self, key: &str) -> anyhow::Result<Option<Vec<u8>>>;
+
+    /// Deleting a missing key is not an error
+    async fn delete(&self, key: &str) -> anyhow::Result<()>;
+}
+
+pub fn get_blob_store(settings: &MediaSettings) -> anyhow::Result<Arc<dyn BlobStore>> {
+    let store: Arc<dyn BlobStore> = match &settings.storage {
+        BlobStorage::Local { directory } => Arc::new(LocalBlobStore::new(directory)),
+    };
+    Ok(store)
+}
+
+/// Keeps every blob as a file under a directory on the local filesystem
+pub struct LocalBlobStore {
+    directory: PathBuf,
+}
+
+impl LocalBlobStore {
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+        }
+    }
+
+    /// Refuses keys that would escape the store's directory
+    fn path(&self, key: &str) -> anyhow::Result<PathBuf> {
+        let relative = Path::new(key);
+        if key.is_empty()
+            || !relative
+                .components()
+                .all(|component| matches!(component, Component::Normal(_)))
+        {
+            return Err(anyhow!("Invalid blob key: {}", key));
+        }
+        Ok(self.directory.join(relative))
+    }
+}
+
+#[async_trait]
+impl BlobStore for LocalBlobStore {
+    #[tracing::instrument(name = "Writing blob to file", skip(self, bytes))]
+    async fn put(&self, key: &str, bytes: Vec<u8>) -> anyhow::Result<()> {
+        let path = self.path(key)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .context("Failed to create blob directory.")?;
+        }
+        tokio::fs::write(path, bytes)
+            .await
+            .context("Failed to write blob to file.")?;
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Reading blob from file", skip(self))]
+    async fn get(&self, key: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        match tokio::fs::read(self.path(key)?).await {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err).context("Failed to read blob from file."),
+        }
+    }
+
+    #[tracing::instrument(name = "Deleting blob file", skip(self))]
+    async fn delete(&self, key: &str) -> anyhow::Result<()> {
+        match tokio::fs::remove_file(self.path(key)?).await {
+            Err(err) if err.kind() != ErrorKind::NotFound => {
+                Err(err).context("Failed to delete blob file.")
+            }
+            _ => Ok(()),
+        }
+    }
+}
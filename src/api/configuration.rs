@@ -38,6 +38,9 @@ impl TryFrom<String> for Environment {
 pub struct Settings {
     pub database: DatabaseSettings,
     pub application: ApplicationSettings,
+    pub auth: AuthSettings,
+    pub email: EmailSettings,
+    pub media: MediaSettings,
 }
 
 #[derive(serde::Deserialize, Clone)]
@@ -48,6 +51,151 @@ pub struct ApplicationSettings {
     pub base_url: String,
 }
 
+#[derive(serde::Deserialize, Clone)]
+pub struct AuthSettings {
+    /// The key new tokens are signed with
+    pub signing_key: JwtKeySettings,
+    /// Keys that are no longer used for signing but whose tokens are still accepted
+    #[serde(default)]
+    pub verification_keys: Vec<JwtKeySettings>,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub access_token_expiration_minutes: i64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub refresh_token_expiration_days: i64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub password_reset_expiration_minutes: i64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub email_verification_expiration_hours: i64,
+    /// How long an old username stays reserved for its previous owner after a change
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub username_reservation_days: i64,
+    pub unverified_accounts: UnverifiedAccountPolicy,
+    /// How long the "mfa pending" token from the first login step stays valid
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub mfa_token_expiration_minutes: i64,
+    /// Shown next to the account name in authenticator apps
+    pub totp_issuer: String,
+    pub password_hashing: PasswordHashingSettings,
+    pub login_throttling: LoginThrottlingSettings,
+    pub account_deletion: AccountDeletionSettings,
+    /// External identity providers users can sign in with, e.g. Google or Apple
+    #[serde(default)]
+    pub oidc_providers: Vec<OidcProviderSettings>,
+}
+
+/// An OpenID Connect provider, discovered from `{issuer_url}/.well-known/openid-configuration`
+///
+/// `name` identifies the provider in the login routes, e.g. `/users/oidc/google/authorize`,
+/// and `redirect_url` has to point at the matching callback route.
+#[derive(serde::Deserialize, Clone)]
+pub struct OidcProviderSettings {
+    pub name: String,
+    pub issuer_url: String,
+    pub client_id: String,
+    pub client_secret: Secret<String>,
+    pub redirect_url: String,
+}
+
+/// Limits on failed logins, tracked per account and per IP address
+///
+/// Once the free attempts are used up every further failure locks logins for
+/// `base_lockout_seconds`, doubling each time up to `max_lockout_seconds`.
+#[derive(serde::Deserialize, Clone)]
+pub struct LoginThrottlingSettings {
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub account_free_attempts: i32,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub ip_free_attempts: i32,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub base_lockout_seconds: i64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_lockout_seconds: i64,
+    /// Failures are forgotten once there hasn't been one for this long
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub reset_after_minutes: i64,
+}
+
+/// Deleted accounts are deactivated first, and purged once the grace period is over
+#[derive(serde::Deserialize, Clone)]
+pub struct AccountDeletionSettings {
+    /// Signing in again within this many days restores the account
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub grace_period_days: i64,
+    /// How often the background purge looks for accounts past their grace period
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub purge_interval_seconds: u64,
+}
+
+/// Argon2id cost parameters for new password hashes
+///
+/// Existing hashes with different parameters are upgraded the next time their owner logs in.
+#[derive(serde::Deserialize, Clone)]
+pub struct PasswordHashingSettings {
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub memory_cost_kib: u32,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub iterations: u32,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub parallelism: u32,
+}
+
+/// What accounts that have not confirmed their email address are allowed to do
+#[derive(serde::Deserialize, Clone)]
+pub struct UnverifiedAccountPolicy {
+    pub can_post: bool,
+    pub can_comment: bool,
+}
+
+/// A single JWT key, identified by the `kid` header of the tokens it signs.
+///
+/// HMAC algorithms read `secret`, asymmetric algorithms (RS256, EdDSA, ...) read the
+/// PEM files at `private_key_path` and `public_key_path`. Verification keys only
+/// need the public half.
+#[derive(serde::Deserialize, Clone)]
+pub struct JwtKeySettings {
+    pub kid: String,
+    pub algorithm: jsonwebtoken::Algorithm,
+    pub secret: Option<Secret<String>>,
+    pub private_key_path: Option<String>,
+    pub public_key_path: Option<String>,
+}
+
+#[derive(serde::Deserialize, Clone)]
+pub struct EmailSettings {
+    pub sender: String,
+    pub transport: EmailTransport,
+}
+
+#[derive(serde::Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum EmailTransport {
+    Smtp {
+        host: String,
+        #[serde(deserialize_with = "deserialize_number_from_string")]
+        port: u16,
+        username: String,
+        password: Secret<String>,
+    },
+    /// Write emails to files in `directory` instead of sending them
+    File { directory: String },
+}
+
+/// Uploaded images, which are resized and kept in the blob store
+#[derive(serde::Deserialize, Clone)]
+pub struct MediaSettings {
+    /// Larger uploads are rejected before they are decoded
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_upload_bytes: usize,
+    pub storage: BlobStorage,
+}
+
+#[derive(serde::Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum BlobStorage {
+    /// Keep blobs as files under `directory`
+    Local { directory: String },
+}
+
 #[derive(serde::Deserialize, Clone)]
 pub struct DatabaseSettings {
     pub username: String,
@@ -61,11 +209,9 @@ pub struct DatabaseSettings {
 
 impl DatabaseSettings {
     pub fn with_db(&self) -> PgConnectOptions {
-        let options = self
-            .without_db()
+        self.without_db()
             .database(&self.database_name)
-            .log_statements(tracing::log::LevelFilter::Trace);
-        options
+            .log_statements(tracing::log::LevelFilter::Trace)
     }
 
     pub fn without_db(&self) -> PgConnectOptions {
@@ -77,7 +223,7 @@ impl DatabaseSettings {
         PgConnectOptions::new()
             .host(&self.host)
             .username(&self.username)
-            .password(&self.password.expose_secret())
+            .password(self.password.expose_secret())
             .port(self.port)
             .ssl_mode(ssl_mode)
     }
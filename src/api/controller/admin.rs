@@ -0,0 +1,48 @@
+use anyhow::anyhow;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::api::{
+    database,
+    models::{
+        error::{ApiError, Result},
+        LockoutEventInfo, Role,
+    },
+};
+
+const LOCKOUT_EVENTS_LIMIT: i64 = 100;
+
+/// The most recent login lockouts, newest first
+pub async fn get_lockout_events(conn: &PgPool) -> Result<Vec<LockoutEventInfo>> {
+    let events = database::get_lockout_events(conn, LOCKOUT_EVENTS_LIMIT).await?;
+    Ok(events.into_iter().map(LockoutEventInfo::from).collect())
+}
+
+pub async fn get_user_roles(user_id: &Uuid, conn: &PgPool) -> Result<Vec<Role>> {
+    ensure_user_exists(user_id, conn).await?;
+    database::get_user_roles(conn, user_id).await
+}
+
+/// Grants a role, which shows up in the user's tokens from their next refresh
+pub async fn grant_role(user_id: &Uuid, role: Role, conn: &PgPool) -> Result<()> {
+    ensure_user_exists(user_id, conn).await?;
+    database::grant_role(conn, user_id, role).await
+}
+
+pub async fn revoke_role(admin_id: &Uuid, user_id: &Uuid, role: Role, conn: &PgPool) -> Result<()> {
+    // Keeps the last admin from locking everyone out of user management
+    if admin_id == user_id && role == Role::Admin {
+        return Err(ApiError::BadRequest(anyhow!(
+            "You can't revoke your own admin role"
+        )));
+    }
+    ensure_user_exists(user_id, conn).await?;
+    database::revoke_role(conn, user_id, role).await
+}
+
+async fn ensure_user_exists(user_id: &Uuid, conn: &PgPool) -> Result<()> {
+    database::get_user_by_id(conn, user_id)
+        .await?
+        .ok_or(ApiError::NotFound(anyhow!("User does not exist")))?;
+    Ok(())
+}
@@ -0,0 +1,61 @@
+use anyhow::anyhow;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::api::{
+    database,
+    models::{
+        error::{ApiError, Result},
+        generate_api_key, token, ApiKeyInfo, CreateApiKey, NewApiKey,
+    },
+};
+
+// Enough of the key for users to recognise it, not enough to help guess the rest
+const DISPLAYED_PREFIX_LENGTH: usize = 11;
+
+/// Creates a key for the user, returning the only copy of it that will ever be shown
+pub async fn create_api_key(
+    user_id: &Uuid,
+    new_key: CreateApiKey,
+    conn: &PgPool,
+) -> Result<NewApiKey> {
+    let key = generate_api_key();
+    let mut scopes: Vec<String> = new_key
+        .scopes
+        .iter()
+        .map(|scope| scope.as_str().to_string())
+        .collect();
+    scopes.sort();
+    scopes.dedup();
+
+    let api_key = database::insert_api_key(
+        conn,
+        &Uuid::new_v4(),
+        user_id,
+        &new_key.name,
+        &key[..DISPLAYED_PREFIX_LENGTH],
+        &token::hash_token(&key),
+        &scopes,
+    )
+    .await?;
+
+    Ok(NewApiKey {
+        info: api_key.try_into()?,
+        key,
+    })
+}
+
+pub async fn get_api_keys(user_id: &Uuid, conn: &PgPool) -> Result<Vec<ApiKeyInfo>> {
+    database::get_active_api_keys(conn, user_id)
+        .await?
+        .into_iter()
+        .map(ApiKeyInfo::try_from)
+        .collect()
+}
+
+pub async fn revoke_api_key(user_id: &Uuid, key_id: &Uuid, conn: &PgPool) -> Result<()> {
+    if !database::revoke_api_key(conn, key_id, user_id).await? {
+        return Err(ApiError::NotFound(anyhow!("API key does not exist")));
+    }
+    Ok(())
+}
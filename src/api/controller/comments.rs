@@ -3,6 +3,8 @@ use sqlx::PgPool;
 use uuid::Uuid;
 
 use crate::api::{
+    configuration::UnverifiedAccountPolicy,
+    controller::{self, posts},
     database,
     models::{
         error::{ApiError, Result},
@@ -14,31 +16,32 @@ pub async fn create_comment(
     user_id: &Uuid,
     post_id: &Uuid,
     comment: CreateComment,
+    policy: &UnverifiedAccountPolicy,
     conn: &PgPool,
 ) -> Result<String> {
-    // Check if user exists
-    let user = database::get_user_by_id(conn, user_id).await?;
-    if user.is_none() {
-        return Err(ApiError::NotFound(anyhow!("User does not exist")));
-    }
-    // Check if posts exists
-    let post = database::get_post_by_id(conn, post_id).await?;
-    if post.is_none() {
-        return Err(ApiError::NotFound(anyhow!("Post does not exist")));
-    }
+    // Check if user exists and is allowed to comment
+    let user = database::get_user_by_id(conn, user_id)
+        .await?
+        .ok_or(ApiError::NotFound(anyhow!("User does not exist")))?;
+    controller::user::ensure_email_verified(&user, policy.can_comment, "commenting")?;
+    // Check that the post exists and its author's posts are visible to the user
+    let post = posts::get_post(post_id, Some(*user_id), conn).await?;
+    controller::user::ensure_not_blocked(user_id, &posts::author_id(&post)?, conn).await?;
     // Create comment
-    let comment_id = database::create_comment(comment, user_id, post_id, conn).await?;
+    let mentions = controller::mentions::resolve_mentions(user_id, &comment.comment, conn).await?;
+    let comment_id = database::create_comment(comment, user_id, post_id, &mentions, conn).await?;
     Ok(comment_id)
 }
 
-pub async fn get_comments(post_id: &Uuid, conn: &PgPool) -> Result<Vec<Comment>> {
-    // Check if post exists
-    let post = database::get_post_by_id(conn, post_id).await?;
-    if post.is_none() {
-        return Err(ApiError::NotFound(anyhow!("Post does not exist")));
-    }
+pub async fn get_comments(
+    post_id: &Uuid,
+    viewer_id: Option<Uuid>,
+    conn: &PgPool,
+) -> Result<Vec<Comment>> {
+    // Check that the post exists and its author's posts are visible to the viewer
+    posts::get_post(post_id, viewer_id, conn).await?;
     // Get comments
-    let comments = database::get_comments(post_id, conn).await?;
+    let comments = database::get_comments(post_id, viewer_id.as_ref(), conn).await?;
     Ok(comments)
 }
 
@@ -65,7 +68,7 @@ pub async fn delete_comment(
         return Err(ApiError::NotFound(anyhow!("Comment does not exist")));
     };
     // Check if user is the owner of the comment
-    if comment.user.id != *user_id.to_string() {
+    if comment.user.map(|user| user.id) != Some(user_id.to_string()) {
         return Err(ApiError::Forbidden(anyhow!(
             "You are not the owner of this comment"
         )));
@@ -80,25 +83,31 @@ pub async fn reply_to_comment(
     post_id: &Uuid,
     comment_id: &Uuid,
     new_comment: CreateComment,
+    policy: &UnverifiedAccountPolicy,
     conn: &PgPool,
 ) -> Result<()> {
-    // Check if user exists
-    let user = database::get_user_by_id(conn, user_id).await?;
-    if user.is_none() {
-        return Err(ApiError::NotFound(anyhow!("User does not exist")));
-    }
-    // Check if post exists
-    let post = database::get_post_by_id(conn, post_id).await?;
-    if post.is_none() {
-        return Err(ApiError::NotFound(anyhow!("Post does not exist")));
-    }
+    // Check if user exists and is allowed to comment
+    let user = database::get_user_by_id(conn, user_id)
+        .await?
+        .ok_or(ApiError::NotFound(anyhow!("User does not exist")))?;
+    controller::user::ensure_email_verified(&user, policy.can_comment, "commenting")?;
+    // Check that the post exists and its author's posts are visible to the user
+    let post = posts::get_post(post_id, Some(*user_id), conn).await?;
+    controller::user::ensure_not_blocked(user_id, &posts::author_id(&post)?, conn).await?;
     // Check if comment exists
-    let comment = database::get_comment_by_id(comment_id, conn).await?;
-    if comment.is_none() {
-        return Err(ApiError::NotFound(anyhow!("Comment does not exist")));
+    let comment = database::get_comment_by_id(comment_id, conn)
+        .await?
+        .ok_or(ApiError::NotFound(anyhow!("Comment does not exist")))?;
+    // Nor can they reply to someone who blocked them, or whom they blocked
+    if let Some(comment_author) = comment.user {
+        let comment_author_id = Uuid::parse_str(&comment_author.id)
+            .map_err(|err| ApiError::InternalServer(anyhow!(err)))?;
+        controller::user::ensure_not_blocked(user_id, &comment_author_id, conn).await?;
     }
 
     // Reply to comment
-    database::reply_to_comment(new_comment, user_id, post_id, comment_id, conn).await?;
+    let mentions =
+        controller::mentions::resolve_mentions(user_id, &new_comment.comment, conn).await?;
+    database::reply_to_comment(new_comment, user_id, post_id, comment_id, &mentions, conn).await?;
     Ok(())
 }
@@ -0,0 +1,160 @@
+use std::io::{Cursor, Write};
+
+use anyhow::{anyhow, Context};
+use sqlx::PgPool;
+use tracing::Instrument;
+use uuid::Uuid;
+use zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+use crate::api::{
+    database,
+    models::{
+        error::{ApiError, Result},
+        AuthUser, DataExportInfo, ExportLookup, ExportStatus, ExportedPost,
+    },
+    telemetry::spawn_blocking_with_tracing,
+};
+
+const EXPORT_LIFETIME_DAYS: i64 = 7;
+
+/// Exports pending for longer than this were lost, most likely to a restart
+const EXPORT_TIMEOUT_MINUTES: i64 = 30;
+
+/// How long a finished export is handed out again instead of building a new one
+const EXPORT_REUSE_HOURS: i64 = 24;
+
+/// Starts generating an archive of everything stored about the user
+///
+/// The archive is built in the background, so this returns straight away with an export
+/// to poll. Asking again while one is still being generated, or soon after one finished,
+/// returns that one instead.
+pub async fn start_export(user_id: &Uuid, conn: &PgPool) -> Result<DataExportInfo> {
+    let reusable = database::get_reusable_export(
+        conn,
+        user_id,
+        chrono::Duration::minutes(EXPORT_TIMEOUT_MINUTES).num_seconds() as f64,
+        chrono::Duration::hours(EXPORT_REUSE_HOURS).num_seconds() as f64,
+    )
+    .await?;
+    if let Some(export) = reusable {
+        return DataExportInfo::try_from(&export);
+    }
+
+    let export = database::insert_export(
+        conn,
+        &Uuid::new_v4(),
+        user_id,
+        chrono::Duration::days(EXPORT_LIFETIME_DAYS).num_seconds() as f64,
+    )
+    .await?;
+
+    let (export_id, user_id, conn) = (export.id, *user_id, conn.clone());
+    tokio::spawn(
+        async move {
+            if let Err(err) = generate_export(&export_id, &user_id, &conn).await {
+                tracing::error!("Failed to generate data export: {:?}", err);
+                if let Err(err) = database::fail_export(&conn, &export_id).await {
+                    tracing::error!("Failed to mark data export as failed: {:?}", err);
+                }
+            }
+        }
+        .in_current_span(),
+    );
+
+    DataExportInfo::try_from(&export)
+}
+
+/// The export's status, or its archive once it is ready
+pub async fn get_export(user_id: &Uuid, export_id: &Uuid, conn: &PgPool) -> Result<ExportLookup> {
+    let export = database::get_export(conn, user_id, export_id)
+        .await?
+        .ok_or(ApiError::NotFound(anyhow!("Data export does not exist")))?;
+    let info = DataExportInfo::try_from(&export)?;
+
+    match (info.status, export.archive) {
+        (ExportStatus::Ready, Some(archive)) => Ok(ExportLookup::Archive(archive)),
+        _ => Ok(ExportLookup::Status(info)),
+    }
+}
+
+/// Fails the exports that have been pending for too long, so that their owners see why
+pub async fn fail_stale_exports(conn: &PgPool) -> Result<u64> {
+    database::fail_stale_exports(
+        conn,
+        chrono::Duration::minutes(EXPORT_TIMEOUT_MINUTES).num_seconds() as f64,
+    )
+    .await
+}
+
+async fn generate_export(export_id: &Uuid, user_id: &Uuid, conn: &PgPool) -> Result<()> {
+    let profile: AuthUser = database::get_user_by_id(conn, user_id)
+        .await?
+        .ok_or(ApiError::NotFound(anyhow!("User does not exist")))?
+        .into();
+    let posts = database::get_users_posts(conn, user_id).await?;
+    let comments = database::get_comments_by_user(user_id, conn).await?;
+    let likes = database::get_likes_by_user(conn, user_id).await?;
+    let followers = database::get_followers(conn, user_id).await?;
+    let following = database::get_following(conn, user_id).await?;
+
+    let files = vec![
+        ("profile.json", to_json(&profile)?),
+        ("posts.json", to_json(&posts)?),
+        (
+            "posts.csv",
+            to_csv(&posts.iter().map(ExportedPost::from).collect::<Vec<_>>())?,
+        ),
+        ("comments.json", to_json(&comments)?),
+        ("comments.csv", to_csv(&comments)?),
+        ("likes.json", to_json(&likes)?),
+        ("likes.csv", to_csv(&likes)?),
+        ("followers.json", to_json(&followers)?),
+        ("followers.csv", to_csv(&followers)?),
+        ("following.json", to_json(&following)?),
+        ("following.csv", to_csv(&following)?),
+    ];
+    let archive = spawn_blocking_with_tracing(move || write_archive(files))
+        .await
+        .context("Failed to spawn blocking task.")
+        .map_err(ApiError::InternalServer)?
+        .map_err(ApiError::InternalServer)?;
+
+    database::complete_export(conn, export_id, &archive).await
+}
+
+fn to_json<T: serde::Serialize + ?Sized>(value: &T) -> Result<Vec<u8>> {
+    serde_json::to_vec_pretty(value)
+        .context("Failed to serialize export to JSON.")
+        .map_err(ApiError::InternalServer)
+}
+
+fn to_csv<T: serde::Serialize>(rows: &[T]) -> Result<Vec<u8>> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    for row in rows {
+        writer
+            .serialize(row)
+            .context("Failed to serialize export to CSV.")
+            .map_err(ApiError::InternalServer)?;
+    }
+    writer
+        .into_inner()
+        .context("Failed to flush CSV export.")
+        .map_err(ApiError::InternalServer)
+}
+
+fn write_archive(files: Vec<(&str, Vec<u8>)>) -> anyhow::Result<Vec<u8>> {
+    let mut archive = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+    for (name, contents) in files {
+        archive
+            .start_file(name, options)
+            .with_context(|| format!("Failed to add {} to the archive.", name))?;
+        archive
+            .write_all(&contents)
+            .with_context(|| format!("Failed to write {} to the archive.", name))?;
+    }
+    Ok(archive
+        .finish()
+        .context("Failed to finish the archive.")?
+        .into_inner())
+}
@@ -0,0 +1,89 @@
+use anyhow::anyhow;
+use sqlx::PgPool;
+
+use crate::api::{
+    configuration::LoginThrottlingSettings,
+    database,
+    models::{
+        error::{ApiError, Result},
+        ClientInfo,
+    },
+};
+
+const ACCOUNT: &str = "account";
+const IP_ADDRESS: &str = "ip";
+
+/// Rejects a login attempt, before any credentials are checked, while the account or IP is locked
+pub async fn ensure_not_locked(email: &str, client: &ClientInfo, conn: &PgPool) -> Result<()> {
+    let lockout = database::get_login_lockout(conn, email, client.ip_address.as_deref()).await?;
+    if let Some(retry_after) = lockout {
+        return Err(ApiError::TooManyRequests(
+            anyhow!("Too many failed login attempts, please try again later"),
+            retry_after.max(1),
+        ));
+    }
+    Ok(())
+}
+
+/// Counts a failed attempt against both the account and the IP, locking either once it
+/// runs out of free attempts
+pub async fn record_failure(
+    email: &str,
+    client: &ClientInfo,
+    settings: &LoginThrottlingSettings,
+    conn: &PgPool,
+) -> Result<()> {
+    let email = email.to_lowercase();
+    let mut scopes = vec![(ACCOUNT, email.as_str(), settings.account_free_attempts)];
+    if let Some(ip_address) = &client.ip_address {
+        scopes.push((IP_ADDRESS, ip_address.as_str(), settings.ip_free_attempts));
+    }
+
+    let reset_after_seconds = (settings.reset_after_minutes * 60) as f64;
+    for (scope, identifier, free_attempts) in scopes {
+        let failed_attempts =
+            database::record_failed_login(conn, scope, identifier, reset_after_seconds).await?;
+        if let Some(lockout_seconds) = lockout_seconds(failed_attempts, free_attempts, settings) {
+            tracing::warn!(
+                "Locking {} logins for {} seconds after {} failed attempts",
+                scope,
+                lockout_seconds,
+                failed_attempts
+            );
+            database::lock_login(
+                conn,
+                scope,
+                identifier,
+                failed_attempts,
+                lockout_seconds as f64,
+            )
+            .await?;
+        }
+    }
+    Ok(())
+}
+
+/// Forgets the account's failed attempts after a successful login
+///
+/// The IP's attempts are left to expire, so that an attacker can't reset them by
+/// logging into an account of their own.
+pub async fn clear_failures(email: &str, conn: &PgPool) -> Result<()> {
+    database::clear_failed_logins(conn, ACCOUNT, &email.to_lowercase()).await
+}
+
+fn lockout_seconds(
+    failed_attempts: i32,
+    free_attempts: i32,
+    settings: &LoginThrottlingSettings,
+) -> Option<i64> {
+    if failed_attempts < free_attempts {
+        return None;
+    }
+    let doublings = (failed_attempts - free_attempts).min(32) as u32;
+    Some(
+        settings
+            .base_lockout_seconds
+            .saturating_mul(2_i64.saturating_pow(doublings))
+            .min(settings.max_lockout_seconds),
+    )
+}
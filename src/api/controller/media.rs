@@ -0,0 +1,222 @@
+use std::io::Cursor;
+
+use anyhow::{anyhow, Context};
+use image::{codecs::jpeg::JpegEncoder, imageops::FilterType, io::Limits, io::Reader, ImageFormat};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::api::{
+    blob_store::BlobStore,
+    database,
+    models::{
+        error::{ApiError, Result},
+        image_blob_key, AuthUser, ImageKind, ImageSize, ImageVariant, PostMedia,
+    },
+    telemetry::spawn_blocking_with_tracing,
+};
+
+use super::user::get_me;
+
+/// Images are decoded in memory, so larger ones are refused before their pixels are read
+const MAX_IMAGE_DIMENSION: u32 = 8192;
+const JPEG_QUALITY: u8 = 85;
+/// How long an uploaded post photo is kept while waiting to be attached to a post
+const UNATTACHED_MEDIA_LIFETIME_HOURS: i64 = 24;
+
+/// Replaces the user's avatar or cover with the uploaded image
+///
+/// The upload is checked to really be a JPEG, PNG or WebP image, whatever the client says
+/// it is, and is stored as a JPEG in every standard size. The previous image is deleted
+/// once the new one is in place.
+pub async fn upload_profile_image(
+    user_id: &Uuid,
+    kind: ImageKind,
+    upload: Vec<u8>,
+    blob_store: &dyn BlobStore,
+    conn: &PgPool,
+) -> Result<AuthUser> {
+    let (image_id, _) = store_image(kind, upload, blob_store).await?;
+
+    let previous = database::update_profile_image(conn, user_id, kind, Some(&image_id)).await?;
+    if let Some(previous) = previous {
+        delete_variants(kind, &previous, blob_store).await;
+    }
+
+    get_me(*user_id, conn).await
+}
+
+pub async fn remove_profile_image(
+    user_id: &Uuid,
+    kind: ImageKind,
+    blob_store: &dyn BlobStore,
+    conn: &PgPool,
+) -> Result<AuthUser> {
+    let previous = database::update_profile_image(conn, user_id, kind, None).await?;
+    if let Some(previous) = previous {
+        delete_variants(kind, &previous, blob_store).await;
+    }
+
+    get_me(*user_id, conn).await
+}
+
+/// Stores a photo to be attached to one of the user's posts
+pub async fn upload_post_media(
+    user_id: &Uuid,
+    upload: Vec<u8>,
+    blob_store: &dyn BlobStore,
+    conn: &PgPool,
+) -> Result<PostMedia> {
+    database::get_user_by_id(conn, user_id)
+        .await?
+        .ok_or(ApiError::NotFound(anyhow!("User does not exist")))?;
+
+    let (media_id, (width, height)) = store_image(ImageKind::Post, upload, blob_store).await?;
+
+    database::insert_post_media(conn, &media_id, user_id, width, height).await
+}
+
+/// Deletes photos that were uploaded but never attached to a post, returning how many
+/// were deleted
+pub async fn purge_unattached_media(blob_store: &dyn BlobStore, conn: &PgPool) -> Result<usize> {
+    let deleted = database::delete_unattached_media(
+        conn,
+        chrono::Duration::hours(UNATTACHED_MEDIA_LIFETIME_HOURS).num_seconds() as f64,
+    )
+    .await?;
+    for media_id in &deleted {
+        delete_variants(ImageKind::Post, media_id, blob_store).await;
+    }
+
+    Ok(deleted.len())
+}
+
+/// Deletes the images of photos whose post has been deleted
+pub async fn delete_post_media(media: &[PostMedia], blob_store: &dyn BlobStore) {
+    for media in media {
+        match Uuid::parse_str(&media.id) {
+            Ok(media_id) => delete_variants(ImageKind::Post, &media_id, blob_store).await,
+            Err(err) => tracing::error!("Invalid post media id: {:?}", err),
+        }
+    }
+}
+
+/// One resized variant of an image, as a JPEG
+pub async fn get_image(
+    kind: ImageKind,
+    image_id: &Uuid,
+    variant: ImageVariant,
+    blob_store: &dyn BlobStore,
+) -> Result<Vec<u8>> {
+    if !kind.variants().contains(&variant) {
+        return Err(ApiError::NotFound(anyhow!("Image does not exist")));
+    }
+    blob_store
+        .get(&image_blob_key(kind, image_id, variant))
+        .await
+        .map_err(ApiError::InternalServer)?
+        .ok_or(ApiError::NotFound(anyhow!("Image does not exist")))
+}
+
+/// Resizes the upload to every variant of the kind and stores them under a new id,
+/// returning the id and the dimensions of the last variant, which is the largest
+async fn store_image(
+    kind: ImageKind,
+    upload: Vec<u8>,
+    blob_store: &dyn BlobStore,
+) -> Result<(Uuid, (u32, u32))> {
+    let variants = spawn_blocking_with_tracing(move || resize_variants(kind, &upload))
+        .await
+        .context("Failed to spawn blocking task.")
+        .map_err(ApiError::InternalServer)??;
+
+    let image_id = Uuid::new_v4();
+    let mut dimensions = (0, 0);
+    for resized in variants {
+        dimensions = resized.dimensions;
+        blob_store
+            .put(
+                &image_blob_key(kind, &image_id, resized.variant),
+                resized.jpeg,
+            )
+            .await
+            .map_err(ApiError::InternalServer)?;
+    }
+
+    Ok((image_id, dimensions))
+}
+
+/// Failing to clean up an old image only leaves an orphaned blob, so it is just logged
+pub(crate) async fn delete_variants(kind: ImageKind, image_id: &Uuid, blob_store: &dyn BlobStore) {
+    for &variant in kind.variants() {
+        if let Err(err) = blob_store
+            .delete(&image_blob_key(kind, image_id, variant))
+            .await
+        {
+            tracing::error!("Failed to delete old image: {:?}", err);
+        }
+    }
+}
+
+/// A variant encoded as a JPEG
+struct Resized {
+    variant: ImageVariant,
+    jpeg: Vec<u8>,
+    dimensions: (u32, u32),
+}
+
+fn resize_variants(kind: ImageKind, upload: &[u8]) -> Result<Vec<Resized>> {
+    let format = image::guess_format(upload)
+        .map_err(|_| ApiError::BadRequest(anyhow!("File is not a supported image")))?;
+    if !matches!(
+        format,
+        ImageFormat::Jpeg | ImageFormat::Png | ImageFormat::WebP
+    ) {
+        return Err(ApiError::BadRequest(anyhow!(
+            "Only JPEG, PNG and WebP images are supported"
+        )));
+    }
+
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_IMAGE_DIMENSION);
+    limits.max_image_height = Some(MAX_IMAGE_DIMENSION);
+    let mut reader = Reader::with_format(Cursor::new(upload), format);
+    reader.limits(limits);
+    let image = reader
+        .decode()
+        .map_err(|err| ApiError::BadRequest(anyhow!("Failed to decode image: {}", err)))?;
+
+    kind.variants()
+        .iter()
+        .map(|&variant| {
+            let resized = match kind.size(variant) {
+                Some(ImageSize::Fill(width, height)) => {
+                    image.resize_to_fill(width, height, FilterType::Lanczos3)
+                }
+                Some(ImageSize::Fit(width, height))
+                    if image.width() > width || image.height() > height =>
+                {
+                    image.resize(width, height, FilterType::Lanczos3)
+                }
+                Some(ImageSize::Fit(_, _)) => image.clone(),
+                None => {
+                    return Err(ApiError::InternalServer(anyhow!(
+                        "{:?} images have no {:?} variant",
+                        kind,
+                        variant
+                    )))
+                }
+            }
+            .to_rgb8();
+            let mut bytes = Vec::new();
+            JpegEncoder::new_with_quality(&mut bytes, JPEG_QUALITY)
+                .encode_image(&resized)
+                .context("Failed to encode resized image.")
+                .map_err(ApiError::InternalServer)?;
+            Ok(Resized {
+                variant,
+                jpeg: bytes,
+                dimensions: resized.dimensions(),
+            })
+        })
+        .collect()
+}
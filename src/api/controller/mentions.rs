@@ -0,0 +1,124 @@
+use std::collections::HashMap;
+
+use anyhow::anyhow;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::api::{
+    database,
+    models::{
+        error::{ApiError, Result},
+        NewMention,
+    },
+};
+
+/// A `@username` in some text, offsets are in characters and include the `@`
+#[derive(Debug, PartialEq, Eq)]
+struct MentionSpan {
+    username: String,
+    start: usize,
+    end: usize,
+}
+
+/// Every `@username` in the text, in order
+///
+/// Like hashtags, an `@` directly after a letter or digit doesn't start a mention so that
+/// email addresses aren't mistaken for one. A trailing `.` or `-` is punctuation rather
+/// than part of the username.
+fn extract_mentions(text: &str) -> Vec<MentionSpan> {
+    let chars = text.chars().collect::<Vec<char>>();
+    let mut mentions = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let after_word = i > 0 && is_username_char(chars[i - 1]);
+        if chars[i] != '@' || after_word {
+            i += 1;
+            continue;
+        }
+
+        let start = i;
+        let mut end = i + 1;
+        while end < chars.len() && is_username_char(chars[end]) {
+            end += 1;
+        }
+        while end > start + 1 && matches!(chars[end - 1], '.' | '-') {
+            end -= 1;
+        }
+        if end > start + 1 {
+            mentions.push(MentionSpan {
+                username: chars[start + 1..end].iter().collect(),
+                start,
+                end,
+            });
+        }
+        i = end.max(start + 1);
+    }
+    mentions
+}
+
+fn is_username_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '.' | '-')
+}
+
+/// The mentions in text written by `author_id`, resolved to the users they tag
+///
+/// Usernames that don't belong to anyone are left as plain text, as are deactivated
+/// accounts and users blocked either way by the author.
+pub async fn resolve_mentions(
+    author_id: &Uuid,
+    text: &str,
+    conn: &PgPool,
+) -> Result<Vec<NewMention>> {
+    let mut resolved = HashMap::<String, Option<Uuid>>::new();
+    let mut mentions = Vec::new();
+    for span in extract_mentions(text) {
+        // Usernames are stored lowercased, so `@Alice` tags `alice`
+        let username = span.username.to_lowercase();
+        let user_id = match resolved.get(&username) {
+            Some(user_id) => *user_id,
+            None => {
+                let user_id = resolve_username(author_id, &username, conn).await?;
+                resolved.insert(username, user_id);
+                user_id
+            }
+        };
+
+        if let Some(user_id) = user_id {
+            mentions.push(NewMention {
+                user_id,
+                start: span.start as i32,
+                end: span.end as i32,
+            });
+        }
+    }
+    Ok(mentions)
+}
+
+async fn resolve_username(author_id: &Uuid, username: &str, conn: &PgPool) -> Result<Option<Uuid>> {
+    let Some(user) = database::get_user_by_username(conn, username)
+        .await?
+        .filter(|user| !user.deactivated)
+    else {
+        return Ok(None);
+    };
+    let user_id = Uuid::parse_str(&user.id).map_err(|e| ApiError::InternalServer(e.into()))?;
+    if database::is_blocked_between(conn, author_id, &user_id).await? {
+        return Ok(None);
+    }
+    Ok(Some(user_id))
+}
+
+/// Untags the user from the post, later edits of the post won't tag them again
+pub async fn remove_post_mention(user_id: &Uuid, post_id: &Uuid, conn: &PgPool) -> Result<()> {
+    database::get_post_by_id(conn, post_id)
+        .await?
+        .ok_or(ApiError::NotFound(anyhow!("Post does not exist")))?;
+
+    let removed = database::remove_post_mention(conn, post_id, user_id).await?;
+    if !removed {
+        return Err(ApiError::NotFound(anyhow!(
+            "You are not mentioned in this post"
+        )));
+    }
+    Ok(())
+}
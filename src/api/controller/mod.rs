@@ -1,3 +1,14 @@
+pub mod admin;
+pub mod api_keys;
 pub mod comments;
+pub mod export;
+pub mod login_throttling;
+pub mod media;
+pub mod mentions;
+pub mod oidc;
+pub mod places;
 pub mod posts;
+pub mod sessions;
+pub mod tags;
+pub mod two_factor;
 pub mod user;
@@ -0,0 +1,196 @@
+use anyhow::anyhow;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use secrecy::{ExposeSecret, Secret};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::api::{
+    configuration::AuthSettings,
+    controller::user,
+    database,
+    models::{
+        error::{ApiError, Result},
+        password,
+        token::{self, JwtKeys},
+        AuthUser, ClientInfo, CreateUser, LoginResponse, OidcCallback, OidcLogin,
+    },
+    oidc_client::{IdTokenClaims, OidcClient},
+};
+
+const LOGIN_LIFETIME_MINUTES: i64 = 10;
+
+/// Starts an authorization code + PKCE login, returning the provider URL to redirect to
+pub async fn start_login(
+    provider: &str,
+    oidc_client: &OidcClient,
+    conn: &PgPool,
+) -> Result<String> {
+    let state = token::generate_opaque_token();
+    let login = OidcLogin {
+        nonce: token::generate_opaque_token(),
+        code_verifier: token::generate_opaque_token(),
+    };
+    let code_challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(login.code_verifier.as_bytes()));
+
+    let authorization_url = oidc_client
+        .authorization_url(provider, &state, &login.nonce, &code_challenge)
+        .await?;
+    database::insert_oidc_login(
+        conn,
+        &token::hash_token(&state),
+        provider,
+        &login,
+        chrono::Duration::minutes(LOGIN_LIFETIME_MINUTES).num_seconds() as f64,
+    )
+    .await?;
+
+    Ok(authorization_url)
+}
+
+pub async fn finish_login(
+    provider: &str,
+    callback: OidcCallback,
+    client: &ClientInfo,
+    keys: &JwtKeys,
+    settings: &AuthSettings,
+    oidc_client: &OidcClient,
+    conn: &PgPool,
+) -> Result<LoginResponse> {
+    let login = database::use_oidc_login(conn, &token::hash_token(&callback.state), provider)
+        .await?
+        .ok_or(ApiError::BadRequest(anyhow!("Invalid or expired login")))?;
+    if let Some(error) = callback.error {
+        return Err(ApiError::BadRequest(anyhow!(
+            "The identity provider returned an error: {}",
+            error
+        )));
+    }
+    let code = callback
+        .code
+        .ok_or(ApiError::BadRequest(anyhow!("Missing authorization code")))?;
+
+    let claims = oidc_client
+        .exchange_code(provider, &code, &login.code_verifier, &login.nonce)
+        .await?;
+    let auth_user = find_or_create_user(provider, claims, settings, conn).await?;
+
+    user::complete_login(auth_user, client, keys, conn).await
+}
+
+async fn find_or_create_user(
+    provider: &str,
+    claims: IdTokenClaims,
+    settings: &AuthSettings,
+    conn: &PgPool,
+) -> Result<AuthUser> {
+    // Returning users are recognised by the provider's subject, which never changes
+    if let Some(user_id) = database::get_user_id_by_identity(conn, provider, &claims.sub).await? {
+        let user = database::get_user_by_id(conn, &user_id)
+            .await?
+            .ok_or(ApiError::NotFound(anyhow!("User does not exist")))?;
+        return Ok(user.into());
+    }
+
+    let email = claims
+        .email
+        .as_deref()
+        .map(str::to_lowercase)
+        .ok_or(ApiError::BadRequest(anyhow!(
+            "The identity provider did not share an email address"
+        )))?;
+
+    // Only link to an existing account when the provider vouches for the address,
+    // otherwise anyone could take over an account by claiming its email
+    if let Some(existing_user) = database::get_user_by_email(conn, &email).await? {
+        if !claims.email_verified {
+            return Err(ApiError::BadRequest(anyhow!(
+                "An account with this email already exists"
+            )));
+        }
+        let user_id = parse_user_id(&existing_user.id)?;
+        database::insert_user_identity(conn, provider, &claims.sub, &user_id, Some(&email)).await?;
+        return Ok(existing_user.into());
+    }
+
+    let user_id = Uuid::new_v4();
+    let (first_name, last_name) = names(&claims);
+    let new_user = CreateUser {
+        username: available_username(&email, conn).await?,
+        email: email.clone(),
+        first_name,
+        last_name,
+        description: String::new(),
+        // Nobody knows this password, the account signs in through the provider
+        password: token::generate_opaque_token(),
+    };
+    let hashed_pwd = password::hash_password(
+        Secret::new(new_user.password.clone()),
+        &settings.password_hashing,
+    )
+    .await?;
+    database::insert_user(
+        conn,
+        &user_id,
+        hashed_pwd.expose_secret().clone(),
+        &new_user,
+    )
+    .await?;
+    if claims.email_verified {
+        database::mark_email_verified(conn, &user_id, &email).await?;
+    }
+    database::insert_user_identity(conn, provider, &claims.sub, &user_id, Some(&email)).await?;
+
+    let user = database::get_user_by_id(conn, &user_id)
+        .await?
+        .ok_or(ApiError::InternalServer(anyhow!("Failed to create user")))?;
+    Ok(user.into())
+}
+
+fn names(claims: &IdTokenClaims) -> (String, String) {
+    match (&claims.given_name, &claims.family_name, &claims.name) {
+        (Some(given_name), family_name, _) => {
+            (given_name.clone(), family_name.clone().unwrap_or_default())
+        }
+        (None, _, Some(name)) => match name.split_once(' ') {
+            Some((first, last)) => (first.to_string(), last.to_string()),
+            None => (name.clone(), String::new()),
+        },
+        _ => (String::new(), String::new()),
+    }
+}
+
+// Usernames are at most 20 characters, so keep 13 of the email's local part and add a
+// random suffix until one is free
+async fn available_username(email: &str, conn: &PgPool) -> Result<String> {
+    let mut base: String = email
+        .split('@')
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '_' || *c == '.')
+        .take(13)
+        .collect();
+    if base.is_empty() {
+        base = "user".to_string();
+    }
+
+    for _ in 0..5 {
+        let suffix = &token::generate_opaque_token()[..6];
+        let username = format!("{}_{}", base, suffix).to_lowercase();
+        if database::get_user_by_username(conn, &username)
+            .await?
+            .is_none()
+            && !database::is_username_reserved(conn, &username).await?
+        {
+            return Ok(username);
+        }
+    }
+    Err(ApiError::InternalServer(anyhow!(
+        "Failed to find an available username"
+    )))
+}
+
+fn parse_user_id(user_id: &str) -> Result<Uuid> {
+    Uuid::parse_str(user_id).map_err(|err| ApiError::InternalServer(anyhow!(err)))
+}
@@ -0,0 +1,137 @@
+use anyhow::{anyhow, Context};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::api::{
+    database,
+    models::{
+        error::{ApiError, Result},
+        CreatePlace, CreatePost, Page, Pagination, Place, PlaceDetails, Post,
+    },
+};
+
+/// How many of the place's posts its page shows, the rest are under its posts
+const RECENT_POSTS: i64 = 10;
+
+const TOP_CONTRIBUTORS: i64 = 5;
+
+/// The place a new post is made at
+///
+/// An explicitly chosen place must exist, otherwise the post's location is looked up by
+/// name and added as a new place if nobody has posted from it before.
+pub async fn resolve_post_place(post: &CreatePost, conn: &PgPool) -> Result<Uuid> {
+    if let Some(place_id) = &post.place_id {
+        let place_id = Uuid::parse_str(place_id)
+            .context("Failed to convert place id to UUID")
+            .map_err(ApiError::BadRequest)?;
+        database::get_place_by_id(conn, &place_id, None)
+            .await?
+            .ok_or(ApiError::BadRequest(anyhow!("Place does not exist")))?;
+        return Ok(place_id);
+    }
+
+    let place = CreatePlace {
+        name: post.location.clone(),
+        country_code: post.country_code.clone(),
+        latitude: post.latitude,
+        longitude: post.longitude,
+        category: None,
+    };
+    database::find_or_insert_place(conn, &place).await
+}
+
+/// The place an edited post moves to, keeping the country and coordinates it had
+pub async fn resolve_edited_place(location: &str, post: &Post, conn: &PgPool) -> Result<Uuid> {
+    let place = CreatePlace {
+        name: location.to_string(),
+        country_code: post.country_code.clone(),
+        latitude: post.latitude,
+        longitude: post.longitude,
+        category: None,
+    };
+    database::find_or_insert_place(conn, &place).await
+}
+
+pub async fn create_place(place: CreatePlace, conn: &PgPool) -> Result<Place> {
+    if place.latitude.is_some() != place.longitude.is_some() {
+        return Err(ApiError::BadRequest(anyhow!(
+            "Latitude and longitude must be given together"
+        )));
+    }
+
+    let place_id = database::insert_place(conn, &place)
+        .await?
+        .ok_or(ApiError::BadRequest(anyhow!("This place already exists")))?;
+    database::get_place_by_id(conn, &place_id, None)
+        .await?
+        .ok_or(ApiError::InternalServer(anyhow!(
+            "Created place is missing"
+        )))
+}
+
+#[tracing::instrument("Controller: Get a place", skip(conn))]
+pub async fn get_place(
+    place_id: &Uuid,
+    viewer_id: Option<Uuid>,
+    conn: &PgPool,
+) -> Result<PlaceDetails> {
+    let viewer_id = viewer_id.as_ref();
+    let place = database::get_place_by_id(conn, place_id, viewer_id)
+        .await?
+        .ok_or(ApiError::NotFound(anyhow!("Place does not exist")))?;
+
+    let num_visitors = database::count_place_visitors(conn, place_id, viewer_id).await?;
+    let top_contributors =
+        database::get_top_contributors(conn, place_id, viewer_id, TOP_CONTRIBUTORS).await?;
+    let recent = Pagination {
+        page: 1,
+        per_page: RECENT_POSTS,
+    };
+    let mut recent_posts = database::get_place_posts(conn, place_id, viewer_id, &recent).await?;
+    recent_posts.truncate(RECENT_POSTS as usize);
+
+    Ok(PlaceDetails {
+        place,
+        num_visitors,
+        top_contributors,
+        recent_posts,
+    })
+}
+
+#[tracing::instrument("Controller: Get a place's posts", skip(conn))]
+pub async fn get_place_posts(
+    place_id: &Uuid,
+    viewer_id: Option<Uuid>,
+    pagination: Pagination,
+    conn: &PgPool,
+) -> Result<Page<Post>> {
+    database::get_place_by_id(conn, place_id, viewer_id.as_ref())
+        .await?
+        .ok_or(ApiError::NotFound(anyhow!("Place does not exist")))?;
+
+    let posts = database::get_place_posts(conn, place_id, viewer_id.as_ref(), &pagination).await?;
+    Ok(Page::new(posts, &pagination))
+}
+
+/// Places matching what has been typed so far, for autocompletion
+pub async fn search_places(
+    query: &str,
+    limit: u32,
+    viewer_id: Option<Uuid>,
+    conn: &PgPool,
+) -> Result<Vec<Place>> {
+    // Matched the same way names are stored, with `LIKE` wildcards taken literally
+    let query = query
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+        .to_lowercase()
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    database::search_places(conn, &query, viewer_id.as_ref(), limit as i64).await
+}
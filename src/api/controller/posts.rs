@@ -3,41 +3,156 @@ use sqlx::PgPool;
 use uuid::Uuid;
 
 use crate::api::{
-    database,
+    blob_store::BlobStore,
+    configuration::UnverifiedAccountPolicy,
+    controller, database,
     models::{
         error::{ApiError, Result},
-        CreatePost, Like, Post,
+        BoundingBox, CreatePost, Like, NearbyPost, Page, Pagination, Post, PostEdit, UpdatePost,
     },
 };
 
-pub async fn get_likes_of_post(post_id: &Uuid, conn: &PgPool) -> Result<Vec<Like>> {
-    // Check that the post exists
-    let post = database::get_post_by_id(conn, post_id).await?;
-    if post.is_none() {
-        return Err(ApiError::NotFound(anyhow!("Post does not exist")));
-    }
+pub async fn get_likes_of_post(
+    post_id: &Uuid,
+    viewer_id: Option<Uuid>,
+    conn: &PgPool,
+) -> Result<Vec<Like>> {
+    // Check that the post exists and its author's posts are visible to the viewer
+    get_post(post_id, viewer_id, conn).await?;
     // Get the likes of the post
     let like = database::get_likes_of_post(conn, post_id).await?;
     Ok(like)
 }
 
 #[tracing::instrument("Controller: Get a users posts", skip(conn))]
-pub async fn get_users_post(conn: &PgPool, user_id: String) -> Result<Vec<Post>> {
+pub async fn get_users_post(
+    conn: &PgPool,
+    user_id: String,
+    viewer_id: Option<Uuid>,
+) -> Result<Vec<Post>> {
     let user_id = Uuid::parse_str(&user_id)
         .context("Failed to convert user id to UUID")
         .map_err(ApiError::BadRequest)?;
-    // Check if the user exists
-    let user = database::get_user_by_id(conn, &user_id).await?;
-    if user.is_none() {
-        return Err(ApiError::NotFound(anyhow::anyhow!("User does not exist")));
-    }
+    // Check if the user exists, deactivated accounts are hidden
+    let user = database::get_user_by_id(conn, &user_id)
+        .await?
+        .filter(|user| !user.deactivated)
+        .ok_or(ApiError::NotFound(anyhow::anyhow!("User does not exist")))?;
+    // Private accounts only show their posts to followers
+    controller::user::ensure_can_view(&user, viewer_id.as_ref(), conn).await?;
     // Get all posts by the user
     let posts = database::get_users_posts(conn, &user_id).await?;
     Ok(posts)
 }
 
-pub async fn create_post(conn: &PgPool, user_id: Uuid, post: CreatePost) -> Result<String> {
-    let post_id = database::insert_post(conn, user_id, post).await?;
+/// A single post, as long as its author's posts are visible to the viewer
+pub async fn get_post(post_id: &Uuid, viewer_id: Option<Uuid>, conn: &PgPool) -> Result<Post> {
+    let post = database::get_post_by_id(conn, post_id)
+        .await?
+        .ok_or(ApiError::NotFound(anyhow!("Post does not exist")))?;
+    // Posts of deactivated accounts are hidden along with the account
+    let author = database::get_user_by_id(conn, &author_id(&post)?)
+        .await?
+        .filter(|author| !author.deactivated)
+        .ok_or(ApiError::NotFound(anyhow!("Post does not exist")))?;
+    controller::user::ensure_can_view(&author, viewer_id.as_ref(), conn).await?;
+
+    Ok(post)
+}
+
+/// Earlier versions of a post, visible to whoever can see the post itself
+pub async fn get_post_history(
+    post_id: &Uuid,
+    viewer_id: Option<Uuid>,
+    conn: &PgPool,
+) -> Result<Vec<PostEdit>> {
+    get_post(post_id, viewer_id, conn).await?;
+
+    let edits = database::get_post_edits(conn, post_id).await?;
+    Ok(edits)
+}
+
+pub async fn update_post(
+    user_id: &Uuid,
+    post_id: &Uuid,
+    update: UpdatePost,
+    conn: &PgPool,
+) -> Result<Post> {
+    let post = get_own_post(user_id, post_id, "edit", conn).await?;
+    if update.is_empty() {
+        return Ok(post);
+    }
+
+    // The tags and mentions follow the post as it will be after the edit
+    let content = update.content.as_deref().unwrap_or(&post.content);
+    let tags =
+        controller::tags::post_hashtags(update.title.as_deref().unwrap_or(&post.title), content);
+    let mentions = controller::mentions::resolve_mentions(user_id, content, conn).await?;
+    let place_id = match &update.location {
+        Some(location) => {
+            Some(controller::places::resolve_edited_place(location, &post, conn).await?)
+        }
+        None => None,
+    };
+    database::update_post(conn, post_id, &update, place_id.as_ref(), &tags, &mentions).await?;
+
+    database::get_post_by_id(conn, post_id)
+        .await?
+        .ok_or(ApiError::NotFound(anyhow!("Post does not exist")))
+}
+
+pub async fn delete_post(
+    user_id: &Uuid,
+    post_id: &Uuid,
+    blob_store: &dyn BlobStore,
+    conn: &PgPool,
+) -> Result<()> {
+    let post = get_own_post(user_id, post_id, "delete", conn).await?;
+
+    // Comments, likes and the edit history go with the post
+    database::delete_post(conn, post_id).await?;
+    controller::media::delete_post_media(&post.media, blob_store).await;
+
+    Ok(())
+}
+
+pub async fn create_post(
+    conn: &PgPool,
+    user_id: Uuid,
+    mut post: CreatePost,
+    policy: &UnverifiedAccountPolicy,
+) -> Result<String> {
+    // Check that the user exists and is allowed to post
+    let user = database::get_user_by_id(conn, &user_id)
+        .await?
+        .ok_or(ApiError::NotFound(anyhow!("User does not exist")))?;
+    controller::user::ensure_email_verified(&user, policy.can_post, "posting")?;
+    if post.latitude.is_some() != post.longitude.is_some() {
+        return Err(ApiError::BadRequest(anyhow!(
+            "Latitude and longitude must be given together"
+        )));
+    }
+
+    let media = std::mem::take(&mut post.media)
+        .into_iter()
+        .map(|media| {
+            let media_id = Uuid::parse_str(&media.id)
+                .context("Failed to convert media id to UUID")
+                .map_err(ApiError::BadRequest)?;
+            Ok((media_id, media.alt_text))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let tags = controller::tags::post_hashtags(&post.title, &post.content);
+    let mentions = controller::mentions::resolve_mentions(&user_id, &post.content, conn).await?;
+
+    let place_id = controller::places::resolve_post_place(&post, conn).await?;
+
+    let post_id = database::insert_post(conn, user_id, post, &place_id, &media, &tags, &mentions)
+        .await?
+        .ok_or(ApiError::BadRequest(anyhow!(
+            "Media does not exist or is already attached to a post"
+        )))?;
     Ok(post_id)
 }
 
@@ -54,17 +169,54 @@ pub async fn get_users_feed(conn: &PgPool, user_id: Uuid) -> Result<Vec<Post>> {
     Ok(feed)
 }
 
+/// Posts within `radius_km` of the point, closest first
+pub async fn get_nearby_posts(
+    latitude: f64,
+    longitude: f64,
+    radius_km: f64,
+    viewer_id: Option<Uuid>,
+    pagination: Pagination,
+    conn: &PgPool,
+) -> Result<Page<NearbyPost>> {
+    let posts = database::get_nearby_posts(
+        conn,
+        latitude,
+        longitude,
+        radius_km,
+        viewer_id.as_ref(),
+        &pagination,
+    )
+    .await?;
+    Ok(Page::new(posts, &pagination))
+}
+
+/// Posts inside a map viewport, newest first
+pub async fn get_posts_in_bounds(
+    bounds: BoundingBox,
+    viewer_id: Option<Uuid>,
+    pagination: Pagination,
+    conn: &PgPool,
+) -> Result<Page<Post>> {
+    if bounds.min_latitude > bounds.max_latitude {
+        return Err(ApiError::BadRequest(anyhow!(
+            "The minimum latitude can't be north of the maximum"
+        )));
+    }
+
+    let posts =
+        database::get_posts_in_bounds(conn, &bounds, viewer_id.as_ref(), &pagination).await?;
+    Ok(Page::new(posts, &pagination))
+}
+
 pub async fn like_a_post(user_id: &Uuid, post_id: &Uuid, conn: &PgPool) -> Result<()> {
     // Check that the user exists
     let user = database::get_user_by_id(conn, user_id).await?;
     if user.is_none() {
         return Err(ApiError::NotFound(anyhow!("User does not exist")));
     }
-    // Check that the post exists
-    let post = database::get_post_by_id(conn, post_id).await?;
-    if post.is_none() {
-        return Err(ApiError::NotFound(anyhow!("Post does not exist")));
-    }
+    // Check that the post exists and its author's posts are visible to the user
+    let post = get_post(post_id, Some(*user_id), conn).await?;
+    controller::user::ensure_not_blocked(user_id, &author_id(&post)?, conn).await?;
     // Check that the user has not already liked the post
     let like = database::get_like_by_user_and_post(conn, user_id, post_id).await?;
     if like.is_some() {
@@ -99,3 +251,21 @@ pub async fn unlike_a_post(user_id: &Uuid, post_id: &Uuid, conn: &PgPool) -> Res
 
     Ok(())
 }
+
+/// The post, if the user wrote it, `action` naming what they are trying to do with it
+async fn get_own_post(user_id: &Uuid, post_id: &Uuid, action: &str, conn: &PgPool) -> Result<Post> {
+    let post = database::get_post_by_id(conn, post_id)
+        .await?
+        .ok_or(ApiError::NotFound(anyhow!("Post does not exist")))?;
+    if author_id(&post)? != *user_id {
+        return Err(ApiError::Forbidden(anyhow!(
+            "You can only {} your own posts",
+            action
+        )));
+    }
+    Ok(post)
+}
+
+pub(crate) fn author_id(post: &Post) -> Result<Uuid> {
+    Uuid::parse_str(&post.author).map_err(|err| ApiError::InternalServer(anyhow!(err)))
+}
@@ -0,0 +1,112 @@
+use anyhow::anyhow;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::api::{
+    database,
+    models::{
+        error::{ApiError, Result},
+        token::{self, JwtKeys, TokenPair},
+        ClientInfo, SessionInfo,
+    },
+};
+
+/// Start a new session for the user and issue its first access and refresh tokens
+pub async fn start_session(
+    user_id: &Uuid,
+    client: &ClientInfo,
+    keys: &JwtKeys,
+    conn: &PgPool,
+) -> Result<TokenPair> {
+    // Signing in during the deletion grace period cancels the deletion
+    if database::restore_user(conn, user_id).await? {
+        tracing::info!("Restored deactivated account {}", user_id);
+    }
+
+    let session_id = Uuid::new_v4();
+    database::insert_session(conn, &session_id, user_id, client).await?;
+    issue_tokens(user_id, &session_id, keys, conn).await
+}
+
+async fn issue_tokens(
+    user_id: &Uuid,
+    session_id: &Uuid,
+    keys: &JwtKeys,
+    conn: &PgPool,
+) -> Result<TokenPair> {
+    let refresh_token = token::generate_opaque_token();
+    database::insert_refresh_token(
+        conn,
+        &token::hash_token(&refresh_token),
+        session_id,
+        keys.refresh_token_lifetime.num_seconds() as f64,
+    )
+    .await?;
+    let roles = database::get_user_roles(conn, user_id).await?;
+    let bearer = token::generate_token(&user_id.to_string(), &session_id.to_string(), roles, keys)?;
+
+    Ok(TokenPair {
+        bearer,
+        refresh_token,
+    })
+}
+
+pub async fn refresh(refresh_token: &str, keys: &JwtKeys, conn: &PgPool) -> Result<TokenPair> {
+    let token = database::use_refresh_token(conn, &token::hash_token(refresh_token))
+        .await?
+        .ok_or(ApiError::Unauthorized(anyhow!("Invalid refresh token")))?;
+
+    // A refresh token that was already rotated is being replayed, so it may have been
+    // stolen. Revoke the whole session to log out both the attacker and the user.
+    if token.used {
+        database::revoke_session(conn, &token.session_id).await?;
+        return Err(ApiError::Unauthorized(anyhow!(
+            "Refresh token has already been used"
+        )));
+    }
+    if token.expired {
+        return Err(ApiError::Unauthorized(anyhow!("Refresh token has expired")));
+    }
+
+    let session = database::get_session_by_id(conn, &token.session_id)
+        .await?
+        .filter(|session| session.revoked_at.is_none())
+        .ok_or(ApiError::Unauthorized(anyhow!("Session has been revoked")))?;
+    database::touch_session(conn, &session.id).await?;
+
+    issue_tokens(&session.user_id, &session.id, keys, conn).await
+}
+
+pub async fn logout(session_id: &Uuid, conn: &PgPool) -> Result<()> {
+    database::revoke_session(conn, session_id).await
+}
+
+pub async fn logout_all(user_id: &Uuid, conn: &PgPool) -> Result<()> {
+    database::revoke_all_sessions(conn, user_id).await
+}
+
+pub async fn get_sessions(
+    user_id: &Uuid,
+    current_session_id: &Uuid,
+    conn: &PgPool,
+) -> Result<Vec<SessionInfo>> {
+    let sessions = database::get_active_sessions(conn, user_id)
+        .await?
+        .into_iter()
+        .map(|session| SessionInfo::new(session, current_session_id))
+        .collect::<Vec<SessionInfo>>();
+
+    Ok(sessions)
+}
+
+pub async fn revoke_session(user_id: &Uuid, session_id: &Uuid, conn: &PgPool) -> Result<()> {
+    // Check that the session exists and belongs to the user
+    let session = database::get_session_by_id(conn, session_id)
+        .await?
+        .filter(|session| session.user_id == *user_id && session.revoked_at.is_none());
+    if session.is_none() {
+        return Err(ApiError::NotFound(anyhow!("Session does not exist")));
+    }
+
+    database::revoke_session(conn, session_id).await
+}
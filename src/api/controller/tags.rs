@@ -0,0 +1,116 @@
+use anyhow::anyhow;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::api::{
+    database,
+    models::{
+        error::{ApiError, Result},
+        FollowedTag, Page, Pagination, Post, TrendingTag,
+    },
+};
+
+/// Longest hashtag that is recognised, longer ones are ignored rather than cut short
+const MAX_TAG_LENGTH: usize = 50;
+
+/// The hashtags in the text, lowercased and in the order they first appear
+///
+/// A hashtag is a `#` followed by letters, digits and underscores, as long as the `#`
+/// doesn't follow one of those itself, so `C#` and URL fragments like `page#top` aren't
+/// mistaken for tags. Tags made only of digits, like `#1`, aren't tags either.
+pub fn extract_hashtags(text: &str) -> Vec<String> {
+    let mut tags = Vec::<String>::new();
+    let mut previous: Option<char> = None;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '#' && !previous.is_some_and(is_tag_char) {
+            let mut tag = String::new();
+            while let Some(&next) = chars.peek() {
+                if !is_tag_char(next) {
+                    break;
+                }
+                tag.push(next);
+                chars.next();
+            }
+            previous = tag.chars().last().or(Some(c));
+
+            if let Some(tag) = normalize_tag(&tag) {
+                if !tags.contains(&tag) {
+                    tags.push(tag);
+                }
+            }
+            continue;
+        }
+        previous = Some(c);
+    }
+    tags
+}
+
+/// The hashtags of a post, taken from its title as well as its content
+pub fn post_hashtags(title: &str, content: &str) -> Vec<String> {
+    extract_hashtags(&format!("{}\n{}", title, content))
+}
+
+fn is_tag_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// The tag in its stored form, or `None` if it isn't a valid hashtag
+///
+/// A leading `#` is allowed so that tags from paths can be given either way.
+fn normalize_tag(tag: &str) -> Option<String> {
+    let tag = tag.strip_prefix('#').unwrap_or(tag);
+    let valid =
+        !tag.is_empty() && tag.chars().all(is_tag_char) && !tag.chars().all(|c| c.is_ascii_digit());
+    // Lowercasing can lengthen a tag, e.g. `İ` becomes two characters, so the stored form
+    // is what has to fit
+    let lowercase = tag.to_lowercase();
+    (valid && lowercase.chars().count() <= MAX_TAG_LENGTH).then_some(lowercase)
+}
+
+#[tracing::instrument("Controller: Get a tag's posts", skip(conn))]
+pub async fn get_tag_posts(
+    tag: &str,
+    viewer_id: Option<Uuid>,
+    pagination: Pagination,
+    conn: &PgPool,
+) -> Result<Page<Post>> {
+    let tag = normalize_tag(tag).ok_or(ApiError::NotFound(anyhow!("Tag does not exist")))?;
+
+    let posts = database::get_tag_posts(conn, &tag, viewer_id.as_ref(), &pagination).await?;
+    Ok(Page::new(posts, &pagination))
+}
+
+pub async fn get_trending_tags(
+    window_hours: u32,
+    limit: u32,
+    conn: &PgPool,
+) -> Result<Vec<TrendingTag>> {
+    let window_seconds = f64::from(window_hours) * 60.0 * 60.0;
+    let tags = database::get_trending_tags(conn, window_seconds, i64::from(limit)).await?;
+    Ok(tags)
+}
+
+pub async fn follow_tag(user_id: &Uuid, tag: &str, conn: &PgPool) -> Result<()> {
+    let tag = normalize_tag(tag).ok_or(ApiError::BadRequest(anyhow!("Invalid hashtag")))?;
+
+    database::follow_tag(conn, user_id, &tag).await?;
+    Ok(())
+}
+
+pub async fn unfollow_tag(user_id: &Uuid, tag: &str, conn: &PgPool) -> Result<()> {
+    let tag = normalize_tag(tag).ok_or(ApiError::BadRequest(anyhow!("Invalid hashtag")))?;
+
+    let unfollowed = database::unfollow_tag(conn, user_id, &tag).await?;
+    if !unfollowed {
+        return Err(ApiError::NotFound(anyhow!(
+            "You are not following this tag"
+        )));
+    }
+    Ok(())
+}
+
+pub async fn get_followed_tags(user_id: &Uuid, conn: &PgPool) -> Result<Vec<FollowedTag>> {
+    let tags = database::get_followed_tags(conn, user_id).await?;
+    Ok(tags)
+}
@@ -0,0 +1,167 @@
+use anyhow::anyhow;
+use rand::{distributions::Alphanumeric, Rng};
+use sqlx::PgPool;
+use totp_rs::{Algorithm, Secret, TOTP};
+use uuid::Uuid;
+
+use crate::api::{
+    database,
+    models::{
+        error::{ApiError, Result},
+        token, RecoveryCodes, TotpEnrollment, UserTotp,
+    },
+};
+
+const TOTP_DIGITS: usize = 6;
+const TOTP_STEP_SECONDS: u64 = 30;
+const RECOVERY_CODE_COUNT: usize = 10;
+
+pub async fn enroll(user_id: &Uuid, issuer: &str, conn: &PgPool) -> Result<TotpEnrollment> {
+    let user = database::get_user_by_id(conn, user_id)
+        .await?
+        .ok_or(ApiError::NotFound(anyhow!("User does not exist")))?;
+
+    let secret = Secret::generate_secret()
+        .to_bytes()
+        .map_err(|err| ApiError::InternalServer(anyhow!("Failed to generate secret: {err}")))?;
+    let totp = TOTP::new(
+        Algorithm::SHA1,
+        TOTP_DIGITS,
+        1,
+        TOTP_STEP_SECONDS,
+        secret,
+        Some(issuer.to_string()),
+        user.email,
+    )
+    .map_err(|err| ApiError::InternalServer(anyhow!("Failed to set up TOTP: {err}")))?;
+    let secret = totp.get_secret_base32();
+
+    if !database::upsert_pending_totp(conn, user_id, &secret).await? {
+        return Err(ApiError::BadRequest(anyhow!(
+            "Two-factor authentication is already enabled"
+        )));
+    }
+
+    Ok(TotpEnrollment {
+        secret,
+        provisioning_uri: totp.get_url(),
+    })
+}
+
+pub async fn confirm(user_id: &Uuid, code: &str, conn: &PgPool) -> Result<RecoveryCodes> {
+    let user_totp = database::get_user_totp(conn, user_id)
+        .await?
+        .ok_or(ApiError::BadRequest(anyhow!(
+            "Two-factor authentication has not been set up"
+        )))?;
+    if user_totp.confirmed {
+        return Err(ApiError::BadRequest(anyhow!(
+            "Two-factor authentication is already enabled"
+        )));
+    }
+
+    // Only a code from the app proves that it was set up correctly
+    if !verify_totp_code(user_id, &user_totp, code, conn).await? {
+        return Err(ApiError::BadRequest(anyhow!("Invalid two-factor code")));
+    }
+
+    let recovery_codes: Vec<String> = (0..RECOVERY_CODE_COUNT)
+        .map(|_| generate_recovery_code())
+        .collect();
+    let hashes: Vec<String> = recovery_codes
+        .iter()
+        .map(|code| token::hash_token(&normalize_recovery_code(code)))
+        .collect();
+    database::confirm_totp(conn, user_id, &hashes).await?;
+
+    Ok(RecoveryCodes { recovery_codes })
+}
+
+pub async fn disable(user_id: &Uuid, code: &str, conn: &PgPool) -> Result<()> {
+    let user_totp = enabled_totp(user_id, conn)
+        .await?
+        .ok_or(ApiError::BadRequest(anyhow!(
+            "Two-factor authentication is not enabled"
+        )))?;
+
+    if !verify_code(user_id, &user_totp, code, conn).await? {
+        return Err(ApiError::BadRequest(anyhow!("Invalid two-factor code")));
+    }
+
+    database::delete_user_totp(conn, user_id).await?;
+    Ok(())
+}
+
+/// The user's TOTP secret, if they have finished enrolling
+pub async fn enabled_totp(user_id: &Uuid, conn: &PgPool) -> Result<Option<UserTotp>> {
+    let user_totp = database::get_user_totp(conn, user_id)
+        .await?
+        .filter(|totp| totp.confirmed);
+    Ok(user_totp)
+}
+
+/// Checks a code from the authenticator app or, failing that, an unused recovery code
+pub async fn verify_code(
+    user_id: &Uuid,
+    user_totp: &UserTotp,
+    code: &str,
+    conn: &PgPool,
+) -> Result<bool> {
+    if verify_totp_code(user_id, user_totp, code, conn).await? {
+        return Ok(true);
+    }
+
+    let code_hash = token::hash_token(&normalize_recovery_code(code));
+    database::use_recovery_code(conn, user_id, &code_hash).await
+}
+
+async fn verify_totp_code(
+    user_id: &Uuid,
+    user_totp: &UserTotp,
+    code: &str,
+    conn: &PgPool,
+) -> Result<bool> {
+    let code = code.trim();
+    if code.len() != TOTP_DIGITS || !code.chars().all(|c| c.is_ascii_digit()) {
+        return Ok(false);
+    }
+
+    let secret = Secret::Encoded(user_totp.secret.clone())
+        .to_bytes()
+        .map_err(|err| ApiError::InternalServer(anyhow!("Invalid stored TOTP secret: {err}")))?;
+    let totp = TOTP::new_unchecked(
+        Algorithm::SHA1,
+        TOTP_DIGITS,
+        1,
+        TOTP_STEP_SECONDS,
+        secret,
+        None,
+        String::new(),
+    );
+
+    // Accept the previous and next step to allow for clock drift
+    let current_step = chrono::Utc::now().timestamp() as u64 / TOTP_STEP_SECONDS;
+    for step in current_step.saturating_sub(1)..=current_step + 1 {
+        let already_used = user_totp
+            .last_used_step
+            .is_some_and(|last_used| step as i64 <= last_used);
+        if !already_used && totp.generate(step * TOTP_STEP_SECONDS) == code {
+            return database::use_totp_step(conn, user_id, step as i64).await;
+        }
+    }
+
+    Ok(false)
+}
+
+fn generate_recovery_code() -> String {
+    let code: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(10)
+        .map(|c| char::from(c).to_ascii_lowercase())
+        .collect();
+    format!("{}-{}", &code[..5], &code[5..])
+}
+
+fn normalize_recovery_code(code: &str) -> String {
+    code.trim().replace('-', "").to_ascii_lowercase()
+}
@@ -1,24 +1,42 @@
 use anyhow::Context;
-use secrecy::ExposeSecret;
+use secrecy::{ExposeSecret, Secret};
 use sqlx::PgPool;
 use uuid::Uuid;
 
 use crate::api::{
+    blob_store::BlobStore,
+    configuration::{AuthSettings, PasswordHashingSettings},
+    controller::{login_throttling, media, sessions, two_factor},
     database,
+    email_client::{Email, EmailClient},
     models::{
         error::{ApiError, Result},
-        token, AuthInfo, AuthUser, CreateUser, LoginInfo, User,
+        password::{self, PasswordVerification},
+        token::{self, JwtKeys, MfaPendingPayload},
+        AuthInfo, AuthUser, ChangePassword, ChangeUsername, ClientInfo, ConfirmEmail, CreateUser,
+        DeleteAccount, FollowOutcome, FollowRequest, ForgotPassword, ImageKind, LoginInfo,
+        LoginResponse, MfaChallenge, MfaLogin, PublicUser, ResetPassword, UpdateUser, User,
+        UsernameLookup,
     },
 };
 
-pub async fn register(new_user: CreateUser, conn: &PgPool) -> Result<AuthInfo> {
+pub async fn register(
+    new_user: CreateUser,
+    client: &ClientInfo,
+    keys: &JwtKeys,
+    settings: &AuthSettings,
+    base_url: &str,
+    email_client: &dyn EmailClient,
+    conn: &PgPool,
+) -> Result<AuthInfo> {
     // Insert user into db
     let user_id = uuid::Uuid::new_v4();
 
-    // Check if username is already taken
+    // Check if username is already taken, or held for someone who recently changed theirs
     let is_username_taken = database::get_user_by_username(conn, &new_user.username)
         .await?
-        .is_some();
+        .is_some()
+        || database::is_username_reserved(conn, &new_user.username).await?;
 
     if is_username_taken {
         return Err(ApiError::BadRequest(anyhow::anyhow!(
@@ -37,62 +55,578 @@ pub async fn register(new_user: CreateUser, conn: &PgPool) -> Result<AuthInfo> {
         )));
     }
 
-    let hashed_pwd = pwhash::bcrypt::hash(&new_user.password)
-        .context("Failed to hash password for new user.")
-        .map_err(ApiError::InternalServer)?;
-
-    database::insert_user(conn, &user_id, hashed_pwd, &new_user).await?;
+    let hashed_pwd = password::hash_password(
+        Secret::new(new_user.password.clone()),
+        &settings.password_hashing,
+    )
+    .await?;
+
+    database::insert_user(
+        conn,
+        &user_id,
+        hashed_pwd.expose_secret().clone(),
+        &new_user,
+    )
+    .await?;
+
+    // The account exists at this point, so a failed email shouldn't fail the signup.
+    // The user can ask for a new confirmation email instead.
+    if let Err(err) = send_verification_email(
+        &user_id,
+        &new_user.username,
+        &new_user.email,
+        chrono::Duration::hours(settings.email_verification_expiration_hours),
+        base_url,
+        email_client,
+        conn,
+    )
+    .await
+    {
+        tracing::error!("Failed to send confirmation email: {:?}", err);
+    }
 
     // Generate JWT
-    let jwt = token::generate_token(&user_id.to_string())?;
+    let tokens = sessions::start_session(&user_id, client, keys, conn).await?;
 
     Ok(AuthInfo {
-        bearer: jwt,
+        bearer: tokens.bearer,
+        refresh_token: tokens.refresh_token,
         user: AuthUser {
             id: user_id.to_string(),
             username: new_user.username,
             email: new_user.email,
             name: format!("{} {}", new_user.first_name, new_user.last_name),
             description: new_user.description,
+            show_email: false,
+            is_private: false,
+            avatar: None,
+            cover: None,
         },
     })
 }
 
-pub async fn login(login: LoginInfo, conn: &PgPool) -> Result<AuthInfo> {
+pub async fn login(
+    login: LoginInfo,
+    client: &ClientInfo,
+    keys: &JwtKeys,
+    settings: &AuthSettings,
+    conn: &PgPool,
+) -> Result<LoginResponse> {
+    login_throttling::ensure_not_locked(&login.email, client, conn).await?;
+
     // Check if user exists
     let user: Option<User> = database::get_user_by_email(conn, &login.email).await?;
-    println!("{:?}", user);
     let auth_user: AuthUser = if let Some(user) = user {
         // Check password
-        if !pwhash::bcrypt::verify(login.password, user.password.expose_secret()) {
-            return Err(ApiError::BadRequest(anyhow::anyhow!(
-                "Email or Password is incorrect".to_string()
-            )));
+        let password = Secret::new(login.password);
+        let hashing = &settings.password_hashing;
+        match password::verify_password(password.clone(), user.password.clone(), hashing).await? {
+            PasswordVerification::Invalid => {
+                login_throttling::record_failure(
+                    &login.email,
+                    client,
+                    &settings.login_throttling,
+                    conn,
+                )
+                .await?;
+                return Err(ApiError::BadRequest(anyhow::anyhow!(
+                    "Email or Password is incorrect".to_string()
+                )));
+            }
+            PasswordVerification::Valid => {}
+            PasswordVerification::ValidNeedsRehash => {
+                // The login itself shouldn't fail if the upgrade does, it'll be retried next time
+                if let Err(err) = rehash_password(&user.id, password, hashing, conn).await {
+                    tracing::warn!("Failed to rehash password: {:?}", err);
+                }
+            }
         }
         user.into()
     } else {
+        // Unknown emails count too, so that the response doesn't reveal which accounts exist
+        login_throttling::record_failure(&login.email, client, &settings.login_throttling, conn)
+            .await?;
         return Err(ApiError::BadRequest(anyhow::anyhow!(
             "Email or Password is incorrect".to_string()
         )));
     };
 
+    complete_login(auth_user, client, keys, conn).await
+}
+
+/// Finishes a login once the user's first factor, their password or an external
+/// identity provider, has been checked
+pub async fn complete_login(
+    auth_user: AuthUser,
+    client: &ClientInfo,
+    keys: &JwtKeys,
+    conn: &PgPool,
+) -> Result<LoginResponse> {
+    let user_id = Uuid::parse_str(&auth_user.id)
+        .context("Failed to parse user id")
+        .map_err(ApiError::InternalServer)?;
+
+    // With 2FA enabled the first factor alone only gets a token for the second step,
+    // and the failed attempts stay on record until that step succeeds too
+    if two_factor::enabled_totp(&user_id, conn).await?.is_some() {
+        return Ok(LoginResponse::MfaRequired(MfaChallenge {
+            mfa_token: token::generate_mfa_token(&auth_user.id, keys)?,
+        }));
+    }
+    login_throttling::clear_failures(&auth_user.email, conn).await?;
+
     // Generate JWT
-    let token = token::generate_token(&auth_user.id)?;
+    let tokens = sessions::start_session(&user_id, client, keys, conn).await?;
 
-    Ok(AuthInfo {
-        bearer: token,
+    Ok(LoginResponse::Authenticated(Box::new(AuthInfo {
+        bearer: tokens.bearer,
+        refresh_token: tokens.refresh_token,
         user: auth_user,
+    })))
+}
+
+pub async fn complete_mfa_login(
+    mfa: MfaLogin,
+    client: &ClientInfo,
+    keys: &JwtKeys,
+    settings: &AuthSettings,
+    conn: &PgPool,
+) -> Result<AuthInfo> {
+    let payload = keys.decode::<MfaPendingPayload>(&mfa.mfa_token)?;
+    if !payload.mfa_pending {
+        return Err(ApiError::Unauthorized(anyhow::anyhow!("Invalid MFA token")));
+    }
+    let user_id = Uuid::parse_str(&payload.user_id)
+        .context("Failed to parse user id")
+        .map_err(ApiError::Unauthorized)?;
+
+    let user = database::get_user_by_id(conn, &user_id)
+        .await?
+        .ok_or(ApiError::Unauthorized(anyhow::anyhow!("Invalid MFA token")))?;
+    login_throttling::ensure_not_locked(&user.email, client, conn).await?;
+    let user_totp =
+        two_factor::enabled_totp(&user_id, conn)
+            .await?
+            .ok_or(ApiError::Unauthorized(anyhow::anyhow!(
+                "Two-factor authentication is not enabled"
+            )))?;
+
+    if !two_factor::verify_code(&user_id, &user_totp, &mfa.code, conn).await? {
+        login_throttling::record_failure(&user.email, client, &settings.login_throttling, conn)
+            .await?;
+        return Err(ApiError::BadRequest(anyhow::anyhow!(
+            "Invalid two-factor code"
+        )));
+    }
+    login_throttling::clear_failures(&user.email, conn).await?;
+
+    let tokens = sessions::start_session(&user_id, client, keys, conn).await?;
+
+    Ok(AuthInfo {
+        bearer: tokens.bearer,
+        refresh_token: tokens.refresh_token,
+        user: user.into(),
+    })
+}
+
+async fn send_verification_email(
+    user_id: &Uuid,
+    username: &str,
+    email: &str,
+    verification_token_lifetime: chrono::Duration,
+    base_url: &str,
+    email_client: &dyn EmailClient,
+    conn: &PgPool,
+) -> Result<()> {
+    let verification_token = token::generate_opaque_token();
+    database::insert_email_verification_token(
+        conn,
+        &token::hash_token(&verification_token),
+        user_id,
+        email,
+        verification_token_lifetime.num_seconds() as f64,
+    )
+    .await?;
+
+    let confirmation_link = format!("{}/users/confirm?token={}", base_url, verification_token);
+    email_client
+        .send_email(Email {
+            recipient: email.to_string(),
+            subject: "Confirm your Voyage Atlas email address".to_string(),
+            html_content: format!(
+                "<p>Hi {},</p><p>Follow <a href=\"{}\">this link</a> to confirm your email address.</p>",
+                username, confirmation_link
+            ),
+            text_content: format!(
+                "Hi {},\n\nVisit {} to confirm your email address.",
+                username, confirmation_link
+            ),
+        })
+        .await
+        .map_err(ApiError::InternalServer)?;
+
+    Ok(())
+}
+
+pub async fn confirm_email(confirm: ConfirmEmail, conn: &PgPool) -> Result<()> {
+    let invalid_token = || {
+        ApiError::BadRequest(anyhow::anyhow!(
+            "Invalid or expired email verification token"
+        ))
+    };
+    let (user_id, email) =
+        database::use_email_verification_token(conn, &token::hash_token(&confirm.token))
+            .await?
+            .ok_or_else(invalid_token)?;
+
+    // The token only confirms the address it was sent to
+    if !database::mark_email_verified(conn, &user_id, &email).await? {
+        return Err(invalid_token());
+    }
+
+    Ok(())
+}
+
+pub async fn resend_verification_email(
+    user_id: &Uuid,
+    verification_token_lifetime: chrono::Duration,
+    base_url: &str,
+    email_client: &dyn EmailClient,
+    conn: &PgPool,
+) -> Result<()> {
+    let user = database::get_user_by_id(conn, user_id)
+        .await?
+        .ok_or(ApiError::NotFound(anyhow::anyhow!("User does not exist")))?;
+
+    if user.email_verified {
+        return Err(ApiError::BadRequest(anyhow::anyhow!(
+            "Email is already verified"
+        )));
+    }
+
+    send_verification_email(
+        user_id,
+        &user.username,
+        &user.email,
+        verification_token_lifetime,
+        base_url,
+        email_client,
+        conn,
+    )
+    .await
+}
+
+pub async fn update_profile(
+    user_id: &Uuid,
+    update: UpdateUser,
+    verification_token_lifetime: chrono::Duration,
+    base_url: &str,
+    email_client: &dyn EmailClient,
+    conn: &PgPool,
+) -> Result<AuthUser> {
+    let old_user = database::get_user_by_id(conn, user_id)
+        .await?
+        .ok_or(ApiError::NotFound(anyhow::anyhow!("User does not exist")))?;
+
+    if !database::update_user(conn, user_id, &update).await? {
+        return Err(ApiError::BadRequest(anyhow::anyhow!(
+            "Email already taken.".to_string()
+        )));
+    }
+    let user = database::get_user_by_id(conn, user_id)
+        .await?
+        .ok_or(ApiError::NotFound(anyhow::anyhow!("User does not exist")))?;
+
+    if user.email != old_user.email {
+        // Like at signup, the profile is saved even if the emails can't be sent
+        if let Err(err) = send_verification_email(
+            user_id,
+            &user.username,
+            &user.email,
+            verification_token_lifetime,
+            base_url,
+            email_client,
+            conn,
+        )
+        .await
+        {
+            tracing::error!("Failed to send confirmation email: {:?}", err);
+        }
+        // Let the previous address know, in case someone else made the change
+        if let Err(err) = email_client
+            .send_email(Email {
+                recipient: old_user.email.clone(),
+                subject: "Your Voyage Atlas email address was changed".to_string(),
+                html_content: format!(
+                    "<p>Hi {},</p><p>The email address of your account was changed to {}. If this wasn't you, please reset your password.</p>",
+                    user.username, user.email
+                ),
+                text_content: format!(
+                    "Hi {},\n\nThe email address of your account was changed to {}. If this wasn't you, please reset your password.",
+                    user.username, user.email
+                ),
+            })
+            .await
+        {
+            tracing::error!("Failed to send email change notice: {:?}", err);
+        }
+    }
+    // Nobody needs approval to follow a public account
+    if old_user.is_private && !user.is_private {
+        database::approve_all_follow_requests(conn, user_id).await?;
+    }
+
+    Ok(user.into())
+}
+
+/// Rejects an action that the unverified account policy doesn't allow
+pub fn ensure_email_verified(user: &User, allowed_unverified: bool, action: &str) -> Result<()> {
+    if !user.email_verified && !allowed_unverified {
+        return Err(ApiError::Forbidden(anyhow::anyhow!(
+            "Please verify your email address before {}",
+            action
+        )));
+    }
+    Ok(())
+}
+
+pub async fn forgot_password(
+    forgot: ForgotPassword,
+    reset_token_lifetime: chrono::Duration,
+    base_url: &str,
+    email_client: &dyn EmailClient,
+    conn: &PgPool,
+) -> Result<()> {
+    // Don't reveal whether an account exists for the email
+    let user = if let Some(user) = database::get_user_by_email(conn, &forgot.email).await? {
+        user
+    } else {
+        return Ok(());
+    };
+    let user_id = Uuid::parse_str(&user.id)
+        .context("Failed to parse user id")
+        .map_err(ApiError::InternalServer)?;
+
+    let reset_token = token::generate_opaque_token();
+    database::insert_password_reset_token(
+        conn,
+        &token::hash_token(&reset_token),
+        &user_id,
+        reset_token_lifetime.num_seconds() as f64,
+    )
+    .await?;
+
+    let reset_link = format!("{}/users/password/reset?token={}", base_url, reset_token);
+    email_client
+        .send_email(Email {
+            recipient: user.email,
+            subject: "Reset your Voyage Atlas password".to_string(),
+            html_content: format!(
+                "<p>Hi {},</p><p>Follow <a href=\"{}\">this link</a> to reset your password. \
+                If you did not ask to reset your password you can ignore this email.</p>",
+                user.username, reset_link
+            ),
+            text_content: format!(
+                "Hi {},\n\nVisit {} to reset your password. \
+                If you did not ask to reset your password you can ignore this email.",
+                user.username, reset_link
+            ),
+        })
+        .await
+        .map_err(ApiError::InternalServer)?;
+
+    Ok(())
+}
+
+async fn rehash_password(
+    user_id: &str,
+    password: Secret<String>,
+    hashing: &PasswordHashingSettings,
+    conn: &PgPool,
+) -> Result<()> {
+    let user_id = Uuid::parse_str(user_id)
+        .context("Failed to parse user id")
+        .map_err(ApiError::InternalServer)?;
+    let hashed_pwd = password::hash_password(password, hashing).await?;
+    database::update_password(conn, &user_id, hashed_pwd.expose_secret().clone()).await
+}
+
+pub async fn reset_password(
+    reset: ResetPassword,
+    hashing: &PasswordHashingSettings,
+    conn: &PgPool,
+) -> Result<()> {
+    let user_id = database::use_password_reset_token(conn, &token::hash_token(&reset.token))
+        .await?
+        .ok_or(ApiError::BadRequest(anyhow::anyhow!(
+            "Invalid or expired password reset token"
+        )))?;
+
+    let hashed_pwd = password::hash_password(Secret::new(reset.password), hashing).await?;
+    database::update_password(conn, &user_id, hashed_pwd.expose_secret().clone()).await?;
+
+    // Any other reset links and every existing session stop working
+    database::invalidate_password_reset_tokens(conn, &user_id).await?;
+    database::revoke_all_sessions(conn, &user_id).await?;
+
+    Ok(())
+}
+
+// Confirms a sensitive change with the user's password. Wrong passwords count as failed
+// logins, so a stolen session can't be used to guess the password.
+async fn verify_current_password(
+    user: &User,
+    password: String,
+    client: &ClientInfo,
+    settings: &AuthSettings,
+    conn: &PgPool,
+) -> Result<()> {
+    login_throttling::ensure_not_locked(&user.email, client, conn).await?;
+
+    let verification = password::verify_password(
+        Secret::new(password),
+        user.password.clone(),
+        &settings.password_hashing,
+    )
+    .await?;
+    if let PasswordVerification::Invalid = verification {
+        login_throttling::record_failure(&user.email, client, &settings.login_throttling, conn)
+            .await?;
+        return Err(ApiError::BadRequest(anyhow::anyhow!(
+            "Current password is incorrect"
+        )));
+    }
+    Ok(())
+}
+
+/// Changes the password of a signed in user, signing out their other sessions
+pub async fn change_password(
+    user_id: &Uuid,
+    session_id: &Uuid,
+    change: ChangePassword,
+    client: &ClientInfo,
+    settings: &AuthSettings,
+    conn: &PgPool,
+) -> Result<()> {
+    let user = database::get_user_by_id(conn, user_id)
+        .await?
+        .ok_or(ApiError::NotFound(anyhow::anyhow!("User does not exist")))?;
+    verify_current_password(&user, change.current_password, client, settings, conn).await?;
+
+    let hashed_pwd =
+        password::hash_password(Secret::new(change.new_password), &settings.password_hashing)
+            .await?;
+    database::update_password(conn, user_id, hashed_pwd.expose_secret().clone()).await?;
+
+    database::invalidate_password_reset_tokens(conn, user_id).await?;
+    database::revoke_other_sessions(conn, user_id, session_id).await?;
+
+    Ok(())
+}
+
+/// Renames the user, keeping the old username reserved for them for `reservation`
+///
+/// Sessions are left alone, tokens identify users by id and logins go by email.
+pub async fn change_username(
+    user_id: &Uuid,
+    change: ChangeUsername,
+    reservation: chrono::Duration,
+    conn: &PgPool,
+) -> Result<AuthUser> {
+    database::get_user_by_id(conn, user_id)
+        .await?
+        .ok_or(ApiError::NotFound(anyhow::anyhow!("User does not exist")))?;
+
+    if !database::change_username(
+        conn,
+        user_id,
+        &change.username,
+        reservation.num_seconds() as f64,
+    )
+    .await?
+    {
+        return Err(ApiError::BadRequest(anyhow::anyhow!(
+            "Username already taken.".to_string()
+        )));
+    }
+
+    get_me(*user_id, conn).await
+}
+
+/// Finds a profile by username, following the username history if it has changed
+pub async fn get_user_by_username(username: &str, conn: &PgPool) -> Result<UsernameLookup> {
+    let username = username.to_lowercase();
+    if let Some(user) = database::get_user_by_username(conn, &username).await? {
+        return Ok(UsernameLookup::Current(Box::new(user.into())));
+    }
+
+    let user_id = database::get_user_id_by_previous_username(conn, &username)
+        .await?
+        .ok_or(ApiError::NotFound(anyhow::anyhow!("User does not exist")))?;
+    let user = get_user_by_id(user_id, conn).await?;
+    Ok(UsernameLookup::Renamed {
+        username: user.username,
     })
 }
 
-pub async fn follow_user(follower_id: Uuid, followed_id: Uuid, conn: &PgPool) -> Result<()> {
+/// Deactivates the account and signs it out everywhere, scheduling it to be purged once
+/// the grace period is over. Signing in again before then restores it.
+pub async fn deactivate_account(
+    user_id: &Uuid,
+    delete: DeleteAccount,
+    client: &ClientInfo,
+    settings: &AuthSettings,
+    conn: &PgPool,
+) -> Result<()> {
+    let user = database::get_user_by_id(conn, user_id)
+        .await?
+        .ok_or(ApiError::NotFound(anyhow::anyhow!("User does not exist")))?;
+    verify_current_password(&user, delete.password, client, settings, conn).await?;
+
+    let grace_period = chrono::Duration::days(settings.account_deletion.grace_period_days);
+    database::deactivate_user(conn, user_id, grace_period.num_seconds() as f64).await?;
+    database::revoke_all_sessions(conn, user_id).await?;
+
+    Ok(())
+}
+
+/// Purges every account whose grace period is over, returning how many were purged
+pub async fn purge_deactivated_accounts(
+    blob_store: &dyn BlobStore,
+    conn: &PgPool,
+) -> Result<usize> {
+    let mut purged = 0;
+    for user_id in database::get_users_due_for_purge(conn).await? {
+        if let Some(user) = database::purge_user(conn, &user_id).await? {
+            // The images are only deleted once the user is, so a failed purge keeps them
+            if let Some(avatar_id) = user.avatar_id {
+                media::delete_variants(ImageKind::Avatar, &avatar_id, blob_store).await;
+            }
+            if let Some(cover_id) = user.cover_id {
+                media::delete_variants(ImageKind::Cover, &cover_id, blob_store).await;
+            }
+            media::delete_post_media(&user.post_media, blob_store).await;
+            purged += 1;
+        }
+    }
+    Ok(purged)
+}
+
+/// Follows the user, or asks to if their account is private
+pub async fn follow_user(
+    follower_id: Uuid,
+    followed_id: Uuid,
+    conn: &PgPool,
+) -> Result<FollowOutcome> {
     // Check if user exists
     let follower = database::get_user_by_id(conn, &follower_id).await?;
-    let followed = database::get_user_by_id(conn, &followed_id).await?;
+    let followed = database::get_user_by_id(conn, &followed_id)
+        .await?
+        .filter(|followed| !followed.deactivated);
 
-    if follower.is_none() || followed.is_none() {
+    let (Some(_), Some(followed)) = (follower, followed) else {
         return Err(ApiError::NotFound(anyhow::anyhow!("User does not exist")));
-    }
+    };
 
     // Check if user is already following
     let is_following = database::is_following(conn, &follower_id, &followed_id).await?;
@@ -102,19 +636,145 @@ pub async fn follow_user(follower_id: Uuid, followed_id: Uuid, conn: &PgPool) ->
             "User is already following this user".to_string()
         )));
     }
+    ensure_not_blocked(&follower_id, &followed_id, conn).await?;
+
+    if followed.is_private {
+        if !database::insert_follow_request(conn, &follower_id, &followed_id).await? {
+            return Err(ApiError::BadRequest(anyhow::anyhow!(
+                "You have already requested to follow this user"
+            )));
+        }
+        return Ok(FollowOutcome::Requested);
+    }
 
     database::follow_user(conn, &follower_id, &followed_id).await?;
 
+    Ok(FollowOutcome::Followed)
+}
+
+pub async fn get_follow_requests(user_id: &Uuid, conn: &PgPool) -> Result<Vec<FollowRequest>> {
+    database::get_follow_requests(conn, user_id).await
+}
+
+pub async fn approve_follow_request(
+    user_id: &Uuid,
+    follower_id: &Uuid,
+    conn: &PgPool,
+) -> Result<()> {
+    if !database::approve_follow_request(conn, follower_id, user_id).await? {
+        return Err(ApiError::NotFound(anyhow::anyhow!(
+            "Follow request does not exist"
+        )));
+    }
+    Ok(())
+}
+
+pub async fn reject_follow_request(
+    user_id: &Uuid,
+    follower_id: &Uuid,
+    conn: &PgPool,
+) -> Result<()> {
+    if !database::delete_follow_request(conn, follower_id, user_id).await? {
+        return Err(ApiError::NotFound(anyhow::anyhow!(
+            "Follow request does not exist"
+        )));
+    }
     Ok(())
 }
 
-pub async fn get_followers(user_id: Uuid, conn: &PgPool) -> Result<Vec<AuthUser>> {
+/// Blocks the user, which also ends any follows between the two
+pub async fn block_user(user_id: &Uuid, blocked_id: &Uuid, conn: &PgPool) -> Result<()> {
+    if user_id == blocked_id {
+        return Err(ApiError::BadRequest(anyhow::anyhow!(
+            "You can't block yourself"
+        )));
+    }
+    get_visible_user(*blocked_id, conn).await?;
+
+    database::block_user(conn, user_id, blocked_id).await
+}
+
+pub async fn unblock_user(user_id: &Uuid, blocked_id: &Uuid, conn: &PgPool) -> Result<()> {
+    if !database::unblock_user(conn, user_id, blocked_id).await? {
+        return Err(ApiError::BadRequest(anyhow::anyhow!(
+            "You have not blocked this user"
+        )));
+    }
+    Ok(())
+}
+
+/// Mutes the user, hiding their posts from the muter's feed only
+pub async fn mute_user(user_id: &Uuid, muted_id: &Uuid, conn: &PgPool) -> Result<()> {
+    if user_id == muted_id {
+        return Err(ApiError::BadRequest(anyhow::anyhow!(
+            "You can't mute yourself"
+        )));
+    }
+    get_visible_user(*muted_id, conn).await?;
+
+    database::mute_user(conn, user_id, muted_id).await
+}
+
+pub async fn unmute_user(user_id: &Uuid, muted_id: &Uuid, conn: &PgPool) -> Result<()> {
+    if !database::unmute_user(conn, user_id, muted_id).await? {
+        return Err(ApiError::BadRequest(anyhow::anyhow!(
+            "You have not muted this user"
+        )));
+    }
+    Ok(())
+}
+
+/// Rejects interactions between two users when either has blocked the other
+pub async fn ensure_not_blocked(user_id: &Uuid, other_id: &Uuid, conn: &PgPool) -> Result<()> {
+    if database::is_blocked_between(conn, user_id, other_id).await? {
+        return Err(ApiError::Forbidden(anyhow::anyhow!(
+            "You can't interact with this user"
+        )));
+    }
+    Ok(())
+}
+
+/// Rejects viewers who may not see a private account's posts and connections, which are
+/// only its owner and approved followers
+pub async fn ensure_can_view(user: &User, viewer_id: Option<&Uuid>, conn: &PgPool) -> Result<()> {
+    if !user.is_private {
+        return Ok(());
+    }
+    let user_id = Uuid::parse_str(&user.id).map_err(|e| ApiError::InternalServer(e.into()))?;
+    let can_view = match viewer_id {
+        Some(viewer_id) if *viewer_id == user_id => true,
+        Some(viewer_id) => database::is_following(conn, viewer_id, &user_id).await?,
+        None => false,
+    };
+    if !can_view {
+        return Err(ApiError::Forbidden(anyhow::anyhow!(
+            "This account is private"
+        )));
+    }
+    Ok(())
+}
+
+pub async fn get_followers(
+    user_id: Uuid,
+    viewer_id: Option<Uuid>,
+    conn: &PgPool,
+) -> Result<Vec<PublicUser>> {
+    let user = get_visible_user(user_id, conn).await?;
+    ensure_can_view(&user, viewer_id.as_ref(), conn).await?;
+
     let followers = database::get_followers(conn, &user_id).await?;
 
     Ok(followers)
 }
 
-pub async fn get_following(user_id: Uuid, conn: &PgPool) -> Result<Vec<AuthUser>> {
+pub async fn get_following(
+    user_id: Uuid,
+    viewer_id: Option<Uuid>,
+    conn: &PgPool,
+) -> Result<Vec<PublicUser>> {
+    let user = get_visible_user(user_id, conn).await?;
+    ensure_can_view(&user, viewer_id.as_ref(), conn).await?;
+
     let following = database::get_following(conn, &user_id).await?;
 
     Ok(following)
@@ -134,6 +794,10 @@ pub async fn unfollow_user(user_id: Uuid, followed_id: Uuid, conn: &PgPool) -> R
     let is_following = database::is_following(conn, &user_id, &followed_id).await?;
 
     if !is_following {
+        // Withdraw a request to follow a private account instead
+        if database::delete_follow_request(conn, &user_id, &followed_id).await? {
+            return Ok(());
+        }
         return Err(ApiError::BadRequest(anyhow::anyhow!(
             "You are not following this user".to_string()
         )));
@@ -144,22 +808,39 @@ pub async fn unfollow_user(user_id: Uuid, followed_id: Uuid, conn: &PgPool) -> R
     Ok(())
 }
 
-pub async fn get_users(query: Option<String>, conn: &PgPool) -> Result<Vec<AuthUser>> {
-    let users: Vec<AuthUser>;
-    match query {
-        Some(query) => {
-            users = database::get_users_by_query(query, conn).await?;
-        }
-        None => {
-            users = database::get_all_users(conn).await?;
-        }
-    }
+pub async fn get_users(
+    query: Option<String>,
+    viewer_id: Option<Uuid>,
+    conn: &PgPool,
+) -> Result<Vec<PublicUser>> {
+    let users: Vec<PublicUser> = match query {
+        Some(query) => database::get_users_by_query(query, viewer_id.as_ref(), conn).await?,
+        None => database::get_all_users(conn, viewer_id.as_ref()).await?,
+    };
 
     Ok(users)
 }
 
-pub async fn get_user_by_id(user_id: Uuid, conn: &PgPool) -> Result<AuthUser> {
-    let user = database::get_user_by_id(conn, &user_id).await?;
+/// The signed in user's own profile, including their private details
+pub async fn get_me(user_id: Uuid, conn: &PgPool) -> Result<AuthUser> {
+    let user = database::get_user_by_id(conn, &user_id)
+        .await?
+        .ok_or(ApiError::NotFound(anyhow::anyhow!("User does not exist")))?;
+
+    Ok(user.into())
+}
+
+async fn get_visible_user(user_id: Uuid, conn: &PgPool) -> Result<User> {
+    database::get_user_by_id(conn, &user_id)
+        .await?
+        .filter(|user| !user.deactivated)
+        .ok_or(ApiError::NotFound(anyhow::anyhow!("User does not exist")))
+}
+
+pub async fn get_user_by_id(user_id: Uuid, conn: &PgPool) -> Result<PublicUser> {
+    let user = database::get_user_by_id(conn, &user_id)
+        .await?
+        .filter(|user| !user.deactivated);
 
     if user.is_none() {
         return Err(ApiError::NotFound(anyhow::anyhow!("User does not exist")));
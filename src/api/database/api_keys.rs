@@ -0,0 +1,108 @@
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::api::models::{
+    error::{ApiError, Result},
+    ApiKey,
+};
+
+pub async fn insert_api_key(
+    conn: &PgPool,
+    key_id: &Uuid,
+    user_id: &Uuid,
+    name: &str,
+    prefix: &str,
+    key_hash: &str,
+    scopes: &[String],
+) -> Result<ApiKey> {
+    let key = sqlx::query_as!(
+        ApiKey,
+        r#"
+        INSERT INTO api_keys (id, user_id, name, prefix, key_hash, scopes)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING id, user_id, name, prefix, scopes, created_at, last_used_at
+        "#,
+        key_id,
+        user_id,
+        name,
+        prefix,
+        key_hash,
+        scopes
+    )
+    .fetch_one(conn)
+    .await
+    .context("Failed to insert new API key into database.")
+    .map_err(ApiError::Database)?;
+
+    Ok(key)
+}
+
+pub async fn get_active_api_keys(conn: &PgPool, user_id: &Uuid) -> Result<Vec<ApiKey>> {
+    let keys = sqlx::query_as!(
+        ApiKey,
+        r#"
+        SELECT id, user_id, name, prefix, scopes, created_at, last_used_at
+        FROM api_keys
+        WHERE user_id = $1 AND revoked_at IS NULL
+        ORDER BY created_at DESC
+        "#,
+        user_id
+    )
+    .fetch_all(conn)
+    .await
+    .context("Failed to get user's API keys.")
+    .map_err(ApiError::Database)?;
+
+    Ok(keys)
+}
+
+// Returns the owner and scopes of an active key, bumping `last_used_at` at most once a
+// minute like `touch_session`
+pub async fn use_api_key(conn: &PgPool, key_hash: &str) -> Result<Option<(Uuid, Vec<String>)>> {
+    let key = sqlx::query!(
+        r#"
+        WITH active AS (
+            SELECT api_keys.id, user_id, scopes, last_used_at
+            FROM api_keys
+            INNER JOIN users ON api_keys.user_id = users.id
+            WHERE key_hash = $1 AND revoked_at IS NULL AND users.deactivated_at IS NULL
+        ), touched AS (
+            UPDATE api_keys
+            SET last_used_at = NOW()
+            WHERE id IN (
+                SELECT id FROM active
+                WHERE last_used_at IS NULL OR last_used_at < NOW() - INTERVAL '1 minute'
+            )
+        )
+        SELECT user_id AS "user_id!", scopes AS "scopes!" FROM active
+        "#,
+        key_hash
+    )
+    .fetch_optional(conn)
+    .await
+    .context("Failed to check API key.")
+    .map_err(ApiError::Database)?
+    .map(|key| (key.user_id, key.scopes));
+
+    Ok(key)
+}
+
+/// Returns whether the user had an active key with this id
+pub async fn revoke_api_key(conn: &PgPool, key_id: &Uuid, user_id: &Uuid) -> Result<bool> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE api_keys
+        SET revoked_at = NOW()
+        WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL
+        "#,
+        key_id,
+        user_id
+    )
+    .execute(conn)
+    .await
+    .context("Failed to revoke API key.")
+    .map_err(ApiError::Database)?;
+
+    Ok(result.rows_affected() > 0)
+}
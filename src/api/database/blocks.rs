@@ -0,0 +1,140 @@
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::api::models::error::{ApiError, Result};
+
+/// Blocks the user, and removes any follows and follow requests between the two
+pub async fn block_user(conn: &PgPool, blocker_id: &Uuid, blocked_id: &Uuid) -> Result<()> {
+    let mut transaction = conn
+        .begin()
+        .await
+        .context("Failed to start transaction.")
+        .map_err(ApiError::Database)?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO user_blocks (blocker_id, blocked_id)
+        VALUES ($1, $2)
+        ON CONFLICT DO NOTHING
+        "#,
+        blocker_id,
+        blocked_id
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to block user.")
+    .map_err(ApiError::Database)?;
+
+    sqlx::query!(
+        r#"
+        DELETE FROM users_followers
+        WHERE (user_id = $1 AND follower_id = $2) OR (user_id = $2 AND follower_id = $1)
+        "#,
+        blocker_id,
+        blocked_id
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to remove follows between blocked users.")
+    .map_err(ApiError::Database)?;
+
+    sqlx::query!(
+        r#"
+        DELETE FROM follow_requests
+        WHERE (user_id = $1 AND follower_id = $2) OR (user_id = $2 AND follower_id = $1)
+        "#,
+        blocker_id,
+        blocked_id
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to remove follow requests between blocked users.")
+    .map_err(ApiError::Database)?;
+
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit transaction.")
+        .map_err(ApiError::Database)?;
+    Ok(())
+}
+
+/// Returns false if the user wasn't blocked
+pub async fn unblock_user(conn: &PgPool, blocker_id: &Uuid, blocked_id: &Uuid) -> Result<bool> {
+    let deleted = sqlx::query!(
+        r#"
+        DELETE FROM user_blocks
+        WHERE blocker_id = $1 AND blocked_id = $2
+        "#,
+        blocker_id,
+        blocked_id
+    )
+    .execute(conn)
+    .await
+    .context("Failed to unblock user.")
+    .map_err(ApiError::Database)?
+    .rows_affected()
+        > 0;
+
+    Ok(deleted)
+}
+
+/// Whether either user has blocked the other
+pub async fn is_blocked_between(conn: &PgPool, user_id: &Uuid, other_id: &Uuid) -> Result<bool> {
+    let is_blocked = sqlx::query!(
+        r#"
+        SELECT EXISTS (
+            SELECT 1
+            FROM user_blocks
+            WHERE (blocker_id = $1 AND blocked_id = $2) OR (blocker_id = $2 AND blocked_id = $1)
+        ) AS "is_blocked!"
+        "#,
+        user_id,
+        other_id
+    )
+    .fetch_one(conn)
+    .await
+    .context("Failed to check if users are blocked.")
+    .map_err(ApiError::Database)?
+    .is_blocked;
+
+    Ok(is_blocked)
+}
+
+pub async fn mute_user(conn: &PgPool, muter_id: &Uuid, muted_id: &Uuid) -> Result<()> {
+    sqlx::query!(
+        r#"
+        INSERT INTO user_mutes (muter_id, muted_id)
+        VALUES ($1, $2)
+        ON CONFLICT DO NOTHING
+        "#,
+        muter_id,
+        muted_id
+    )
+    .execute(conn)
+    .await
+    .context("Failed to mute user.")
+    .map_err(ApiError::Database)?;
+    Ok(())
+}
+
+/// Returns false if the user wasn't muted
+pub async fn unmute_user(conn: &PgPool, muter_id: &Uuid, muted_id: &Uuid) -> Result<bool> {
+    let deleted = sqlx::query!(
+        r#"
+        DELETE FROM user_mutes
+        WHERE muter_id = $1 AND muted_id = $2
+        "#,
+        muter_id,
+        muted_id
+    )
+    .execute(conn)
+    .await
+    .context("Failed to unmute user.")
+    .map_err(ApiError::Database)?
+    .rows_affected()
+        > 0;
+
+    Ok(deleted)
+}
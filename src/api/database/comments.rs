@@ -1,7 +1,9 @@
 use crate::api::models::{
     error::{ApiError, Result},
-    AuthUser, Comment, CreateComment,
+    Comment, CreateComment, ExportedComment, NewMention, ProfileImage, PublicUser,
 };
+
+use super::{get_mentions_of_comments, insert_comment_mentions};
 use anyhow::Context;
 use sqlx::PgPool;
 use uuid::Uuid;
@@ -10,9 +12,16 @@ pub async fn create_comment(
     new_comment: CreateComment,
     user_id: &Uuid,
     post_id: &Uuid,
+    mentions: &[NewMention],
     conn: &PgPool,
 ) -> Result<String> {
     let comment_id = Uuid::new_v4();
+    let mut transaction = conn
+        .begin()
+        .await
+        .context("Failed to start transaction.")
+        .map_err(ApiError::Database)?;
+
     sqlx::query!(
         r#"
         INSERT INTO comments (id, user_id, post_id, comment)
@@ -23,74 +32,130 @@ pub async fn create_comment(
         post_id,
         new_comment.comment
     )
-    .execute(conn)
+    .execute(&mut *transaction)
     .await
     .context("Failed to insert new comment into database.")
     .map_err(ApiError::Database)?;
+    insert_comment_mentions(&mut transaction, &comment_id, mentions).await?;
+
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit transaction.")
+        .map_err(ApiError::Database)?;
     Ok(comment_id.to_string())
 }
 
-pub async fn get_comments(post_id: &Uuid, conn: &PgPool) -> Result<Vec<Comment>> {
-    let comments = sqlx::query!(
+/// The post's comments, except those by users blocked either way by `viewer_id`
+pub async fn get_comments(
+    post_id: &Uuid,
+    viewer_id: Option<&Uuid>,
+    conn: &PgPool,
+) -> Result<Vec<Comment>> {
+    let rows = sqlx::query!(
         r#"
             SELECT comments.id, user_id, post_id, parent_comment_id, comments.created_at, comment,
-            users.username, users.email as "user_email!", users.description, users.first_name, users.last_name
-            FROM comments, users
-            where comments.user_id = users.id and post_id = $1
+            users.username AS "username?", users.email AS "user_email?", users.description AS "description?",
+            users.first_name AS "first_name?", users.last_name AS "last_name?",
+            users.show_email AS "show_email?", users.is_private AS "is_private?",
+            users.avatar_id, users.cover_id
+            FROM comments
+            LEFT JOIN users ON comments.user_id = users.id
+            WHERE post_id = $1
+                AND NOT EXISTS (
+                    SELECT 1
+                    FROM user_blocks
+                    WHERE (blocker_id = $2 AND blocked_id = comments.user_id)
+                        OR (blocker_id = comments.user_id AND blocked_id = $2)
+                )
         "#,
-        post_id
+        post_id,
+        viewer_id
     )
     .fetch_all(conn)
     .await
     .context("Failed to get comments")
-    .map_err(ApiError::Database)?
-    .into_iter()
-    .map(|row| Comment {
-        id: row.id.to_string(),
-        post_id: row.post_id.to_string(),
-        comment: row.comment,
-        created_at: row.created_at.timestamp(),
-        parent_comment_id: row.parent_comment_id.map(|id| id.to_string()),
-        user: AuthUser {
-            id: row.user_id.to_string(),
-            username: row.username,
-            email: row.user_email,
-            name: format!("{} {}", row.first_name, row.last_name),
-            description: row.description
-        },
-    })
-    .collect::<Vec<Comment>>();
+    .map_err(ApiError::Database)?;
+
+    let comment_ids = rows.iter().map(|row| row.id).collect::<Vec<Uuid>>();
+    let mut mentions = get_mentions_of_comments(conn, &comment_ids).await?;
+    let comments = rows
+        .into_iter()
+        .map(|row| Comment {
+            id: row.id.to_string(),
+            post_id: row.post_id.to_string(),
+            comment: row.comment,
+            created_at: row.created_at.timestamp(),
+            parent_comment_id: row.parent_comment_id.map(|id| id.to_string()),
+            user: row.user_id.map(|user_id| PublicUser {
+                id: user_id.to_string(),
+                username: row.username.unwrap_or_default(),
+                email: row
+                    .user_email
+                    .filter(|_| row.show_email.unwrap_or_default()),
+                name: format!(
+                    "{} {}",
+                    row.first_name.unwrap_or_default(),
+                    row.last_name.unwrap_or_default()
+                ),
+                description: row.description.unwrap_or_default(),
+                is_private: row.is_private.unwrap_or_default(),
+                avatar: ProfileImage::avatar(row.avatar_id),
+                cover: ProfileImage::cover(row.cover_id),
+            }),
+            mentions: mentions.remove(&row.id).unwrap_or_default(),
+        })
+        .collect::<Vec<Comment>>();
 
     Ok(comments)
 }
 
 pub async fn get_comment_by_id(comment_id: &Uuid, conn: &PgPool) -> Result<Option<Comment>> {
-    let comment = sqlx::query!(
+    let row = sqlx::query!(
         r#"
             SELECT comments.id, user_id, post_id, parent_comment_id, comments.created_at, comment,
-            users.username, users.email as "user_email!", users.description, users.first_name, users.last_name
-            FROM comments, users
-            where comments.id = $1
+            users.username AS "username?", users.email AS "user_email?", users.description AS "description?",
+            users.first_name AS "first_name?", users.last_name AS "last_name?",
+            users.show_email AS "show_email?", users.is_private AS "is_private?",
+            users.avatar_id, users.cover_id
+            FROM comments
+            LEFT JOIN users ON comments.user_id = users.id
+            WHERE comments.id = $1
         "#,
         comment_id
     )
     .fetch_optional(conn)
     .await
     .context("Failed to get comment")
-    .map_err(ApiError::Database)?
-    .map(|row| Comment {
+    .map_err(ApiError::Database)?;
+    let Some(row) = row else {
+        return Ok(None);
+    };
+
+    let mut mentions = get_mentions_of_comments(conn, &[row.id]).await?;
+    let comment = Some(Comment {
         id: row.id.to_string(),
         post_id: row.post_id.to_string(),
         comment: row.comment,
         created_at: row.created_at.timestamp(),
         parent_comment_id: row.parent_comment_id.map(|id| id.to_string()),
-        user: AuthUser {
-            id: row.user_id.to_string(),
-            username: row.username,
-            email: row.user_email,
-            name: format!("{} {}", row.first_name, row.last_name),
-            description: row.description
-        },
+        user: row.user_id.map(|user_id| PublicUser {
+            id: user_id.to_string(),
+            username: row.username.unwrap_or_default(),
+            email: row
+                .user_email
+                .filter(|_| row.show_email.unwrap_or_default()),
+            name: format!(
+                "{} {}",
+                row.first_name.unwrap_or_default(),
+                row.last_name.unwrap_or_default()
+            ),
+            description: row.description.unwrap_or_default(),
+            is_private: row.is_private.unwrap_or_default(),
+            avatar: ProfileImage::avatar(row.avatar_id),
+            cover: ProfileImage::cover(row.cover_id),
+        }),
+        mentions: mentions.remove(&row.id).unwrap_or_default(),
     });
 
     Ok(comment)
@@ -116,9 +181,16 @@ pub async fn reply_to_comment(
     user_id: &Uuid,
     post_id: &Uuid,
     comment_id: &Uuid,
+    mentions: &[NewMention],
     conn: &PgPool,
 ) -> Result<()> {
     let reply_id = Uuid::new_v4();
+    let mut transaction = conn
+        .begin()
+        .await
+        .context("Failed to start transaction.")
+        .map_err(ApiError::Database)?;
+
     sqlx::query!(
         r#"
         INSERT INTO comments (id, user_id, post_id, comment, parent_comment_id)
@@ -130,9 +202,43 @@ pub async fn reply_to_comment(
         comment.comment,
         comment_id
     )
-    .execute(conn)
+    .execute(&mut *transaction)
     .await
     .context("Failed to insert new comment into database.")
     .map_err(ApiError::Database)?;
+    insert_comment_mentions(&mut transaction, &reply_id, mentions).await?;
+
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit transaction.")
+        .map_err(ApiError::Database)?;
     Ok(())
 }
+
+pub async fn get_comments_by_user(user_id: &Uuid, conn: &PgPool) -> Result<Vec<ExportedComment>> {
+    let comments = sqlx::query!(
+        r#"
+            SELECT id, post_id, parent_comment_id, comment, created_at
+            FROM comments
+            WHERE user_id = $1
+            ORDER BY created_at
+        "#,
+        user_id
+    )
+    .fetch_all(conn)
+    .await
+    .context("Failed to get user's comments")
+    .map_err(ApiError::Database)?
+    .into_iter()
+    .map(|row| ExportedComment {
+        id: row.id.to_string(),
+        post_id: row.post_id.to_string(),
+        parent_comment_id: row.parent_comment_id.map(|id| id.to_string()),
+        comment: row.comment,
+        created_at: row.created_at.timestamp(),
+    })
+    .collect::<Vec<ExportedComment>>();
+
+    Ok(comments)
+}
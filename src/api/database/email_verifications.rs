@@ -0,0 +1,71 @@
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::api::models::error::{ApiError, Result};
+
+pub async fn insert_email_verification_token(
+    conn: &PgPool,
+    token_hash: &str,
+    user_id: &Uuid,
+    email: &str,
+    expires_in_seconds: f64,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+        INSERT INTO email_verification_tokens (token_hash, user_id, email, expires_at)
+        VALUES ($1, $2, $3, NOW() + make_interval(secs => $4))
+        "#,
+        token_hash,
+        user_id,
+        email.to_lowercase(),
+        expires_in_seconds
+    )
+    .execute(conn)
+    .await
+    .context("Failed to insert new email verification token into database.")
+    .map_err(ApiError::Database)?;
+    Ok(())
+}
+
+/// Consumes a verification token, returning the user and the email address it confirms
+pub async fn use_email_verification_token(
+    conn: &PgPool,
+    token_hash: &str,
+) -> Result<Option<(Uuid, String)>> {
+    let verification = sqlx::query!(
+        r#"
+        UPDATE email_verification_tokens
+        SET used_at = NOW()
+        WHERE token_hash = $1 AND used_at IS NULL AND expires_at > NOW()
+        RETURNING user_id, email
+        "#,
+        token_hash
+    )
+    .fetch_optional(conn)
+    .await
+    .context("Failed to use email verification token.")
+    .map_err(ApiError::Database)?
+    .map(|row| (row.user_id, row.email));
+
+    Ok(verification)
+}
+
+/// Marks the user's email as verified, as long as it is still the address that was confirmed
+pub async fn mark_email_verified(conn: &PgPool, user_id: &Uuid, email: &str) -> Result<bool> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE users
+        SET email_verified_at = NOW()
+        WHERE id = $1 AND email = $2
+        "#,
+        user_id,
+        email
+    )
+    .execute(conn)
+    .await
+    .context("Failed to mark email as verified.")
+    .map_err(ApiError::Database)?;
+
+    Ok(result.rows_affected() > 0)
+}
@@ -0,0 +1,162 @@
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::api::models::{
+    error::{ApiError, Result},
+    DataExport,
+};
+
+pub async fn insert_export(
+    conn: &PgPool,
+    export_id: &Uuid,
+    user_id: &Uuid,
+    expires_in_seconds: f64,
+) -> Result<DataExport> {
+    let export = sqlx::query_as!(
+        DataExport,
+        r#"
+        INSERT INTO data_exports (id, user_id, expires_at)
+        VALUES ($1, $2, NOW() + make_interval(secs => $3))
+        RETURNING id, user_id, status, archive, created_at, completed_at, expires_at
+        "#,
+        export_id,
+        user_id,
+        expires_in_seconds
+    )
+    .fetch_one(conn)
+    .await
+    .context("Failed to insert new data export into database.")
+    .map_err(ApiError::Database)?;
+
+    Ok(export)
+}
+
+/// The export a new request can be answered with: one still being generated, as long as
+/// it hasn't been pending for longer than `pending_timeout_seconds`, or one that finished
+/// within the last `ready_max_age_seconds`
+///
+/// The archive itself isn't fetched.
+pub async fn get_reusable_export(
+    conn: &PgPool,
+    user_id: &Uuid,
+    pending_timeout_seconds: f64,
+    ready_max_age_seconds: f64,
+) -> Result<Option<DataExport>> {
+    let export = sqlx::query_as!(
+        DataExport,
+        r#"
+        SELECT id, user_id, status, NULL::BYTEA AS archive, created_at, completed_at, expires_at
+        FROM data_exports
+        WHERE user_id = $1 AND expires_at > NOW()
+            AND (
+                (status = 'pending' AND created_at > NOW() - make_interval(secs => $2))
+                OR (status = 'ready' AND completed_at > NOW() - make_interval(secs => $3))
+            )
+        ORDER BY created_at DESC
+        LIMIT 1
+        "#,
+        user_id,
+        pending_timeout_seconds,
+        ready_max_age_seconds
+    )
+    .fetch_optional(conn)
+    .await
+    .context("Failed to get reusable data export.")
+    .map_err(ApiError::Database)?;
+
+    Ok(export)
+}
+
+pub async fn get_export(
+    conn: &PgPool,
+    user_id: &Uuid,
+    export_id: &Uuid,
+) -> Result<Option<DataExport>> {
+    let export = sqlx::query_as!(
+        DataExport,
+        r#"
+        SELECT id, user_id, status, archive, created_at, completed_at, expires_at
+        FROM data_exports
+        WHERE id = $1 AND user_id = $2 AND expires_at > NOW()
+        "#,
+        export_id,
+        user_id
+    )
+    .fetch_optional(conn)
+    .await
+    .context("Failed to get data export.")
+    .map_err(ApiError::Database)?;
+
+    Ok(export)
+}
+
+pub async fn complete_export(conn: &PgPool, export_id: &Uuid, archive: &[u8]) -> Result<()> {
+    sqlx::query!(
+        r#"
+        UPDATE data_exports
+        SET status = 'ready', archive = $2, completed_at = NOW()
+        WHERE id = $1
+        "#,
+        export_id,
+        archive
+    )
+    .execute(conn)
+    .await
+    .context("Failed to complete data export.")
+    .map_err(ApiError::Database)?;
+    Ok(())
+}
+
+pub async fn fail_export(conn: &PgPool, export_id: &Uuid) -> Result<()> {
+    sqlx::query!(
+        r#"
+        UPDATE data_exports
+        SET status = 'failed', completed_at = NOW()
+        WHERE id = $1
+        "#,
+        export_id
+    )
+    .execute(conn)
+    .await
+    .context("Failed to mark data export as failed.")
+    .map_err(ApiError::Database)?;
+    Ok(())
+}
+
+/// Marks exports that have been pending for longer than `timeout_seconds` as failed,
+/// such as ones whose generation was cut short by a restart, returning how many there were
+pub async fn fail_stale_exports(conn: &PgPool, timeout_seconds: f64) -> Result<u64> {
+    let failed = sqlx::query!(
+        r#"
+        UPDATE data_exports
+        SET status = 'failed', completed_at = NOW()
+        WHERE status = 'pending' AND created_at <= NOW() - make_interval(secs => $1)
+        "#,
+        timeout_seconds
+    )
+    .execute(conn)
+    .await
+    .context("Failed to mark stale data exports as failed.")
+    .map_err(ApiError::Database)?
+    .rows_affected();
+
+    Ok(failed)
+}
+
+/// Deletes exports that can no longer be downloaded, returning how many were deleted
+pub async fn delete_expired_exports(conn: &PgPool) -> Result<u64> {
+    let deleted = sqlx::query!(
+        r#"
+        DELETE FROM data_exports
+        WHERE expires_at <= NOW()
+        "#
+    )
+    .execute(conn)
+    .await
+    .context("Failed to delete expired data exports.")
+    .map_err(ApiError::Database)?
+    .rows_affected();
+
+    Ok(deleted)
+}
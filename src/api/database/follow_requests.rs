@@ -0,0 +1,166 @@
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::api::models::{
+    error::{ApiError, Result},
+    FollowRequest, ProfileImage, PublicUser,
+};
+
+/// Asks to follow a private account, returning false if the request already exists
+pub async fn insert_follow_request(
+    conn: &PgPool,
+    follower_id: &Uuid,
+    user_id: &Uuid,
+) -> Result<bool> {
+    let inserted = sqlx::query!(
+        r#"
+        INSERT INTO follow_requests (follower_id, user_id)
+        VALUES ($1, $2)
+        ON CONFLICT DO NOTHING
+        "#,
+        follower_id,
+        user_id
+    )
+    .execute(conn)
+    .await
+    .context("Failed to insert follow request.")
+    .map_err(ApiError::Database)?
+    .rows_affected()
+        > 0;
+
+    Ok(inserted)
+}
+
+pub async fn get_follow_requests(conn: &PgPool, user_id: &Uuid) -> Result<Vec<FollowRequest>> {
+    let requests = sqlx::query!(
+        r#"
+        SELECT users.id, users.username, users.email, users.first_name, users.last_name,
+            users.description, users.show_email, users.is_private, users.avatar_id, users.cover_id,
+            follow_requests.created_at
+        FROM follow_requests
+        INNER JOIN users ON users.id = follow_requests.follower_id
+        WHERE follow_requests.user_id = $1 AND users.deactivated_at IS NULL
+        ORDER BY follow_requests.created_at
+        "#,
+        user_id
+    )
+    .fetch_all(conn)
+    .await
+    .context("Failed to get follow requests.")
+    .map_err(ApiError::Database)?
+    .into_iter()
+    .map(|request| FollowRequest {
+        user: PublicUser {
+            id: request.id.to_string(),
+            username: request.username,
+            name: format!("{} {}", request.first_name, request.last_name),
+            description: request.description,
+            email: request.show_email.then_some(request.email),
+            is_private: request.is_private,
+            avatar: ProfileImage::avatar(request.avatar_id),
+            cover: ProfileImage::cover(request.cover_id),
+        },
+        created_at: request.created_at.timestamp(),
+    })
+    .collect::<Vec<FollowRequest>>();
+
+    Ok(requests)
+}
+
+/// Turns a pending request into a follow, returning false if there was no such request
+pub async fn approve_follow_request(
+    conn: &PgPool,
+    follower_id: &Uuid,
+    user_id: &Uuid,
+) -> Result<bool> {
+    let mut transaction = conn
+        .begin()
+        .await
+        .context("Failed to start transaction.")
+        .map_err(ApiError::Database)?;
+
+    let deleted = sqlx::query!(
+        r#"
+        DELETE FROM follow_requests
+        WHERE follower_id = $1 AND user_id = $2
+        "#,
+        follower_id,
+        user_id
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to delete follow request.")
+    .map_err(ApiError::Database)?
+    .rows_affected();
+    if deleted == 0 {
+        return Ok(false);
+    }
+
+    sqlx::query!(
+        r#"
+        INSERT INTO users_followers (user_id, follower_id)
+        VALUES ($1, $2)
+        ON CONFLICT DO NOTHING
+        "#,
+        user_id,
+        follower_id
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to insert new follower into database.")
+    .map_err(ApiError::Database)?;
+
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit transaction.")
+        .map_err(ApiError::Database)?;
+    Ok(true)
+}
+
+/// Approves every pending request, for when the account stops being private
+pub async fn approve_all_follow_requests(conn: &PgPool, user_id: &Uuid) -> Result<()> {
+    sqlx::query!(
+        r#"
+        WITH approved AS (
+            DELETE FROM follow_requests
+            WHERE user_id = $1
+            RETURNING follower_id
+        )
+        INSERT INTO users_followers (user_id, follower_id)
+        SELECT $1, follower_id FROM approved
+        ON CONFLICT DO NOTHING
+        "#,
+        user_id
+    )
+    .execute(conn)
+    .await
+    .context("Failed to approve follow requests.")
+    .map_err(ApiError::Database)?;
+    Ok(())
+}
+
+/// Rejects or withdraws a request, returning false if there was no such request
+pub async fn delete_follow_request(
+    conn: &PgPool,
+    follower_id: &Uuid,
+    user_id: &Uuid,
+) -> Result<bool> {
+    let deleted = sqlx::query!(
+        r#"
+        DELETE FROM follow_requests
+        WHERE follower_id = $1 AND user_id = $2
+        "#,
+        follower_id,
+        user_id
+    )
+    .execute(conn)
+    .await
+    .context("Failed to delete follow request.")
+    .map_err(ApiError::Database)?
+    .rows_affected()
+        > 0;
+
+    Ok(deleted)
+}
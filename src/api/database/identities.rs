@@ -0,0 +1,108 @@
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::api::models::{
+    error::{ApiError, Result},
+    OidcLogin,
+};
+
+pub async fn insert_oidc_login(
+    conn: &PgPool,
+    state_hash: &str,
+    provider: &str,
+    login: &OidcLogin,
+    expires_in_seconds: f64,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+        INSERT INTO oidc_logins (state_hash, provider, nonce, code_verifier, expires_at)
+        VALUES ($1, $2, $3, $4, NOW() + make_interval(secs => $5))
+        "#,
+        state_hash,
+        provider,
+        login.nonce,
+        login.code_verifier,
+        expires_in_seconds
+    )
+    .execute(conn)
+    .await
+    .context("Failed to insert new OIDC login into database.")
+    .map_err(ApiError::Database)?;
+    Ok(())
+}
+
+/// Consumes a pending login, so that each `state` can only come back once
+pub async fn use_oidc_login(
+    conn: &PgPool,
+    state_hash: &str,
+    provider: &str,
+) -> Result<Option<OidcLogin>> {
+    let login = sqlx::query!(
+        r#"
+        DELETE FROM oidc_logins
+        WHERE state_hash = $1 AND provider = $2
+        RETURNING nonce, code_verifier, expires_at > NOW() AS "is_valid!"
+        "#,
+        state_hash,
+        provider
+    )
+    .fetch_optional(conn)
+    .await
+    .context("Failed to use OIDC login.")
+    .map_err(ApiError::Database)?
+    .filter(|login| login.is_valid)
+    .map(|login| OidcLogin {
+        nonce: login.nonce,
+        code_verifier: login.code_verifier,
+    });
+
+    Ok(login)
+}
+
+pub async fn get_user_id_by_identity(
+    conn: &PgPool,
+    provider: &str,
+    subject: &str,
+) -> Result<Option<Uuid>> {
+    let user_id = sqlx::query!(
+        r#"
+        SELECT user_id
+        FROM user_identities
+        WHERE provider = $1 AND subject = $2
+        "#,
+        provider,
+        subject
+    )
+    .fetch_optional(conn)
+    .await
+    .context("Failed to get user by external identity.")
+    .map_err(ApiError::Database)?
+    .map(|identity| identity.user_id);
+
+    Ok(user_id)
+}
+
+pub async fn insert_user_identity(
+    conn: &PgPool,
+    provider: &str,
+    subject: &str,
+    user_id: &Uuid,
+    email: Option<&str>,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+        INSERT INTO user_identities (provider, subject, user_id, email)
+        VALUES ($1, $2, $3, $4)
+        "#,
+        provider,
+        subject,
+        user_id,
+        email
+    )
+    .execute(conn)
+    .await
+    .context("Failed to link external identity to user.")
+    .map_err(ApiError::Database)?;
+    Ok(())
+}
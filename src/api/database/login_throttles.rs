@@ -0,0 +1,156 @@
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::api::models::{
+    error::{ApiError, Result},
+    LockoutEvent,
+};
+
+/// Seconds until the account or IP address may try to log in again, if either is locked
+pub async fn get_login_lockout(
+    conn: &PgPool,
+    email: &str,
+    ip_address: Option<&str>,
+) -> Result<Option<i64>> {
+    let retry_after = sqlx::query!(
+        r#"
+        SELECT CEIL(EXTRACT(EPOCH FROM MAX(locked_until) - NOW()))::BIGINT AS retry_after
+        FROM login_throttles
+        WHERE locked_until > NOW()
+            AND ((scope = 'account' AND identifier = $1) OR (scope = 'ip' AND identifier = $2))
+        "#,
+        email.to_lowercase(),
+        ip_address
+    )
+    .fetch_one(conn)
+    .await
+    .context("Failed to check login lockout.")
+    .map_err(ApiError::Database)?
+    .retry_after;
+
+    Ok(retry_after)
+}
+
+// Counts a failed login, starting over when the previous failure is older than
+// `reset_after_seconds`. Returns the number of recent failures.
+pub async fn record_failed_login(
+    conn: &PgPool,
+    scope: &str,
+    identifier: &str,
+    reset_after_seconds: f64,
+) -> Result<i32> {
+    let failed_attempts = sqlx::query!(
+        r#"
+        INSERT INTO login_throttles (scope, identifier, failed_attempts)
+        VALUES ($1, $2, 1)
+        ON CONFLICT (scope, identifier) DO UPDATE
+        SET failed_attempts = CASE
+                WHEN login_throttles.last_failed_at < NOW() - make_interval(secs => $3) THEN 1
+                ELSE login_throttles.failed_attempts + 1
+            END,
+            last_failed_at = NOW()
+        RETURNING failed_attempts
+        "#,
+        scope,
+        identifier,
+        reset_after_seconds
+    )
+    .fetch_one(conn)
+    .await
+    .context("Failed to record failed login.")
+    .map_err(ApiError::Database)?
+    .failed_attempts;
+
+    Ok(failed_attempts)
+}
+
+/// Locks the account or IP address and records the lockout for admins
+pub async fn lock_login(
+    conn: &PgPool,
+    scope: &str,
+    identifier: &str,
+    failed_attempts: i32,
+    lockout_seconds: f64,
+) -> Result<()> {
+    let mut transaction = conn
+        .begin()
+        .await
+        .context("Failed to start transaction.")
+        .map_err(ApiError::Database)?;
+
+    let locked_until = sqlx::query!(
+        r#"
+        UPDATE login_throttles
+        SET locked_until = NOW() + make_interval(secs => $3)
+        WHERE scope = $1 AND identifier = $2
+        RETURNING locked_until AS "locked_until!"
+        "#,
+        scope,
+        identifier,
+        lockout_seconds
+    )
+    .fetch_one(&mut *transaction)
+    .await
+    .context("Failed to lock login.")
+    .map_err(ApiError::Database)?
+    .locked_until;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO lockout_events (id, scope, identifier, failed_attempts, locked_until)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+        Uuid::new_v4(),
+        scope,
+        identifier,
+        failed_attempts,
+        locked_until
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to record lockout event.")
+    .map_err(ApiError::Database)?;
+
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit transaction.")
+        .map_err(ApiError::Database)?;
+    Ok(())
+}
+
+pub async fn clear_failed_logins(conn: &PgPool, scope: &str, identifier: &str) -> Result<()> {
+    sqlx::query!(
+        r#"
+        DELETE FROM login_throttles
+        WHERE scope = $1 AND identifier = $2
+        "#,
+        scope,
+        identifier
+    )
+    .execute(conn)
+    .await
+    .context("Failed to clear failed logins.")
+    .map_err(ApiError::Database)?;
+    Ok(())
+}
+
+pub async fn get_lockout_events(conn: &PgPool, limit: i64) -> Result<Vec<LockoutEvent>> {
+    let events = sqlx::query_as!(
+        LockoutEvent,
+        r#"
+        SELECT id, scope, identifier, failed_attempts, locked_until, created_at
+        FROM lockout_events
+        ORDER BY created_at DESC
+        LIMIT $1
+        "#,
+        limit
+    )
+    .fetch_all(conn)
+    .await
+    .context("Failed to get lockout events.")
+    .map_err(ApiError::Database)?;
+
+    Ok(events)
+}
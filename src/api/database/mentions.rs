@@ -0,0 +1,209 @@
+use std::collections::HashMap;
+
+use anyhow::Context;
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+use crate::api::models::{
+    error::{ApiError, Result},
+    Mention, NewMention,
+};
+
+/// Replaces the post's mentions, leaving out users who have removed themselves from it
+pub async fn set_post_mentions(
+    conn: &mut PgConnection,
+    post_id: &Uuid,
+    mentions: &[NewMention],
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+        DELETE FROM post_mentions
+        WHERE post_id = $1
+        "#,
+        post_id
+    )
+    .execute(&mut *conn)
+    .await
+    .context("Failed to remove post's old mentions.")
+    .map_err(ApiError::Database)?;
+    if mentions.is_empty() {
+        return Ok(());
+    }
+
+    let (user_ids, starts, ends) = mention_columns(mentions);
+    sqlx::query!(
+        r#"
+        INSERT INTO post_mentions (post_id, user_id, start_offset, end_offset)
+        SELECT $1, mention.user_id, mention.start_offset, mention.end_offset
+        FROM UNNEST($2::UUID[], $3::INTEGER[], $4::INTEGER[])
+            AS mention(user_id, start_offset, end_offset)
+        WHERE NOT EXISTS (
+            SELECT 1
+            FROM post_mention_removals
+            WHERE post_id = $1 AND user_id = mention.user_id
+        )
+        "#,
+        post_id,
+        &user_ids,
+        &starts,
+        &ends
+    )
+    .execute(&mut *conn)
+    .await
+    .context("Failed to insert post mentions.")
+    .map_err(ApiError::Database)?;
+    Ok(())
+}
+
+pub async fn insert_comment_mentions(
+    conn: &mut PgConnection,
+    comment_id: &Uuid,
+    mentions: &[NewMention],
+) -> Result<()> {
+    if mentions.is_empty() {
+        return Ok(());
+    }
+
+    let (user_ids, starts, ends) = mention_columns(mentions);
+    sqlx::query!(
+        r#"
+        INSERT INTO comment_mentions (comment_id, user_id, start_offset, end_offset)
+        SELECT $1, * FROM UNNEST($2::UUID[], $3::INTEGER[], $4::INTEGER[])
+        "#,
+        comment_id,
+        &user_ids,
+        &starts,
+        &ends
+    )
+    .execute(&mut *conn)
+    .await
+    .context("Failed to insert comment mentions.")
+    .map_err(ApiError::Database)?;
+    Ok(())
+}
+
+/// The mentions in each of the posts, in order, keyed by post id
+///
+/// Deactivated users are left out, like everywhere else they would show up.
+pub async fn get_mentions_of_posts(
+    conn: &PgPool,
+    post_ids: &[Uuid],
+) -> Result<HashMap<Uuid, Vec<Mention>>> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT post_mentions.post_id, post_mentions.user_id, users.username,
+            post_mentions.start_offset, post_mentions.end_offset
+        FROM post_mentions
+        INNER JOIN users ON users.id = post_mentions.user_id
+        WHERE post_mentions.post_id = ANY($1) AND users.deactivated_at IS NULL
+        ORDER BY post_mentions.post_id, post_mentions.start_offset
+        "#,
+        post_ids
+    )
+    .fetch_all(conn)
+    .await
+    .context("Failed to get mentions of posts.")
+    .map_err(ApiError::Database)?;
+
+    let mut mentions = HashMap::<Uuid, Vec<Mention>>::new();
+    for row in rows {
+        mentions.entry(row.post_id).or_default().push(Mention {
+            user_id: row.user_id.to_string(),
+            username: row.username,
+            start: row.start_offset as u32,
+            end: row.end_offset as u32,
+        });
+    }
+
+    Ok(mentions)
+}
+
+/// The mentions in each of the comments, in order, keyed by comment id
+pub async fn get_mentions_of_comments(
+    conn: &PgPool,
+    comment_ids: &[Uuid],
+) -> Result<HashMap<Uuid, Vec<Mention>>> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT comment_mentions.comment_id, comment_mentions.user_id, users.username,
+            comment_mentions.start_offset, comment_mentions.end_offset
+        FROM comment_mentions
+        INNER JOIN users ON users.id = comment_mentions.user_id
+        WHERE comment_mentions.comment_id = ANY($1) AND users.deactivated_at IS NULL
+        ORDER BY comment_mentions.comment_id, comment_mentions.start_offset
+        "#,
+        comment_ids
+    )
+    .fetch_all(conn)
+    .await
+    .context("Failed to get mentions of comments.")
+    .map_err(ApiError::Database)?;
+
+    let mut mentions = HashMap::<Uuid, Vec<Mention>>::new();
+    for row in rows {
+        mentions.entry(row.comment_id).or_default().push(Mention {
+            user_id: row.user_id.to_string(),
+            username: row.username,
+            start: row.start_offset as u32,
+            end: row.end_offset as u32,
+        });
+    }
+
+    Ok(mentions)
+}
+
+/// Untags the user from the post for good, returns false if they weren't tagged in it
+pub async fn remove_post_mention(conn: &PgPool, post_id: &Uuid, user_id: &Uuid) -> Result<bool> {
+    let mut transaction = conn
+        .begin()
+        .await
+        .context("Failed to start transaction.")
+        .map_err(ApiError::Database)?;
+
+    let removed = sqlx::query!(
+        r#"
+        DELETE FROM post_mentions
+        WHERE post_id = $1 AND user_id = $2
+        "#,
+        post_id,
+        user_id
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to remove post mention.")
+    .map_err(ApiError::Database)?
+    .rows_affected()
+        > 0;
+    if !removed {
+        return Ok(false);
+    }
+
+    sqlx::query!(
+        r#"
+        INSERT INTO post_mention_removals (post_id, user_id)
+        VALUES ($1, $2)
+        ON CONFLICT DO NOTHING
+        "#,
+        post_id,
+        user_id
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to record post mention removal.")
+    .map_err(ApiError::Database)?;
+
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit transaction.")
+        .map_err(ApiError::Database)?;
+    Ok(true)
+}
+
+/// Splits the mentions into the arrays `UNNEST` takes
+fn mention_columns(mentions: &[NewMention]) -> (Vec<Uuid>, Vec<i32>, Vec<i32>) {
+    let user_ids = mentions.iter().map(|mention| mention.user_id).collect();
+    let starts = mentions.iter().map(|mention| mention.start).collect();
+    let ends = mentions.iter().map(|mention| mention.end).collect();
+    (user_ids, starts, ends)
+}
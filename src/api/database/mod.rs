@@ -1,7 +1,37 @@
+mod api_keys;
+mod blocks;
 mod comments;
+mod email_verifications;
+mod exports;
+mod follow_requests;
+mod identities;
+mod login_throttles;
+mod mentions;
+mod password_resets;
+mod places;
+mod post_media;
 mod posts;
+mod roles;
+mod sessions;
+mod tags;
+mod two_factor;
 mod users;
 
+pub use api_keys::*;
+pub use blocks::*;
 pub use comments::*;
+pub use email_verifications::*;
+pub use exports::*;
+pub use follow_requests::*;
+pub use identities::*;
+pub use login_throttles::*;
+pub use mentions::*;
+pub use password_resets::*;
+pub use places::*;
+pub use post_media::*;
 pub use posts::*;
+pub use roles::*;
+pub use sessions::*;
+pub use tags::*;
+pub use two_factor::*;
 pub use users::*;
@@ -0,0 +1,63 @@
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::api::models::error::{ApiError, Result};
+
+pub async fn insert_password_reset_token(
+    conn: &PgPool,
+    token_hash: &str,
+    user_id: &Uuid,
+    expires_in_seconds: f64,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+        INSERT INTO password_reset_tokens (token_hash, user_id, expires_at)
+        VALUES ($1, $2, NOW() + make_interval(secs => $3))
+        "#,
+        token_hash,
+        user_id,
+        expires_in_seconds
+    )
+    .execute(conn)
+    .await
+    .context("Failed to insert new password reset token into database.")
+    .map_err(ApiError::Database)?;
+    Ok(())
+}
+
+/// Consumes a reset token, returning the user it belongs to if it was unused and unexpired
+pub async fn use_password_reset_token(conn: &PgPool, token_hash: &str) -> Result<Option<Uuid>> {
+    let user_id = sqlx::query!(
+        r#"
+        UPDATE password_reset_tokens
+        SET used_at = NOW()
+        WHERE token_hash = $1 AND used_at IS NULL AND expires_at > NOW()
+        RETURNING user_id
+        "#,
+        token_hash
+    )
+    .fetch_optional(conn)
+    .await
+    .context("Failed to use password reset token.")
+    .map_err(ApiError::Database)?
+    .map(|row| row.user_id);
+
+    Ok(user_id)
+}
+
+pub async fn invalidate_password_reset_tokens(conn: &PgPool, user_id: &Uuid) -> Result<()> {
+    sqlx::query!(
+        r#"
+        UPDATE password_reset_tokens
+        SET used_at = NOW()
+        WHERE user_id = $1 AND used_at IS NULL
+        "#,
+        user_id
+    )
+    .execute(conn)
+    .await
+    .context("Failed to invalidate password reset tokens.")
+    .map_err(ApiError::Database)?;
+    Ok(())
+}
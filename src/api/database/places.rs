@@ -0,0 +1,288 @@
+use std::str::FromStr;
+
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::api::models::{
+    error::{ApiError, Result},
+    CreatePlace, Pagination, Place, PlaceCategory, PlaceContributor, Post, ProfileImage,
+    PublicUser,
+};
+
+use super::{posts_from_rows, PostRow};
+
+/// Adds the place, returning `None` if there already is one with the same name in the
+/// same country
+pub async fn insert_place(conn: &PgPool, place: &CreatePlace) -> Result<Option<Uuid>> {
+    let place_id = sqlx::query!(
+        r#"
+        INSERT INTO places (id, name, normalized_name, country_code, latitude, longitude, category)
+        VALUES ($1, REGEXP_REPLACE(TRIM($2), '\s+', ' ', 'g'), normalize_place_name($2), UPPER($3),
+            $4, $5, $6)
+        ON CONFLICT (normalized_name, (COALESCE(country_code, ''))) DO NOTHING
+        RETURNING id
+        "#,
+        Uuid::new_v4(),
+        place.name,
+        place.country_code,
+        place.latitude,
+        place.longitude,
+        place.category.map(|category| category.as_str())
+    )
+    .fetch_optional(conn)
+    .await
+    .context("Failed to insert new place into database.")
+    .map_err(ApiError::Database)?
+    .map(|place| place.id);
+
+    Ok(place_id)
+}
+
+/// The place with the same name, adding it if there isn't one yet
+///
+/// Without a country any place with the name will do, the one with the most posts if
+/// there are several.
+pub async fn find_or_insert_place(conn: &PgPool, place: &CreatePlace) -> Result<Uuid> {
+    let existing = sqlx::query!(
+        r#"
+        SELECT id
+        FROM places
+        WHERE normalized_name = normalize_place_name($1)
+            AND ($2::TEXT IS NULL OR country_code = UPPER($2))
+        ORDER BY (SELECT COUNT(*) FROM posts WHERE posts.place_id = places.id) DESC, created_at
+        LIMIT 1
+        "#,
+        place.name,
+        place.country_code
+    )
+    .fetch_optional(conn)
+    .await
+    .context("Failed to find place by name.")
+    .map_err(ApiError::Database)?;
+    if let Some(existing) = existing {
+        return Ok(existing.id);
+    }
+
+    // Updating on conflict returns the id of a place added since the lookup
+    let place_id = sqlx::query!(
+        r#"
+        INSERT INTO places (id, name, normalized_name, country_code, latitude, longitude, category)
+        VALUES ($1, REGEXP_REPLACE(TRIM($2), '\s+', ' ', 'g'), normalize_place_name($2), UPPER($3),
+            $4, $5, $6)
+        ON CONFLICT (normalized_name, (COALESCE(country_code, '')))
+            DO UPDATE SET normalized_name = EXCLUDED.normalized_name
+        RETURNING id
+        "#,
+        Uuid::new_v4(),
+        place.name,
+        place.country_code,
+        place.latitude,
+        place.longitude,
+        place.category.map(|category| category.as_str())
+    )
+    .fetch_one(conn)
+    .await
+    .context("Failed to insert new place into database.")
+    .map_err(ApiError::Database)?
+    .id;
+
+    Ok(place_id)
+}
+
+/// The place, counting only the posts from it that the viewer is allowed to see
+pub async fn get_place_by_id(
+    conn: &PgPool,
+    place_id: &Uuid,
+    viewer_id: Option<&Uuid>,
+) -> Result<Option<Place>> {
+    let place = sqlx::query!(
+        r#"
+        SELECT id, name, country_code, latitude, longitude, category,
+        (
+            SELECT COUNT(*)
+            FROM posts
+            WHERE posts.place_id = places.id AND can_view_author(posts.author, $2)
+        ) AS "num_posts!"
+        FROM places
+        WHERE id = $1
+        "#,
+        place_id,
+        viewer_id
+    )
+    .fetch_optional(conn)
+    .await
+    .context("Failed to get place by id.")
+    .map_err(ApiError::Database)?;
+    let Some(place) = place else {
+        return Ok(None);
+    };
+
+    Ok(Some(Place {
+        id: place.id.to_string(),
+        name: place.name,
+        country_code: place.country_code,
+        latitude: place.latitude,
+        longitude: place.longitude,
+        category: place
+            .category
+            .map(|category| PlaceCategory::from_str(&category))
+            .transpose()?,
+        num_posts: place.num_posts as u32,
+    }))
+}
+
+/// Places whose name contains `query`, names starting with it first and then the places
+/// with the most posts the viewer is allowed to see
+///
+/// `query` must already be normalized and have its `LIKE` wildcards escaped.
+pub async fn search_places(
+    conn: &PgPool,
+    query: &str,
+    viewer_id: Option<&Uuid>,
+    limit: i64,
+) -> Result<Vec<Place>> {
+    sqlx::query!(
+        r#"
+        SELECT id, name, country_code, latitude, longitude, category,
+        (
+            SELECT COUNT(*)
+            FROM posts
+            WHERE posts.place_id = places.id AND can_view_author(posts.author, $2)
+        ) AS "num_posts!"
+        FROM places
+        WHERE normalized_name LIKE '%' || $1 || '%'
+        ORDER BY normalized_name LIKE $1 || '%' DESC, "num_posts!" DESC, name
+        LIMIT $3
+        "#,
+        query,
+        viewer_id,
+        limit
+    )
+    .fetch_all(conn)
+    .await
+    .context("Failed to search places.")
+    .map_err(ApiError::Database)?
+    .into_iter()
+    .map(|place| {
+        Ok(Place {
+            id: place.id.to_string(),
+            name: place.name,
+            country_code: place.country_code,
+            latitude: place.latitude,
+            longitude: place.longitude,
+            category: place
+                .category
+                .map(|category| PlaceCategory::from_str(&category))
+                .transpose()?,
+            num_posts: place.num_posts as u32,
+        })
+    })
+    .collect()
+}
+
+/// How many different active users have posted from the place, counting only those whose
+/// posts the viewer is allowed to see
+pub async fn count_place_visitors(
+    conn: &PgPool,
+    place_id: &Uuid,
+    viewer_id: Option<&Uuid>,
+) -> Result<u32> {
+    let visitors = sqlx::query!(
+        r#"
+        SELECT COUNT(DISTINCT posts.author) AS "num_visitors!"
+        FROM posts
+        WHERE posts.place_id = $1 AND can_view_author(posts.author, $2)
+        "#,
+        place_id,
+        viewer_id
+    )
+    .fetch_one(conn)
+    .await
+    .context("Failed to count place's visitors.")
+    .map_err(ApiError::Database)?
+    .num_visitors;
+
+    Ok(visitors as u32)
+}
+
+/// The users with the most posts from the place, whoever got there first breaking ties
+///
+/// Users whose posts the viewer isn't allowed to see are left out.
+pub async fn get_top_contributors(
+    conn: &PgPool,
+    place_id: &Uuid,
+    viewer_id: Option<&Uuid>,
+    limit: i64,
+) -> Result<Vec<PlaceContributor>> {
+    let contributors = sqlx::query!(
+        r#"
+        SELECT users.id, users.username, users.email, users.description, users.first_name,
+            users.last_name, users.show_email, users.is_private, users.avatar_id, users.cover_id,
+            COUNT(*) AS "num_posts!"
+        FROM posts
+        INNER JOIN users ON users.id = posts.author
+        WHERE posts.place_id = $1 AND can_view_author(users.id, $2)
+        GROUP BY users.id
+        ORDER BY "num_posts!" DESC, MIN(posts.created_at)
+        LIMIT $3
+        "#,
+        place_id,
+        viewer_id,
+        limit
+    )
+    .fetch_all(conn)
+    .await
+    .context("Failed to get place's top contributors.")
+    .map_err(ApiError::Database)?
+    .into_iter()
+    .map(|user| PlaceContributor {
+        num_posts: user.num_posts as u32,
+        user: PublicUser {
+            id: user.id.to_string(),
+            username: user.username,
+            description: user.description,
+            name: format!("{} {}", user.first_name, user.last_name),
+            email: user.show_email.then_some(user.email),
+            is_private: user.is_private,
+            avatar: ProfileImage::avatar(user.avatar_id),
+            cover: ProfileImage::cover(user.cover_id),
+        },
+    })
+    .collect::<Vec<PlaceContributor>>();
+
+    Ok(contributors)
+}
+
+/// Newest posts from the place, leaving out posts the viewer isn't allowed to see
+pub async fn get_place_posts(
+    conn: &PgPool,
+    place_id: &Uuid,
+    viewer_id: Option<&Uuid>,
+    pagination: &Pagination,
+) -> Result<Vec<Post>> {
+    let rows = sqlx::query_as!(
+        PostRow,
+        r#"
+        SELECT posts.id, posts.title, posts.location, posts.latitude, posts.longitude,
+            posts.country_code, posts.place_id, posts.content, posts.author, posts.created_at,
+            posts.edited_at,
+        (SELECT COUNT(*) FROM comments WHERE comments.post_id = posts.id) AS "num_comments!",
+        (SELECT COUNT(*) FROM likes WHERE likes.post_id = posts.id) AS "num_likes!"
+        FROM posts
+        WHERE posts.place_id = $1 AND can_view_author(posts.author, $2)
+        ORDER BY posts.created_at DESC, posts.id
+        LIMIT $3 OFFSET $4
+        "#,
+        place_id,
+        viewer_id,
+        pagination.limit(),
+        pagination.offset()
+    )
+    .fetch_all(conn)
+    .await
+    .context("Failed to get place's posts.")
+    .map_err(ApiError::Database)?;
+
+    posts_from_rows(conn, rows).await
+}
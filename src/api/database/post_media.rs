@@ -0,0 +1,95 @@
+use std::collections::HashMap;
+
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::api::models::{
+    error::{ApiError, Result},
+    PostMedia,
+};
+
+pub async fn insert_post_media(
+    conn: &PgPool,
+    media_id: &Uuid,
+    uploader_id: &Uuid,
+    width: u32,
+    height: u32,
+) -> Result<PostMedia> {
+    let media = sqlx::query!(
+        r#"
+        INSERT INTO post_media (id, uploader_id, width, height)
+        VALUES ($1, $2, $3, $4)
+        RETURNING id, alt_text, width, height
+        "#,
+        media_id,
+        uploader_id,
+        width as i32,
+        height as i32
+    )
+    .fetch_one(conn)
+    .await
+    .context("Failed to insert new post media into database.")
+    .map_err(ApiError::Database)?;
+
+    Ok(PostMedia::new(
+        &media.id,
+        media.alt_text,
+        media.width,
+        media.height,
+    ))
+}
+
+/// The photos attached to each of the posts, in order, keyed by post id
+pub async fn get_media_of_posts(
+    conn: &PgPool,
+    post_ids: &[Uuid],
+) -> Result<HashMap<Uuid, Vec<PostMedia>>> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT id, post_id AS "post_id!", alt_text, width, height
+        FROM post_media
+        WHERE post_id = ANY($1)
+        ORDER BY post_id, position
+        "#,
+        post_ids
+    )
+    .fetch_all(conn)
+    .await
+    .context("Failed to get media of posts.")
+    .map_err(ApiError::Database)?;
+
+    let mut media = HashMap::<Uuid, Vec<PostMedia>>::new();
+    for row in rows {
+        media.entry(row.post_id).or_default().push(PostMedia::new(
+            &row.id,
+            row.alt_text,
+            row.width,
+            row.height,
+        ));
+    }
+
+    Ok(media)
+}
+
+/// Deletes uploads that were never attached to a post, returning their ids so that the
+/// images can be deleted as well
+pub async fn delete_unattached_media(conn: &PgPool, older_than_seconds: f64) -> Result<Vec<Uuid>> {
+    let deleted = sqlx::query!(
+        r#"
+        DELETE FROM post_media
+        WHERE post_id IS NULL AND created_at < NOW() - make_interval(secs => $1)
+        RETURNING id
+        "#,
+        older_than_seconds
+    )
+    .fetch_all(conn)
+    .await
+    .context("Failed to delete unattached post media.")
+    .map_err(ApiError::Database)?
+    .into_iter()
+    .map(|media| media.id)
+    .collect::<Vec<Uuid>>();
+
+    Ok(deleted)
+}
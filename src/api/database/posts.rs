@@ -1,15 +1,68 @@
 use crate::api::models::{
     error::{ApiError, Result},
-    AuthUser, CreatePost, Like, Post,
+    BoundingBox, CreatePost, ExportedLike, Like, NearbyPost, NewMention, Pagination, Post,
+    PostEdit, ProfileImage, PublicUser, UpdatePost,
 };
+
+use super::{get_media_of_posts, get_mentions_of_posts, set_post_mentions, set_post_tags};
 use anyhow::Context;
+use chrono::NaiveDateTime;
 use sqlx::PgPool;
+use std::collections::HashMap;
 use uuid::Uuid;
 
+/// A post as it's selected, before its media and mentions are looked up
+pub struct PostRow {
+    pub id: Uuid,
+    pub title: String,
+    pub location: String,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub country_code: Option<String>,
+    pub place_id: Option<Uuid>,
+    pub content: String,
+    pub author: Uuid,
+    pub created_at: NaiveDateTime,
+    pub edited_at: Option<NaiveDateTime>,
+    pub num_comments: i64,
+    pub num_likes: i64,
+}
+
+/// The posts with their media and mentions, in the same order as the rows
+pub async fn posts_from_rows(conn: &PgPool, rows: Vec<PostRow>) -> Result<Vec<Post>> {
+    let post_ids = rows.iter().map(|post| post.id).collect::<Vec<Uuid>>();
+    let mut media = get_media_of_posts(conn, &post_ids).await?;
+    let mut mentions = get_mentions_of_posts(conn, &post_ids).await?;
+    let posts = rows
+        .into_iter()
+        .map(|post| Post {
+            id: post.id.to_string(),
+            title: post.title,
+            location: post.location,
+            latitude: post.latitude,
+            longitude: post.longitude,
+            country_code: post.country_code,
+            place_id: post.place_id.map(|place_id| place_id.to_string()),
+            content: post.content,
+            author: post.author.to_string(),
+            created_at: post.created_at.timestamp(),
+            edited_at: post.edited_at.map(|edited_at| edited_at.timestamp()),
+            num_likes: post.num_likes as u32,
+            num_comments: post.num_comments as u32,
+            media: media.remove(&post.id).unwrap_or_default(),
+            mentions: mentions.remove(&post.id).unwrap_or_default(),
+        })
+        .collect::<Vec<Post>>();
+
+    Ok(posts)
+}
+
 pub async fn get_users_posts(conn: &PgPool, user_id: &Uuid) -> Result<Vec<Post>> {
-    let posts = sqlx::query!(
+    let rows = sqlx::query_as!(
+        PostRow,
         r#"
-        SELECT id, title, location, author, content, created_at,
+        SELECT id, title, location, latitude, longitude, country_code, place_id, author, content,
+            created_at, edited_at,
         (SELECT COUNT(*) FROM comments WHERE comments.post_id = posts.id) AS "num_comments!", 
         (SELECT COUNT(*) FROM likes WHERE likes.post_id = posts.id) AS "num_likes!"
         FROM posts
@@ -20,47 +73,91 @@ pub async fn get_users_posts(conn: &PgPool, user_id: &Uuid) -> Result<Vec<Post>>
     .fetch_all(conn)
     .await
     .context("Failed to get user's posts.")
-    .map_err(ApiError::Database)?
-    .into_iter()
-    .map(|post| Post {
-        id: post.id.to_string(),
-        title: post.title,
-        location: post.location,
-        content: post.content,
-        author: post.author.to_string(),
-        created_at: post.created_at.timestamp(),
-        num_likes: post.num_likes as u32,
-        num_comments: post.num_comments as u32,
-    })
-    .collect::<Vec<Post>>();
+    .map_err(ApiError::Database)?;
 
-    Ok(posts)
+    posts_from_rows(conn, rows).await
 }
 
-pub async fn insert_post(conn: &PgPool, user_id: Uuid, new_post: CreatePost) -> Result<String> {
+/// Inserts the post at the place with its hashtags and mentions and attaches the uploaded
+/// media to it in order, returning `None` if any of the media isn't the user's own
+/// unattached upload
+pub async fn insert_post(
+    conn: &PgPool,
+    user_id: Uuid,
+    new_post: CreatePost,
+    place_id: &Uuid,
+    media: &[(Uuid, Option<String>)],
+    tags: &[String],
+    mentions: &[NewMention],
+) -> Result<Option<String>> {
     let id = Uuid::new_v4();
+    let mut transaction = conn
+        .begin()
+        .await
+        .context("Failed to start transaction.")
+        .map_err(ApiError::Database)?;
+
     sqlx::query!(
         r#"
-        INSERT INTO posts (id, title, location, author, content)
-        VALUES ($1, $2, $3, $4, $5)
+        INSERT INTO posts (
+            id, title, location, latitude, longitude, country_code, place_id, author, content
+        )
+        VALUES ($1, $2, $3, $4, $5, UPPER($6), $7, $8, $9)
         "#,
         id,
         new_post.title,
         new_post.location,
+        new_post.latitude,
+        new_post.longitude,
+        new_post.country_code,
+        place_id,
         user_id,
         new_post.content
     )
-    .execute(conn)
+    .execute(&mut *transaction)
     .await
     .context("Failed to insert new post into database.")
     .map_err(ApiError::Database)?;
-    Ok(id.to_string())
+
+    for (position, (media_id, alt_text)) in media.iter().enumerate() {
+        let attached = sqlx::query!(
+            r#"
+            UPDATE post_media
+            SET post_id = $1, position = $2, alt_text = $3
+            WHERE id = $4 AND uploader_id = $5 AND post_id IS NULL
+            "#,
+            id,
+            position as i32,
+            alt_text.as_deref(),
+            media_id,
+            user_id
+        )
+        .execute(&mut *transaction)
+        .await
+        .context("Failed to attach media to post.")
+        .map_err(ApiError::Database)?
+        .rows_affected();
+        if attached == 0 {
+            return Ok(None);
+        }
+    }
+    set_post_tags(&mut transaction, &id, tags).await?;
+    set_post_mentions(&mut transaction, &id, mentions).await?;
+
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit transaction.")
+        .map_err(ApiError::Database)?;
+    Ok(Some(id.to_string()))
 }
 
 pub async fn get_post_by_id(conn: &PgPool, post_id: &Uuid) -> Result<Option<Post>> {
-    let post = sqlx::query!(
+    let row = sqlx::query_as!(
+        PostRow,
         r#"
-        SELECT id, title, location, author, content, created_at,
+        SELECT id, title, location, latitude, longitude, country_code, place_id, author, content,
+            created_at, edited_at,
         (SELECT COUNT(*) FROM comments WHERE comments.post_id = posts.id) AS "num_comments!", 
         (SELECT COUNT(*) FROM likes WHERE likes.post_id = posts.id) AS "num_likes!"
         FROM posts
@@ -71,21 +168,245 @@ pub async fn get_post_by_id(conn: &PgPool, post_id: &Uuid) -> Result<Option<Post
     .fetch_optional(conn)
     .await
     .context("Failed to get post by id.")
-    .map_err(ApiError::Database)?
-    .map(|post| Post {
-        id: post.id.to_string(),
-        title: post.title,
-        location: post.location,
-        content: post.content,
-        author: post.author.to_string(),
-        created_at: post.created_at.timestamp(),
-        num_comments: post.num_comments as u32,
-        num_likes: post.num_likes as u32,
-    });
+    .map_err(ApiError::Database)?;
+    let Some(row) = row else {
+        return Ok(None);
+    };
 
+    let post = posts_from_rows(conn, vec![row]).await?.pop();
     Ok(post)
 }
 
+/// Applies an edit, keeping the version it replaces in the post's history, and replaces
+/// the post's hashtags and mentions with those of the edited post
+///
+/// `place_id` is the place of the new location, if the location was edited.
+pub async fn update_post(
+    conn: &PgPool,
+    post_id: &Uuid,
+    update: &UpdatePost,
+    place_id: Option<&Uuid>,
+    tags: &[String],
+    mentions: &[NewMention],
+) -> Result<()> {
+    let mut transaction = conn
+        .begin()
+        .await
+        .context("Failed to start transaction.")
+        .map_err(ApiError::Database)?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO post_edits (id, post_id, title, location, content)
+        SELECT $1, id, title, location, content
+        FROM posts
+        WHERE id = $2
+        "#,
+        Uuid::new_v4(),
+        post_id
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to save previous version of post.")
+    .map_err(ApiError::Database)?;
+
+    sqlx::query!(
+        r#"
+        UPDATE posts
+        SET title = COALESCE($2, title),
+            location = COALESCE($3, location),
+            content = COALESCE($4, content),
+            place_id = COALESCE($5, place_id),
+            edited_at = NOW()
+        WHERE id = $1
+        "#,
+        post_id,
+        update.title,
+        update.location,
+        update.content,
+        place_id
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to update post.")
+    .map_err(ApiError::Database)?;
+    set_post_tags(&mut transaction, post_id, tags).await?;
+    set_post_mentions(&mut transaction, post_id, mentions).await?;
+
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit transaction.")
+        .map_err(ApiError::Database)?;
+    Ok(())
+}
+
+/// Earlier versions of the post, most recently replaced first
+pub async fn get_post_edits(conn: &PgPool, post_id: &Uuid) -> Result<Vec<PostEdit>> {
+    let edits = sqlx::query!(
+        r#"
+        SELECT title, location, content, edited_at
+        FROM post_edits
+        WHERE post_id = $1
+        ORDER BY edited_at DESC
+        "#,
+        post_id
+    )
+    .fetch_all(conn)
+    .await
+    .context("Failed to get post's edit history.")
+    .map_err(ApiError::Database)?
+    .into_iter()
+    .map(|edit| PostEdit {
+        title: edit.title,
+        location: edit.location,
+        content: edit.content,
+        edited_at: edit.edited_at.timestamp(),
+    })
+    .collect::<Vec<PostEdit>>();
+
+    Ok(edits)
+}
+
+/// Deletes the post along with its comments, likes, media and history
+pub async fn delete_post(conn: &PgPool, post_id: &Uuid) -> Result<()> {
+    sqlx::query!(
+        r#"
+        DELETE FROM posts
+        WHERE id = $1
+        "#,
+        post_id
+    )
+    .execute(conn)
+    .await
+    .context("Failed to delete post.")
+    .map_err(ApiError::Database)?;
+
+    Ok(())
+}
+
+/// Posts within `radius_km` of the point, closest first, leaving out posts the viewer isn't
+/// allowed to see
+pub async fn get_nearby_posts(
+    conn: &PgPool,
+    latitude: f64,
+    longitude: f64,
+    radius_km: f64,
+    viewer_id: Option<&Uuid>,
+    pagination: &Pagination,
+) -> Result<Vec<NearbyPost>> {
+    let bounds = BoundingBox::around(latitude, longitude, radius_km);
+    let nearby = sqlx::query!(
+        r#"
+        SELECT posts.id,
+        great_circle_distance_km($1, $2, posts.latitude, posts.longitude) AS "distance_km!"
+        FROM posts
+        WHERE posts.latitude BETWEEN $4 AND $5
+            AND (
+                CASE WHEN $6::DOUBLE PRECISION <= $7::DOUBLE PRECISION
+                    THEN posts.longitude BETWEEN $6 AND $7
+                    ELSE posts.longitude >= $6 OR posts.longitude <= $7
+                END
+            )
+            AND great_circle_distance_km($1, $2, posts.latitude, posts.longitude) <= $3
+            AND can_view_author(posts.author, $8)
+        ORDER BY "distance_km!", posts.id
+        LIMIT $9 OFFSET $10
+        "#,
+        latitude,
+        longitude,
+        radius_km,
+        bounds.min_latitude,
+        bounds.max_latitude,
+        bounds.min_longitude,
+        bounds.max_longitude,
+        viewer_id,
+        pagination.limit(),
+        pagination.offset()
+    )
+    .fetch_all(conn)
+    .await
+    .context("Failed to get nearby posts.")
+    .map_err(ApiError::Database)?;
+
+    let post_ids = nearby.iter().map(|post| post.id).collect::<Vec<Uuid>>();
+    let rows = sqlx::query_as!(
+        PostRow,
+        r#"
+        SELECT id, title, location, latitude, longitude, country_code, place_id, author, content,
+            created_at, edited_at,
+        (SELECT COUNT(*) FROM comments WHERE comments.post_id = posts.id) AS "num_comments!",
+        (SELECT COUNT(*) FROM likes WHERE likes.post_id = posts.id) AS "num_likes!"
+        FROM posts
+        WHERE id = ANY($1)
+        "#,
+        &post_ids
+    )
+    .fetch_all(conn)
+    .await
+    .context("Failed to get nearby posts.")
+    .map_err(ApiError::Database)?;
+    let mut posts = posts_from_rows(conn, rows)
+        .await?
+        .into_iter()
+        .map(|post| (post.id.clone(), post))
+        .collect::<HashMap<String, Post>>();
+
+    let posts = nearby
+        .into_iter()
+        .filter_map(|nearby| {
+            Some(NearbyPost {
+                post: posts.remove(&nearby.id.to_string())?,
+                distance_km: nearby.distance_km,
+            })
+        })
+        .collect::<Vec<NearbyPost>>();
+
+    Ok(posts)
+}
+
+/// Newest posts inside the box, leaving out posts the viewer isn't allowed to see
+pub async fn get_posts_in_bounds(
+    conn: &PgPool,
+    bounds: &BoundingBox,
+    viewer_id: Option<&Uuid>,
+    pagination: &Pagination,
+) -> Result<Vec<Post>> {
+    let rows = sqlx::query_as!(
+        PostRow,
+        r#"
+        SELECT id, title, location, latitude, longitude, country_code, place_id, author, content,
+            created_at, edited_at,
+        (SELECT COUNT(*) FROM comments WHERE comments.post_id = posts.id) AS "num_comments!",
+        (SELECT COUNT(*) FROM likes WHERE likes.post_id = posts.id) AS "num_likes!"
+        FROM posts
+        WHERE posts.latitude BETWEEN $1 AND $2
+            AND (
+                CASE WHEN $3::DOUBLE PRECISION <= $4::DOUBLE PRECISION
+                    THEN posts.longitude BETWEEN $3 AND $4
+                    ELSE posts.longitude >= $3 OR posts.longitude <= $4
+                END
+            )
+            AND can_view_author(posts.author, $5)
+        ORDER BY posts.created_at DESC, posts.id
+        LIMIT $6 OFFSET $7
+        "#,
+        bounds.min_latitude,
+        bounds.max_latitude,
+        bounds.min_longitude,
+        bounds.max_longitude,
+        viewer_id,
+        pagination.limit(),
+        pagination.offset()
+    )
+    .fetch_all(conn)
+    .await
+    .context("Failed to get posts in bounds.")
+    .map_err(ApiError::Database)?;
+
+    posts_from_rows(conn, rows).await
+}
+
 pub async fn get_like_by_user_and_post(
     conn: &PgPool,
     user_id: &Uuid,
@@ -93,7 +414,8 @@ pub async fn get_like_by_user_and_post(
 ) -> Result<Option<Like>> {
     let like = sqlx::query!(
         r#"
-        SELECT user_id, post_id, likes.created_at, username, email, description, first_name, last_name
+        SELECT user_id, post_id, likes.created_at, username, email, description, first_name, last_name,
+            show_email, is_private, avatar_id, cover_id
         FROM likes, users
         WHERE likes.user_id = users.id AND likes.user_id = $1 AND likes.post_id = $2
         "#,
@@ -107,12 +429,15 @@ pub async fn get_like_by_user_and_post(
     .map(|like| Like {
         post_id: like.post_id.to_string(),
         created_at: like.created_at.timestamp(),
-        user: AuthUser {
+        user: PublicUser {
             id: like.user_id.to_string(),
             username: like.username,
             description: like.description,
             name: format!("{} {}", like.first_name, like.last_name),
-            email: like.email,
+            email: like.show_email.then_some(like.email),
+            is_private: like.is_private,
+            avatar: ProfileImage::avatar(like.avatar_id),
+            cover: ProfileImage::cover(like.cover_id),
         },
     });
 
@@ -122,7 +447,8 @@ pub async fn get_like_by_user_and_post(
 pub async fn get_likes_of_post(conn: &PgPool, post_id: &Uuid) -> Result<Vec<Like>> {
     let likes = sqlx::query!(
         r#"
-        SELECT user_id, post_id, likes.created_at, username, email, description, first_name, last_name
+        SELECT user_id, post_id, likes.created_at, username, email, description, first_name, last_name,
+            show_email, is_private, avatar_id, cover_id
         FROM likes, users
         WHERE likes.user_id = users.id AND likes.post_id = $1
         "#,
@@ -134,12 +460,15 @@ pub async fn get_likes_of_post(conn: &PgPool, post_id: &Uuid) -> Result<Vec<Like
     .map_err(ApiError::Database)?
     .into_iter()
     .map(|like| Like {
-        user: AuthUser {
+        user: PublicUser {
             id: like.user_id.to_string(),
             description: like.description,
             name: format!("{} {}", like.first_name, like.last_name),
             username: like.username,
-            email: like.email,
+            email: like.show_email.then_some(like.email),
+            is_private: like.is_private,
+            avatar: ProfileImage::avatar(like.avatar_id),
+            cover: ProfileImage::cover(like.cover_id),
         },
         post_id: like.post_id.to_string(),
         created_at: like.created_at.timestamp(),
@@ -149,6 +478,30 @@ pub async fn get_likes_of_post(conn: &PgPool, post_id: &Uuid) -> Result<Vec<Like
     Ok(likes)
 }
 
+pub async fn get_likes_by_user(conn: &PgPool, user_id: &Uuid) -> Result<Vec<ExportedLike>> {
+    let likes = sqlx::query!(
+        r#"
+        SELECT post_id, created_at
+        FROM likes
+        WHERE user_id = $1
+        ORDER BY created_at
+        "#,
+        user_id
+    )
+    .fetch_all(conn)
+    .await
+    .context("Failed to get user's likes.")
+    .map_err(ApiError::Database)?
+    .into_iter()
+    .map(|like| ExportedLike {
+        post_id: like.post_id.to_string(),
+        created_at: like.created_at.timestamp(),
+    })
+    .collect::<Vec<ExportedLike>>();
+
+    Ok(likes)
+}
+
 pub async fn like_post(conn: &PgPool, user_id: &Uuid, post_id: &Uuid) -> Result<()> {
     sqlx::query!(
         r#"
@@ -0,0 +1,62 @@
+use std::str::FromStr;
+
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::api::models::{
+    error::{ApiError, Result},
+    Role,
+};
+
+pub async fn get_user_roles(conn: &PgPool, user_id: &Uuid) -> Result<Vec<Role>> {
+    sqlx::query!(
+        r#"
+        SELECT role
+        FROM user_roles
+        WHERE user_id = $1
+        ORDER BY role
+        "#,
+        user_id
+    )
+    .fetch_all(conn)
+    .await
+    .context("Failed to get user roles.")
+    .map_err(ApiError::Database)?
+    .into_iter()
+    .map(|row| Role::from_str(&row.role))
+    .collect()
+}
+
+pub async fn grant_role(conn: &PgPool, user_id: &Uuid, role: Role) -> Result<()> {
+    sqlx::query!(
+        r#"
+        INSERT INTO user_roles (user_id, role)
+        VALUES ($1, $2)
+        ON CONFLICT (user_id, role) DO NOTHING
+        "#,
+        user_id,
+        role.as_str()
+    )
+    .execute(conn)
+    .await
+    .context("Failed to grant role.")
+    .map_err(ApiError::Database)?;
+    Ok(())
+}
+
+pub async fn revoke_role(conn: &PgPool, user_id: &Uuid, role: Role) -> Result<()> {
+    sqlx::query!(
+        r#"
+        DELETE FROM user_roles
+        WHERE user_id = $1 AND role = $2
+        "#,
+        user_id,
+        role.as_str()
+    )
+    .execute(conn)
+    .await
+    .context("Failed to revoke role.")
+    .map_err(ApiError::Database)?;
+    Ok(())
+}
@@ -0,0 +1,204 @@
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::api::models::{
+    error::{ApiError, Result},
+    ClientInfo, RefreshToken, Session,
+};
+
+pub async fn insert_session(
+    conn: &PgPool,
+    session_id: &Uuid,
+    user_id: &Uuid,
+    client: &ClientInfo,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+        INSERT INTO sessions (id, user_id, user_agent, ip_address)
+        VALUES ($1, $2, $3, $4)
+        "#,
+        session_id,
+        user_id,
+        client.user_agent,
+        client.ip_address
+    )
+    .execute(conn)
+    .await
+    .context("Failed to insert new session into database.")
+    .map_err(ApiError::Database)?;
+    Ok(())
+}
+
+pub async fn get_session_by_id(conn: &PgPool, session_id: &Uuid) -> Result<Option<Session>> {
+    let session = sqlx::query_as!(
+        Session,
+        r#"
+        SELECT id, user_id, created_at, last_seen_at, revoked_at, user_agent, ip_address
+        FROM sessions
+        WHERE id = $1
+        "#,
+        session_id
+    )
+    .fetch_optional(conn)
+    .await
+    .context("Failed to get session by id.")
+    .map_err(ApiError::Database)?;
+
+    Ok(session)
+}
+
+pub async fn get_active_sessions(conn: &PgPool, user_id: &Uuid) -> Result<Vec<Session>> {
+    let sessions = sqlx::query_as!(
+        Session,
+        r#"
+        SELECT id, user_id, created_at, last_seen_at, revoked_at, user_agent, ip_address
+        FROM sessions
+        WHERE user_id = $1 AND revoked_at IS NULL
+        ORDER BY last_seen_at DESC
+        "#,
+        user_id
+    )
+    .fetch_all(conn)
+    .await
+    .context("Failed to get user's sessions.")
+    .map_err(ApiError::Database)?;
+
+    Ok(sessions)
+}
+
+// Returns whether the session is still active, bumping `last_seen_at` at most once a
+// minute so that authenticated requests don't all turn into writes
+pub async fn touch_session(conn: &PgPool, session_id: &Uuid) -> Result<bool> {
+    let is_active = sqlx::query!(
+        r#"
+        WITH active AS (
+            SELECT id, last_seen_at
+            FROM sessions
+            WHERE id = $1 AND revoked_at IS NULL
+        ), touched AS (
+            UPDATE sessions
+            SET last_seen_at = NOW()
+            WHERE id IN (
+                SELECT id FROM active WHERE last_seen_at < NOW() - INTERVAL '1 minute'
+            )
+        )
+        SELECT EXISTS (SELECT 1 FROM active) AS "is_active!"
+        "#,
+        session_id
+    )
+    .fetch_one(conn)
+    .await
+    .context("Failed to check if session is active.")
+    .map_err(ApiError::Database)?
+    .is_active;
+
+    Ok(is_active)
+}
+
+pub async fn revoke_session(conn: &PgPool, session_id: &Uuid) -> Result<()> {
+    sqlx::query!(
+        r#"
+        UPDATE sessions
+        SET revoked_at = NOW()
+        WHERE id = $1 AND revoked_at IS NULL
+        "#,
+        session_id
+    )
+    .execute(conn)
+    .await
+    .context("Failed to revoke session.")
+    .map_err(ApiError::Database)?;
+    Ok(())
+}
+
+pub async fn revoke_all_sessions(conn: &PgPool, user_id: &Uuid) -> Result<()> {
+    sqlx::query!(
+        r#"
+        UPDATE sessions
+        SET revoked_at = NOW()
+        WHERE user_id = $1 AND revoked_at IS NULL
+        "#,
+        user_id
+    )
+    .execute(conn)
+    .await
+    .context("Failed to revoke user's sessions.")
+    .map_err(ApiError::Database)?;
+    Ok(())
+}
+
+/// Revokes every session of the user except the one making the request
+pub async fn revoke_other_sessions(
+    conn: &PgPool,
+    user_id: &Uuid,
+    current_session_id: &Uuid,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+        UPDATE sessions
+        SET revoked_at = NOW()
+        WHERE user_id = $1 AND id <> $2 AND revoked_at IS NULL
+        "#,
+        user_id,
+        current_session_id
+    )
+    .execute(conn)
+    .await
+    .context("Failed to revoke user's other sessions.")
+    .map_err(ApiError::Database)?;
+    Ok(())
+}
+
+pub async fn insert_refresh_token(
+    conn: &PgPool,
+    token_hash: &str,
+    session_id: &Uuid,
+    expires_in_seconds: f64,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+        INSERT INTO refresh_tokens (token_hash, session_id, expires_at)
+        VALUES ($1, $2, NOW() + make_interval(secs => $3))
+        "#,
+        token_hash,
+        session_id,
+        expires_in_seconds
+    )
+    .execute(conn)
+    .await
+    .context("Failed to insert new refresh token into database.")
+    .map_err(ApiError::Database)?;
+    Ok(())
+}
+
+// Marks the token as used in a single statement so that two concurrent refreshes
+// with the same token cannot both see it as unused
+pub async fn use_refresh_token(conn: &PgPool, token_hash: &str) -> Result<Option<RefreshToken>> {
+    let token = sqlx::query!(
+        r#"
+        UPDATE refresh_tokens
+        SET used_at = COALESCE(used_at, NOW())
+        FROM (
+            SELECT token_hash, used_at IS NOT NULL AS was_used
+            FROM refresh_tokens
+            WHERE token_hash = $1
+            FOR UPDATE
+        ) AS previous
+        WHERE refresh_tokens.token_hash = previous.token_hash
+        RETURNING session_id, previous.was_used AS "was_used!", expires_at < NOW() AS "expired!"
+        "#,
+        token_hash
+    )
+    .fetch_optional(conn)
+    .await
+    .context("Failed to use refresh token.")
+    .map_err(ApiError::Database)?
+    .map(|token| RefreshToken {
+        session_id: token.session_id,
+        used: token.was_used,
+        expired: token.expired,
+    });
+
+    Ok(token)
+}
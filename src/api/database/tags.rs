@@ -0,0 +1,222 @@
+use anyhow::Context;
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+use crate::api::models::{
+    error::{ApiError, Result},
+    FollowedTag, Pagination, Post, TrendingTag,
+};
+
+use super::{posts_from_rows, PostRow};
+
+/// Replaces the post's hashtags, creating any tags that haven't been used before
+///
+/// Takes a connection so that it can run in the same transaction as the post's insert
+/// or edit.
+pub async fn set_post_tags(conn: &mut PgConnection, post_id: &Uuid, tags: &[String]) -> Result<()> {
+    sqlx::query!(
+        r#"
+        DELETE FROM post_tags
+        WHERE post_id = $1 AND tag_id NOT IN (SELECT id FROM tags WHERE name = ANY($2))
+        "#,
+        post_id,
+        tags
+    )
+    .execute(&mut *conn)
+    .await
+    .context("Failed to remove post's old tags.")
+    .map_err(ApiError::Database)?;
+    if tags.is_empty() {
+        return Ok(());
+    }
+
+    let ids = tags.iter().map(|_| Uuid::new_v4()).collect::<Vec<Uuid>>();
+    sqlx::query!(
+        r#"
+        INSERT INTO tags (id, name)
+        SELECT * FROM UNNEST($1::UUID[], $2::VARCHAR[])
+        ON CONFLICT (name) DO NOTHING
+        "#,
+        &ids,
+        tags
+    )
+    .execute(&mut *conn)
+    .await
+    .context("Failed to insert new tags.")
+    .map_err(ApiError::Database)?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO post_tags (post_id, tag_id)
+        SELECT $1, id FROM tags WHERE name = ANY($2)
+        ON CONFLICT DO NOTHING
+        "#,
+        post_id,
+        tags
+    )
+    .execute(&mut *conn)
+    .await
+    .context("Failed to tag post.")
+    .map_err(ApiError::Database)?;
+    Ok(())
+}
+
+/// Newest posts with the tag, leaving out posts the viewer isn't allowed to see
+pub async fn get_tag_posts(
+    conn: &PgPool,
+    tag: &str,
+    viewer_id: Option<&Uuid>,
+    pagination: &Pagination,
+) -> Result<Vec<Post>> {
+    let rows = sqlx::query_as!(
+        PostRow,
+        r#"
+        SELECT posts.id, posts.title, posts.location, posts.latitude, posts.longitude,
+            posts.country_code, posts.place_id, posts.content, posts.author, posts.created_at,
+            posts.edited_at,
+        (SELECT COUNT(*) FROM comments WHERE comments.post_id = posts.id) AS "num_comments!",
+        (SELECT COUNT(*) FROM likes WHERE likes.post_id = posts.id) AS "num_likes!"
+        FROM posts
+        INNER JOIN post_tags ON post_tags.post_id = posts.id
+        INNER JOIN tags ON tags.id = post_tags.tag_id
+        WHERE tags.name = $1 AND can_view_author(posts.author, $2)
+        ORDER BY posts.created_at DESC, posts.id
+        LIMIT $3 OFFSET $4
+        "#,
+        tag,
+        viewer_id,
+        pagination.limit(),
+        pagination.offset()
+    )
+    .fetch_all(conn)
+    .await
+    .context("Failed to get tag's posts.")
+    .map_err(ApiError::Database)?;
+
+    posts_from_rows(conn, rows).await
+}
+
+/// The tags used on the most posts within the last `window_seconds`, counting only posts
+/// that anyone can see so that the list doesn't reveal what private accounts post about
+pub async fn get_trending_tags(
+    conn: &PgPool,
+    window_seconds: f64,
+    limit: i64,
+) -> Result<Vec<TrendingTag>> {
+    let tags = sqlx::query!(
+        r#"
+        SELECT tags.name, COUNT(*) AS "num_posts!"
+        FROM post_tags
+        INNER JOIN tags ON tags.id = post_tags.tag_id
+        INNER JOIN posts ON posts.id = post_tags.post_id
+        WHERE post_tags.created_at > NOW() - make_interval(secs => $1)
+            AND can_view_author(posts.author, NULL)
+        GROUP BY tags.name
+        ORDER BY "num_posts!" DESC, tags.name
+        LIMIT $2
+        "#,
+        window_seconds,
+        limit
+    )
+    .fetch_all(conn)
+    .await
+    .context("Failed to get trending tags.")
+    .map_err(ApiError::Database)?
+    .into_iter()
+    .map(|tag| TrendingTag {
+        name: tag.name,
+        num_posts: tag.num_posts as u32,
+    })
+    .collect::<Vec<TrendingTag>>();
+
+    Ok(tags)
+}
+
+/// Follows the tag, creating it if nobody has used it yet
+pub async fn follow_tag(conn: &PgPool, user_id: &Uuid, tag: &str) -> Result<()> {
+    let mut transaction = conn
+        .begin()
+        .await
+        .context("Failed to start transaction.")
+        .map_err(ApiError::Database)?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO tags (id, name)
+        VALUES ($1, $2)
+        ON CONFLICT (name) DO NOTHING
+        "#,
+        Uuid::new_v4(),
+        tag
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to insert new tag.")
+    .map_err(ApiError::Database)?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO tag_follows (user_id, tag_id)
+        SELECT $1, id FROM tags WHERE name = $2
+        ON CONFLICT DO NOTHING
+        "#,
+        user_id,
+        tag
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to follow tag.")
+    .map_err(ApiError::Database)?;
+
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit transaction.")
+        .map_err(ApiError::Database)?;
+    Ok(())
+}
+
+/// Returns false if the user wasn't following the tag
+pub async fn unfollow_tag(conn: &PgPool, user_id: &Uuid, tag: &str) -> Result<bool> {
+    let deleted = sqlx::query!(
+        r#"
+        DELETE FROM tag_follows
+        WHERE user_id = $1 AND tag_id = (SELECT id FROM tags WHERE name = $2)
+        "#,
+        user_id,
+        tag
+    )
+    .execute(conn)
+    .await
+    .context("Failed to unfollow tag.")
+    .map_err(ApiError::Database)?
+    .rows_affected()
+        > 0;
+
+    Ok(deleted)
+}
+
+pub async fn get_followed_tags(conn: &PgPool, user_id: &Uuid) -> Result<Vec<FollowedTag>> {
+    let tags = sqlx::query!(
+        r#"
+        SELECT tags.name, tag_follows.created_at
+        FROM tag_follows
+        INNER JOIN tags ON tags.id = tag_follows.tag_id
+        WHERE tag_follows.user_id = $1
+        ORDER BY tags.name
+        "#,
+        user_id
+    )
+    .fetch_all(conn)
+    .await
+    .context("Failed to get followed tags.")
+    .map_err(ApiError::Database)?
+    .into_iter()
+    .map(|tag| FollowedTag {
+        name: tag.name,
+        created_at: tag.created_at.timestamp(),
+    })
+    .collect::<Vec<FollowedTag>>();
+
+    Ok(tags)
+}
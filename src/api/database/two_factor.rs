@@ -0,0 +1,186 @@
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::api::models::{
+    error::{ApiError, Result},
+    UserTotp,
+};
+
+pub async fn get_user_totp(conn: &PgPool, user_id: &Uuid) -> Result<Option<UserTotp>> {
+    let totp = sqlx::query!(
+        r#"
+        SELECT secret, confirmed_at, last_used_step
+        FROM user_totp
+        WHERE user_id = $1
+        "#,
+        user_id
+    )
+    .fetch_optional(conn)
+    .await
+    .context("Failed to get user's TOTP secret.")
+    .map_err(ApiError::Database)?
+    .map(|totp| UserTotp {
+        secret: totp.secret,
+        confirmed: totp.confirmed_at.is_some(),
+        last_used_step: totp.last_used_step,
+    });
+
+    Ok(totp)
+}
+
+// Starting over replaces a secret that was never confirmed, but never a confirmed one
+pub async fn upsert_pending_totp(conn: &PgPool, user_id: &Uuid, secret: &str) -> Result<bool> {
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO user_totp (user_id, secret)
+        VALUES ($1, $2)
+        ON CONFLICT (user_id) DO UPDATE
+        SET secret = EXCLUDED.secret, created_at = NOW(), last_used_step = NULL
+        WHERE user_totp.confirmed_at IS NULL
+        "#,
+        user_id,
+        secret
+    )
+    .execute(conn)
+    .await
+    .context("Failed to store TOTP secret.")
+    .map_err(ApiError::Database)?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Enables 2FA and replaces any previous recovery codes in one transaction
+pub async fn confirm_totp(
+    conn: &PgPool,
+    user_id: &Uuid,
+    recovery_code_hashes: &[String],
+) -> Result<()> {
+    let mut transaction = conn
+        .begin()
+        .await
+        .context("Failed to start transaction.")
+        .map_err(ApiError::Database)?;
+
+    sqlx::query!(
+        r#"
+        UPDATE user_totp
+        SET confirmed_at = NOW()
+        WHERE user_id = $1
+        "#,
+        user_id
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to confirm TOTP secret.")
+    .map_err(ApiError::Database)?;
+
+    sqlx::query!(
+        r#"
+        DELETE FROM totp_recovery_codes
+        WHERE user_id = $1
+        "#,
+        user_id
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to delete previous recovery codes.")
+    .map_err(ApiError::Database)?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO totp_recovery_codes (code_hash, user_id)
+        SELECT code_hash, $2 FROM UNNEST($1::VARCHAR[]) AS code_hash
+        "#,
+        recovery_code_hashes,
+        user_id
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to insert recovery codes.")
+    .map_err(ApiError::Database)?;
+
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit transaction.")
+        .map_err(ApiError::Database)?;
+    Ok(())
+}
+
+// Only moves forward, so that each time step can be used once and two concurrent
+// logins with the same code can't both succeed
+pub async fn use_totp_step(conn: &PgPool, user_id: &Uuid, step: i64) -> Result<bool> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE user_totp
+        SET last_used_step = $2
+        WHERE user_id = $1 AND (last_used_step IS NULL OR last_used_step < $2)
+        "#,
+        user_id,
+        step
+    )
+    .execute(conn)
+    .await
+    .context("Failed to record used TOTP code.")
+    .map_err(ApiError::Database)?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn use_recovery_code(conn: &PgPool, user_id: &Uuid, code_hash: &str) -> Result<bool> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE totp_recovery_codes
+        SET used_at = NOW()
+        WHERE code_hash = $1 AND user_id = $2 AND used_at IS NULL
+        "#,
+        code_hash,
+        user_id
+    )
+    .execute(conn)
+    .await
+    .context("Failed to use recovery code.")
+    .map_err(ApiError::Database)?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn delete_user_totp(conn: &PgPool, user_id: &Uuid) -> Result<()> {
+    let mut transaction = conn
+        .begin()
+        .await
+        .context("Failed to start transaction.")
+        .map_err(ApiError::Database)?;
+
+    sqlx::query!(
+        r#"
+        DELETE FROM totp_recovery_codes
+        WHERE user_id = $1
+        "#,
+        user_id
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to delete recovery codes.")
+    .map_err(ApiError::Database)?;
+
+    sqlx::query!(
+        r#"
+        DELETE FROM user_totp
+        WHERE user_id = $1
+        "#,
+        user_id
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to delete TOTP secret.")
+    .map_err(ApiError::Database)?;
+
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit transaction.")
+        .map_err(ApiError::Database)?;
+    Ok(())
+}
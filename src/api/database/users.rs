@@ -5,13 +5,16 @@ use uuid::Uuid;
 
 use crate::api::models::{
     error::{ApiError, Result},
-    AuthUser, CreateUser, Post, User,
+    CreateUser, ImageKind, Post, PostMedia, ProfileImage, PublicUser, PurgedUser, UpdateUser, User,
 };
 
+use super::{posts_from_rows, PostRow};
+
 pub async fn get_user_by_id(conn: &PgPool, user_id: &Uuid) -> Result<Option<User>> {
     let user = sqlx::query!(
         r#"
-        SELECT id, username, email, password, first_name, last_name, description
+        SELECT id, username, email, password, first_name, last_name, description, email_verified_at,
+            deactivated_at, show_email, is_private, avatar_id, cover_id
         FROM users
         WHERE id = $1
         "#,
@@ -28,6 +31,12 @@ pub async fn get_user_by_id(conn: &PgPool, user_id: &Uuid) -> Result<Option<User
         description: user.description,
         email: user.email,
         password: Secret::new(user.password),
+        email_verified: user.email_verified_at.is_some(),
+        deactivated: user.deactivated_at.is_some(),
+        show_email: user.show_email,
+        is_private: user.is_private,
+        avatar: ProfileImage::avatar(user.avatar_id),
+        cover: ProfileImage::cover(user.cover_id),
     });
 
     Ok(user)
@@ -36,7 +45,8 @@ pub async fn get_user_by_id(conn: &PgPool, user_id: &Uuid) -> Result<Option<User
 pub async fn get_user_by_username(conn: &PgPool, username: &str) -> Result<Option<User>> {
     let user = sqlx::query!(
         r#"
-        SELECT id, username, email, password, first_name, last_name, description
+        SELECT id, username, email, password, first_name, last_name, description, email_verified_at,
+            deactivated_at, show_email, is_private, avatar_id, cover_id
         FROM users
         WHERE username = $1
         "#,
@@ -53,6 +63,12 @@ pub async fn get_user_by_username(conn: &PgPool, username: &str) -> Result<Optio
         description: user.description,
         email: user.email,
         password: Secret::new(user.password),
+        email_verified: user.email_verified_at.is_some(),
+        deactivated: user.deactivated_at.is_some(),
+        show_email: user.show_email,
+        is_private: user.is_private,
+        avatar: ProfileImage::avatar(user.avatar_id),
+        cover: ProfileImage::cover(user.cover_id),
     });
 
     Ok(user)
@@ -61,7 +77,8 @@ pub async fn get_user_by_username(conn: &PgPool, username: &str) -> Result<Optio
 pub async fn get_user_by_email(conn: &PgPool, email: &str) -> Result<Option<User>> {
     let user = sqlx::query!(
         r#"
-        SELECT id, username, email, password, first_name, last_name, description
+        SELECT id, username, email, password, first_name, last_name, description, email_verified_at,
+            deactivated_at, show_email, is_private, avatar_id, cover_id
         FROM users
         WHERE email = $1
         "#,
@@ -78,6 +95,12 @@ pub async fn get_user_by_email(conn: &PgPool, email: &str) -> Result<Option<User
         username: user.username,
         email: user.email,
         password: Secret::new(user.password),
+        email_verified: user.email_verified_at.is_some(),
+        deactivated: user.deactivated_at.is_some(),
+        show_email: user.show_email,
+        is_private: user.is_private,
+        avatar: ProfileImage::avatar(user.avatar_id),
+        cover: ProfileImage::cover(user.cover_id),
     });
 
     Ok(user)
@@ -110,6 +133,460 @@ pub async fn insert_user(
     Ok(())
 }
 
+/// Applies a profile update, returning false if the new email belongs to another account
+///
+/// Changing the email marks it as unverified until the new address is confirmed.
+pub async fn update_user(conn: &PgPool, user_id: &Uuid, update: &UpdateUser) -> Result<bool> {
+    let email = update.email.as_deref().map(str::to_lowercase);
+    let mut transaction = conn
+        .begin()
+        .await
+        .context("Failed to start transaction.")
+        .map_err(ApiError::Database)?;
+
+    if let Some(email) = &email {
+        let is_email_taken = sqlx::query!(
+            r#"
+            SELECT id
+            FROM users
+            WHERE email = $1 AND id <> $2
+            FOR UPDATE
+            "#,
+            email,
+            user_id
+        )
+        .fetch_optional(&mut *transaction)
+        .await
+        .context("Failed to check if email is taken.")
+        .map_err(ApiError::Database)?
+        .is_some();
+        if is_email_taken {
+            return Ok(false);
+        }
+    }
+
+    let result = sqlx::query!(
+        r#"
+        UPDATE users
+        SET first_name = COALESCE($2, first_name),
+            last_name = COALESCE($3, last_name),
+            description = COALESCE($4, description),
+            show_email = COALESCE($6, show_email),
+            is_private = COALESCE($7, is_private),
+            email_verified_at = CASE
+                WHEN $5::VARCHAR IS NULL OR $5 = email THEN email_verified_at
+                ELSE NULL
+            END,
+            email = COALESCE($5, email),
+            updated_at = NOW()
+        WHERE id = $1
+        "#,
+        user_id,
+        update.first_name,
+        update.last_name,
+        update.description,
+        email,
+        update.show_email,
+        update.is_private
+    )
+    .execute(&mut *transaction)
+    .await;
+    match result {
+        Ok(_) => {}
+        // Another account took the email between the check and the update
+        Err(sqlx::Error::Database(err)) if err.is_unique_violation() => return Ok(false),
+        Err(err) => {
+            return Err(ApiError::Database(
+                anyhow::Error::new(err).context("Failed to update user."),
+            ))
+        }
+    }
+
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit transaction.")
+        .map_err(ApiError::Database)?;
+    Ok(true)
+}
+
+pub async fn update_password(conn: &PgPool, user_id: &Uuid, password: String) -> Result<()> {
+    sqlx::query!(
+        r#"
+        UPDATE users
+        SET password = $2
+        WHERE id = $1
+        "#,
+        user_id,
+        password
+    )
+    .execute(conn)
+    .await
+    .context("Failed to update user's password.")
+    .map_err(ApiError::Database)?;
+
+    Ok(())
+}
+
+/// Replaces or clears the user's avatar or cover, returning the id of the one it replaced
+pub async fn update_profile_image(
+    conn: &PgPool,
+    user_id: &Uuid,
+    kind: ImageKind,
+    image_id: Option<&Uuid>,
+) -> Result<Option<Uuid>> {
+    let previous = match kind {
+        ImageKind::Avatar => sqlx::query!(
+            r#"
+            UPDATE users
+            SET avatar_id = $2, updated_at = NOW()
+            FROM users AS previous
+            WHERE users.id = $1 AND previous.id = $1
+            RETURNING previous.avatar_id
+            "#,
+            user_id,
+            image_id
+        )
+        .fetch_one(conn)
+        .await
+        .map(|row| row.avatar_id),
+        ImageKind::Cover => sqlx::query!(
+            r#"
+            UPDATE users
+            SET cover_id = $2, updated_at = NOW()
+            FROM users AS previous
+            WHERE users.id = $1 AND previous.id = $1
+            RETURNING previous.cover_id
+            "#,
+            user_id,
+            image_id
+        )
+        .fetch_one(conn)
+        .await
+        .map(|row| row.cover_id),
+        ImageKind::Post => {
+            return Err(ApiError::InternalServer(anyhow::anyhow!(
+                "Post photos aren't profile images"
+            )))
+        }
+    }
+    .context("Failed to update user's profile image.")
+    .map_err(ApiError::Database)?;
+
+    Ok(previous)
+}
+
+/// Renames the user, reserving the old username for them, and returns false if the new
+/// username is taken or still reserved for someone else
+pub async fn change_username(
+    conn: &PgPool,
+    user_id: &Uuid,
+    username: &str,
+    reserved_for_seconds: f64,
+) -> Result<bool> {
+    let username = username.to_lowercase();
+    let mut transaction = conn
+        .begin()
+        .await
+        .context("Failed to start transaction.")
+        .map_err(ApiError::Database)?;
+
+    let old_username = sqlx::query!(
+        r#"
+        SELECT username
+        FROM users
+        WHERE id = $1
+        FOR UPDATE
+        "#,
+        user_id
+    )
+    .fetch_one(&mut *transaction)
+    .await
+    .context("Failed to get user's username.")
+    .map_err(ApiError::Database)?
+    .username;
+    if old_username == username {
+        return Ok(true);
+    }
+
+    let is_unavailable = sqlx::query!(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM users WHERE username = $1
+        ) OR EXISTS (
+            SELECT 1 FROM username_history
+            WHERE username = $1 AND user_id <> $2 AND reserved_until > NOW()
+        ) AS "is_unavailable!"
+        "#,
+        username,
+        user_id
+    )
+    .fetch_one(&mut *transaction)
+    .await
+    .context("Failed to check if username is available.")
+    .map_err(ApiError::Database)?
+    .is_unavailable;
+    if is_unavailable {
+        return Ok(false);
+    }
+
+    let result = sqlx::query!(
+        r#"
+        UPDATE users
+        SET username = $2, updated_at = NOW()
+        WHERE id = $1
+        "#,
+        user_id,
+        username
+    )
+    .execute(&mut *transaction)
+    .await;
+    match result {
+        Ok(_) => {}
+        // Another account took the username between the check and the update
+        Err(sqlx::Error::Database(err)) if err.is_unique_violation() => return Ok(false),
+        Err(err) => {
+            return Err(ApiError::Database(
+                anyhow::Error::new(err).context("Failed to change username."),
+            ))
+        }
+    }
+
+    sqlx::query!(
+        r#"
+        INSERT INTO username_history (id, user_id, username, reserved_until)
+        VALUES ($1, $2, $3, NOW() + make_interval(secs => $4))
+        "#,
+        Uuid::new_v4(),
+        user_id,
+        old_username,
+        reserved_for_seconds
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to record username change.")
+    .map_err(ApiError::Database)?;
+
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit transaction.")
+        .map_err(ApiError::Database)?;
+    Ok(true)
+}
+
+/// Whether the username was recently given up and is still held for its previous owner
+pub async fn is_username_reserved(conn: &PgPool, username: &str) -> Result<bool> {
+    let is_reserved = sqlx::query!(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM username_history
+            WHERE username = $1 AND reserved_until > NOW()
+        ) AS "is_reserved!"
+        "#,
+        username.to_lowercase()
+    )
+    .fetch_one(conn)
+    .await
+    .context("Failed to check if username is reserved.")
+    .map_err(ApiError::Database)?
+    .is_reserved;
+
+    Ok(is_reserved)
+}
+
+/// The user who most recently gave up the username
+pub async fn get_user_id_by_previous_username(
+    conn: &PgPool,
+    username: &str,
+) -> Result<Option<Uuid>> {
+    let user_id = sqlx::query!(
+        r#"
+        SELECT user_id
+        FROM username_history
+        WHERE username = $1
+        ORDER BY changed_at DESC
+        LIMIT 1
+        "#,
+        username.to_lowercase()
+    )
+    .fetch_optional(conn)
+    .await
+    .context("Failed to get user by previous username.")
+    .map_err(ApiError::Database)?
+    .map(|history| history.user_id);
+
+    Ok(user_id)
+}
+
+/// Deactivates the account until `purge_after_seconds` from now, when it is purged
+pub async fn deactivate_user(
+    conn: &PgPool,
+    user_id: &Uuid,
+    purge_after_seconds: f64,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+        UPDATE users
+        SET deactivated_at = NOW(), purge_after = NOW() + make_interval(secs => $2)
+        WHERE id = $1
+        "#,
+        user_id,
+        purge_after_seconds
+    )
+    .execute(conn)
+    .await
+    .context("Failed to deactivate user.")
+    .map_err(ApiError::Database)?;
+    Ok(())
+}
+
+/// Cancels a pending deletion, returning whether the account was deactivated
+pub async fn restore_user(conn: &PgPool, user_id: &Uuid) -> Result<bool> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE users
+        SET deactivated_at = NULL, purge_after = NULL
+        WHERE id = $1 AND deactivated_at IS NOT NULL
+        "#,
+        user_id
+    )
+    .execute(conn)
+    .await
+    .context("Failed to restore user.")
+    .map_err(ApiError::Database)?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn get_users_due_for_purge(conn: &PgPool) -> Result<Vec<Uuid>> {
+    let user_ids = sqlx::query!(
+        r#"
+        SELECT id
+        FROM users
+        WHERE deactivated_at IS NOT NULL AND purge_after <= NOW()
+        "#
+    )
+    .fetch_all(conn)
+    .await
+    .context("Failed to get users due for purge.")
+    .map_err(ApiError::Database)?
+    .into_iter()
+    .map(|user| user.id)
+    .collect();
+
+    Ok(user_ids)
+}
+
+/// Deletes the user for good, unless they restored their account in the meantime
+///
+/// Posts, likes, follows and account data go with the user through `ON DELETE CASCADE`.
+/// Comments that others replied to are kept in their threads, emptied and without an
+/// author, the rest are removed, as are the user's post photos. Returns the images left
+/// to delete from the blob store, or `None` if the user wasn't purged.
+pub async fn purge_user(conn: &PgPool, user_id: &Uuid) -> Result<Option<PurgedUser>> {
+    let mut transaction = conn
+        .begin()
+        .await
+        .context("Failed to start transaction.")
+        .map_err(ApiError::Database)?;
+
+    let user = sqlx::query!(
+        r#"
+        SELECT email, avatar_id, cover_id
+        FROM users
+        WHERE id = $1 AND deactivated_at IS NOT NULL AND purge_after <= NOW()
+        FOR UPDATE
+        "#,
+        user_id
+    )
+    .fetch_optional(&mut *transaction)
+    .await
+    .context("Failed to get user to purge.")
+    .map_err(ApiError::Database)?;
+    let Some(user) = user else {
+        return Ok(None);
+    };
+
+    // Photos on the user's posts go with the posts, the rest with the uploader
+    let post_media = sqlx::query!(
+        r#"
+        SELECT id, alt_text, width, height
+        FROM post_media
+        WHERE uploader_id = $1 OR post_id IN (SELECT id FROM posts WHERE author = $1)
+        "#,
+        user_id
+    )
+    .fetch_all(&mut *transaction)
+    .await
+    .context("Failed to get user's post media.")
+    .map_err(ApiError::Database)?
+    .into_iter()
+    .map(|media| PostMedia::new(&media.id, media.alt_text, media.width, media.height))
+    .collect::<Vec<PostMedia>>();
+
+    sqlx::query!(
+        r#"
+        DELETE FROM comments
+        WHERE user_id = $1 AND NOT EXISTS (
+            SELECT 1 FROM comments AS replies WHERE replies.parent_comment_id = comments.id
+        )
+        "#,
+        user_id
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to delete user's comments.")
+    .map_err(ApiError::Database)?;
+    sqlx::query!(
+        r#"
+        UPDATE comments
+        SET comment = '[deleted]'
+        WHERE user_id = $1
+        "#,
+        user_id
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to anonymize user's comments.")
+    .map_err(ApiError::Database)?;
+
+    sqlx::query!(
+        r#"
+        DELETE FROM login_throttles
+        WHERE scope = 'account' AND identifier = $1
+        "#,
+        user.email
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to delete user's login throttle.")
+    .map_err(ApiError::Database)?;
+
+    sqlx::query!(
+        r#"
+        DELETE FROM users
+        WHERE id = $1
+        "#,
+        user_id
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to delete user.")
+    .map_err(ApiError::Database)?;
+
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit transaction.")
+        .map_err(ApiError::Database)?;
+    Ok(Some(PurgedUser {
+        avatar_id: user.avatar_id,
+        cover_id: user.cover_id,
+        post_media,
+    }))
+}
+
 pub async fn follow_user(conn: &PgPool, follower_id: &Uuid, followed_id: &Uuid) -> Result<()> {
     sqlx::query!(
         r#"
@@ -147,13 +624,14 @@ pub async fn is_following(conn: &PgPool, follower_id: &Uuid, followed_id: &Uuid)
     Ok(is_following)
 }
 
-pub async fn get_followers(conn: &PgPool, user_id: &Uuid) -> Result<Vec<AuthUser>> {
+pub async fn get_followers(conn: &PgPool, user_id: &Uuid) -> Result<Vec<PublicUser>> {
     let followers = sqlx::query!(
         r#"
-        SELECT users.id, users.username, users.email, users.first_name, users.last_name, users.description
+        SELECT users.id, users.username, users.email, users.first_name, users.last_name, users.description,
+            users.show_email, users.is_private, users.avatar_id, users.cover_id
         FROM users
         INNER JOIN users_followers ON users.id = users_followers.follower_id
-        WHERE users_followers.user_id = $1
+        WHERE users_followers.user_id = $1 AND users.deactivated_at IS NULL
         "#,
         user_id
     )
@@ -162,25 +640,29 @@ pub async fn get_followers(conn: &PgPool, user_id: &Uuid) -> Result<Vec<AuthUser
     .context("Failed to get user's followers.")
     .map_err(ApiError::Database)?
     .into_iter()
-    .map(|user| AuthUser {
+    .map(|user| PublicUser {
         id: user.id.to_string(),
         username: user.username,
         name: format!("{} {}", user.first_name, user.last_name),
         description: user.description,
-        email: user.email,
+        email: user.show_email.then_some(user.email),
+        is_private: user.is_private,
+        avatar: ProfileImage::avatar(user.avatar_id),
+        cover: ProfileImage::cover(user.cover_id),
     })
-    .collect::<Vec<AuthUser>>();
+    .collect::<Vec<PublicUser>>();
 
     Ok(followers)
 }
 
-pub async fn get_following(conn: &PgPool, user_id: &Uuid) -> Result<Vec<AuthUser>> {
+pub async fn get_following(conn: &PgPool, user_id: &Uuid) -> Result<Vec<PublicUser>> {
     let following = sqlx::query!(
         r#"
-        SELECT users.id, users.username, users.email, users.first_name, users.last_name, users.description
+        SELECT users.id, users.username, users.email, users.first_name, users.last_name, users.description,
+            users.show_email, users.is_private, users.avatar_id, users.cover_id
         FROM users
         INNER JOIN users_followers ON users.id = users_followers.user_id
-        WHERE users_followers.follower_id = $1
+        WHERE users_followers.follower_id = $1 AND users.deactivated_at IS NULL
         "#,
         user_id
     )
@@ -189,14 +671,17 @@ pub async fn get_following(conn: &PgPool, user_id: &Uuid) -> Result<Vec<AuthUser
     .context("Failed to get user's following.")
     .map_err(ApiError::Database)?
     .into_iter()
-    .map(|user| AuthUser {
+    .map(|user| PublicUser {
         id: user.id.to_string(),
         username: user.username,
         name: format!("{} {}", user.first_name, user.last_name),
         description: user.description,
-        email: user.email,
+        email: user.show_email.then_some(user.email),
+        is_private: user.is_private,
+        avatar: ProfileImage::avatar(user.avatar_id),
+        cover: ProfileImage::cover(user.cover_id),
     })
-    .collect::<Vec<AuthUser>>();
+    .collect::<Vec<PublicUser>>();
 
     Ok(following)
 }
@@ -217,65 +702,119 @@ pub async fn unfollow_user(conn: &PgPool, user_id: &Uuid, followed_id: &Uuid) ->
     Ok(())
 }
 
-pub async fn get_all_users(conn: &PgPool) -> Result<Vec<AuthUser>> {
+/// Every active user, except those blocked either way by `viewer_id`
+pub async fn get_all_users(conn: &PgPool, viewer_id: Option<&Uuid>) -> Result<Vec<PublicUser>> {
     let users = sqlx::query!(
         r#"
-        SELECT id, username, email, first_name, last_name, description
+        SELECT id, username, email, first_name, last_name, description, show_email, is_private,
+            avatar_id, cover_id
         FROM users
+        WHERE deactivated_at IS NULL
+            AND NOT EXISTS (
+                SELECT 1
+                FROM user_blocks
+                WHERE (blocker_id = $1 AND blocked_id = users.id)
+                    OR (blocker_id = users.id AND blocked_id = $1)
+            )
         "#,
+        viewer_id
     )
     .fetch_all(conn)
     .await
     .context("Failed to get all users.")
     .map_err(ApiError::Database)?
     .into_iter()
-    .map(|user| AuthUser {
+    .map(|user| PublicUser {
         id: user.id.to_string(),
         username: user.username,
         name: format!("{} {}", user.first_name, user.last_name),
         description: user.description,
-        email: user.email,
+        email: user.show_email.then_some(user.email),
+        is_private: user.is_private,
+        avatar: ProfileImage::avatar(user.avatar_id),
+        cover: ProfileImage::cover(user.cover_id),
     })
-    .collect::<Vec<AuthUser>>();
+    .collect::<Vec<PublicUser>>();
 
     Ok(users)
 }
 
-pub async fn get_users_by_query(query: String, conn: &PgPool) -> Result<Vec<AuthUser>> {
+pub async fn get_users_by_query(
+    query: String,
+    viewer_id: Option<&Uuid>,
+    conn: &PgPool,
+) -> Result<Vec<PublicUser>> {
     let users = sqlx::query!(
         r#"
-        SELECT id, username, email, first_name, last_name, description
+        SELECT id, username, email, first_name, last_name, description, show_email, is_private,
+            avatar_id, cover_id
         FROM users
-        WHERE username LIKE $1
+        WHERE username LIKE $1 AND deactivated_at IS NULL
+            AND NOT EXISTS (
+                SELECT 1
+                FROM user_blocks
+                WHERE (blocker_id = $2 AND blocked_id = users.id)
+                    OR (blocker_id = users.id AND blocked_id = $2)
+            )
         "#,
-        format!("%{}%", query.to_lowercase())
+        format!("%{}%", query.to_lowercase()),
+        viewer_id
     )
     .fetch_all(conn)
     .await
     .context("Failed to get users by query.")
     .map_err(ApiError::Database)?
     .into_iter()
-    .map(|user| AuthUser {
+    .map(|user| PublicUser {
         id: user.id.to_string(),
         username: user.username,
         name: format!("{} {}", user.first_name, user.last_name),
         description: user.description,
-        email: user.email,
+        email: user.show_email.then_some(user.email),
+        is_private: user.is_private,
+        avatar: ProfileImage::avatar(user.avatar_id),
+        cover: ProfileImage::cover(user.cover_id),
     })
-    .collect::<Vec<AuthUser>>();
+    .collect::<Vec<PublicUser>>();
 
     Ok(users)
 }
 
+/// Posts by the accounts the user follows, along with posts tagged with the hashtags they
+/// follow
 pub async fn get_users_feed(conn: &PgPool, user_id: &Uuid) -> Result<Vec<Post>> {
-    let posts = sqlx::query!(
+    let rows = sqlx::query_as!(
+        PostRow,
         r#"
-        SELECT posts.id, posts.title, posts.location, posts.content, posts.author, posts.created_at, 
+        SELECT posts.id, posts.title, posts.location, posts.latitude, posts.longitude,
+            posts.country_code, posts.place_id, posts.content, posts.author, posts.created_at,
+        posts.edited_at,
         (SELECT COUNT(*) FROM comments WHERE comments.post_id = posts.id) AS "num_comments!", 
         (SELECT COUNT(*) FROM likes WHERE likes.post_id = posts.id) AS "num_likes!"
         FROM posts
-        INNER JOIN users_followers ON posts.author = users_followers.user_id
-        WHERE users_followers.follower_id = $1
+        WHERE can_view_author(posts.author, $1)
+            AND (
+                EXISTS (
+                    SELECT 1
+                    FROM users_followers
+                    WHERE user_id = posts.author AND follower_id = $1
+                )
+                -- Posts with a followed hashtag, from accounts other than the user's own
+                OR (
+                    posts.author <> $1
+                    AND EXISTS (
+                        SELECT 1
+                        FROM post_tags
+                        INNER JOIN tag_follows ON tag_follows.tag_id = post_tags.tag_id
+                        WHERE post_tags.post_id = posts.id AND tag_follows.user_id = $1
+                    )
+                )
+            )
+            AND NOT EXISTS (
+                SELECT 1
+                FROM user_mutes
+                WHERE muter_id = $1 AND muted_id = posts.author
+            )
         ORDER BY posts.created_at DESC
         "#,
         user_id
@@ -283,19 +822,7 @@ pub async fn get_users_feed(conn: &PgPool, user_id: &Uuid) -> Result<Vec<Post>>
     .fetch_all(conn)
     .await
     .context("Failed to get user's feed.")
-    .map_err(ApiError::Database)?
-    .into_iter()
-    .map(|post| Post {
-        id: post.id.to_string(),
-        title: post.title,
-        location: post.location,
-        content: post.content,
-        author: post.author.to_string(),
-        created_at: post.created_at.timestamp(),
-        num_comments: post.num_comments as u32,
-        num_likes: post.num_likes as u32,
-    })
-    .collect::<Vec<Post>>();
+    .map_err(ApiError::Database)?;
 
-    Ok(posts)
+    posts_from_rows(conn, rows).await
 }
@@ -0,0 +1,126 @@
+use std::{path::PathBuf, sync::Arc};
+
+use anyhow::Context;
+use async_trait::async_trait;
+use lettre::{
+    message::{Mailbox, MultiPart},
+    transport::smtp::authentication::Credentials,
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+use secrecy::ExposeSecret;
+use uuid::Uuid;
+
+use super::configuration::{EmailSettings, EmailTransport};
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct Email {
+    pub recipient: String,
+    pub subject: String,
+    pub html_content: String,
+    pub text_content: String,
+}
+
+/// Sends transactional emails such as password reset links
+///
+/// The implementation is picked from `EmailSettings` at startup and shared with the
+/// routes as `Data<dyn EmailClient>`.
+#[async_trait]
+pub trait EmailClient: Send + Sync {
+    async fn send_email(&self, email: Email) -> anyhow::Result<()>;
+}
+
+pub fn get_email_client(settings: &EmailSettings) -> anyhow::Result<Arc<dyn EmailClient>> {
+    let client: Arc<dyn EmailClient> = match &settings.transport {
+        EmailTransport::Smtp {
+            host,
+            port,
+            username,
+            password,
+        } => Arc::new(SmtpEmailClient::new(
+            &settings.sender,
+            host,
+            *port,
+            Credentials::new(username.clone(), password.expose_secret().clone()),
+        )?),
+        EmailTransport::File { directory } => Arc::new(FileEmailClient::new(directory)),
+    };
+    Ok(client)
+}
+
+pub struct SmtpEmailClient {
+    sender: Mailbox,
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+}
+
+impl SmtpEmailClient {
+    pub fn new(
+        sender: &str,
+        host: &str,
+        port: u16,
+        credentials: Credentials,
+    ) -> anyhow::Result<Self> {
+        let sender = sender.parse().context("Invalid sender email address.")?;
+        let transport = AsyncSmtpTransport::<Tokio1Executor>::relay(host)
+            .context("Failed to configure SMTP transport.")?
+            .port(port)
+            .credentials(credentials)
+            .build();
+        Ok(Self { sender, transport })
+    }
+}
+
+#[async_trait]
+impl EmailClient for SmtpEmailClient {
+    #[tracing::instrument(name = "Sending email over SMTP", skip(self, email))]
+    async fn send_email(&self, email: Email) -> anyhow::Result<()> {
+        let message = Message::builder()
+            .from(self.sender.clone())
+            .to(email
+                .recipient
+                .parse()
+                .context("Invalid recipient email address.")?)
+            .subject(email.subject)
+            .multipart(MultiPart::alternative_plain_html(
+                email.text_content,
+                email.html_content,
+            ))
+            .context("Failed to build email.")?;
+        self.transport
+            .send(message)
+            .await
+            .context("Failed to send email.")?;
+        Ok(())
+    }
+}
+
+/// Writes every email as a JSON file instead of sending it, for local development and tests
+pub struct FileEmailClient {
+    directory: PathBuf,
+}
+
+impl FileEmailClient {
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl EmailClient for FileEmailClient {
+    #[tracing::instrument(name = "Writing email to file", skip(self, email))]
+    async fn send_email(&self, email: Email) -> anyhow::Result<()> {
+        tokio::fs::create_dir_all(&self.directory)
+            .await
+            .context("Failed to create email directory.")?;
+        let path = self.directory.join(format!(
+            "{}-{}.json",
+            chrono::Utc::now().timestamp_millis(),
+            Uuid::new_v4()
+        ));
+        tokio::fs::write(path, serde_json::to_vec_pretty(&email)?)
+            .await
+            .context("Failed to write email to file.")?;
+        Ok(())
+    }
+}
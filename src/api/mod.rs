@@ -1,7 +1,11 @@
+pub mod blob_store;
 pub mod configuration;
 pub mod controller;
 pub mod database;
+pub mod email_client;
 pub mod models;
+pub mod oidc_client;
+pub mod purge_worker;
 pub mod routes;
 pub mod startup;
 pub mod telemetry;
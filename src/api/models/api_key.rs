@@ -0,0 +1,206 @@
+use std::{fmt::Debug, future::Future, marker::PhantomData, pin::Pin, str::FromStr};
+
+use actix_web::{web::Data, FromRequest};
+use anyhow::anyhow;
+use sqlx::PgPool;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::api::database;
+
+use super::{
+    error::{ApiError, Result},
+    token::{self, JwtPayload},
+};
+
+pub const API_KEY_HEADER: &str = "X-Api-Key";
+const API_KEY_PREFIX: &str = "va_";
+
+/// What a personal API key is allowed to do
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum ApiKeyScope {
+    #[serde(rename = "feed:read")]
+    FeedRead,
+    #[serde(rename = "posts:write")]
+    PostsWrite,
+    #[serde(rename = "comments:write")]
+    CommentsWrite,
+    #[serde(rename = "follows:write")]
+    FollowsWrite,
+}
+
+impl ApiKeyScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApiKeyScope::FeedRead => "feed:read",
+            ApiKeyScope::PostsWrite => "posts:write",
+            ApiKeyScope::CommentsWrite => "comments:write",
+            ApiKeyScope::FollowsWrite => "follows:write",
+        }
+    }
+}
+
+impl FromStr for ApiKeyScope {
+    type Err = ApiError;
+
+    fn from_str(scope: &str) -> Result<Self> {
+        match scope {
+            "feed:read" => Ok(ApiKeyScope::FeedRead),
+            "posts:write" => Ok(ApiKeyScope::PostsWrite),
+            "comments:write" => Ok(ApiKeyScope::CommentsWrite),
+            "follows:write" => Ok(ApiKeyScope::FollowsWrite),
+            other => Err(ApiError::BadRequest(anyhow!("Unknown scope: {}", other))),
+        }
+    }
+}
+
+pub struct ApiKey {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+    pub prefix: String,
+    pub scopes: Vec<String>,
+    pub created_at: chrono::NaiveDateTime,
+    pub last_used_at: Option<chrono::NaiveDateTime>,
+}
+
+#[derive(serde::Deserialize, Validate)]
+pub struct CreateApiKey {
+    #[validate(length(min = 1, max = 64))]
+    pub name: String,
+    #[validate(length(min = 1))]
+    pub scopes: Vec<ApiKeyScope>,
+}
+
+/// An API key as listed to its owner, without the key itself
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct ApiKeyInfo {
+    pub id: String,
+    pub name: String,
+    pub prefix: String,
+    pub scopes: Vec<ApiKeyScope>,
+    pub created_at: i64,
+    pub last_used_at: Option<i64>,
+}
+
+impl TryFrom<ApiKey> for ApiKeyInfo {
+    type Error = ApiError;
+
+    fn try_from(key: ApiKey) -> Result<Self> {
+        Ok(Self {
+            id: key.id.to_string(),
+            name: key.name,
+            prefix: key.prefix,
+            scopes: key
+                .scopes
+                .iter()
+                .map(|scope| ApiKeyScope::from_str(scope))
+                .collect::<Result<_>>()?,
+            created_at: key.created_at.timestamp(),
+            last_used_at: key
+                .last_used_at
+                .map(|last_used_at| last_used_at.timestamp()),
+        })
+    }
+}
+
+/// A newly created API key, the only time the key is shown
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct NewApiKey {
+    #[serde(flatten)]
+    pub info: ApiKeyInfo,
+    pub key: String,
+}
+
+pub fn generate_api_key() -> String {
+    format!("{}{}", API_KEY_PREFIX, token::generate_opaque_token())
+}
+
+/// Marker types for `Authenticated`
+pub trait RequiredScope {
+    const SCOPE: ApiKeyScope;
+}
+
+#[derive(Debug)]
+pub struct FeedRead;
+
+impl RequiredScope for FeedRead {
+    const SCOPE: ApiKeyScope = ApiKeyScope::FeedRead;
+}
+
+#[derive(Debug)]
+pub struct PostsWrite;
+
+impl RequiredScope for PostsWrite {
+    const SCOPE: ApiKeyScope = ApiKeyScope::PostsWrite;
+}
+
+#[derive(Debug)]
+pub struct CommentsWrite;
+
+impl RequiredScope for CommentsWrite {
+    const SCOPE: ApiKeyScope = ApiKeyScope::CommentsWrite;
+}
+
+#[derive(Debug)]
+pub struct FollowsWrite;
+
+impl RequiredScope for FollowsWrite {
+    const SCOPE: ApiKeyScope = ApiKeyScope::FollowsWrite;
+}
+
+/// The user behind a request, signed in with a bearer token or sending a personal API key
+/// in `X-Api-Key`
+///
+/// Bearer tokens may do anything, API keys only what their scopes include.
+#[derive(Debug)]
+pub struct Authenticated<S: RequiredScope> {
+    pub user_id: String,
+    scope: PhantomData<S>,
+}
+
+impl<S: RequiredScope> FromRequest for Authenticated<S> {
+    type Error = ApiError;
+    type Future = Pin<Box<dyn Future<Output = core::result::Result<Self, Self::Error>>>>;
+
+    fn from_request(
+        req: &actix_web::HttpRequest,
+        payload: &mut actix_web::dev::Payload,
+    ) -> Self::Future {
+        let api_key = req
+            .headers()
+            .get(API_KEY_HEADER)
+            .map(|value| value.to_str().map(|value| value.to_string()));
+        let Some(api_key) = api_key else {
+            let jwt = JwtPayload::from_request(req, payload);
+            return Box::pin(async move {
+                Ok(Authenticated {
+                    user_id: jwt.await?.user_id,
+                    scope: PhantomData,
+                })
+            });
+        };
+        let conn = req.app_data::<Data<PgPool>>().cloned();
+
+        Box::pin(async move {
+            let api_key =
+                api_key.map_err(|_| ApiError::Unauthorized(anyhow!("Invalid API key.")))?;
+            let conn = conn.ok_or(ApiError::InternalServer(anyhow!(
+                "Database is not configured."
+            )))?;
+            let (user_id, scopes) = database::use_api_key(&conn, &token::hash_token(&api_key))
+                .await?
+                .ok_or(ApiError::Unauthorized(anyhow!("Invalid API key.")))?;
+            if !scopes.iter().any(|scope| scope == S::SCOPE.as_str()) {
+                return Err(ApiError::Forbidden(anyhow!(
+                    "This API key is missing the {} scope",
+                    S::SCOPE.as_str()
+                )));
+            }
+            Ok(Authenticated {
+                user_id: user_id.to_string(),
+                scope: PhantomData,
+            })
+        })
+    }
+}
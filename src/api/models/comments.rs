@@ -1,6 +1,6 @@
 use validator::Validate;
 
-use super::AuthUser;
+use super::{Mention, PublicUser};
 
 #[derive(serde::Deserialize, serde::Serialize, Validate)]
 pub struct CreateComment {
@@ -11,9 +11,12 @@ pub struct CreateComment {
 #[derive(serde::Deserialize, serde::Serialize)]
 pub struct Comment {
     pub id: String,
-    pub user: AuthUser,
+    /// `None` once the author's account has been deleted
+    pub user: Option<PublicUser>,
     pub post_id: String,
     pub comment: String,
     pub created_at: i64,
     pub parent_comment_id: Option<String>,
+    /// Users tagged in the comment, in the order they appear
+    pub mentions: Vec<Mention>,
 }
@@ -1,4 +1,4 @@
-use actix_web::ResponseError;
+use actix_web::{http::header::RETRY_AFTER, ResponseError};
 use serde_json::json;
 use tracing::log::error;
 
@@ -11,6 +11,8 @@ pub enum ApiError {
     Forbidden(anyhow::Error),
     Database(anyhow::Error),
     NotFound(anyhow::Error),
+    /// Sent with a `Retry-After` header of the given number of seconds
+    TooManyRequests(anyhow::Error, i64),
     InternalServer(anyhow::Error),
 }
 
@@ -21,6 +23,7 @@ impl std::fmt::Display for ApiError {
             ApiError::Unauthorized(message) => write!(f, "{message}",),
             ApiError::Forbidden(message) => write!(f, "{message}",),
             ApiError::NotFound(message) => write!(f, "{message}",),
+            ApiError::TooManyRequests(message, _) => write!(f, "{message}",),
             ApiError::Database(message) => write!(f, "Database Error: {message}",),
             ApiError::InternalServer(message) => {
                 write!(f, "Internal Service Error: {message}",)
@@ -37,12 +40,16 @@ impl ResponseError for ApiError {
             ApiError::Unauthorized(_) => reqwest::StatusCode::UNAUTHORIZED,
             ApiError::Forbidden(_) => reqwest::StatusCode::FORBIDDEN,
             ApiError::NotFound(_) => reqwest::StatusCode::NOT_FOUND,
+            ApiError::TooManyRequests(_, _) => reqwest::StatusCode::TOO_MANY_REQUESTS,
             _ => reqwest::StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
 
     fn error_response(&self) -> actix_web::HttpResponse<actix_web::body::BoxBody> {
         let mut response_builder = actix_web::HttpResponse::build(self.status_code());
+        if let ApiError::TooManyRequests(_, retry_after) = self {
+            response_builder.insert_header((RETRY_AFTER, retry_after.to_string()));
+        }
         response_builder.json(json!({
             "error": self.to_string(),
         }))
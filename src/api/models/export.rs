@@ -0,0 +1,148 @@
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use uuid::Uuid;
+
+use super::{
+    error::{ApiError, Result},
+    Post,
+};
+
+/// Where a personal data export is in its generation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportStatus {
+    Pending,
+    Ready,
+    Failed,
+}
+
+impl ExportStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExportStatus::Pending => "pending",
+            ExportStatus::Ready => "ready",
+            ExportStatus::Failed => "failed",
+        }
+    }
+}
+
+impl FromStr for ExportStatus {
+    type Err = ApiError;
+
+    fn from_str(status: &str) -> Result<Self> {
+        match status {
+            "pending" => Ok(ExportStatus::Pending),
+            "ready" => Ok(ExportStatus::Ready),
+            "failed" => Ok(ExportStatus::Failed),
+            other => Err(ApiError::InternalServer(anyhow!(
+                "Unknown export status: {}",
+                other
+            ))),
+        }
+    }
+}
+
+pub struct DataExport {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub status: String,
+    pub archive: Option<Vec<u8>>,
+    pub created_at: chrono::NaiveDateTime,
+    pub completed_at: Option<chrono::NaiveDateTime>,
+    pub expires_at: chrono::NaiveDateTime,
+}
+
+/// A personal data export as shown to its owner while polling
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct DataExportInfo {
+    pub id: String,
+    pub status: ExportStatus,
+    pub created_at: i64,
+    pub completed_at: Option<i64>,
+    pub expires_at: i64,
+}
+
+impl TryFrom<&DataExport> for DataExportInfo {
+    type Error = ApiError;
+
+    fn try_from(export: &DataExport) -> Result<Self> {
+        Ok(Self {
+            id: export.id.to_string(),
+            status: ExportStatus::from_str(&export.status)?,
+            created_at: export.created_at.timestamp(),
+            completed_at: export
+                .completed_at
+                .map(|completed_at| completed_at.timestamp()),
+            expires_at: export.expires_at.timestamp(),
+        })
+    }
+}
+
+/// One of the user's own posts, flat so that it also fits in a CSV row
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct ExportedPost {
+    pub id: String,
+    pub title: String,
+    pub location: String,
+    pub content: String,
+    pub author: String,
+    pub created_at: i64,
+    pub edited_at: Option<i64>,
+    pub num_likes: u32,
+    pub num_comments: u32,
+    /// URLs of the attached photos, separated by spaces
+    pub media: String,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub country_code: Option<String>,
+    pub place_id: Option<String>,
+}
+
+impl From<&Post> for ExportedPost {
+    fn from(post: &Post) -> Self {
+        Self {
+            id: post.id.clone(),
+            title: post.title.clone(),
+            location: post.location.clone(),
+            content: post.content.clone(),
+            author: post.author.clone(),
+            created_at: post.created_at,
+            edited_at: post.edited_at,
+            num_likes: post.num_likes,
+            num_comments: post.num_comments,
+            media: post
+                .media
+                .iter()
+                .map(|media| media.url.as_str())
+                .collect::<Vec<&str>>()
+                .join(" "),
+            latitude: post.latitude,
+            longitude: post.longitude,
+            country_code: post.country_code.clone(),
+            place_id: post.place_id.clone(),
+        }
+    }
+}
+
+/// One of the user's own comments, flat so that it also fits in a CSV row
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct ExportedComment {
+    pub id: String,
+    pub post_id: String,
+    pub parent_comment_id: Option<String>,
+    pub comment: String,
+    pub created_at: i64,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct ExportedLike {
+    pub post_id: String,
+    pub created_at: i64,
+}
+
+/// A data export looked up by its owner, which can only be downloaded once it is ready
+pub enum ExportLookup {
+    Status(DataExportInfo),
+    Archive(Vec<u8>),
+}
@@ -0,0 +1,50 @@
+/// Mean radius of the Earth, the same one `great_circle_distance_km` in the database uses
+pub const EARTH_RADIUS_KM: f64 = 6371.0088;
+
+/// A range of latitudes and longitudes, such as a map viewport
+///
+/// When the box crosses the antimeridian `min_longitude` is greater than `max_longitude`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingBox {
+    pub min_latitude: f64,
+    pub max_latitude: f64,
+    pub min_longitude: f64,
+    pub max_longitude: f64,
+}
+
+impl BoundingBox {
+    /// The smallest box holding every point within `radius_km` of the centre, used to
+    /// narrow posts down before working out their actual distance
+    pub fn around(latitude: f64, longitude: f64, radius_km: f64) -> Self {
+        let angular_radius = (radius_km / EARTH_RADIUS_KM).to_degrees();
+        let min_latitude = latitude - angular_radius;
+        let max_latitude = latitude + angular_radius;
+        // Circles around a pole include every longitude
+        if min_latitude <= -90.0 || max_latitude >= 90.0 {
+            return Self {
+                min_latitude: min_latitude.max(-90.0),
+                max_latitude: max_latitude.min(90.0),
+                min_longitude: -180.0,
+                max_longitude: 180.0,
+            };
+        }
+
+        let longitude_radius = ((radius_km / EARTH_RADIUS_KM).sin() / latitude.to_radians().cos())
+            .asin()
+            .to_degrees();
+        let mut min_longitude = longitude - longitude_radius;
+        let mut max_longitude = longitude + longitude_radius;
+        if min_longitude < -180.0 {
+            min_longitude += 360.0;
+        }
+        if max_longitude > 180.0 {
+            max_longitude -= 360.0;
+        }
+        Self {
+            min_latitude,
+            max_latitude,
+            min_longitude,
+            max_longitude,
+        }
+    }
+}
@@ -0,0 +1,13 @@
+/// A login that was sent to an identity provider, kept until the provider redirects back
+pub struct OidcLogin {
+    pub nonce: String,
+    pub code_verifier: String,
+}
+
+/// The query string an identity provider redirects back with
+#[derive(serde::Deserialize)]
+pub struct OidcCallback {
+    pub state: String,
+    pub code: Option<String>,
+    pub error: Option<String>,
+}
@@ -0,0 +1,154 @@
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use uuid::Uuid;
+
+use super::error::{ApiError, Result};
+
+/// What an uploaded image is used for, which decides the sizes it is resized to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageKind {
+    Avatar,
+    Cover,
+    /// A photo attached to a post
+    Post,
+}
+
+impl ImageKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ImageKind::Avatar => "avatars",
+            ImageKind::Cover => "covers",
+            ImageKind::Post => "posts",
+        }
+    }
+
+    /// Every variant stored for an image of this kind
+    pub fn variants(&self) -> &'static [ImageVariant] {
+        match self {
+            ImageKind::Avatar | ImageKind::Cover => &[
+                ImageVariant::Small,
+                ImageVariant::Medium,
+                ImageVariant::Large,
+            ],
+            ImageKind::Post => &[ImageVariant::Thumbnail, ImageVariant::Full],
+        }
+    }
+
+    /// How the variant is resized, avatars are square and covers are 3:1, while post photos
+    /// keep their aspect ratio apart from the square thumbnail
+    pub fn size(&self, variant: ImageVariant) -> Option<ImageSize> {
+        match (self, variant) {
+            (ImageKind::Avatar, ImageVariant::Small) => Some(ImageSize::Fill(64, 64)),
+            (ImageKind::Avatar, ImageVariant::Medium) => Some(ImageSize::Fill(256, 256)),
+            (ImageKind::Avatar, ImageVariant::Large) => Some(ImageSize::Fill(512, 512)),
+            (ImageKind::Cover, ImageVariant::Small) => Some(ImageSize::Fill(600, 200)),
+            (ImageKind::Cover, ImageVariant::Medium) => Some(ImageSize::Fill(1200, 400)),
+            (ImageKind::Cover, ImageVariant::Large) => Some(ImageSize::Fill(1500, 500)),
+            (ImageKind::Post, ImageVariant::Thumbnail) => Some(ImageSize::Fill(320, 320)),
+            (ImageKind::Post, ImageVariant::Full) => Some(ImageSize::Fit(2048, 2048)),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for ImageKind {
+    type Err = ApiError;
+
+    fn from_str(kind: &str) -> Result<Self> {
+        match kind {
+            "avatars" => Ok(ImageKind::Avatar),
+            "covers" => Ok(ImageKind::Cover),
+            "posts" => Ok(ImageKind::Post),
+            other => Err(ApiError::NotFound(anyhow!("Unknown image kind: {}", other))),
+        }
+    }
+}
+
+/// One of the standard sizes an uploaded image is resized to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageVariant {
+    Small,
+    Medium,
+    Large,
+    Thumbnail,
+    Full,
+}
+
+impl ImageVariant {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ImageVariant::Small => "small",
+            ImageVariant::Medium => "medium",
+            ImageVariant::Large => "large",
+            ImageVariant::Thumbnail => "thumbnail",
+            ImageVariant::Full => "full",
+        }
+    }
+}
+
+impl FromStr for ImageVariant {
+    type Err = ApiError;
+
+    fn from_str(variant: &str) -> Result<Self> {
+        match variant {
+            "small" => Ok(ImageVariant::Small),
+            "medium" => Ok(ImageVariant::Medium),
+            "large" => Ok(ImageVariant::Large),
+            "thumbnail" => Ok(ImageVariant::Thumbnail),
+            "full" => Ok(ImageVariant::Full),
+            other => Err(ApiError::NotFound(anyhow!(
+                "Unknown image variant: {}",
+                other
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageSize {
+    /// Scaled and cropped to exactly this width and height
+    Fill(u32, u32),
+    /// Scaled down to fit within this width and height, smaller images are left as they are
+    Fit(u32, u32),
+}
+
+/// Where a variant is kept in the blob store
+pub fn image_blob_key(kind: ImageKind, image_id: &Uuid, variant: ImageVariant) -> String {
+    format!("{}/{}/{}.jpg", kind.as_str(), image_id, variant.as_str())
+}
+
+/// Path the API serves a variant from
+pub fn image_url(kind: ImageKind, image_id: &Uuid, variant: ImageVariant) -> String {
+    format!("/media/{}", image_blob_key(kind, image_id, variant))
+}
+
+/// URLs of every variant of an avatar or cover
+///
+/// The API serves the images itself, so the URLs stay the same whichever blob store
+/// they are kept in.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ProfileImage {
+    pub small: String,
+    pub medium: String,
+    pub large: String,
+}
+
+impl ProfileImage {
+    pub fn new(kind: ImageKind, image_id: &Uuid) -> Self {
+        let url = |variant| image_url(kind, image_id, variant);
+        Self {
+            small: url(ImageVariant::Small),
+            medium: url(ImageVariant::Medium),
+            large: url(ImageVariant::Large),
+        }
+    }
+
+    pub fn avatar(image_id: Option<Uuid>) -> Option<Self> {
+        image_id.map(|id| Self::new(ImageKind::Avatar, &id))
+    }
+
+    pub fn cover(image_id: Option<Uuid>) -> Option<Self> {
+        image_id.map(|id| Self::new(ImageKind::Cover, &id))
+    }
+}
@@ -0,0 +1,34 @@
+use uuid::Uuid;
+
+pub struct LockoutEvent {
+    pub id: Uuid,
+    pub scope: String,
+    pub identifier: String,
+    pub failed_attempts: i32,
+    pub locked_until: chrono::NaiveDateTime,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+/// A login lockout as shown to admins
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct LockoutEventInfo {
+    pub id: String,
+    pub scope: String,
+    pub identifier: String,
+    pub failed_attempts: i32,
+    pub locked_until: i64,
+    pub created_at: i64,
+}
+
+impl From<LockoutEvent> for LockoutEventInfo {
+    fn from(event: LockoutEvent) -> Self {
+        Self {
+            id: event.id.to_string(),
+            scope: event.scope,
+            identifier: event.identifier,
+            failed_attempts: event.failed_attempts,
+            locked_until: event.locked_until.timestamp(),
+            created_at: event.created_at.timestamp(),
+        }
+    }
+}
@@ -0,0 +1,21 @@
+use uuid::Uuid;
+
+/// A user tagged with `@username`, for clients to turn into a link to their profile
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Mention {
+    pub user_id: String,
+    /// The user's current username, which may differ from the text if they have renamed
+    pub username: String,
+    /// Character offset of the `@`
+    pub start: u32,
+    /// Character offset just past the end of the username
+    pub end: u32,
+}
+
+/// A mention found in new text, resolved to the user it tags
+#[derive(Debug)]
+pub struct NewMention {
+    pub user_id: Uuid,
+    pub start: i32,
+    pub end: i32,
+}
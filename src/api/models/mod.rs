@@ -1,10 +1,37 @@
 pub mod error;
+pub mod password;
 pub mod token;
 
+mod api_key;
 mod comments;
+mod export;
+mod geo;
+mod identity;
+mod image;
+mod lockout;
+mod mention;
+mod pagination;
+mod place;
 mod posts;
+mod role;
+mod session;
+mod tag;
+mod two_factor;
 mod user;
 
+pub use api_key::*;
 pub use comments::*;
+pub use export::*;
+pub use geo::*;
+pub use identity::*;
+pub use image::*;
+pub use lockout::*;
+pub use mention::*;
+pub use pagination::*;
+pub use place::*;
 pub use posts::*;
+pub use role::*;
+pub use session::*;
+pub use tag::*;
+pub use two_factor::*;
 pub use user::*;
@@ -0,0 +1,56 @@
+use validator::Validate;
+
+/// Which page of a listing to return, taken from the query string
+#[derive(serde::Deserialize, Validate, Debug)]
+pub struct Pagination {
+    /// Capped so that the offset can't overflow
+    #[serde(default = "first_page")]
+    #[validate(range(min = 1, max = 1_000_000))]
+    pub page: i64,
+    #[serde(default = "default_per_page")]
+    #[validate(range(min = 1, max = 100))]
+    pub per_page: i64,
+}
+
+fn first_page() -> i64 {
+    1
+}
+
+fn default_per_page() -> i64 {
+    20
+}
+
+impl Pagination {
+    /// One more than the page holds, so that the query also tells whether there is more
+    pub fn limit(&self) -> i64 {
+        self.per_page + 1
+    }
+
+    pub fn offset(&self) -> i64 {
+        (self.page - 1) * self.per_page
+    }
+}
+
+/// One page of a listing
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub page: i64,
+    pub per_page: i64,
+    /// Whether there is a next page
+    pub has_more: bool,
+}
+
+impl<T> Page<T> {
+    /// Builds the page from rows fetched with `Pagination::limit`
+    pub fn new(mut items: Vec<T>, pagination: &Pagination) -> Self {
+        let has_more = items.len() as i64 > pagination.per_page;
+        items.truncate(pagination.per_page as usize);
+        Self {
+            items,
+            page: pagination.page,
+            per_page: pagination.per_page,
+            has_more,
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use anyhow::{anyhow, Context};
+use argon2::{
+    password_hash::SaltString, Algorithm, Argon2, Params, PasswordHash, PasswordHasher,
+    PasswordVerifier, Version,
+};
+use secrecy::{ExposeSecret, Secret};
+
+use crate::api::{configuration::PasswordHashingSettings, telemetry::spawn_blocking_with_tracing};
+
+use super::error::{ApiError, Result};
+
+pub enum PasswordVerification {
+    Invalid,
+    Valid,
+    /// The password is correct but was hashed with a legacy algorithm or outdated parameters
+    ValidNeedsRehash,
+}
+
+/// Hash a password with Argon2id, returning a PHC string
+///
+/// Hashing is CPU bound so it runs on the blocking thread pool instead of an actix worker.
+pub async fn hash_password(
+    password: Secret<String>,
+    settings: &PasswordHashingSettings,
+) -> Result<Secret<String>> {
+    let params = params(settings)?;
+    spawn_blocking_with_tracing(move || {
+        let salt = SaltString::generate(&mut rand::thread_rng());
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password(password.expose_secret().as_bytes(), &salt)
+            .map(|hash| Secret::new(hash.to_string()))
+            .map_err(|err| anyhow!("Failed to hash password: {err}"))
+    })
+    .await
+    .context("Failed to spawn blocking task.")
+    .map_err(ApiError::InternalServer)?
+    .map_err(ApiError::InternalServer)
+}
+
+/// Verify a password against a stored hash
+///
+/// Argon2 hashes are stored as PHC strings, which carry their own algorithm and parameters.
+/// Hashes from before Argon2 are bcrypt's `$2b$` strings and always need rehashing.
+pub async fn verify_password(
+    password: Secret<String>,
+    stored_hash: Secret<String>,
+    settings: &PasswordHashingSettings,
+) -> Result<PasswordVerification> {
+    let current_params = params(settings)?;
+    spawn_blocking_with_tracing(move || {
+        let stored_hash = stored_hash.expose_secret();
+        let password = password.expose_secret();
+
+        if is_bcrypt_hash(stored_hash) {
+            return Ok(if pwhash::bcrypt::verify(password, stored_hash) {
+                PasswordVerification::ValidNeedsRehash
+            } else {
+                PasswordVerification::Invalid
+            });
+        }
+
+        let hash = PasswordHash::new(stored_hash)
+            .map_err(|err| anyhow!("Failed to parse stored password hash: {err}"))?;
+        if Argon2::default()
+            .verify_password(password.as_bytes(), &hash)
+            .is_err()
+        {
+            return Ok(PasswordVerification::Invalid);
+        }
+
+        let is_current = hash.algorithm == Algorithm::Argon2id.ident()
+            && Params::try_from(&hash).is_ok_and(|params| {
+                params.m_cost() == current_params.m_cost()
+                    && params.t_cost() == current_params.t_cost()
+                    && params.p_cost() == current_params.p_cost()
+            });
+        Ok(if is_current {
+            PasswordVerification::Valid
+        } else {
+            PasswordVerification::ValidNeedsRehash
+        })
+    })
+    .await
+    .context("Failed to spawn blocking task.")
+    .map_err(ApiError::InternalServer)?
+    .map_err(ApiError::InternalServer)
+}
+
+fn is_bcrypt_hash(hash: &str) -> bool {
+    ["$2a$", "$2b$", "$2x$", "$2y$"]
+        .iter()
+        .any(|prefix| hash.starts_with(prefix))
+}
+
+fn params(settings: &PasswordHashingSettings) -> Result<Params> {
+    Params::new(
+        settings.memory_cost_kib,
+        settings.iterations,
+        settings.parallelism,
+        None,
+    )
+    .map_err(|err| ApiError::InternalServer(anyhow!("Invalid password hashing settings: {err}")))
+}
@@ -0,0 +1,104 @@
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use validator::Validate;
+
+use super::{
+    error::{ApiError, Result},
+    posts::validate_country_code,
+    Post, PublicUser,
+};
+
+/// What kind of place it is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PlaceCategory {
+    City,
+    Landmark,
+    Nature,
+    Beach,
+    Mountain,
+    Restaurant,
+    Accommodation,
+    Other,
+}
+
+impl PlaceCategory {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PlaceCategory::City => "city",
+            PlaceCategory::Landmark => "landmark",
+            PlaceCategory::Nature => "nature",
+            PlaceCategory::Beach => "beach",
+            PlaceCategory::Mountain => "mountain",
+            PlaceCategory::Restaurant => "restaurant",
+            PlaceCategory::Accommodation => "accommodation",
+            PlaceCategory::Other => "other",
+        }
+    }
+}
+
+impl FromStr for PlaceCategory {
+    type Err = ApiError;
+
+    fn from_str(category: &str) -> Result<Self> {
+        match category {
+            "city" => Ok(PlaceCategory::City),
+            "landmark" => Ok(PlaceCategory::Landmark),
+            "nature" => Ok(PlaceCategory::Nature),
+            "beach" => Ok(PlaceCategory::Beach),
+            "mountain" => Ok(PlaceCategory::Mountain),
+            "restaurant" => Ok(PlaceCategory::Restaurant),
+            "accommodation" => Ok(PlaceCategory::Accommodation),
+            "other" => Ok(PlaceCategory::Other),
+            other => Err(ApiError::BadRequest(anyhow!(
+                "Unknown place category: {}",
+                other
+            ))),
+        }
+    }
+}
+
+/// A place posts are made at, shared by every post with the same location
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct Place {
+    pub id: String,
+    pub name: String,
+    pub country_code: Option<String>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    /// Left out for places created from a post's location
+    pub category: Option<PlaceCategory>,
+    pub num_posts: u32,
+}
+
+#[derive(serde::Deserialize, Validate)]
+pub struct CreatePlace {
+    #[validate(length(min = 3), length(max = 100))]
+    pub name: String,
+    #[validate(custom = "validate_country_code")]
+    pub country_code: Option<String>,
+    #[validate(range(min = -90.0, max = 90.0))]
+    pub latitude: Option<f64>,
+    #[validate(range(min = -180.0, max = 180.0))]
+    pub longitude: Option<f64>,
+    pub category: Option<PlaceCategory>,
+}
+
+/// One of the users who posted most often from a place
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct PlaceContributor {
+    pub user: PublicUser,
+    pub num_posts: u32,
+}
+
+/// A place's page, with its most recent posts, the rest are paginated separately
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct PlaceDetails {
+    #[serde(flatten)]
+    pub place: Place,
+    /// How many different users have posted from the place
+    pub num_visitors: u32,
+    pub top_contributors: Vec<PlaceContributor>,
+    pub recent_posts: Vec<Post>,
+}
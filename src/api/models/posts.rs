@@ -1,30 +1,133 @@
-use super::AuthUser;
+use uuid::Uuid;
+use validator::Validate;
+
+use super::{image_url, ImageKind, ImageVariant, Mention, PublicUser};
 
 #[derive(serde::Serialize, serde::Deserialize)]
 pub struct Post {
     pub id: String,
     pub title: String,
     pub location: String,
+    /// Where the post was made, if the author shared it
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub country_code: Option<String>,
+    /// The place `location` refers to
+    pub place_id: Option<String>,
     pub content: String,
     pub author: String,
     pub created_at: i64,
+    /// When the post was last edited, its earlier versions are in its history
+    pub edited_at: Option<i64>,
     pub num_likes: u32,
     pub num_comments: u32,
+    /// Attached photos, in the order the author chose
+    pub media: Vec<PostMedia>,
+    /// Users tagged in the content, in the order they appear
+    pub mentions: Vec<Mention>,
+}
+
+/// A photo uploaded to be attached to a post
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PostMedia {
+    pub id: String,
+    pub url: String,
+    pub thumbnail_url: String,
+    pub alt_text: Option<String>,
+    /// Dimensions of the image at `url`
+    pub width: u32,
+    pub height: u32,
 }
 
-#[derive(serde::Deserialize, validator::Validate)]
+impl PostMedia {
+    pub fn new(id: &Uuid, alt_text: Option<String>, width: i32, height: i32) -> Self {
+        Self {
+            id: id.to_string(),
+            url: image_url(ImageKind::Post, id, ImageVariant::Full),
+            thumbnail_url: image_url(ImageKind::Post, id, ImageVariant::Thumbnail),
+            alt_text,
+            width: width as u32,
+            height: height as u32,
+        }
+    }
+}
+
+#[derive(serde::Deserialize, Validate)]
 pub struct CreatePost {
-    #[validate(length(min = 3), length(max = 20))]
     pub title: String,
-    #[validate(length(min = 3), length(max = 100))]
     pub location: String,
+    /// Optional coordinates of `location`, both or neither must be given
+    #[validate(range(min = -90.0, max = 90.0))]
+    pub latitude: Option<f64>,
+    #[validate(range(min = -180.0, max = 180.0))]
+    pub longitude: Option<f64>,
+    /// ISO 3166-1 alpha-2 code, such as `PT`
+    #[validate(custom = "validate_country_code")]
+    pub country_code: Option<String>,
+    /// An existing place to post from, otherwise `location` is looked up or added as one
+    pub place_id: Option<String>,
+    pub content: String,
+    /// Uploaded photos to attach, in the order they should be shown
+    #[serde(default)]
+    #[validate(length(max = 10))]
+    #[validate]
+    pub media: Vec<AttachMedia>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Validate)]
+pub struct AttachMedia {
+    pub id: String,
+    #[validate(length(max = 1000))]
+    pub alt_text: Option<String>,
+}
+
+/// A partial edit of a post, fields that are left out keep their current value
+#[derive(serde::Deserialize, Validate)]
+pub struct UpdatePost {
+    #[validate(length(min = 3), length(max = 20))]
+    pub title: Option<String>,
+    #[validate(length(min = 3), length(max = 100))]
+    pub location: Option<String>,
     #[validate(length(min = 3), length(max = 255))]
+    pub content: Option<String>,
+}
+
+pub(super) fn validate_country_code(country_code: &str) -> Result<(), validator::ValidationError> {
+    if country_code.len() != 2 || !country_code.chars().all(|c| c.is_ascii_alphabetic()) {
+        return Err(validator::ValidationError::new(
+            "Country code must be two letters.",
+        ));
+    }
+    Ok(())
+}
+
+impl UpdatePost {
+    pub fn is_empty(&self) -> bool {
+        self.title.is_none() && self.location.is_none() && self.content.is_none()
+    }
+}
+
+/// A post found by a nearby search, with how far it is from the searched point
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct NearbyPost {
+    #[serde(flatten)]
+    pub post: Post,
+    pub distance_km: f64,
+}
+
+/// A version of a post from before it was edited
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct PostEdit {
+    pub title: String,
+    pub location: String,
     pub content: String,
+    /// When this version was replaced by the next one
+    pub edited_at: i64,
 }
 
 #[derive(serde::Serialize, serde::Deserialize)]
 pub struct Like {
-    pub user: AuthUser,
+    pub user: PublicUser,
     pub post_id: String,
     pub created_at: i64,
 }
@@ -0,0 +1,106 @@
+use std::{future::Future, marker::PhantomData, ops::Deref, pin::Pin, str::FromStr};
+
+use actix_web::FromRequest;
+use anyhow::anyhow;
+
+use super::{
+    error::{ApiError, Result},
+    token::JwtPayload,
+};
+
+/// A role granted on top of a regular account
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Moderator,
+    Admin,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Moderator => "moderator",
+            Role::Admin => "admin",
+        }
+    }
+
+    /// Admins can do everything moderators can
+    pub fn includes(&self, other: Role) -> bool {
+        matches!(
+            (self, other),
+            (Role::Admin, _) | (Role::Moderator, Role::Moderator)
+        )
+    }
+}
+
+impl FromStr for Role {
+    type Err = ApiError;
+
+    fn from_str(role: &str) -> Result<Self> {
+        match role {
+            "moderator" => Ok(Role::Moderator),
+            "admin" => Ok(Role::Admin),
+            other => Err(ApiError::BadRequest(anyhow!("Unknown role: {}", other))),
+        }
+    }
+}
+
+impl JwtPayload {
+    pub fn has_role(&self, role: Role) -> bool {
+        self.roles.iter().any(|granted| granted.includes(role))
+    }
+}
+
+/// Marker types for `RequireRole`
+pub trait RequiredRole {
+    const ROLE: Role;
+}
+
+pub struct Admin;
+
+impl RequiredRole for Admin {
+    const ROLE: Role = Role::Admin;
+}
+
+/// An authenticated request from a user with at least the role `R`, e.g. `RequireRole<Admin>`
+///
+/// Roles come from the token claims, so a change to a user's roles applies from their
+/// next token refresh.
+pub struct RequireRole<R: RequiredRole> {
+    jwt: JwtPayload,
+    role: PhantomData<R>,
+}
+
+impl<R: RequiredRole> Deref for RequireRole<R> {
+    type Target = JwtPayload;
+
+    fn deref(&self) -> &Self::Target {
+        &self.jwt
+    }
+}
+
+impl<R: RequiredRole> FromRequest for RequireRole<R> {
+    type Error = ApiError;
+    type Future = Pin<Box<dyn Future<Output = core::result::Result<Self, Self::Error>>>>;
+
+    fn from_request(
+        req: &actix_web::HttpRequest,
+        payload: &mut actix_web::dev::Payload,
+    ) -> Self::Future {
+        let jwt = JwtPayload::from_request(req, payload);
+
+        Box::pin(async move {
+            let jwt = jwt.await?;
+            if !jwt.has_role(R::ROLE) {
+                return Err(ApiError::Forbidden(anyhow!(
+                    "You need the {} role to do this",
+                    R::ROLE.as_str()
+                )));
+            }
+            Ok(RequireRole {
+                jwt,
+                role: PhantomData,
+            })
+        })
+    }
+}
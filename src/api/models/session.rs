@@ -0,0 +1,73 @@
+use std::future::{ready, Ready};
+
+use actix_web::{http::header::USER_AGENT, FromRequest};
+use uuid::Uuid;
+
+use super::error::ApiError;
+
+pub struct Session {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub created_at: chrono::NaiveDateTime,
+    pub last_seen_at: chrono::NaiveDateTime,
+    pub revoked_at: Option<chrono::NaiveDateTime>,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+}
+
+pub struct RefreshToken {
+    pub session_id: Uuid,
+    pub used: bool,
+    pub expired: bool,
+}
+
+/// A session as shown to its owner on the "signed in devices" screen
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct SessionInfo {
+    pub id: String,
+    pub created_at: i64,
+    pub last_seen_at: i64,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub current: bool,
+}
+
+impl SessionInfo {
+    pub fn new(session: Session, current_session_id: &Uuid) -> Self {
+        Self {
+            current: session.id == *current_session_id,
+            id: session.id.to_string(),
+            created_at: session.created_at.timestamp(),
+            last_seen_at: session.last_seen_at.timestamp(),
+            user_agent: session.user_agent,
+            ip_address: session.ip_address,
+        }
+    }
+}
+
+/// The device a request came from, recorded when a session is started
+#[derive(Debug, Default)]
+pub struct ClientInfo {
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+}
+
+impl FromRequest for ClientInfo {
+    type Error = ApiError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &actix_web::HttpRequest, _: &mut actix_web::dev::Payload) -> Self::Future {
+        let user_agent = req
+            .headers()
+            .get(USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string());
+        // Use the peer address rather than forwarding headers, which the client controls
+        let ip_address = req.peer_addr().map(|addr| addr.ip().to_string());
+
+        ready(Ok(ClientInfo {
+            user_agent,
+            ip_address,
+        }))
+    }
+}
@@ -0,0 +1,14 @@
+/// One of the hashtags used on the most posts lately
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct TrendingTag {
+    pub name: String,
+    /// Posts tagged with it during the window
+    pub num_posts: u32,
+}
+
+/// A hashtag the user follows, whose posts show up in their feed
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct FollowedTag {
+    pub name: String,
+    pub created_at: i64,
+}
@@ -1,14 +1,42 @@
-use std::{future::Future, pin::Pin};
+use std::{collections::HashMap, future::Future, pin::Pin};
 
-use actix_web::FromRequest;
-use anyhow::Context;
-use jsonwebtoken::{DecodingKey, EncodingKey, Header, TokenData, Validation};
+use actix_web::{web::Data, FromRequest};
+use anyhow::{anyhow, Context};
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, TokenData, Validation};
+use rand::{distributions::Alphanumeric, Rng};
+use secrecy::ExposeSecret;
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use uuid::Uuid;
 
-use super::error::{ApiError, Result};
+use crate::api::{
+    configuration::{AuthSettings, JwtKeySettings},
+    database,
+};
+
+use super::{
+    error::{ApiError, Result},
+    Role,
+};
 
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub struct JwtPayload {
     pub user_id: String,
+    pub session_id: String,
+    #[serde(default)]
+    pub roles: Vec<Role>,
+    pub iss: u64,
+    pub exp: u64,
+}
+
+/// Claims of the token handed out by the first login step when 2FA is enabled
+///
+/// It has no session, so it can't be used as a bearer token, and it can only be
+/// exchanged for one together with a valid code.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct MfaPendingPayload {
+    pub user_id: String,
+    pub mfa_pending: bool,
     pub iss: u64,
     pub exp: u64,
 }
@@ -18,6 +46,147 @@ pub struct Token {
     pub token: String,
 }
 
+#[derive(Debug, serde::Deserialize)]
+pub struct RefreshTokenRequest {
+    pub refresh_token: String,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct TokenPair {
+    pub bearer: String,
+    pub refresh_token: String,
+}
+
+/// The keys used to sign and verify bearer tokens, built once from `AuthSettings`
+///
+/// Every token carries the `kid` of the key that signed it, so tokens signed by a
+/// previous key keep verifying for as long as that key is listed in
+/// `verification_keys`.
+#[derive(Clone)]
+pub struct JwtKeys {
+    kid: String,
+    algorithm: Algorithm,
+    encoding_key: EncodingKey,
+    decoding_keys: HashMap<String, (Algorithm, DecodingKey)>,
+    pub access_token_lifetime: chrono::Duration,
+    pub refresh_token_lifetime: chrono::Duration,
+    pub mfa_token_lifetime: chrono::Duration,
+}
+
+impl JwtKeys {
+    pub fn from_settings(settings: &AuthSettings) -> anyhow::Result<Self> {
+        let signing_key = &settings.signing_key;
+        let encoding_key = load_encoding_key(signing_key)?;
+
+        let mut decoding_keys = HashMap::new();
+        for key in std::iter::once(signing_key).chain(settings.verification_keys.iter()) {
+            if decoding_keys.contains_key(&key.kid) {
+                return Err(anyhow!("Duplicate JWT key id: {}", key.kid));
+            }
+            decoding_keys.insert(key.kid.clone(), (key.algorithm, load_decoding_key(key)?));
+        }
+
+        Ok(Self {
+            kid: signing_key.kid.clone(),
+            algorithm: signing_key.algorithm,
+            encoding_key,
+            decoding_keys,
+            access_token_lifetime: chrono::Duration::minutes(
+                settings.access_token_expiration_minutes,
+            ),
+            refresh_token_lifetime: chrono::Duration::days(settings.refresh_token_expiration_days),
+            mfa_token_lifetime: chrono::Duration::minutes(settings.mfa_token_expiration_minutes),
+        })
+    }
+
+    pub fn encode<T: serde::Serialize>(&self, claims: &T) -> Result<String> {
+        let mut header = Header::new(self.algorithm);
+        header.kid = Some(self.kid.clone());
+        jsonwebtoken::encode(&header, claims, &self.encoding_key)
+            .context("Failed to generate JWT.")
+            .map_err(ApiError::InternalServer)
+    }
+
+    pub fn decode<T: serde::de::DeserializeOwned>(&self, token: &str) -> Result<T> {
+        let header = jsonwebtoken::decode_header(token)
+            .map_err(|err| ApiError::Unauthorized(anyhow!(err.to_string())))?;
+        let kid = header
+            .kid
+            .ok_or_else(|| ApiError::Unauthorized(anyhow!("Token is missing a key id.")))?;
+        let (algorithm, key) = self.decoding_keys.get(&kid).ok_or_else(|| {
+            ApiError::Unauthorized(anyhow!("Token was signed by an unknown key."))
+        })?;
+
+        // Only accept the algorithm configured for the key, never the one claimed by the token
+        let token: TokenData<T> = jsonwebtoken::decode(token, key, &Validation::new(*algorithm))
+            .map_err(|err| ApiError::Unauthorized(anyhow!(err.to_string())))?;
+        Ok(token.claims)
+    }
+}
+
+fn read_pem(path: &Option<String>, kid: &str, which: &str) -> anyhow::Result<Vec<u8>> {
+    let path = path
+        .as_ref()
+        .ok_or_else(|| anyhow!("JWT key `{kid}` is missing a {which} key path."))?;
+    std::fs::read(path).with_context(|| format!("Failed to read {which} key for JWT key `{kid}`."))
+}
+
+fn load_encoding_key(settings: &JwtKeySettings) -> anyhow::Result<EncodingKey> {
+    let kid = &settings.kid;
+    let key = match settings.algorithm {
+        Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => {
+            EncodingKey::from_secret(secret(settings)?)
+        }
+        Algorithm::RS256
+        | Algorithm::RS384
+        | Algorithm::RS512
+        | Algorithm::PS256
+        | Algorithm::PS384
+        | Algorithm::PS512 => {
+            EncodingKey::from_rsa_pem(&read_pem(&settings.private_key_path, kid, "private")?)?
+        }
+        Algorithm::ES256 | Algorithm::ES384 => {
+            EncodingKey::from_ec_pem(&read_pem(&settings.private_key_path, kid, "private")?)?
+        }
+        Algorithm::EdDSA => {
+            EncodingKey::from_ed_pem(&read_pem(&settings.private_key_path, kid, "private")?)?
+        }
+    };
+    Ok(key)
+}
+
+fn load_decoding_key(settings: &JwtKeySettings) -> anyhow::Result<DecodingKey> {
+    let kid = &settings.kid;
+    let key = match settings.algorithm {
+        Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => {
+            DecodingKey::from_secret(secret(settings)?)
+        }
+        Algorithm::RS256
+        | Algorithm::RS384
+        | Algorithm::RS512
+        | Algorithm::PS256
+        | Algorithm::PS384
+        | Algorithm::PS512 => {
+            DecodingKey::from_rsa_pem(&read_pem(&settings.public_key_path, kid, "public")?)?
+        }
+        Algorithm::ES256 | Algorithm::ES384 => {
+            DecodingKey::from_ec_pem(&read_pem(&settings.public_key_path, kid, "public")?)?
+        }
+        Algorithm::EdDSA => {
+            DecodingKey::from_ed_pem(&read_pem(&settings.public_key_path, kid, "public")?)?
+        }
+    };
+    Ok(key)
+}
+
+fn secret(settings: &JwtKeySettings) -> anyhow::Result<&[u8]> {
+    settings
+        .secret
+        .as_ref()
+        .map(|secret| secret.expose_secret().as_bytes())
+        .ok_or_else(|| anyhow!("JWT key `{}` is missing a secret.", settings.kid))
+}
+
 impl FromRequest for JwtPayload {
     type Error = ApiError;
     type Future = Pin<Box<dyn Future<Output = core::result::Result<Self, Self::Error>>>>;
@@ -32,35 +201,63 @@ impl FromRequest for JwtPayload {
             .ok_or(ApiError::Unauthorized(anyhow::anyhow!(
                 "Missing Authorization Token."
             )));
+        let keys = req.app_data::<Data<JwtKeys>>().cloned();
+        let conn = req.app_data::<Data<PgPool>>().cloned();
 
         Box::pin(async move {
-            match token {
-                Ok(token) => {
-                    // Validate token
-                    let token: TokenData<JwtPayload> = jsonwebtoken::decode(
-                        &token,
-                        &DecodingKey::from_secret("Secret".as_bytes()),
-                        &Validation::default(),
-                    )
-                    .map_err(|err| ApiError::Unauthorized(anyhow::anyhow!(err.to_string())))?;
-                    Ok(token.claims)
-                }
-                Err(err) => Err(err),
+            let token = token?;
+            let (keys, conn) = keys.zip(conn).ok_or(ApiError::InternalServer(anyhow!(
+                "JWT keys or database are not configured."
+            )))?;
+            // Validate token
+            let payload = keys.decode::<JwtPayload>(&token)?;
+            // Check that the session has not been revoked
+            let session_id = Uuid::parse_str(&payload.session_id)
+                .context("Failed to parse session id")
+                .map_err(ApiError::Unauthorized)?;
+            if !database::touch_session(&conn, &session_id).await? {
+                return Err(ApiError::Unauthorized(anyhow!("Session has been revoked.")));
             }
+            Ok(payload)
         })
     }
 }
 
-pub fn generate_token(user_id: &str) -> Result<String> {
-    Ok(jsonwebtoken::encode(
-        &Header::default(),
-        &JwtPayload {
-            user_id: user_id.to_string(),
-            iss: chrono::Utc::now().timestamp() as u64,
-            exp: (chrono::Utc::now() + chrono::Duration::days(30)).timestamp() as u64,
-        },
-        &EncodingKey::from_secret("Secret".as_ref()),
-    )
-    .context("Failed to generate JWT.")
-    .map_err(ApiError::InternalServer)?)
+pub fn generate_token(
+    user_id: &str,
+    session_id: &str,
+    roles: Vec<Role>,
+    keys: &JwtKeys,
+) -> Result<String> {
+    keys.encode(&JwtPayload {
+        user_id: user_id.to_string(),
+        session_id: session_id.to_string(),
+        roles,
+        iss: chrono::Utc::now().timestamp() as u64,
+        exp: (chrono::Utc::now() + keys.access_token_lifetime).timestamp() as u64,
+    })
+}
+
+pub fn generate_mfa_token(user_id: &str, keys: &JwtKeys) -> Result<String> {
+    keys.encode(&MfaPendingPayload {
+        user_id: user_id.to_string(),
+        mfa_pending: true,
+        iss: chrono::Utc::now().timestamp() as u64,
+        exp: (chrono::Utc::now() + keys.mfa_token_lifetime).timestamp() as u64,
+    })
+}
+
+/// Generate an opaque, random token such as a refresh token
+///
+/// Only the hash of these tokens is stored, see `hash_token`.
+pub fn generate_opaque_token() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(64)
+        .map(char::from)
+        .collect()
+}
+
+pub fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
 }
@@ -0,0 +1,45 @@
+use super::AuthInfo;
+
+pub struct UserTotp {
+    pub secret: String,
+    pub confirmed: bool,
+    pub last_used_step: Option<i64>,
+}
+
+/// Returned when starting enrolment, to be shown as a QR code or typed into an authenticator app
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct TotpEnrollment {
+    pub secret: String,
+    pub provisioning_uri: String,
+}
+
+/// A code from the authenticator app, or one of the recovery codes
+#[derive(serde::Deserialize)]
+pub struct TotpCode {
+    pub code: String,
+}
+
+/// Shown once, when 2FA is confirmed. Only their hashes are stored.
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct RecoveryCodes {
+    pub recovery_codes: Vec<String>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct MfaChallenge {
+    pub mfa_token: String,
+}
+
+#[derive(serde::Deserialize)]
+pub struct MfaLogin {
+    pub mfa_token: String,
+    pub code: String,
+}
+
+/// The outcome of the password step of a login
+#[derive(serde::Serialize)]
+#[serde(untagged)]
+pub enum LoginResponse {
+    Authenticated(Box<AuthInfo>),
+    MfaRequired(MfaChallenge),
+}
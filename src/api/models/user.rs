@@ -1,6 +1,9 @@
 use secrecy::Secret;
+use uuid::Uuid;
 use validator::Validate;
 
+use super::{PostMedia, ProfileImage};
+
 #[derive(Debug)]
 pub struct User {
     pub id: String,
@@ -9,8 +12,17 @@ pub struct User {
     pub password: Secret<String>,
     pub name: String,
     pub description: String,
+    pub email_verified: bool,
+    /// Scheduled for deletion, hidden from everyone else until restored or purged
+    pub deactivated: bool,
+    pub show_email: bool,
+    /// Only approved followers can see the account's posts and connections
+    pub is_private: bool,
+    pub avatar: Option<ProfileImage>,
+    pub cover: Option<ProfileImage>,
 }
 
+/// The account owner's own view of their profile, never shown to anyone else
 #[derive(serde::Serialize, serde::Deserialize, Debug)]
 pub struct AuthUser {
     pub id: String,
@@ -18,11 +30,32 @@ pub struct AuthUser {
     pub name: String,
     pub description: String,
     pub email: String,
+    /// Whether the email is shown to other users
+    pub show_email: bool,
+    pub is_private: bool,
+    pub avatar: Option<ProfileImage>,
+    pub cover: Option<ProfileImage>,
+}
+
+/// A user as seen by everyone else
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct PublicUser {
+    pub id: String,
+    pub username: String,
+    pub name: String,
+    pub description: String,
+    /// Only present if the user has chosen to show it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    pub is_private: bool,
+    pub avatar: Option<ProfileImage>,
+    pub cover: Option<ProfileImage>,
 }
 
 #[derive(serde::Serialize, serde::Deserialize)]
 pub struct AuthInfo {
     pub bearer: String,
+    pub refresh_token: String,
     pub user: AuthUser,
 }
 
@@ -34,10 +67,48 @@ impl From<User> for AuthUser {
             email: user.email,
             name: user.name,
             description: user.description,
+            show_email: user.show_email,
+            is_private: user.is_private,
+            avatar: user.avatar,
+            cover: user.cover,
+        }
+    }
+}
+
+impl From<User> for PublicUser {
+    fn from(user: User) -> Self {
+        Self {
+            id: user.id,
+            username: user.username,
+            name: user.name,
+            description: user.description,
+            email: user.show_email.then_some(user.email),
+            is_private: user.is_private,
+            avatar: user.avatar,
+            cover: user.cover,
         }
     }
 }
 
+/// A follow request waiting for the private account's approval
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct FollowRequest {
+    pub user: PublicUser,
+    pub created_at: i64,
+}
+
+/// What following a user did, private accounts have to approve the follow first
+pub enum FollowOutcome {
+    Followed,
+    Requested,
+}
+
+/// A profile looked up by username, which may be one the user has since changed
+pub enum UsernameLookup {
+    Current(Box<PublicUser>),
+    Renamed { username: String },
+}
+
 #[derive(serde::Deserialize)]
 pub struct LoginInfo {
     pub email: String,
@@ -57,6 +128,68 @@ pub struct CreateUser {
     pub password: String,
 }
 
+#[derive(serde::Deserialize, Validate)]
+pub struct ChangePassword {
+    pub current_password: String,
+    #[validate(custom = "validate_password")]
+    pub new_password: String,
+}
+
+#[derive(serde::Deserialize, Validate)]
+pub struct ChangeUsername {
+    #[validate(length(min = 3), length(max = 20))]
+    pub username: String,
+}
+
+#[derive(serde::Deserialize)]
+pub struct DeleteAccount {
+    pub password: String,
+}
+
+/// The images a purged user leaves behind in the blob store
+#[derive(Debug, Default)]
+pub struct PurgedUser {
+    pub avatar_id: Option<Uuid>,
+    pub cover_id: Option<Uuid>,
+    /// Post photos the user uploaded, attached or not
+    pub post_media: Vec<PostMedia>,
+}
+
+/// A partial profile update, fields that are left out keep their current value
+#[derive(serde::Deserialize, Validate)]
+pub struct UpdateUser {
+    #[validate(email)]
+    pub email: Option<String>,
+    pub first_name: Option<String>,
+    pub last_name: Option<String>,
+    pub description: Option<String>,
+    pub show_email: Option<bool>,
+    pub is_private: Option<bool>,
+}
+
+#[derive(serde::Deserialize)]
+pub struct ConfirmEmail {
+    pub token: String,
+}
+
+#[derive(serde::Deserialize)]
+pub struct ForgotPassword {
+    pub email: String,
+}
+
+/// The token in the link of a password reset email
+#[derive(serde::Deserialize)]
+pub struct PasswordResetLink {
+    pub token: String,
+}
+
+#[derive(serde::Deserialize, Validate)]
+pub struct ResetPassword {
+    pub token: String,
+    #[validate(custom = "validate_password")]
+    pub password: String,
+}
+
 fn validate_password(password: &str) -> Result<(), validator::ValidationError> {
     if password.len() < 8 {
         return Err(validator::ValidationError::new("Password too short."));
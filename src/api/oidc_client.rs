@@ -0,0 +1,217 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Context};
+use jsonwebtoken::{jwk::JwkSet, Algorithm, DecodingKey, Validation};
+use secrecy::ExposeSecret;
+use serde_aux::field_attributes::deserialize_bool_from_anything;
+
+use super::{
+    configuration::OidcProviderSettings,
+    models::error::{ApiError, Result},
+};
+
+/// The parts of a provider's discovery document that the authorization code flow needs
+#[derive(serde::Deserialize)]
+struct ProviderMetadata {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    jwks_uri: String,
+}
+
+#[derive(serde::Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct IdTokenClaims {
+    pub sub: String,
+    pub nonce: Option<String>,
+    pub email: Option<String>,
+    // Some providers send this as a string
+    #[serde(default, deserialize_with = "deserialize_bool_from_anything")]
+    pub email_verified: bool,
+    pub name: Option<String>,
+    pub given_name: Option<String>,
+    pub family_name: Option<String>,
+}
+
+/// Signs users in with the OpenID Connect providers from `AuthSettings`
+///
+/// Discovery and key documents are fetched on every login, so that key rotation at
+/// the provider never needs a restart.
+pub struct OidcClient {
+    http_client: reqwest::Client,
+    providers: HashMap<String, OidcProviderSettings>,
+}
+
+impl OidcClient {
+    pub fn new(providers: &[OidcProviderSettings]) -> Self {
+        Self {
+            http_client: reqwest::Client::new(),
+            providers: providers
+                .iter()
+                .map(|provider| (provider.name.clone(), provider.clone()))
+                .collect(),
+        }
+    }
+
+    pub async fn authorization_url(
+        &self,
+        provider_name: &str,
+        state: &str,
+        nonce: &str,
+        code_challenge: &str,
+    ) -> Result<String> {
+        let provider = self.provider(provider_name)?;
+        let metadata = self.discover(provider).await?;
+
+        let url = reqwest::Url::parse_with_params(
+            &metadata.authorization_endpoint,
+            &[
+                ("response_type", "code"),
+                ("client_id", &provider.client_id),
+                ("redirect_uri", &provider.redirect_url),
+                ("scope", "openid email profile"),
+                ("state", state),
+                ("nonce", nonce),
+                ("code_challenge", code_challenge),
+                ("code_challenge_method", "S256"),
+            ],
+        )
+        .context("Invalid authorization endpoint.")
+        .map_err(ApiError::InternalServer)?;
+        Ok(url.to_string())
+    }
+
+    /// Exchanges an authorization code for the user's verified ID token claims
+    pub async fn exchange_code(
+        &self,
+        provider_name: &str,
+        code: &str,
+        code_verifier: &str,
+        nonce: &str,
+    ) -> Result<IdTokenClaims> {
+        let provider = self.provider(provider_name)?;
+        let metadata = self.discover(provider).await?;
+
+        let response = self
+            .http_client
+            .post(&metadata.token_endpoint)
+            .basic_auth(
+                &provider.client_id,
+                Some(provider.client_secret.expose_secret()),
+            )
+            .form(&[
+                ("grant_type", "authorization_code"),
+                ("code", code),
+                ("redirect_uri", &provider.redirect_url),
+                ("code_verifier", code_verifier),
+            ])
+            .send()
+            .await
+            .context("Failed to reach the identity provider's token endpoint.")
+            .map_err(ApiError::InternalServer)?;
+        if !response.status().is_success() {
+            return Err(ApiError::Unauthorized(anyhow!(
+                "The identity provider rejected the authorization code"
+            )));
+        }
+        let tokens: TokenResponse = response
+            .json()
+            .await
+            .context("Invalid token response from the identity provider.")
+            .map_err(ApiError::InternalServer)?;
+
+        let claims = self
+            .verify_id_token(provider, &metadata, &tokens.id_token)
+            .await?;
+        if claims.nonce.as_deref() != Some(nonce) {
+            return Err(ApiError::Unauthorized(anyhow!(
+                "ID token nonce does not match"
+            )));
+        }
+        Ok(claims)
+    }
+
+    fn provider(&self, name: &str) -> Result<&OidcProviderSettings> {
+        self.providers
+            .get(name)
+            .ok_or(ApiError::NotFound(anyhow!("Unknown identity provider")))
+    }
+
+    async fn discover(&self, provider: &OidcProviderSettings) -> Result<ProviderMetadata> {
+        let issuer = provider.issuer_url.trim_end_matches('/');
+        let metadata: ProviderMetadata = self
+            .get_json(&format!("{}/.well-known/openid-configuration", issuer))
+            .await?;
+        if metadata.issuer.trim_end_matches('/') != issuer {
+            return Err(ApiError::InternalServer(anyhow!(
+                "Discovery document is for a different issuer: {}",
+                metadata.issuer
+            )));
+        }
+        Ok(metadata)
+    }
+
+    async fn verify_id_token(
+        &self,
+        provider: &OidcProviderSettings,
+        metadata: &ProviderMetadata,
+        id_token: &str,
+    ) -> Result<IdTokenClaims> {
+        let header = jsonwebtoken::decode_header(id_token)
+            .map_err(|err| ApiError::Unauthorized(anyhow!("Invalid ID token: {err}")))?;
+        // Providers sign with their private keys, never accept a shared secret algorithm
+        if !matches!(
+            header.alg,
+            Algorithm::RS256
+                | Algorithm::RS384
+                | Algorithm::RS512
+                | Algorithm::PS256
+                | Algorithm::PS384
+                | Algorithm::PS512
+                | Algorithm::ES256
+                | Algorithm::ES384
+                | Algorithm::EdDSA
+        ) {
+            return Err(ApiError::Unauthorized(anyhow!(
+                "Unsupported ID token algorithm"
+            )));
+        }
+
+        let jwks: JwkSet = self.get_json(&metadata.jwks_uri).await?;
+        let jwk = match &header.kid {
+            Some(kid) => jwks.find(kid),
+            None if jwks.keys.len() == 1 => jwks.keys.first(),
+            None => None,
+        }
+        .ok_or(ApiError::Unauthorized(anyhow!(
+            "ID token was signed by an unknown key"
+        )))?;
+        let key = DecodingKey::from_jwk(jwk)
+            .map_err(|err| ApiError::InternalServer(anyhow!("Invalid provider key: {err}")))?;
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_audience(&[&provider.client_id]);
+        validation.set_issuer(&[&metadata.issuer]);
+        let token = jsonwebtoken::decode::<IdTokenClaims>(id_token, &key, &validation)
+            .map_err(|err| ApiError::Unauthorized(anyhow!("Invalid ID token: {err}")))?;
+        Ok(token.claims)
+    }
+
+    async fn get_json<T: serde::de::DeserializeOwned>(&self, url: &str) -> Result<T> {
+        self.http_client
+            .get(url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .with_context(|| format!("Failed to fetch {}.", url))
+            .map_err(ApiError::InternalServer)?
+            .json()
+            .await
+            .with_context(|| format!("Invalid JSON document at {}.", url))
+            .map_err(ApiError::InternalServer)
+    }
+}
@@ -0,0 +1,34 @@
+use std::{sync::Arc, time::Duration};
+
+use sqlx::PgPool;
+
+use super::{blob_store::BlobStore, controller, database};
+
+/// Purges deactivated accounts once their grace period is over, data exports that have
+/// expired or stalled and post photos that were never attached, checking every `interval`
+pub async fn run_purge_worker(conn: PgPool, blob_store: Arc<dyn BlobStore>, interval: Duration) {
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+        ticker.tick().await;
+        match controller::user::purge_deactivated_accounts(blob_store.as_ref(), &conn).await {
+            Ok(0) => {}
+            Ok(purged) => tracing::info!("Purged {} deactivated accounts", purged),
+            Err(err) => tracing::error!("Failed to purge deactivated accounts: {:?}", err),
+        }
+        match controller::export::fail_stale_exports(&conn).await {
+            Ok(0) => {}
+            Ok(failed) => tracing::info!("Marked {} stalled data exports as failed", failed),
+            Err(err) => tracing::error!("Failed to mark stalled data exports as failed: {:?}", err),
+        }
+        match database::delete_expired_exports(&conn).await {
+            Ok(0) => {}
+            Ok(deleted) => tracing::info!("Deleted {} expired data exports", deleted),
+            Err(err) => tracing::error!("Failed to delete expired data exports: {:?}", err),
+        }
+        match controller::media::purge_unattached_media(blob_store.as_ref(), &conn).await {
+            Ok(0) => {}
+            Ok(deleted) => tracing::info!("Deleted {} unattached post photos", deleted),
+            Err(err) => tracing::error!("Failed to delete unattached post photos: {:?}", err),
+        }
+    }
+}
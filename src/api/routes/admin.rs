@@ -0,0 +1,87 @@
+use std::str::FromStr;
+
+use actix_web::{
+    delete, get, put,
+    web::{self, Data, Path},
+    HttpResponse,
+};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::api::{
+    controller,
+    models::{
+        error::{ApiError, Result},
+        Admin, RequireRole, Role,
+    },
+};
+
+pub fn init_admin_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_lockout_events)
+        .service(get_user_roles)
+        .service(grant_role)
+        .service(revoke_role);
+}
+
+#[get("/admin/lockouts")]
+#[tracing::instrument(name = "Listing login lockouts", skip(_admin, conn))]
+async fn get_lockout_events(
+    _admin: RequireRole<Admin>,
+    conn: Data<PgPool>,
+) -> Result<HttpResponse> {
+    let events = controller::admin::get_lockout_events(&conn).await?;
+
+    Ok(HttpResponse::Ok().json(events))
+}
+
+#[get("/admin/users/{user_id}/roles")]
+#[tracing::instrument(name = "Listing a user's roles", skip(_admin, conn))]
+async fn get_user_roles(
+    _admin: RequireRole<Admin>,
+    user_id: Path<(String,)>,
+    conn: Data<PgPool>,
+) -> Result<HttpResponse> {
+    let (user_id,) = user_id.into_inner();
+    let user_id =
+        Uuid::parse_str(&user_id).map_err(|e| ApiError::BadRequest(anyhow::anyhow!(e)))?;
+
+    let roles = controller::admin::get_user_roles(&user_id, &conn).await?;
+
+    Ok(HttpResponse::Ok().json(roles))
+}
+
+#[put("/admin/users/{user_id}/roles/{role}")]
+#[tracing::instrument(name = "Granting a role", skip(_admin, conn))]
+async fn grant_role(
+    _admin: RequireRole<Admin>,
+    path: Path<(String, String)>,
+    conn: Data<PgPool>,
+) -> Result<HttpResponse> {
+    let (user_id, role) = path.into_inner();
+    let user_id =
+        Uuid::parse_str(&user_id).map_err(|e| ApiError::BadRequest(anyhow::anyhow!(e)))?;
+    let role = Role::from_str(&role)?;
+
+    controller::admin::grant_role(&user_id, role, &conn).await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[delete("/admin/users/{user_id}/roles/{role}")]
+#[tracing::instrument(name = "Revoking a role", skip(admin, conn))]
+async fn revoke_role(
+    admin: RequireRole<Admin>,
+    path: Path<(String, String)>,
+    conn: Data<PgPool>,
+) -> Result<HttpResponse> {
+    let (user_id, role) = path.into_inner();
+    let user_id =
+        Uuid::parse_str(&user_id).map_err(|e| ApiError::BadRequest(anyhow::anyhow!(e)))?;
+    let role = Role::from_str(&role)?;
+    let admin_id =
+        Uuid::parse_str(&admin.user_id).map_err(|e| ApiError::BadRequest(anyhow::anyhow!(e)))?;
+
+    controller::admin::revoke_role(&admin_id, &user_id, role, &conn).await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
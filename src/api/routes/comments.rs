@@ -10,14 +10,17 @@ use uuid::Uuid;
 use validator::Validate;
 
 use crate::api::{
+    configuration::AuthSettings,
     controller,
     models::{
         error::{ApiError, Result},
         token::JwtPayload,
-        CreateComment,
+        Authenticated, CommentsWrite, CreateComment,
     },
 };
 
+use super::users::viewer_id;
+
 pub fn init_comment_routes(cfg: &mut web::ServiceConfig) {
     cfg.service(get_comments)
         .service(create_comment)
@@ -26,22 +29,28 @@ pub fn init_comment_routes(cfg: &mut web::ServiceConfig) {
 }
 
 #[get("/post/{post_id}/comment")]
-#[tracing::instrument(name = "Get Comments", skip(path, conn))]
-async fn get_comments(path: Path<(String,)>, conn: Data<PgPool>) -> Result<HttpResponse> {
+#[tracing::instrument(name = "Get Comments", skip(token, path, conn))]
+async fn get_comments(
+    token: Option<JwtPayload>,
+    path: Path<(String,)>,
+    conn: Data<PgPool>,
+) -> Result<HttpResponse> {
     let (post_id,) = path.into_inner();
     let post_id = Uuid::parse_str(&post_id)
         .context("Failed to parse post id")
         .map_err(ApiError::BadRequest)?;
-    let comments = controller::comments::get_comments(&post_id, &conn).await?;
+    let viewer_id = viewer_id(token.as_ref())?;
+    let comments = controller::comments::get_comments(&post_id, viewer_id, &conn).await?;
     Ok(HttpResponse::Ok().json(comments))
 }
 
 #[post("/post/{post_id}/comment")]
-#[tracing::instrument(name = "Create Comment", skip(post_id, token, comment, conn))]
+#[tracing::instrument(name = "Create Comment", skip(post_id, token, comment, settings, conn))]
 async fn create_comment(
     post_id: Path<(String,)>,
-    token: JwtPayload,
+    token: Authenticated<CommentsWrite>,
     comment: Json<CreateComment>,
+    settings: Data<AuthSettings>,
     conn: Data<PgPool>,
 ) -> Result<HttpResponse> {
     let (post_id,) = post_id.into_inner();
@@ -56,18 +65,24 @@ async fn create_comment(
         .context("Failed to convert user id from token")
         .map_err(ApiError::InternalServer)?;
 
-    let comment_id =
-        controller::comments::create_comment(&user_id, &post_id, comment.into_inner(), &conn)
-            .await?;
+    let comment_id = controller::comments::create_comment(
+        &user_id,
+        &post_id,
+        comment.into_inner(),
+        &settings.unverified_accounts,
+        &conn,
+    )
+    .await?;
     Ok(HttpResponse::Created().json(json!({ "comment_id": comment_id })))
 }
 
 #[post("/post/{post_id}/comment/{comment_id}/reply")]
-#[tracing::instrument(name = "Reply to Comment", skip(path, token, comment, conn))]
+#[tracing::instrument(name = "Reply to Comment", skip(path, token, comment, settings, conn))]
 async fn reply_to_comment(
     path: Path<(String, String)>,
-    token: JwtPayload,
+    token: Authenticated<CommentsWrite>,
     comment: Json<CreateComment>,
+    settings: Data<AuthSettings>,
     conn: Data<PgPool>,
 ) -> Result<HttpResponse> {
     let (post_id, comment_id) = path.into_inner();
@@ -84,8 +99,15 @@ async fn reply_to_comment(
         .validate()
         .context("Validation failed")
         .map_err(ApiError::BadRequest)?;
-    controller::comments::reply_to_comment(&user_id, &post_id, &comment_id, comment.0, &conn)
-        .await?;
+    controller::comments::reply_to_comment(
+        &user_id,
+        &post_id,
+        &comment_id,
+        comment.0,
+        &settings.unverified_accounts,
+        &conn,
+    )
+    .await?;
     Ok(HttpResponse::Created().finish())
 }
 
@@ -93,7 +115,7 @@ async fn reply_to_comment(
 #[tracing::instrument(name = "Delete Comment")]
 async fn delete_comment(
     path: Path<(String, String)>,
-    token: JwtPayload,
+    token: Authenticated<CommentsWrite>,
     conn: Data<PgPool>,
 ) -> Result<HttpResponse> {
     let (post_id, comment_id) = path.into_inner();
@@ -0,0 +1,82 @@
+use std::str::FromStr;
+
+use actix_multipart::Multipart;
+use actix_web::{
+    get,
+    http::header::{CacheControl, CacheDirective},
+    web::{self, Data, Path},
+    HttpResponse,
+};
+use futures_util::StreamExt;
+use uuid::Uuid;
+
+use crate::api::{
+    blob_store::BlobStore,
+    controller,
+    models::{
+        error::{ApiError, Result},
+        ImageKind, ImageVariant,
+    },
+};
+
+pub fn init_media_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_image);
+}
+
+/// Images never change once uploaded, a new upload always gets a new id
+#[get("/media/{kind}/{image_id}/{variant}.jpg")]
+#[tracing::instrument(name = "Get image", skip(blob_store))]
+async fn get_image(
+    path: Path<(String, String, String)>,
+    blob_store: Data<dyn BlobStore>,
+) -> Result<HttpResponse> {
+    let (kind, image_id, variant) = path.into_inner();
+    let kind = ImageKind::from_str(&kind)?;
+    let variant = ImageVariant::from_str(&variant)?;
+    let image_id = Uuid::parse_str(&image_id)
+        .map_err(|_| ApiError::NotFound(anyhow::anyhow!("Image does not exist")))?;
+
+    let image =
+        controller::media::get_image(kind, &image_id, variant, blob_store.get_ref()).await?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("image/jpeg")
+        .insert_header(CacheControl(vec![
+            CacheDirective::Public,
+            CacheDirective::MaxAge(31_536_000),
+            CacheDirective::Extension("immutable".to_string(), None),
+        ]))
+        .body(image))
+}
+
+/// Reads the multipart field with the given name, refusing uploads over `max_bytes`
+pub(super) async fn read_upload(
+    mut payload: Multipart,
+    field_name: &str,
+    max_bytes: usize,
+) -> Result<Vec<u8>> {
+    while let Some(field) = payload.next().await {
+        let mut field = field.map_err(|err| ApiError::BadRequest(anyhow::anyhow!("{}", err)))?;
+        if field.name() != Some(field_name) {
+            continue;
+        }
+
+        let mut upload = Vec::new();
+        while let Some(chunk) = field.next().await {
+            let chunk = chunk.map_err(|err| ApiError::BadRequest(anyhow::anyhow!("{}", err)))?;
+            if upload.len() + chunk.len() > max_bytes {
+                return Err(ApiError::BadRequest(anyhow::anyhow!(
+                    "Upload is too large, the limit is {} bytes",
+                    max_bytes
+                )));
+            }
+            upload.extend_from_slice(&chunk);
+        }
+        return Ok(upload);
+    }
+
+    Err(ApiError::BadRequest(anyhow::anyhow!(
+        "Missing `{}` field",
+        field_name
+    )))
+}
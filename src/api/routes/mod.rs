@@ -1,9 +1,18 @@
+mod admin;
 mod comments;
+#[allow(hidden_glob_reexports)]
 mod health_check;
+mod media;
+mod places;
 mod posts;
+mod tags;
 mod users;
 
+pub use admin::*;
 pub use comments::*;
 pub use health_check::*;
+pub use media::*;
+pub use places::*;
 pub use posts::*;
+pub use tags::*;
 pub use users::*;
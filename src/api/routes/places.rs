@@ -0,0 +1,112 @@
+use crate::api::{
+    controller,
+    models::{
+        error::{ApiError, Result},
+        token::JwtPayload,
+        Authenticated, CreatePlace, Pagination, PostsWrite,
+    },
+};
+use actix_web::{
+    get, post,
+    web::{self, Data, Json, Path, Query},
+    HttpResponse,
+};
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+use validator::Validate;
+
+use super::users::{validate_input, viewer_id};
+
+pub fn init_place_routes(cfg: &mut web::ServiceConfig) {
+    // The search is registered first so that `search` isn't taken for a place id
+    cfg.service(search_places)
+        .service(create_place)
+        .service(get_place)
+        .service(get_place_posts);
+}
+
+#[post("/places")]
+#[tracing::instrument(name = "Create a place", skip(place, _token, conn))]
+async fn create_place(
+    place: Json<CreatePlace>,
+    _token: Authenticated<PostsWrite>,
+    conn: Data<PgPool>,
+) -> Result<HttpResponse> {
+    validate_input(&place.0)?;
+
+    let place = controller::places::create_place(place.into_inner(), &conn).await?;
+
+    Ok(HttpResponse::Created().json(place))
+}
+
+#[get("/places/search")]
+#[tracing::instrument(name = "Search places", skip(token, conn))]
+async fn search_places(
+    token: Option<JwtPayload>,
+    query: Query<SearchQuery>,
+    conn: Data<PgPool>,
+) -> Result<HttpResponse> {
+    validate_input(&query.0)?;
+
+    let viewer_id = viewer_id(token.as_ref())?;
+
+    let places =
+        controller::places::search_places(&query.query, query.limit, viewer_id, &conn).await?;
+
+    Ok(HttpResponse::Ok().json(places))
+}
+
+#[get("/places/{place_id}")]
+#[tracing::instrument(name = "Get a place", skip(token, conn))]
+async fn get_place(
+    token: Option<JwtPayload>,
+    path: Path<(String,)>,
+    conn: Data<PgPool>,
+) -> Result<HttpResponse> {
+    let (place_id,) = path.into_inner();
+    let place_id = Uuid::parse_str(&place_id)
+        .context("Failed to convert place id to UUID")
+        .map_err(ApiError::BadRequest)?;
+    let viewer_id = viewer_id(token.as_ref())?;
+
+    let place = controller::places::get_place(&place_id, viewer_id, &conn).await?;
+
+    Ok(HttpResponse::Ok().json(place))
+}
+
+#[get("/places/{place_id}/posts")]
+#[tracing::instrument(name = "Get a place's posts", skip(token, conn))]
+async fn get_place_posts(
+    token: Option<JwtPayload>,
+    path: Path<(String,)>,
+    pagination: Query<Pagination>,
+    conn: Data<PgPool>,
+) -> Result<HttpResponse> {
+    let (place_id,) = path.into_inner();
+    let place_id = Uuid::parse_str(&place_id)
+        .context("Failed to convert place id to UUID")
+        .map_err(ApiError::BadRequest)?;
+    validate_input(&pagination.0)?;
+    let viewer_id = viewer_id(token.as_ref())?;
+
+    let page =
+        controller::places::get_place_posts(&place_id, viewer_id, pagination.into_inner(), &conn)
+            .await?;
+
+    Ok(HttpResponse::Ok().json(page))
+}
+
+#[derive(serde::Deserialize, Validate, Debug)]
+struct SearchQuery {
+    /// The start of a place's name, or any part of it
+    #[validate(length(max = 100))]
+    query: String,
+    #[serde(default = "default_search_limit")]
+    #[validate(range(min = 1, max = 50))]
+    limit: u32,
+}
+
+fn default_search_limit() -> u32 {
+    10
+}
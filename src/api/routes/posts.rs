@@ -1,14 +1,17 @@
 use crate::api::{
+    blob_store::BlobStore,
+    configuration::{AuthSettings, MediaSettings},
     controller,
     models::{
         error::{ApiError, Result},
         token::JwtPayload,
-        CreatePost,
+        Authenticated, BoundingBox, CreatePost, FeedRead, Pagination, PostsWrite, UpdatePost,
     },
 };
+use actix_multipart::Multipart;
 use actix_web::{
-    delete, get, post,
-    web::{self, Data, Json, Path},
+    delete, get, patch, post,
+    web::{self, Data, Json, Path, Query},
     HttpResponse,
 };
 use anyhow::{anyhow, Context};
@@ -16,42 +19,190 @@ use serde_json::json;
 use sqlx::PgPool;
 use std::str::FromStr;
 use uuid::Uuid;
+use validator::Validate;
+
+use super::{
+    media::read_upload,
+    users::{validate_input, viewer_id},
+};
 
 pub fn init_post_routes(cfg: &mut web::ServiceConfig) {
     cfg.service(get_users_post)
         .service(create_post)
+        .service(upload_post_media)
+        .service(get_post)
+        .service(update_post)
+        .service(delete_post)
+        .service(get_post_history)
+        .service(remove_post_mention)
         .service(get_users_feed)
+        .service(get_nearby_posts)
+        .service(get_posts_in_bounds)
         .service(like_a_post)
         .service(unlike_a_post)
         .service(get_likes_of_post);
 }
 
 #[get("/users/{user_id}/posts")]
-#[tracing::instrument(name = "Get A Users Post", skip(conn))]
-async fn get_users_post(path: Path<(String,)>, conn: Data<PgPool>) -> Result<HttpResponse> {
+#[tracing::instrument(name = "Get A Users Post", skip(token, conn))]
+async fn get_users_post(
+    token: Option<JwtPayload>,
+    path: Path<(String,)>,
+    conn: Data<PgPool>,
+) -> Result<HttpResponse> {
     let (user_id,) = path.into_inner();
-    let posts = controller::posts::get_users_post(&conn, user_id).await?;
+    let viewer_id = viewer_id(token.as_ref())?;
+    let posts = controller::posts::get_users_post(&conn, user_id, viewer_id).await?;
     Ok(HttpResponse::Ok().json(posts))
 }
 
 #[post("/users/post")]
-#[tracing::instrument(name = "Create a New Post", skip(new_post, jwt, conn))]
+#[tracing::instrument(name = "Create a New Post", skip(new_post, jwt, settings, conn))]
 async fn create_post(
     new_post: Json<CreatePost>,
-    jwt: JwtPayload,
+    jwt: Authenticated<PostsWrite>,
+    settings: Data<AuthSettings>,
     conn: Data<PgPool>,
 ) -> Result<HttpResponse> {
+    validate_input(&new_post.0)?;
     let user_id =
         uuid::Uuid::from_str(&jwt.user_id).map_err(|e| ApiError::InternalServer(anyhow!(e)))?;
 
-    let post_id = controller::posts::create_post(&conn, user_id, new_post.0).await?;
+    let post_id =
+        controller::posts::create_post(&conn, user_id, new_post.0, &settings.unverified_accounts)
+            .await?;
 
     Ok(HttpResponse::Created().json(json!({ "post_id": post_id })))
 }
 
+/// Uploads a photo, whose id can then be used to attach it to a new post
+#[post("/post/media")]
+#[tracing::instrument(
+    name = "Upload post media",
+    skip(payload, jwt, settings, blob_store, conn)
+)]
+async fn upload_post_media(
+    payload: Multipart,
+    jwt: Authenticated<PostsWrite>,
+    settings: Data<MediaSettings>,
+    blob_store: Data<dyn BlobStore>,
+    conn: Data<PgPool>,
+) -> Result<HttpResponse> {
+    let user_id =
+        uuid::Uuid::from_str(&jwt.user_id).map_err(|e| ApiError::InternalServer(anyhow!(e)))?;
+    let upload = read_upload(payload, "image", settings.max_upload_bytes).await?;
+
+    let media =
+        controller::media::upload_post_media(&user_id, upload, blob_store.get_ref(), &conn).await?;
+
+    Ok(HttpResponse::Created().json(media))
+}
+
+#[get("/post/{post_id}")]
+#[tracing::instrument(name = "Get a post", skip(token, conn))]
+async fn get_post(
+    token: Option<JwtPayload>,
+    path: Path<(String,)>,
+    conn: Data<PgPool>,
+) -> Result<HttpResponse> {
+    let (post_id,) = path.into_inner();
+    let post_id = Uuid::from_str(&post_id)
+        .context("Failed to convert UUID")
+        .map_err(ApiError::BadRequest)?;
+    let viewer_id = viewer_id(token.as_ref())?;
+
+    let post = controller::posts::get_post(&post_id, viewer_id, &conn).await?;
+
+    Ok(HttpResponse::Ok().json(post))
+}
+
+#[patch("/post/{post_id}")]
+#[tracing::instrument(name = "Edit a post", skip(update, token, conn))]
+async fn update_post(
+    token: Authenticated<PostsWrite>,
+    path: Path<(String,)>,
+    update: Json<UpdatePost>,
+    conn: Data<PgPool>,
+) -> Result<HttpResponse> {
+    validate_input(&update.0)?;
+    let (post_id,) = path.into_inner();
+    let user_id = Uuid::from_str(&token.user_id)
+        .context("Failed to convert UUID")
+        .map_err(ApiError::InternalServer)?;
+    let post_id = Uuid::from_str(&post_id)
+        .context("Failed to convert UUID")
+        .map_err(ApiError::BadRequest)?;
+
+    let post = controller::posts::update_post(&user_id, &post_id, update.0, &conn).await?;
+
+    Ok(HttpResponse::Ok().json(post))
+}
+
+#[delete("/post/{post_id}")]
+#[tracing::instrument(name = "Delete a post", skip(token, blob_store, conn))]
+async fn delete_post(
+    token: Authenticated<PostsWrite>,
+    path: Path<(String,)>,
+    blob_store: Data<dyn BlobStore>,
+    conn: Data<PgPool>,
+) -> Result<HttpResponse> {
+    let (post_id,) = path.into_inner();
+    let user_id = Uuid::from_str(&token.user_id)
+        .context("Failed to convert UUID")
+        .map_err(ApiError::InternalServer)?;
+    let post_id = Uuid::from_str(&post_id)
+        .context("Failed to convert UUID")
+        .map_err(ApiError::BadRequest)?;
+
+    controller::posts::delete_post(&user_id, &post_id, blob_store.get_ref(), &conn).await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[delete("/post/{post_id}/mentions/me")]
+#[tracing::instrument(name = "Remove yourself from a post's mentions", skip(token, conn))]
+async fn remove_post_mention(
+    token: Authenticated<PostsWrite>,
+    path: Path<(String,)>,
+    conn: Data<PgPool>,
+) -> Result<HttpResponse> {
+    let (post_id,) = path.into_inner();
+    let user_id = Uuid::from_str(&token.user_id)
+        .context("Failed to convert UUID")
+        .map_err(ApiError::InternalServer)?;
+    let post_id = Uuid::from_str(&post_id)
+        .context("Failed to convert UUID")
+        .map_err(ApiError::BadRequest)?;
+
+    controller::mentions::remove_post_mention(&user_id, &post_id, &conn).await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[get("/post/{post_id}/history")]
+#[tracing::instrument(name = "Get a post's edit history", skip(token, conn))]
+async fn get_post_history(
+    token: Option<JwtPayload>,
+    path: Path<(String,)>,
+    conn: Data<PgPool>,
+) -> Result<HttpResponse> {
+    let (post_id,) = path.into_inner();
+    let post_id = Uuid::from_str(&post_id)
+        .context("Failed to convert UUID")
+        .map_err(ApiError::BadRequest)?;
+    let viewer_id = viewer_id(token.as_ref())?;
+
+    let edits = controller::posts::get_post_history(&post_id, viewer_id, &conn).await?;
+
+    Ok(HttpResponse::Ok().json(edits))
+}
+
 #[get("/feed")]
 #[tracing::instrument(name = "Get A Users Feed", skip(token, conn))]
-async fn get_users_feed(token: JwtPayload, conn: Data<PgPool>) -> Result<HttpResponse> {
+async fn get_users_feed(
+    token: Authenticated<FeedRead>,
+    conn: Data<PgPool>,
+) -> Result<HttpResponse> {
     // TODO: Implement
     let user_id = Uuid::from_str(&token.user_id)
         .context("Failed to convert UUID")
@@ -64,23 +215,79 @@ async fn get_users_feed(token: JwtPayload, conn: Data<PgPool>) -> Result<HttpRes
 }
 
 #[get("/post/{post_id}/like")]
-#[tracing::instrument(name = "Get Likes for a post", skip(path, conn))]
-async fn get_likes_of_post(path: Path<(String,)>, conn: Data<PgPool>) -> Result<HttpResponse> {
+#[tracing::instrument(name = "Get Likes for a post", skip(token, path, conn))]
+async fn get_likes_of_post(
+    token: Option<JwtPayload>,
+    path: Path<(String,)>,
+    conn: Data<PgPool>,
+) -> Result<HttpResponse> {
     let (post_id,) = path.into_inner();
 
     let post_id = Uuid::from_str(&post_id)
         .context("Failed to convert UUID")
         .map_err(ApiError::BadRequest)?;
+    let viewer_id = viewer_id(token.as_ref())?;
 
-    let likes = controller::posts::get_likes_of_post(&post_id, &conn).await?;
+    let likes = controller::posts::get_likes_of_post(&post_id, viewer_id, &conn).await?;
 
     Ok(HttpResponse::Ok().json(json!(likes)))
 }
 
+#[get("/posts/nearby")]
+#[tracing::instrument(name = "Get nearby posts", skip(token, conn))]
+async fn get_nearby_posts(
+    token: Option<JwtPayload>,
+    query: Query<NearbyQuery>,
+    pagination: Query<Pagination>,
+    conn: Data<PgPool>,
+) -> Result<HttpResponse> {
+    validate_input(&query.0)?;
+    validate_input(&pagination.0)?;
+    let viewer_id = viewer_id(token.as_ref())?;
+
+    let page = controller::posts::get_nearby_posts(
+        query.lat,
+        query.lon,
+        query.radius_km,
+        viewer_id,
+        pagination.into_inner(),
+        &conn,
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().json(page))
+}
+
+/// Posts to show on a map, the box may cross the antimeridian with `min_lon` > `max_lon`
+#[get("/posts/within")]
+#[tracing::instrument(name = "Get posts within bounds", skip(token, conn))]
+async fn get_posts_in_bounds(
+    token: Option<JwtPayload>,
+    query: Query<BoundsQuery>,
+    pagination: Query<Pagination>,
+    conn: Data<PgPool>,
+) -> Result<HttpResponse> {
+    validate_input(&query.0)?;
+    validate_input(&pagination.0)?;
+    let viewer_id = viewer_id(token.as_ref())?;
+    let bounds = BoundingBox {
+        min_latitude: query.min_lat,
+        max_latitude: query.max_lat,
+        min_longitude: query.min_lon,
+        max_longitude: query.max_lon,
+    };
+
+    let page =
+        controller::posts::get_posts_in_bounds(bounds, viewer_id, pagination.into_inner(), &conn)
+            .await?;
+
+    Ok(HttpResponse::Ok().json(page))
+}
+
 #[post("/post/{post_id}/like")]
 #[tracing::instrument(name = "Like a Post", skip(path, token, conn))]
 async fn like_a_post(
-    token: JwtPayload,
+    token: Authenticated<PostsWrite>,
     path: Path<(String,)>,
     conn: Data<PgPool>,
 ) -> Result<HttpResponse> {
@@ -100,7 +307,7 @@ async fn like_a_post(
 #[delete("/post/{post_id}/like")]
 #[tracing::instrument(name = "Unlike a Post", skip(path, token, conn))]
 async fn unlike_a_post(
-    token: JwtPayload,
+    token: Authenticated<PostsWrite>,
     path: Path<(String,)>,
     conn: Data<PgPool>,
 ) -> Result<HttpResponse> {
@@ -116,3 +323,30 @@ async fn unlike_a_post(
 
     Ok(HttpResponse::NoContent().finish())
 }
+
+#[derive(serde::Deserialize, Validate, Debug)]
+struct NearbyQuery {
+    #[validate(range(min = -90.0, max = 90.0))]
+    lat: f64,
+    #[validate(range(min = -180.0, max = 180.0))]
+    lon: f64,
+    #[serde(default = "default_radius_km")]
+    #[validate(range(min = 0.1, max = 500.0))]
+    radius_km: f64,
+}
+
+fn default_radius_km() -> f64 {
+    10.0
+}
+
+#[derive(serde::Deserialize, Validate, Debug)]
+struct BoundsQuery {
+    #[validate(range(min = -90.0, max = 90.0))]
+    min_lat: f64,
+    #[validate(range(min = -90.0, max = 90.0))]
+    max_lat: f64,
+    #[validate(range(min = -180.0, max = 180.0))]
+    min_lon: f64,
+    #[validate(range(min = -180.0, max = 180.0))]
+    max_lon: f64,
+}
@@ -0,0 +1,126 @@
+use crate::api::{
+    controller,
+    models::{
+        error::{ApiError, Result},
+        token::JwtPayload,
+        Authenticated, FeedRead, FollowsWrite, Pagination,
+    },
+};
+use actix_web::{
+    delete, get, post,
+    web::{self, Data, Path, Query},
+    HttpResponse,
+};
+use anyhow::{anyhow, Context};
+use sqlx::PgPool;
+use std::str::FromStr;
+use uuid::Uuid;
+use validator::Validate;
+
+use super::users::{validate_input, viewer_id};
+
+pub fn init_tag_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_trending_tags)
+        .service(get_tag_posts)
+        .service(follow_tag)
+        .service(unfollow_tag)
+        .service(get_followed_tags);
+}
+
+#[get("/tags/trending")]
+#[tracing::instrument(name = "Get trending tags", skip(conn))]
+async fn get_trending_tags(
+    query: Query<TrendingQuery>,
+    conn: Data<PgPool>,
+) -> Result<HttpResponse> {
+    validate_input(&query.0)?;
+
+    let tags = controller::tags::get_trending_tags(query.window_hours, query.limit, &conn).await?;
+
+    Ok(HttpResponse::Ok().json(tags))
+}
+
+#[get("/tags/{tag}/posts")]
+#[tracing::instrument(name = "Get a tag's posts", skip(token, conn))]
+async fn get_tag_posts(
+    token: Option<JwtPayload>,
+    path: Path<(String,)>,
+    pagination: Query<Pagination>,
+    conn: Data<PgPool>,
+) -> Result<HttpResponse> {
+    let (tag,) = path.into_inner();
+    validate_input(&pagination.0)?;
+    let viewer_id = viewer_id(token.as_ref())?;
+
+    let page =
+        controller::tags::get_tag_posts(&tag, viewer_id, pagination.into_inner(), &conn).await?;
+
+    Ok(HttpResponse::Ok().json(page))
+}
+
+#[post("/tags/{tag}/follow")]
+#[tracing::instrument(name = "Follow a tag", skip(conn))]
+async fn follow_tag(
+    token: Authenticated<FollowsWrite>,
+    path: Path<(String,)>,
+    conn: Data<PgPool>,
+) -> Result<HttpResponse> {
+    let (tag,) = path.into_inner();
+    let user_id = Uuid::from_str(&token.user_id)
+        .context("Failed to convert UUID")
+        .map_err(ApiError::InternalServer)?;
+
+    controller::tags::follow_tag(&user_id, &tag, &conn).await?;
+
+    Ok(HttpResponse::Created().finish())
+}
+
+#[delete("/tags/{tag}/follow")]
+#[tracing::instrument(name = "Unfollow a tag", skip(conn))]
+async fn unfollow_tag(
+    token: Authenticated<FollowsWrite>,
+    path: Path<(String,)>,
+    conn: Data<PgPool>,
+) -> Result<HttpResponse> {
+    let (tag,) = path.into_inner();
+    let user_id = Uuid::from_str(&token.user_id)
+        .context("Failed to convert UUID")
+        .map_err(ApiError::InternalServer)?;
+
+    controller::tags::unfollow_tag(&user_id, &tag, &conn).await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[get("/users/me/tags")]
+#[tracing::instrument(name = "Get a user's followed tags", skip(conn))]
+async fn get_followed_tags(
+    token: Authenticated<FeedRead>,
+    conn: Data<PgPool>,
+) -> Result<HttpResponse> {
+    let user_id =
+        Uuid::from_str(&token.user_id).map_err(|e| ApiError::InternalServer(anyhow!(e)))?;
+
+    let tags = controller::tags::get_followed_tags(&user_id, &conn).await?;
+
+    Ok(HttpResponse::Ok().json(tags))
+}
+
+#[derive(serde::Deserialize, Validate, Debug)]
+struct TrendingQuery {
+    /// How far back to count posts
+    #[serde(default = "default_window_hours")]
+    #[validate(range(min = 1, max = 720))]
+    window_hours: u32,
+    #[serde(default = "default_trending_limit")]
+    #[validate(range(min = 1, max = 50))]
+    limit: u32,
+}
+
+fn default_window_hours() -> u32 {
+    24
+}
+
+fn default_trending_limit() -> u32 {
+    10
+}
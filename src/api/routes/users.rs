@@ -1,24 +1,75 @@
+use actix_multipart::Multipart;
 use actix_web::{
-    delete, get, post,
-    web::{self, Data, Json, Path, Query},
-    HttpResponse,
+    delete, get,
+    http::header::{ContentDisposition, DispositionParam, DispositionType, LOCATION},
+    patch, post, put,
+    web::{self, Data, Form, Json, Path, Query},
+    Either, HttpResponse,
 };
 use sqlx::PgPool;
 use uuid::Uuid;
 use validator::Validate;
 
 use crate::api::{
+    blob_store::BlobStore,
+    configuration::{AuthSettings, MediaSettings},
     controller,
+    email_client::EmailClient,
     models::{
         error::{ApiError, Result},
-        token::JwtPayload,
-        CreateUser, LoginInfo,
+        token::{JwtKeys, JwtPayload, RefreshTokenRequest},
+        Authenticated, ChangePassword, ChangeUsername, ClientInfo, ConfirmEmail, CreateApiKey,
+        CreateUser, DeleteAccount, ExportLookup, ExportStatus, FollowOutcome, FollowsWrite,
+        ForgotPassword, ImageKind, LoginInfo, MfaLogin, OidcCallback, PasswordResetLink,
+        ResetPassword, TotpCode, UpdateUser, UsernameLookup,
     },
+    oidc_client::OidcClient,
+    startup::ApplicationBaseUrl,
 };
 
+use super::media::read_upload;
+
 pub fn init_user_routes(cfg: &mut web::ServiceConfig) {
     cfg.service(create_user)
         .service(login)
+        .service(login_mfa)
+        .service(start_oidc_login)
+        .service(finish_oidc_login)
+        .service(refresh_token)
+        .service(logout)
+        .service(logout_all)
+        .service(confirm_email)
+        .service(resend_confirmation_email)
+        .service(forgot_password)
+        .service(password_reset_form)
+        .service(reset_password)
+        .service(get_me)
+        .service(update_profile)
+        .service(upload_avatar)
+        .service(remove_avatar)
+        .service(upload_cover)
+        .service(remove_cover)
+        .service(delete_account)
+        .service(change_password)
+        .service(change_username)
+        .service(get_user_by_username)
+        .service(get_sessions)
+        .service(revoke_session)
+        .service(enroll_two_factor)
+        .service(confirm_two_factor)
+        .service(disable_two_factor)
+        .service(create_api_key)
+        .service(get_api_keys)
+        .service(revoke_api_key)
+        .service(start_export)
+        .service(get_export)
+        .service(get_follow_requests)
+        .service(approve_follow_request)
+        .service(reject_follow_request)
+        .service(block_user)
+        .service(unblock_user)
+        .service(mute_user)
+        .service(unmute_user)
         .service(follow_user)
         .service(unfollow_user)
         .service(get_followers)
@@ -28,34 +79,627 @@ pub fn init_user_routes(cfg: &mut web::ServiceConfig) {
 }
 
 #[post("/users")]
-#[tracing::instrument(name = "Create a new user", skip(new_user, conn))]
-async fn create_user(new_user: Json<CreateUser>, conn: Data<PgPool>) -> Result<HttpResponse> {
+#[tracing::instrument(
+    name = "Create a new user",
+    skip(new_user, keys, settings, base_url, email_client, conn)
+)]
+async fn create_user(
+    new_user: Json<CreateUser>,
+    client: ClientInfo,
+    keys: Data<JwtKeys>,
+    settings: Data<AuthSettings>,
+    base_url: Data<ApplicationBaseUrl>,
+    email_client: Data<dyn EmailClient>,
+    conn: Data<PgPool>,
+) -> Result<HttpResponse> {
     // Validate new user
-    new_user.0.validate().map_err(|err| {
-        // TODO: Return a more specific error
-        let errors = err
-            .errors()
-            .keys()
-            .map(|k| k.to_string())
-            .collect::<Vec<String>>()
-            .join(", ");
-        ApiError::BadRequest(anyhow::anyhow!("Invalid fields: {}", errors))
-    })?;
-    let auth_info = controller::user::register(new_user.0, &conn).await?;
+    validate_input(&new_user.0)?;
+    let auth_info = controller::user::register(
+        new_user.0,
+        &client,
+        &keys,
+        &settings,
+        &base_url.0,
+        email_client.get_ref(),
+        &conn,
+    )
+    .await?;
     Ok(HttpResponse::Created().json(auth_info))
 }
 
 #[post("/users/login")]
-#[tracing::instrument(name = "Logging a user in", skip(login_info, conn))]
-async fn login(login_info: Json<LoginInfo>, conn: Data<PgPool>) -> Result<HttpResponse> {
-    let auth_info = controller::user::login(login_info.0, &conn).await?;
+#[tracing::instrument(name = "Logging a user in", skip(login_info, keys, settings, conn))]
+async fn login(
+    login_info: Json<LoginInfo>,
+    client: ClientInfo,
+    keys: Data<JwtKeys>,
+    settings: Data<AuthSettings>,
+    conn: Data<PgPool>,
+) -> Result<HttpResponse> {
+    let login_response =
+        controller::user::login(login_info.0, &client, &keys, &settings, &conn).await?;
+    Ok(HttpResponse::Ok().json(login_response))
+}
+
+#[post("/users/login/mfa")]
+#[tracing::instrument(
+    name = "Completing a two-factor login",
+    skip(body, keys, settings, conn)
+)]
+async fn login_mfa(
+    body: Json<MfaLogin>,
+    client: ClientInfo,
+    keys: Data<JwtKeys>,
+    settings: Data<AuthSettings>,
+    conn: Data<PgPool>,
+) -> Result<HttpResponse> {
+    let auth_info =
+        controller::user::complete_mfa_login(body.0, &client, &keys, &settings, &conn).await?;
     Ok(HttpResponse::Ok().json(auth_info))
 }
 
+#[post("/users/token/refresh")]
+#[tracing::instrument(name = "Refreshing a user's token", skip(body, keys, conn))]
+async fn refresh_token(
+    body: Json<RefreshTokenRequest>,
+    keys: Data<JwtKeys>,
+    conn: Data<PgPool>,
+) -> Result<HttpResponse> {
+    let tokens = controller::sessions::refresh(&body.refresh_token, &keys, &conn).await?;
+    Ok(HttpResponse::Ok().json(tokens))
+}
+
+#[post("/users/logout")]
+#[tracing::instrument(name = "Logging a user out", skip(token, conn))]
+async fn logout(token: JwtPayload, conn: Data<PgPool>) -> Result<HttpResponse> {
+    let session_id =
+        Uuid::parse_str(&token.session_id).map_err(|e| ApiError::BadRequest(anyhow::anyhow!(e)))?;
+
+    controller::sessions::logout(&session_id, &conn).await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[post("/users/logout-all")]
+#[tracing::instrument(name = "Logging a user out of every session", skip(token, conn))]
+async fn logout_all(token: JwtPayload, conn: Data<PgPool>) -> Result<HttpResponse> {
+    let user_id =
+        Uuid::parse_str(&token.user_id).map_err(|e| ApiError::BadRequest(anyhow::anyhow!(e)))?;
+
+    controller::sessions::logout_all(&user_id, &conn).await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[get("/users/oidc/{provider}/authorize")]
+#[tracing::instrument(name = "Starting an OIDC login", skip(oidc_client, conn))]
+async fn start_oidc_login(
+    provider: Path<(String,)>,
+    oidc_client: Data<OidcClient>,
+    conn: Data<PgPool>,
+) -> Result<HttpResponse> {
+    let (provider,) = provider.into_inner();
+    let authorization_url = controller::oidc::start_login(&provider, &oidc_client, &conn).await?;
+    Ok(HttpResponse::Found()
+        .insert_header((LOCATION, authorization_url))
+        .finish())
+}
+
+#[get("/users/oidc/{provider}/callback")]
+#[tracing::instrument(
+    name = "Finishing an OIDC login",
+    skip(query, keys, settings, oidc_client, conn)
+)]
+async fn finish_oidc_login(
+    provider: Path<(String,)>,
+    query: Query<OidcCallback>,
+    client: ClientInfo,
+    keys: Data<JwtKeys>,
+    settings: Data<AuthSettings>,
+    oidc_client: Data<OidcClient>,
+    conn: Data<PgPool>,
+) -> Result<HttpResponse> {
+    let (provider,) = provider.into_inner();
+    let login_response = controller::oidc::finish_login(
+        &provider,
+        query.0,
+        &client,
+        &keys,
+        &settings,
+        &oidc_client,
+        &conn,
+    )
+    .await?;
+    Ok(HttpResponse::Ok().json(login_response))
+}
+
+#[get("/users/confirm")]
+#[tracing::instrument(name = "Confirming an email address", skip(query, conn))]
+async fn confirm_email(query: Query<ConfirmEmail>, conn: Data<PgPool>) -> Result<HttpResponse> {
+    controller::user::confirm_email(query.0, &conn).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+#[post("/users/confirm/resend")]
+#[tracing::instrument(
+    name = "Resending the email confirmation",
+    skip(token, settings, base_url, email_client, conn)
+)]
+async fn resend_confirmation_email(
+    token: JwtPayload,
+    settings: Data<AuthSettings>,
+    base_url: Data<ApplicationBaseUrl>,
+    email_client: Data<dyn EmailClient>,
+    conn: Data<PgPool>,
+) -> Result<HttpResponse> {
+    let user_id =
+        Uuid::parse_str(&token.user_id).map_err(|e| ApiError::BadRequest(anyhow::anyhow!(e)))?;
+
+    controller::user::resend_verification_email(
+        &user_id,
+        chrono::Duration::hours(settings.email_verification_expiration_hours),
+        &base_url.0,
+        email_client.get_ref(),
+        &conn,
+    )
+    .await?;
+    Ok(HttpResponse::Accepted().finish())
+}
+
+#[post("/users/password/forgot")]
+#[tracing::instrument(
+    name = "Requesting a password reset",
+    skip(body, settings, base_url, email_client, conn)
+)]
+async fn forgot_password(
+    body: Json<ForgotPassword>,
+    settings: Data<AuthSettings>,
+    base_url: Data<ApplicationBaseUrl>,
+    email_client: Data<dyn EmailClient>,
+    conn: Data<PgPool>,
+) -> Result<HttpResponse> {
+    controller::user::forgot_password(
+        body.0,
+        chrono::Duration::minutes(settings.password_reset_expiration_minutes),
+        &base_url.0,
+        email_client.get_ref(),
+        &conn,
+    )
+    .await?;
+    Ok(HttpResponse::Accepted().finish())
+}
+
+/// The page the reset email links to, a form that posts the new password with the token
+#[get("/users/password/reset")]
+#[tracing::instrument(name = "Showing the password reset form", skip(query))]
+async fn password_reset_form(query: Query<PasswordResetLink>) -> Result<HttpResponse> {
+    // Tokens are alphanumeric, which also keeps the page safe to build by hand
+    if query.token.is_empty() || !query.token.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(ApiError::BadRequest(anyhow::anyhow!(
+            "Invalid password reset token"
+        )));
+    }
+
+    let page = format!(
+        r#"<!DOCTYPE html>
+<html>
+<head><meta charset="utf-8"><title>Reset your Voyage Atlas password</title></head>
+<body>
+<form method="post" action="/users/password/reset">
+<input type="hidden" name="token" value="{}">
+<label>New password <input type="password" name="password" minlength="8" required></label>
+<button type="submit">Reset password</button>
+</form>
+</body>
+</html>"#,
+        query.token
+    );
+    Ok(HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(page))
+}
+
+/// Takes JSON from API clients and form data from the reset page
+#[post("/users/password/reset")]
+#[tracing::instrument(name = "Resetting a password", skip(body, settings, conn))]
+async fn reset_password(
+    body: Either<Json<ResetPassword>, Form<ResetPassword>>,
+    settings: Data<AuthSettings>,
+    conn: Data<PgPool>,
+) -> Result<HttpResponse> {
+    let body = body.into_inner();
+    validate_input(&body)?;
+    controller::user::reset_password(body, &settings.password_hashing, &conn).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[get("/users/me")]
+#[tracing::instrument(name = "Get the signed in user", skip(token, conn))]
+async fn get_me(token: JwtPayload, conn: Data<PgPool>) -> Result<HttpResponse> {
+    let user_id =
+        Uuid::parse_str(&token.user_id).map_err(|e| ApiError::BadRequest(anyhow::anyhow!(e)))?;
+
+    let user = controller::user::get_me(user_id, &conn).await?;
+
+    Ok(HttpResponse::Ok().json(user))
+}
+
+#[put("/users/me/avatar")]
+#[tracing::instrument(
+    name = "Uploading a user's avatar",
+    skip(token, payload, settings, blob_store, conn)
+)]
+async fn upload_avatar(
+    token: JwtPayload,
+    payload: Multipart,
+    settings: Data<MediaSettings>,
+    blob_store: Data<dyn BlobStore>,
+    conn: Data<PgPool>,
+) -> Result<HttpResponse> {
+    let user_id =
+        Uuid::parse_str(&token.user_id).map_err(|e| ApiError::BadRequest(anyhow::anyhow!(e)))?;
+    let upload = read_upload(payload, "image", settings.max_upload_bytes).await?;
+
+    let user = controller::media::upload_profile_image(
+        &user_id,
+        ImageKind::Avatar,
+        upload,
+        blob_store.get_ref(),
+        &conn,
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().json(user))
+}
+
+#[delete("/users/me/avatar")]
+#[tracing::instrument(name = "Removing a user's avatar", skip(token, blob_store, conn))]
+async fn remove_avatar(
+    token: JwtPayload,
+    blob_store: Data<dyn BlobStore>,
+    conn: Data<PgPool>,
+) -> Result<HttpResponse> {
+    let user_id =
+        Uuid::parse_str(&token.user_id).map_err(|e| ApiError::BadRequest(anyhow::anyhow!(e)))?;
+
+    let user = controller::media::remove_profile_image(
+        &user_id,
+        ImageKind::Avatar,
+        blob_store.get_ref(),
+        &conn,
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().json(user))
+}
+
+#[put("/users/me/cover")]
+#[tracing::instrument(
+    name = "Uploading a user's cover",
+    skip(token, payload, settings, blob_store, conn)
+)]
+async fn upload_cover(
+    token: JwtPayload,
+    payload: Multipart,
+    settings: Data<MediaSettings>,
+    blob_store: Data<dyn BlobStore>,
+    conn: Data<PgPool>,
+) -> Result<HttpResponse> {
+    let user_id =
+        Uuid::parse_str(&token.user_id).map_err(|e| ApiError::BadRequest(anyhow::anyhow!(e)))?;
+    let upload = read_upload(payload, "image", settings.max_upload_bytes).await?;
+
+    let user = controller::media::upload_profile_image(
+        &user_id,
+        ImageKind::Cover,
+        upload,
+        blob_store.get_ref(),
+        &conn,
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().json(user))
+}
+
+#[delete("/users/me/cover")]
+#[tracing::instrument(name = "Removing a user's cover", skip(token, blob_store, conn))]
+async fn remove_cover(
+    token: JwtPayload,
+    blob_store: Data<dyn BlobStore>,
+    conn: Data<PgPool>,
+) -> Result<HttpResponse> {
+    let user_id =
+        Uuid::parse_str(&token.user_id).map_err(|e| ApiError::BadRequest(anyhow::anyhow!(e)))?;
+
+    let user = controller::media::remove_profile_image(
+        &user_id,
+        ImageKind::Cover,
+        blob_store.get_ref(),
+        &conn,
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().json(user))
+}
+
+#[patch("/users/me")]
+#[tracing::instrument(
+    name = "Updating a user's profile",
+    skip(token, update, settings, base_url, email_client, conn)
+)]
+async fn update_profile(
+    token: JwtPayload,
+    update: Json<UpdateUser>,
+    settings: Data<AuthSettings>,
+    base_url: Data<ApplicationBaseUrl>,
+    email_client: Data<dyn EmailClient>,
+    conn: Data<PgPool>,
+) -> Result<HttpResponse> {
+    validate_input(&update.0)?;
+    let user_id =
+        Uuid::parse_str(&token.user_id).map_err(|e| ApiError::BadRequest(anyhow::anyhow!(e)))?;
+
+    let user = controller::user::update_profile(
+        &user_id,
+        update.0,
+        chrono::Duration::hours(settings.email_verification_expiration_hours),
+        &base_url.0,
+        email_client.get_ref(),
+        &conn,
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().json(user))
+}
+
+#[delete("/users/me")]
+#[tracing::instrument(name = "Deleting a user's account", skip(token, body, settings, conn))]
+async fn delete_account(
+    token: JwtPayload,
+    body: Json<DeleteAccount>,
+    client: ClientInfo,
+    settings: Data<AuthSettings>,
+    conn: Data<PgPool>,
+) -> Result<HttpResponse> {
+    let user_id =
+        Uuid::parse_str(&token.user_id).map_err(|e| ApiError::BadRequest(anyhow::anyhow!(e)))?;
+
+    controller::user::deactivate_account(&user_id, body.0, &client, &settings, &conn).await?;
+
+    Ok(HttpResponse::Accepted().finish())
+}
+
+#[post("/users/me/password")]
+#[tracing::instrument(name = "Changing a user's password", skip(token, body, settings, conn))]
+async fn change_password(
+    token: JwtPayload,
+    body: Json<ChangePassword>,
+    client: ClientInfo,
+    settings: Data<AuthSettings>,
+    conn: Data<PgPool>,
+) -> Result<HttpResponse> {
+    validate_input(&body.0)?;
+    let user_id =
+        Uuid::parse_str(&token.user_id).map_err(|e| ApiError::BadRequest(anyhow::anyhow!(e)))?;
+    let session_id =
+        Uuid::parse_str(&token.session_id).map_err(|e| ApiError::BadRequest(anyhow::anyhow!(e)))?;
+
+    controller::user::change_password(&user_id, &session_id, body.0, &client, &settings, &conn)
+        .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[post("/users/me/username")]
+#[tracing::instrument(name = "Changing a user's username", skip(token, body, settings, conn))]
+async fn change_username(
+    token: JwtPayload,
+    body: Json<ChangeUsername>,
+    settings: Data<AuthSettings>,
+    conn: Data<PgPool>,
+) -> Result<HttpResponse> {
+    validate_input(&body.0)?;
+    let user_id =
+        Uuid::parse_str(&token.user_id).map_err(|e| ApiError::BadRequest(anyhow::anyhow!(e)))?;
+
+    let user = controller::user::change_username(
+        &user_id,
+        body.0,
+        chrono::Duration::days(settings.username_reservation_days),
+        &conn,
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().json(user))
+}
+
+#[get("/users/by-username/{username}")]
+#[tracing::instrument(name = "Get a user by username", skip(conn))]
+async fn get_user_by_username(
+    username: Path<(String,)>,
+    conn: Data<PgPool>,
+) -> Result<HttpResponse> {
+    let (username,) = username.into_inner();
+
+    match controller::user::get_user_by_username(&username, &conn).await? {
+        UsernameLookup::Current(user) => Ok(HttpResponse::Ok().json(user)),
+        // Old profile links keep working after a rename
+        UsernameLookup::Renamed { username } => Ok(HttpResponse::MovedPermanently()
+            .insert_header((LOCATION, format!("/users/by-username/{}", username)))
+            .finish()),
+    }
+}
+
+#[get("/users/me/sessions")]
+#[tracing::instrument(name = "Get a user's sessions", skip(token, conn))]
+async fn get_sessions(token: JwtPayload, conn: Data<PgPool>) -> Result<HttpResponse> {
+    let user_id =
+        Uuid::parse_str(&token.user_id).map_err(|e| ApiError::BadRequest(anyhow::anyhow!(e)))?;
+    let session_id =
+        Uuid::parse_str(&token.session_id).map_err(|e| ApiError::BadRequest(anyhow::anyhow!(e)))?;
+
+    let sessions = controller::sessions::get_sessions(&user_id, &session_id, &conn).await?;
+
+    Ok(HttpResponse::Ok().json(sessions))
+}
+
+#[delete("/users/me/sessions/{session_id}")]
+#[tracing::instrument(name = "Revoke a user's session", skip(token, conn))]
+async fn revoke_session(
+    token: JwtPayload,
+    session_id: Path<(String,)>,
+    conn: Data<PgPool>,
+) -> Result<HttpResponse> {
+    let (session_id,) = session_id.into_inner();
+    let session_id =
+        Uuid::parse_str(&session_id).map_err(|e| ApiError::BadRequest(anyhow::anyhow!(e)))?;
+    let user_id =
+        Uuid::parse_str(&token.user_id).map_err(|e| ApiError::BadRequest(anyhow::anyhow!(e)))?;
+
+    controller::sessions::revoke_session(&user_id, &session_id, &conn).await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[post("/users/me/2fa")]
+#[tracing::instrument(name = "Starting two-factor enrolment", skip(token, settings, conn))]
+async fn enroll_two_factor(
+    token: JwtPayload,
+    settings: Data<AuthSettings>,
+    conn: Data<PgPool>,
+) -> Result<HttpResponse> {
+    let user_id =
+        Uuid::parse_str(&token.user_id).map_err(|e| ApiError::BadRequest(anyhow::anyhow!(e)))?;
+
+    let enrollment = controller::two_factor::enroll(&user_id, &settings.totp_issuer, &conn).await?;
+
+    Ok(HttpResponse::Ok().json(enrollment))
+}
+
+#[post("/users/me/2fa/confirm")]
+#[tracing::instrument(name = "Confirming two-factor enrolment", skip(token, body, conn))]
+async fn confirm_two_factor(
+    token: JwtPayload,
+    body: Json<TotpCode>,
+    conn: Data<PgPool>,
+) -> Result<HttpResponse> {
+    let user_id =
+        Uuid::parse_str(&token.user_id).map_err(|e| ApiError::BadRequest(anyhow::anyhow!(e)))?;
+
+    let recovery_codes = controller::two_factor::confirm(&user_id, &body.code, &conn).await?;
+
+    Ok(HttpResponse::Ok().json(recovery_codes))
+}
+
+#[delete("/users/me/2fa")]
+#[tracing::instrument(name = "Disabling two-factor authentication", skip(token, body, conn))]
+async fn disable_two_factor(
+    token: JwtPayload,
+    body: Json<TotpCode>,
+    conn: Data<PgPool>,
+) -> Result<HttpResponse> {
+    let user_id =
+        Uuid::parse_str(&token.user_id).map_err(|e| ApiError::BadRequest(anyhow::anyhow!(e)))?;
+
+    controller::two_factor::disable(&user_id, &body.code, &conn).await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[post("/users/me/api-keys")]
+#[tracing::instrument(name = "Creating an API key", skip(token, new_key, conn))]
+async fn create_api_key(
+    token: JwtPayload,
+    new_key: Json<CreateApiKey>,
+    conn: Data<PgPool>,
+) -> Result<HttpResponse> {
+    validate_input(&new_key.0)?;
+    let user_id =
+        Uuid::parse_str(&token.user_id).map_err(|e| ApiError::BadRequest(anyhow::anyhow!(e)))?;
+
+    let api_key = controller::api_keys::create_api_key(&user_id, new_key.0, &conn).await?;
+
+    Ok(HttpResponse::Created().json(api_key))
+}
+
+#[get("/users/me/api-keys")]
+#[tracing::instrument(name = "Listing a user's API keys", skip(token, conn))]
+async fn get_api_keys(token: JwtPayload, conn: Data<PgPool>) -> Result<HttpResponse> {
+    let user_id =
+        Uuid::parse_str(&token.user_id).map_err(|e| ApiError::BadRequest(anyhow::anyhow!(e)))?;
+
+    let api_keys = controller::api_keys::get_api_keys(&user_id, &conn).await?;
+
+    Ok(HttpResponse::Ok().json(api_keys))
+}
+
+#[delete("/users/me/api-keys/{key_id}")]
+#[tracing::instrument(name = "Revoking an API key", skip(token, conn))]
+async fn revoke_api_key(
+    token: JwtPayload,
+    key_id: Path<(String,)>,
+    conn: Data<PgPool>,
+) -> Result<HttpResponse> {
+    let (key_id,) = key_id.into_inner();
+    let key_id = Uuid::parse_str(&key_id).map_err(|e| ApiError::BadRequest(anyhow::anyhow!(e)))?;
+    let user_id =
+        Uuid::parse_str(&token.user_id).map_err(|e| ApiError::BadRequest(anyhow::anyhow!(e)))?;
+
+    controller::api_keys::revoke_api_key(&user_id, &key_id, &conn).await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[post("/users/me/export")]
+#[tracing::instrument(name = "Starting a personal data export", skip(token, conn))]
+async fn start_export(token: JwtPayload, conn: Data<PgPool>) -> Result<HttpResponse> {
+    let user_id =
+        Uuid::parse_str(&token.user_id).map_err(|e| ApiError::BadRequest(anyhow::anyhow!(e)))?;
+
+    let export = controller::export::start_export(&user_id, &conn).await?;
+
+    // A recent export that is already ready is handed out again
+    if export.status == ExportStatus::Ready {
+        return Ok(HttpResponse::Ok().json(export));
+    }
+    Ok(HttpResponse::Accepted().json(export))
+}
+
+#[get("/users/me/export/{export_id}")]
+#[tracing::instrument(name = "Polling a personal data export", skip(token, conn))]
+async fn get_export(
+    token: JwtPayload,
+    export_id: Path<(String,)>,
+    conn: Data<PgPool>,
+) -> Result<HttpResponse> {
+    let (export_id,) = export_id.into_inner();
+    let export_id =
+        Uuid::parse_str(&export_id).map_err(|e| ApiError::BadRequest(anyhow::anyhow!(e)))?;
+    let user_id =
+        Uuid::parse_str(&token.user_id).map_err(|e| ApiError::BadRequest(anyhow::anyhow!(e)))?;
+
+    match controller::export::get_export(&user_id, &export_id, &conn).await? {
+        ExportLookup::Status(export) if export.status == ExportStatus::Pending => {
+            Ok(HttpResponse::Accepted().json(export))
+        }
+        ExportLookup::Status(export) => Ok(HttpResponse::Ok().json(export)),
+        ExportLookup::Archive(archive) => Ok(HttpResponse::Ok()
+            .content_type("application/zip")
+            .insert_header(ContentDisposition {
+                disposition: DispositionType::Attachment,
+                parameters: vec![DispositionParam::Filename(format!(
+                    "voyage-atlas-export-{}.zip",
+                    export_id
+                ))],
+            })
+            .body(archive)),
+    }
+}
+
 #[post("/users/{user_id}/follow")]
 #[tracing::instrument(name = "Follow a user", skip(conn))]
 async fn follow_user(
-    token: JwtPayload,
+    token: Authenticated<FollowsWrite>,
     followed_user: Path<(String,)>,
     conn: Data<PgPool>,
 ) -> Result<HttpResponse> {
@@ -65,31 +709,161 @@ async fn follow_user(
     let user_id =
         Uuid::parse_str(&token.user_id).map_err(|e| ApiError::BadRequest(anyhow::anyhow!(e)))?;
 
-    controller::user::follow_user(user_id, followed_user_id, &conn).await?;
+    match controller::user::follow_user(user_id, followed_user_id, &conn).await? {
+        FollowOutcome::Followed => Ok(HttpResponse::Created().finish()),
+        FollowOutcome::Requested => Ok(HttpResponse::Accepted().finish()),
+    }
+}
+
+#[get("/users/me/follow-requests")]
+#[tracing::instrument(name = "Listing a user's follow requests", skip(token, conn))]
+async fn get_follow_requests(token: JwtPayload, conn: Data<PgPool>) -> Result<HttpResponse> {
+    let user_id =
+        Uuid::parse_str(&token.user_id).map_err(|e| ApiError::BadRequest(anyhow::anyhow!(e)))?;
+
+    let requests = controller::user::get_follow_requests(&user_id, &conn).await?;
+
+    Ok(HttpResponse::Ok().json(requests))
+}
+
+#[post("/users/me/follow-requests/{follower_id}/approve")]
+#[tracing::instrument(name = "Approving a follow request", skip(token, conn))]
+async fn approve_follow_request(
+    token: JwtPayload,
+    follower_id: Path<(String,)>,
+    conn: Data<PgPool>,
+) -> Result<HttpResponse> {
+    let (follower_id,) = follower_id.into_inner();
+    let follower_id =
+        Uuid::parse_str(&follower_id).map_err(|e| ApiError::BadRequest(anyhow::anyhow!(e)))?;
+    let user_id =
+        Uuid::parse_str(&token.user_id).map_err(|e| ApiError::BadRequest(anyhow::anyhow!(e)))?;
+
+    controller::user::approve_follow_request(&user_id, &follower_id, &conn).await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[post("/users/me/follow-requests/{follower_id}/reject")]
+#[tracing::instrument(name = "Rejecting a follow request", skip(token, conn))]
+async fn reject_follow_request(
+    token: JwtPayload,
+    follower_id: Path<(String,)>,
+    conn: Data<PgPool>,
+) -> Result<HttpResponse> {
+    let (follower_id,) = follower_id.into_inner();
+    let follower_id =
+        Uuid::parse_str(&follower_id).map_err(|e| ApiError::BadRequest(anyhow::anyhow!(e)))?;
+    let user_id =
+        Uuid::parse_str(&token.user_id).map_err(|e| ApiError::BadRequest(anyhow::anyhow!(e)))?;
+
+    controller::user::reject_follow_request(&user_id, &follower_id, &conn).await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[post("/users/{user_id}/block")]
+#[tracing::instrument(name = "Block a user", skip(token, conn))]
+async fn block_user(
+    token: JwtPayload,
+    other_user: Path<(String,)>,
+    conn: Data<PgPool>,
+) -> Result<HttpResponse> {
+    let (other_user_id,) = other_user.into_inner();
+    let other_user_id =
+        Uuid::parse_str(&other_user_id).map_err(|e| ApiError::BadRequest(anyhow::anyhow!(e)))?;
+    let user_id =
+        Uuid::parse_str(&token.user_id).map_err(|e| ApiError::BadRequest(anyhow::anyhow!(e)))?;
+
+    controller::user::block_user(&user_id, &other_user_id, &conn).await?;
 
     Ok(HttpResponse::Created().finish())
 }
 
+#[delete("/users/{user_id}/block")]
+#[tracing::instrument(name = "Unblock a user", skip(token, conn))]
+async fn unblock_user(
+    token: JwtPayload,
+    other_user: Path<(String,)>,
+    conn: Data<PgPool>,
+) -> Result<HttpResponse> {
+    let (other_user_id,) = other_user.into_inner();
+    let other_user_id =
+        Uuid::parse_str(&other_user_id).map_err(|e| ApiError::BadRequest(anyhow::anyhow!(e)))?;
+    let user_id =
+        Uuid::parse_str(&token.user_id).map_err(|e| ApiError::BadRequest(anyhow::anyhow!(e)))?;
+
+    controller::user::unblock_user(&user_id, &other_user_id, &conn).await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[post("/users/{user_id}/mute")]
+#[tracing::instrument(name = "Mute a user", skip(token, conn))]
+async fn mute_user(
+    token: JwtPayload,
+    other_user: Path<(String,)>,
+    conn: Data<PgPool>,
+) -> Result<HttpResponse> {
+    let (other_user_id,) = other_user.into_inner();
+    let other_user_id =
+        Uuid::parse_str(&other_user_id).map_err(|e| ApiError::BadRequest(anyhow::anyhow!(e)))?;
+    let user_id =
+        Uuid::parse_str(&token.user_id).map_err(|e| ApiError::BadRequest(anyhow::anyhow!(e)))?;
+
+    controller::user::mute_user(&user_id, &other_user_id, &conn).await?;
+
+    Ok(HttpResponse::Created().finish())
+}
+
+#[delete("/users/{user_id}/mute")]
+#[tracing::instrument(name = "Unmute a user", skip(token, conn))]
+async fn unmute_user(
+    token: JwtPayload,
+    other_user: Path<(String,)>,
+    conn: Data<PgPool>,
+) -> Result<HttpResponse> {
+    let (other_user_id,) = other_user.into_inner();
+    let other_user_id =
+        Uuid::parse_str(&other_user_id).map_err(|e| ApiError::BadRequest(anyhow::anyhow!(e)))?;
+    let user_id =
+        Uuid::parse_str(&token.user_id).map_err(|e| ApiError::BadRequest(anyhow::anyhow!(e)))?;
+
+    controller::user::unmute_user(&user_id, &other_user_id, &conn).await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
 #[get("/users/{user_id}/followers")]
-#[tracing::instrument(name = "Get a user's followers", skip(conn))]
-async fn get_followers(user_id: Path<(String,)>, conn: Data<PgPool>) -> Result<HttpResponse> {
+#[tracing::instrument(name = "Get a user's followers", skip(token, conn))]
+async fn get_followers(
+    token: Option<JwtPayload>,
+    user_id: Path<(String,)>,
+    conn: Data<PgPool>,
+) -> Result<HttpResponse> {
     let (user_id,) = user_id.into_inner();
     let user_id =
         Uuid::parse_str(&user_id).map_err(|e| ApiError::BadRequest(anyhow::anyhow!(e)))?;
+    let viewer_id = viewer_id(token.as_ref())?;
 
-    let followers = controller::user::get_followers(user_id, &conn).await?;
+    let followers = controller::user::get_followers(user_id, viewer_id, &conn).await?;
 
     Ok(HttpResponse::Ok().json(followers))
 }
 
 #[get("/users/{user_id}/following")]
-#[tracing::instrument(name = "Get a user's following", skip(conn))]
-async fn get_following(user_id: Path<(String,)>, conn: Data<PgPool>) -> Result<HttpResponse> {
+#[tracing::instrument(name = "Get a user's following", skip(token, conn))]
+async fn get_following(
+    token: Option<JwtPayload>,
+    user_id: Path<(String,)>,
+    conn: Data<PgPool>,
+) -> Result<HttpResponse> {
     let (user_id,) = user_id.into_inner();
     let user_id =
         Uuid::parse_str(&user_id).map_err(|e| ApiError::BadRequest(anyhow::anyhow!(e)))?;
+    let viewer_id = viewer_id(token.as_ref())?;
 
-    let following = controller::user::get_following(user_id, &conn).await?;
+    let following = controller::user::get_following(user_id, viewer_id, &conn).await?;
 
     Ok(HttpResponse::Ok().json(following))
 }
@@ -97,7 +871,7 @@ async fn get_following(user_id: Path<(String,)>, conn: Data<PgPool>) -> Result<H
 #[delete("/users/{user_id}/unfollow")]
 #[tracing::instrument(name = "Unfollow a user", skip(conn))]
 async fn unfollow_user(
-    token: JwtPayload,
+    token: Authenticated<FollowsWrite>,
     followed_user: Path<(String,)>,
     conn: Data<PgPool>,
 ) -> Result<HttpResponse> {
@@ -113,9 +887,14 @@ async fn unfollow_user(
 }
 
 #[get("/users")]
-#[tracing::instrument(name = "Get All Users", skip(conn))]
-async fn get_all_users(query: Query<UserSearchQuery>, conn: Data<PgPool>) -> Result<HttpResponse> {
-    let users = controller::user::get_users(query.query.clone(), &conn).await?;
+#[tracing::instrument(name = "Get All Users", skip(token, conn))]
+async fn get_all_users(
+    token: Option<JwtPayload>,
+    query: Query<UserSearchQuery>,
+    conn: Data<PgPool>,
+) -> Result<HttpResponse> {
+    let viewer_id = viewer_id(token.as_ref())?;
+    let users = controller::user::get_users(query.query.clone(), viewer_id, &conn).await?;
     Ok(HttpResponse::Ok().json(users))
 }
 
@@ -131,6 +910,28 @@ async fn get_user(user_id: Path<(String,)>, conn: Data<PgPool>) -> Result<HttpRe
     Ok(HttpResponse::Ok().json(user))
 }
 
+/// Rejects the input as a bad request naming the fields that failed validation
+pub(super) fn validate_input(input: &impl Validate) -> Result<()> {
+    input.validate().map_err(|err| {
+        // TODO: Return a more specific error
+        let errors = err
+            .errors()
+            .keys()
+            .map(|k| k.to_string())
+            .collect::<Vec<String>>()
+            .join(", ");
+        ApiError::BadRequest(anyhow::anyhow!("Invalid fields: {}", errors))
+    })
+}
+
+/// The signed in viewer of a profile, if there is one
+pub(super) fn viewer_id(token: Option<&JwtPayload>) -> Result<Option<Uuid>> {
+    token
+        .map(|token| Uuid::parse_str(&token.user_id))
+        .transpose()
+        .map_err(|e| ApiError::BadRequest(anyhow::anyhow!(e)))
+}
+
 #[derive(serde::Deserialize, Debug)]
 struct UserSearchQuery {
     query: Option<String>,
@@ -1,21 +1,52 @@
-use std::net::TcpListener;
+use std::{net::TcpListener, sync::Arc, time::Duration};
 
 use actix_web::{dev::Server, web::Data, App, HttpServer};
 use sqlx::{postgres::PgPoolOptions, PgPool};
 use tracing::info;
 
-use crate::api::routes::{health_check, init_comment_routes, init_post_routes, init_user_routes};
+use crate::api::{
+    blob_store::{get_blob_store, BlobStore},
+    email_client::{get_email_client, EmailClient},
+    models::token::JwtKeys,
+    oidc_client::OidcClient,
+    purge_worker::run_purge_worker,
+    routes::{
+        health_check, init_admin_routes, init_comment_routes, init_media_routes, init_place_routes,
+        init_post_routes, init_tag_routes, init_user_routes,
+    },
+};
 
 use super::configuration::{DatabaseSettings, Settings};
 
 pub struct Application {
     port: u16,
     server: Server,
+    connection_pool: PgPool,
+    blob_store: Arc<dyn BlobStore>,
+    purge_interval: Duration,
 }
 
 impl Application {
     pub async fn build(configuration: Settings) -> Result<Self, std::io::Error> {
         let connection_pool = get_connection_pool(&configuration.database);
+        let jwt_keys = JwtKeys::from_settings(&configuration.auth).map_err(|err| {
+            std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("{err:#}"))
+        })?;
+        let email_client = get_email_client(&configuration.email).map_err(|err| {
+            std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("{err:#}"))
+        })?;
+        let blob_store = get_blob_store(&configuration.media).map_err(|err| {
+            std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("{err:#}"))
+        })?;
+
+        let oidc_client = OidcClient::new(&configuration.auth.oidc_providers);
+        let purge_interval = Duration::from_secs(
+            configuration
+                .auth
+                .account_deletion
+                .purge_interval_seconds
+                .max(1),
+        );
 
         let address = format!(
             "{}:{}",
@@ -25,11 +56,21 @@ impl Application {
         let port = listener.local_addr().unwrap().port();
         let server = run(
             listener,
-            connection_pool,
-            configuration.application.base_url,
+            connection_pool.clone(),
+            configuration,
+            jwt_keys,
+            email_client,
+            blob_store.clone(),
+            oidc_client,
         )?;
 
-        Ok(Self { port, server })
+        Ok(Self {
+            port,
+            server,
+            connection_pool,
+            blob_store,
+            purge_interval,
+        })
     }
 
     pub fn port(&self) -> u16 {
@@ -38,7 +79,14 @@ impl Application {
 
     pub async fn run_until_stopped(self) -> Result<(), std::io::Error> {
         info!("Server running on port: {}", self.port);
-        self.server.await
+        let purge_worker = tokio::spawn(run_purge_worker(
+            self.connection_pool,
+            self.blob_store,
+            self.purge_interval,
+        ));
+        let result = self.server.await;
+        purge_worker.abort();
+        result
     }
 }
 
@@ -51,10 +99,20 @@ pub struct ApplicationPort(pub u16);
 pub fn run(
     listener: TcpListener,
     connection_pool: PgPool,
-    base_url: String,
+    configuration: Settings,
+    jwt_keys: JwtKeys,
+    email_client: Arc<dyn EmailClient>,
+    blob_store: Arc<dyn BlobStore>,
+    oidc_client: OidcClient,
 ) -> Result<Server, std::io::Error> {
     let connection = Data::new(connection_pool);
-    let base_url = Data::new(ApplicationBaseUrl(base_url));
+    let auth_settings = Data::new(configuration.auth);
+    let media_settings = Data::new(configuration.media);
+    let jwt_keys = Data::new(jwt_keys);
+    let email_client: Data<dyn EmailClient> = Data::from(email_client);
+    let blob_store: Data<dyn BlobStore> = Data::from(blob_store);
+    let oidc_client = Data::new(oidc_client);
+    let base_url = Data::new(ApplicationBaseUrl(configuration.application.base_url));
     let port = Data::new(ApplicationPort(
         listener.local_addr().expect("Cannot Get Port").port(),
     ));
@@ -65,9 +123,19 @@ pub fn run(
             .configure(init_comment_routes)
             .configure(init_user_routes)
             .configure(init_post_routes)
+            .configure(init_admin_routes)
+            .configure(init_media_routes)
+            .configure(init_tag_routes)
+            .configure(init_place_routes)
             .app_data(connection.clone())
             .app_data(base_url.clone())
             .app_data(port.clone())
+            .app_data(auth_settings.clone())
+            .app_data(jwt_keys.clone())
+            .app_data(email_client.clone())
+            .app_data(blob_store.clone())
+            .app_data(media_settings.clone())
+            .app_data(oidc_client.clone())
     })
     .listen(listener)?
     .run();
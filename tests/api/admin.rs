@@ -0,0 +1,127 @@
+use crate::helpers::{spawn_app, spawn_app_with, TestAuthInfo, JWT_KEYS};
+use serde_json::Value;
+use voyage_atlas_api::api::models::{token::JwtPayload, AuthInfo, LockoutEventInfo, Role};
+
+#[tokio::test]
+async fn test_admin_routes_require_admin_role() {
+    let mut test_app = spawn_app().await;
+
+    let res = reqwest::get(format!("{}/admin/lockouts", &test_app.address))
+        .await
+        .unwrap();
+    assert_eq!(res.status().as_u16(), 401);
+
+    let res = test_app
+        .get_lockout_events(&test_app.auth_info.bearer)
+        .await;
+    assert_eq!(res.status().as_u16(), 403);
+
+    // Moderators don't get to manage users either
+    let pool = test_app.db_pool.clone();
+    test_app.auth_info.grant_role(&pool, Role::Moderator).await;
+    let res = test_app
+        .get_lockout_events(&test_app.auth_info.bearer)
+        .await;
+    assert_eq!(res.status().as_u16(), 403);
+    let res = test_app
+        .grant_role(
+            &test_app.auth_info.user.id,
+            "admin",
+            &test_app.auth_info.bearer,
+        )
+        .await;
+    assert_eq!(res.status().as_u16(), 403);
+}
+
+#[tokio::test]
+async fn test_admin_can_list_lockouts() {
+    let mut test_app = spawn_app_with(|c| {
+        c.auth.login_throttling.account_free_attempts = 2;
+    })
+    .await;
+    let user = TestAuthInfo::generate();
+    user.store(&test_app.db_pool).await;
+    for _ in 0..2 {
+        test_app.login(&user.user.email, "WrongPassword123!").await;
+    }
+
+    let pool = test_app.db_pool.clone();
+    test_app.auth_info.grant_role(&pool, Role::Admin).await;
+    let res = test_app
+        .get_lockout_events(&test_app.auth_info.bearer)
+        .await;
+    assert_eq!(res.status().as_u16(), 200);
+    let events: Vec<LockoutEventInfo> = res.json().await.unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].scope, "account");
+    assert_eq!(events[0].identifier, user.user.email);
+    assert_eq!(events[0].failed_attempts, 2);
+}
+
+#[tokio::test]
+async fn test_granted_roles_are_in_token_claims() {
+    let mut test_app = spawn_app().await;
+    let pool = test_app.db_pool.clone();
+    test_app.auth_info.grant_role(&pool, Role::Admin).await;
+    let user = TestAuthInfo::generate();
+    user.store(&test_app.db_pool).await;
+
+    let res = test_app
+        .grant_role(&user.user.id, "moderator", &test_app.auth_info.bearer)
+        .await;
+    assert_eq!(res.status().as_u16(), 204);
+    let res = test_app
+        .get_user_roles(&user.user.id, &test_app.auth_info.bearer)
+        .await;
+    let roles: Vec<Role> = res.json().await.unwrap();
+    assert_eq!(roles, vec![Role::Moderator]);
+
+    let res = test_app.login(&user.user.email, "Password123!").await;
+    let auth_info: AuthInfo = res.json().await.unwrap();
+    let claims: JwtPayload = JWT_KEYS.decode(&auth_info.bearer).unwrap();
+    assert_eq!(claims.roles, vec![Role::Moderator]);
+
+    // Revoking takes effect with the next token
+    let res = test_app
+        .revoke_role(&user.user.id, "moderator", &test_app.auth_info.bearer)
+        .await;
+    assert_eq!(res.status().as_u16(), 204);
+    let res = test_app.refresh_token(&auth_info.refresh_token).await;
+    let tokens: Value = res.json().await.unwrap();
+    let claims: JwtPayload = JWT_KEYS.decode(tokens["bearer"].as_str().unwrap()).unwrap();
+    assert!(claims.roles.is_empty());
+}
+
+#[tokio::test]
+async fn test_grant_role_rejects_unknown_roles_and_users() {
+    let mut test_app = spawn_app().await;
+    let pool = test_app.db_pool.clone();
+    test_app.auth_info.grant_role(&pool, Role::Admin).await;
+
+    let res = test_app
+        .grant_role(
+            &test_app.auth_info.user.id,
+            "superuser",
+            &test_app.auth_info.bearer,
+        )
+        .await;
+    assert_eq!(res.status().as_u16(), 400);
+
+    let res = test_app
+        .grant_role(
+            &uuid::Uuid::new_v4().to_string(),
+            "moderator",
+            &test_app.auth_info.bearer,
+        )
+        .await;
+    assert_eq!(res.status().as_u16(), 404);
+
+    let res = test_app
+        .revoke_role(
+            &test_app.auth_info.user.id,
+            "admin",
+            &test_app.auth_info.bearer,
+        )
+        .await;
+    assert_eq!(res.status().as_u16(), 400);
+}
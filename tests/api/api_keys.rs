@@ -0,0 +1,130 @@
+use serde_json::json;
+use voyage_atlas_api::api::models::{ApiKeyInfo, ApiKeyScope, NewApiKey};
+
+use crate::helpers::{spawn_app, test_post_body, TestApp};
+
+async fn create_key(test_app: &TestApp, scopes: &[&str]) -> NewApiKey {
+    let res = test_app
+        .create_api_key(
+            json!({ "name": "trip logger", "scopes": scopes }),
+            &test_app.auth_info.bearer,
+        )
+        .await;
+    assert_eq!(res.status().as_u16(), 201);
+    res.json().await.unwrap()
+}
+
+#[tokio::test]
+async fn test_api_key_is_shown_once_and_stored_hashed() {
+    let test_app = spawn_app().await;
+
+    let new_key = create_key(&test_app, &["posts:write"]).await;
+    assert!(new_key.key.starts_with("va_"));
+    assert!(new_key.key.starts_with(&new_key.info.prefix));
+    assert_eq!(new_key.info.scopes, vec![ApiKeyScope::PostsWrite]);
+
+    let stored = sqlx::query!("SELECT key_hash FROM api_keys")
+        .fetch_one(&test_app.db_pool)
+        .await
+        .unwrap();
+    assert_ne!(stored.key_hash, new_key.key);
+
+    // Listing never includes the key itself
+    let res = test_app.get_api_keys(&test_app.auth_info.bearer).await;
+    assert_eq!(res.status().as_u16(), 200);
+    let keys: Vec<serde_json::Value> = res.json().await.unwrap();
+    assert_eq!(keys.len(), 1);
+    assert!(keys[0].get("key").is_none());
+    assert_eq!(keys[0]["name"], "trip logger");
+}
+
+#[tokio::test]
+async fn test_create_api_key_rejects_invalid_requests() {
+    let test_app = spawn_app().await;
+    let bearer = &test_app.auth_info.bearer;
+
+    let res = test_app
+        .create_api_key(json!({ "name": "no scopes", "scopes": [] }), bearer)
+        .await;
+    assert_eq!(res.status().as_u16(), 400);
+    let res = test_app
+        .create_api_key(json!({ "name": "", "scopes": ["feed:read"] }), bearer)
+        .await;
+    assert_eq!(res.status().as_u16(), 400);
+    let res = test_app
+        .create_api_key(json!({ "name": "admin", "scopes": ["admin"] }), bearer)
+        .await;
+    assert_eq!(res.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn test_api_key_acts_as_its_owner_within_its_scopes() {
+    let test_app = spawn_app().await;
+    let new_key = create_key(&test_app, &["posts:write"]).await;
+
+    let res = test_app
+        .create_post_with_api_key(
+            test_post_body(json!({ "title": "Posted by a script" })),
+            &new_key.key,
+        )
+        .await;
+    assert_eq!(res.status().as_u16(), 201);
+    let post = sqlx::query!("SELECT author FROM posts")
+        .fetch_one(&test_app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(post.author.to_string(), test_app.auth_info.user.id);
+
+    // The key can't do what its scopes don't include
+    let res = test_app.get_user_feed_with_api_key(&new_key.key).await;
+    assert_eq!(res.status().as_u16(), 403);
+
+    // Nor can it manage keys, which needs a signed in user
+    let res = test_app.get_api_keys(&new_key.key).await;
+    assert_eq!(res.status().as_u16(), 401);
+
+    let res = test_app
+        .create_post_with_api_key(
+            test_post_body(json!({ "title": "Posted by a script" })),
+            "va_not-a-real-key",
+        )
+        .await;
+    assert_eq!(res.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn test_api_key_tracks_last_use() {
+    let test_app = spawn_app().await;
+    let new_key = create_key(&test_app, &["feed:read"]).await;
+    assert!(new_key.info.last_used_at.is_none());
+
+    let res = test_app.get_user_feed_with_api_key(&new_key.key).await;
+    assert_eq!(res.status().as_u16(), 200);
+
+    let res = test_app.get_api_keys(&test_app.auth_info.bearer).await;
+    let keys: Vec<ApiKeyInfo> = res.json().await.unwrap();
+    assert!(keys[0].last_used_at.is_some());
+}
+
+#[tokio::test]
+async fn test_revoked_api_key_is_rejected() {
+    let test_app = spawn_app().await;
+    let new_key = create_key(&test_app, &["feed:read"]).await;
+
+    let res = test_app
+        .revoke_api_key(&new_key.info.id, &test_app.auth_info.bearer)
+        .await;
+    assert_eq!(res.status().as_u16(), 204);
+
+    let res = test_app.get_user_feed_with_api_key(&new_key.key).await;
+    assert_eq!(res.status().as_u16(), 401);
+    let res = test_app.get_api_keys(&test_app.auth_info.bearer).await;
+    let keys: Vec<ApiKeyInfo> = res.json().await.unwrap();
+    assert!(keys.is_empty());
+
+    // Revoking twice, or someone else's key, finds nothing
+    let res = test_app
+        .revoke_api_key(&new_key.info.id, &test_app.auth_info.bearer)
+        .await;
+    assert_eq!(res.status().as_u16(), 404);
+}
@@ -0,0 +1,194 @@
+use serde_json::{json, Value};
+use voyage_atlas_api::api::models::{Comment, CreateComment, Post, PublicUser};
+
+use crate::helpers::{spawn_app, TestAuthInfo};
+
+fn comment(text: &str) -> CreateComment {
+    CreateComment {
+        comment: text.to_string(),
+    }
+}
+
+#[tokio::test]
+async fn test_block_removes_follows_both_ways() {
+    let test_app = spawn_app().await;
+    let user = &test_app.auth_info;
+    let other_user = TestAuthInfo::generate();
+    other_user.store(&test_app.db_pool).await;
+    test_app
+        .follow_user(&other_user.user.id, &user.bearer)
+        .await;
+    test_app
+        .follow_user(&user.user.id, &other_user.bearer)
+        .await;
+
+    let res = test_app.block_user(&other_user.user.id, &user.bearer).await;
+    assert_eq!(res.status().as_u16(), 201);
+
+    let followers: Vec<PublicUser> = test_app
+        .get_followers(&user.user.id)
+        .await
+        .json()
+        .await
+        .unwrap();
+    let following: Vec<PublicUser> = test_app
+        .get_following(&user.user.id)
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert!(followers.is_empty());
+    assert!(following.is_empty());
+
+    // Neither side can follow again
+    let res = test_app
+        .follow_user(&user.user.id, &other_user.bearer)
+        .await;
+    assert_eq!(res.status().as_u16(), 403);
+    let res = test_app
+        .follow_user(&other_user.user.id, &user.bearer)
+        .await;
+    assert_eq!(res.status().as_u16(), 403);
+
+    let res = test_app
+        .unblock_user(&other_user.user.id, &user.bearer)
+        .await;
+    assert_eq!(res.status().as_u16(), 204);
+    let res = test_app
+        .follow_user(&user.user.id, &other_user.bearer)
+        .await;
+    assert_eq!(res.status().as_u16(), 201);
+}
+
+#[tokio::test]
+async fn test_blocked_user_cannot_like_or_comment() {
+    let test_app = spawn_app().await;
+    let user = &test_app.auth_info;
+    let other_user = TestAuthInfo::generate();
+    other_user.store(&test_app.db_pool).await;
+    let post_id = test_app.create_test_post(json!({}), &user.bearer).await;
+    let res = test_app
+        .create_comment(&post_id, comment("Before the block"), &user.bearer)
+        .await;
+    let comment_id = res.json::<Value>().await.unwrap()["comment_id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    test_app.block_user(&other_user.user.id, &user.bearer).await;
+
+    let res = test_app.like_a_post(&post_id, &other_user.bearer).await;
+    assert_eq!(res.status().as_u16(), 403);
+    let res = test_app
+        .create_comment(&post_id, comment("Hello?"), &other_user.bearer)
+        .await;
+    assert_eq!(res.status().as_u16(), 403);
+    let res = test_app
+        .create_reply_comment(&post_id, &comment_id, comment("Hello?"), &other_user.bearer)
+        .await;
+    assert_eq!(res.status().as_u16(), 403);
+
+    // The block applies to the blocker too
+    let other_post_id = test_app
+        .create_test_post(json!({}), &other_user.bearer)
+        .await;
+    let res = test_app.like_a_post(&other_post_id, &user.bearer).await;
+    assert_eq!(res.status().as_u16(), 403);
+}
+
+#[tokio::test]
+async fn test_block_hides_users_from_search_and_comments() {
+    let test_app = spawn_app().await;
+    let user = &test_app.auth_info;
+    let other_user = TestAuthInfo::new("blocked_traveller");
+    other_user.store(&test_app.db_pool).await;
+    let post_id = test_app.create_test_post(json!({}), &user.bearer).await;
+    test_app
+        .create_comment(&post_id, comment("Nice view"), &other_user.bearer)
+        .await;
+
+    test_app.block_user(&other_user.user.id, &user.bearer).await;
+
+    let users: Vec<PublicUser> = test_app
+        .search_users_as("blocked", &user.bearer)
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert!(users.is_empty());
+    let users: Vec<PublicUser> = test_app
+        .search_users_as(&user.user.username[..8], &other_user.bearer)
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert!(users.is_empty());
+
+    let comments: Vec<Comment> = test_app
+        .get_comments_as(&post_id, &user.bearer)
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert!(comments.is_empty());
+    // Everyone else still sees the comment
+    let comments: Vec<Comment> = test_app.get_comments(&post_id).await.json().await.unwrap();
+    assert_eq!(comments.len(), 1);
+}
+
+#[tokio::test]
+async fn test_mute_only_hides_posts_from_feed() {
+    let test_app = spawn_app().await;
+    let user = &test_app.auth_info;
+    let other_user = TestAuthInfo::generate();
+    other_user.store(&test_app.db_pool).await;
+    test_app
+        .follow_user(&other_user.user.id, &user.bearer)
+        .await;
+    let post_id = test_app
+        .create_test_post(json!({}), &other_user.bearer)
+        .await;
+
+    let res = test_app.mute_user(&other_user.user.id, &user.bearer).await;
+    assert_eq!(res.status().as_u16(), 201);
+
+    let feed: Vec<Post> = test_app
+        .get_user_feed(&user.bearer)
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert!(feed.is_empty());
+    // Muting doesn't stop interactions or end the follow
+    let res = test_app.like_a_post(&post_id, &user.bearer).await;
+    assert_eq!(res.status().as_u16(), 201);
+    let followers: Vec<PublicUser> = test_app
+        .get_followers(&other_user.user.id)
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(followers.len(), 1);
+
+    let res = test_app
+        .unmute_user(&other_user.user.id, &user.bearer)
+        .await;
+    assert_eq!(res.status().as_u16(), 204);
+    let feed: Vec<Post> = test_app
+        .get_user_feed(&user.bearer)
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(feed.len(), 1);
+}
+
+#[tokio::test]
+async fn test_cannot_block_yourself() {
+    let test_app = spawn_app().await;
+
+    let res = test_app
+        .block_user(&test_app.auth_info.user.id, &test_app.auth_info.bearer)
+        .await;
+    assert_eq!(res.status().as_u16(), 400);
+}
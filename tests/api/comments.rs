@@ -36,7 +36,7 @@ async fn test_create_comment() {
         .await;
     assert_eq!(res.status().as_u16(), 201);
     let comment_id = Uuid::from_str(
-        &res.json::<serde_json::Value>().await.unwrap()["comment_id"]
+        res.json::<serde_json::Value>().await.unwrap()["comment_id"]
             .as_str()
             .unwrap(),
     )
@@ -0,0 +1,144 @@
+use std::io::{Cursor, Read};
+
+use serde_json::json;
+use voyage_atlas_api::api::models::{
+    AuthUser, CreateComment, DataExportInfo, ExportStatus, ExportedComment, PublicUser,
+};
+use zip::ZipArchive;
+
+use crate::helpers::{spawn_app, TestApp, TestAuthInfo};
+
+// Polls the export until it is no longer pending
+async fn wait_for_export(test_app: &TestApp, export_id: &str) -> reqwest::Response {
+    for _ in 0..50 {
+        let res = test_app
+            .get_export(export_id, &test_app.auth_info.bearer)
+            .await;
+        if res.status().as_u16() != 202 {
+            return res;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    panic!("Data export was never generated");
+}
+
+fn read_file(archive: &mut ZipArchive<Cursor<Vec<u8>>>, name: &str) -> String {
+    let mut contents = String::new();
+    archive
+        .by_name(name)
+        .unwrap()
+        .read_to_string(&mut contents)
+        .unwrap();
+    contents
+}
+
+#[tokio::test]
+async fn test_export_contains_the_users_data() {
+    let test_app = spawn_app().await;
+    let post_id = test_app
+        .create_test_post(json!({}), &test_app.auth_info.bearer)
+        .await;
+    test_app
+        .create_comment(
+            &post_id,
+            CreateComment {
+                comment: "Lovely, comma, place".to_string(),
+            },
+            &test_app.auth_info.bearer,
+        )
+        .await;
+    test_app
+        .like_a_post(&post_id, &test_app.auth_info.bearer)
+        .await;
+    let follower = TestAuthInfo::generate();
+    follower.store(&test_app.db_pool).await;
+    test_app
+        .follow_user(&test_app.auth_info.user.id, &follower.bearer)
+        .await;
+
+    let res = test_app.start_export(&test_app.auth_info.bearer).await;
+    assert_eq!(res.status().as_u16(), 202);
+    let export: DataExportInfo = res.json().await.unwrap();
+    assert_eq!(export.status, ExportStatus::Pending);
+
+    let res = wait_for_export(&test_app, &export.id).await;
+    assert_eq!(res.status().as_u16(), 200);
+    assert_eq!(res.headers()["content-type"], "application/zip");
+    assert!(res.headers()["content-disposition"]
+        .to_str()
+        .unwrap()
+        .starts_with("attachment"));
+
+    let bytes = res.bytes().await.unwrap().to_vec();
+    let mut archive = ZipArchive::new(Cursor::new(bytes)).unwrap();
+    let profile: AuthUser = serde_json::from_str(&read_file(&mut archive, "profile.json")).unwrap();
+    assert_eq!(profile.id, test_app.auth_info.user.id);
+    let comments: Vec<ExportedComment> =
+        serde_json::from_str(&read_file(&mut archive, "comments.json")).unwrap();
+    assert_eq!(comments.len(), 1);
+    assert_eq!(comments[0].post_id, post_id);
+    let followers: Vec<PublicUser> =
+        serde_json::from_str(&read_file(&mut archive, "followers.json")).unwrap();
+    assert_eq!(followers[0].id, follower.user.id);
+
+    let posts_csv = read_file(&mut archive, "posts.csv");
+    assert!(posts_csv.starts_with("id,title,location,content,author,created_at"));
+    assert!(posts_csv.contains(&post_id));
+    assert!(read_file(&mut archive, "comments.csv").contains("\"Lovely, comma, place\""));
+    assert!(read_file(&mut archive, "likes.csv").contains(&post_id));
+}
+
+#[tokio::test]
+async fn test_export_of_another_user_is_not_found() {
+    let test_app = spawn_app().await;
+    let other_user = TestAuthInfo::generate();
+    other_user.store(&test_app.db_pool).await;
+
+    let res = test_app.start_export(&other_user.bearer).await;
+    let export: DataExportInfo = res.json().await.unwrap();
+
+    let res = test_app
+        .get_export(&export.id, &test_app.auth_info.bearer)
+        .await;
+    assert_eq!(res.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn test_export_stuck_pending_does_not_block_a_new_one() {
+    let test_app = spawn_app().await;
+    let res = test_app.start_export(&test_app.auth_info.bearer).await;
+    let stuck: DataExportInfo = res.json().await.unwrap();
+    wait_for_export(&test_app, &stuck.id).await;
+    // As if the process had restarted while generating it
+    sqlx::query!(
+        r#"
+        UPDATE data_exports
+        SET status = 'pending', archive = NULL, completed_at = NULL,
+            created_at = NOW() - INTERVAL '2 hours'
+        "#
+    )
+    .execute(&test_app.db_pool)
+    .await
+    .unwrap();
+
+    let res = test_app.start_export(&test_app.auth_info.bearer).await;
+    assert_eq!(res.status().as_u16(), 202);
+    let export: DataExportInfo = res.json().await.unwrap();
+    assert_ne!(export.id, stuck.id);
+    let res = wait_for_export(&test_app, &export.id).await;
+    assert_eq!(res.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn test_recent_ready_export_is_returned_again() {
+    let test_app = spawn_app().await;
+    let res = test_app.start_export(&test_app.auth_info.bearer).await;
+    let first: DataExportInfo = res.json().await.unwrap();
+    wait_for_export(&test_app, &first.id).await;
+
+    let res = test_app.start_export(&test_app.auth_info.bearer).await;
+    assert_eq!(res.status().as_u16(), 200);
+    let export: DataExportInfo = res.json().await.unwrap();
+    assert_eq!(export.id, first.id);
+    assert_eq!(export.status, ExportStatus::Ready);
+}
@@ -1,13 +1,63 @@
+use std::{path::PathBuf, sync::Arc};
+
 use once_cell::sync::Lazy;
 use sqlx::{sqlx_macros::migrate, Connection, Executor, PgConnection, PgPool};
 use uuid::Uuid;
 use voyage_atlas_api::api::{
-    configuration::{get_configuration, DatabaseSettings},
-    models::{token, AuthUser, CreateComment},
+    blob_store::{get_blob_store, BlobStore},
+    configuration::{
+        get_configuration, BlobStorage, DatabaseSettings, EmailTransport, PasswordHashingSettings,
+        Settings,
+    },
+    email_client::Email,
+    models::{
+        token::{self, JwtKeys},
+        AuthUser, CreateComment, Role,
+    },
     startup::{get_connection_pool, Application},
     telemetry::{get_subscriber, init_subscriber},
 };
 
+/// A PNG of a single colour, for upload tests
+pub fn png_image(width: u32, height: u32) -> Vec<u8> {
+    let image = image::RgbImage::from_pixel(width, height, image::Rgb([30, 144, 255]));
+    let mut bytes = std::io::Cursor::new(Vec::new());
+    image
+        .write_to(&mut bytes, image::ImageOutputFormat::Png)
+        .unwrap();
+    bytes.into_inner()
+}
+
+/// A new post made of placeholder fields, with `fields` replacing or adding to them, such
+/// as coordinates, a `place_id` or `media`
+pub fn test_post_body(fields: serde_json::Value) -> serde_json::Value {
+    let mut post = serde_json::json!({
+        "title": "My first post",
+        "location": "location",
+        "content": "content"
+    });
+    for (key, value) in fields.as_object().unwrap() {
+        post[key] = value.clone();
+    }
+    post
+}
+
+/// The code an authenticator app would show, `steps` time steps from now
+pub fn totp_code(secret: &str, steps: i64) -> String {
+    let totp = totp_rs::TOTP::new_unchecked(
+        totp_rs::Algorithm::SHA1,
+        6,
+        1,
+        30,
+        totp_rs::Secret::Encoded(secret.to_string())
+            .to_bytes()
+            .unwrap(),
+        None,
+        String::new(),
+    );
+    totp.generate((chrono::Utc::now().timestamp() + steps * 30) as u64)
+}
+
 static TRACING: Lazy<()> = Lazy::new(|| {
     let default_filter_level = "info".to_string();
     let subscriber_name = "test".to_string();
@@ -21,46 +71,67 @@ static TRACING: Lazy<()> = Lazy::new(|| {
     }
 });
 
+pub static JWT_KEYS: Lazy<JwtKeys> = Lazy::new(|| {
+    let configuration = get_configuration().expect("Failed to read configuration");
+    JwtKeys::from_settings(&configuration.auth).expect("Failed to load JWT keys")
+});
+
 pub struct TestApp {
     pub address: String,
     pub db_pool: PgPool,
     pub port: u16,
     pub auth_info: TestAuthInfo,
+    pub email_directory: PathBuf,
+    /// The same store the app keeps uploaded images in
+    pub blob_store: Arc<dyn BlobStore>,
 }
 
 #[derive(Debug)]
 pub struct TestAuthInfo {
     pub bearer: String,
+    pub session_id: String,
     pub user: AuthUser,
 }
 
 impl TestAuthInfo {
     pub fn new(username: &str) -> Self {
         let id = Uuid::new_v4().to_string();
-        let token = token::generate_token(&id).unwrap();
+        let session_id = Uuid::new_v4().to_string();
+        let token = token::generate_token(&id, &session_id, vec![], &JWT_KEYS).unwrap();
         TestAuthInfo {
             bearer: token,
+            session_id,
             user: AuthUser {
                 id,
                 username: username.to_string(),
                 email: format!("{}@email", username),
                 name: "Test User".to_string(),
                 description: "Test Description".to_string(),
+                show_email: false,
+                is_private: false,
+                avatar: None,
+                cover: None,
             },
         }
     }
 
     pub fn generate() -> Self {
         let id = Uuid::new_v4().to_string();
-        let token = token::generate_token(&id).unwrap();
+        let session_id = Uuid::new_v4().to_string();
+        let token = token::generate_token(&id, &session_id, vec![], &JWT_KEYS).unwrap();
         TestAuthInfo {
             bearer: token,
+            session_id,
             user: AuthUser {
                 id,
                 username: Uuid::new_v4().to_string(),
-                email: format!("{}@email", Uuid::new_v4().to_string()),
+                email: format!("{}@email", Uuid::new_v4()),
                 name: "Test User".to_string(),
                 description: "Test Description".to_string(),
+                show_email: false,
+                is_private: false,
+                avatar: None,
+                cover: None,
             },
         }
     }
@@ -70,27 +141,480 @@ impl TestAuthInfo {
         let id = Uuid::parse_str(&self.user.id).unwrap();
         sqlx::query!(
             r#"
-            INSERT INTO users (id, username, email, password)
-            VALUES ($1, $2, $3, $4)
+            INSERT INTO users (id, username, email, password, first_name, last_name, description, email_verified_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, NOW())
             "#,
             id,
             self.user.username,
             self.user.email,
-            password
+            password,
+            "Test",
+            "User",
+            self.user.description
+        )
+        .execute(pool)
+        .await
+        .unwrap();
+        sqlx::query!(
+            r#"
+            INSERT INTO sessions (id, user_id)
+            VALUES ($1, $2)
+            "#,
+            Uuid::parse_str(&self.session_id).unwrap(),
+            id
         )
         .execute(pool)
         .await
         .unwrap();
     }
+
+    /// Grants the role and reissues the bearer, as a token refresh would
+    pub async fn grant_role(&mut self, pool: &PgPool, role: Role) {
+        sqlx::query!(
+            r#"
+            INSERT INTO user_roles (user_id, role)
+            VALUES ($1, $2)
+            "#,
+            Uuid::parse_str(&self.user.id).unwrap(),
+            role.as_str()
+        )
+        .execute(pool)
+        .await
+        .unwrap();
+        let roles = JWT_KEYS
+            .decode::<token::JwtPayload>(&self.bearer)
+            .unwrap()
+            .roles;
+        self.bearer = token::generate_token(
+            &self.user.id,
+            &self.session_id,
+            roles.into_iter().chain([role]).collect(),
+            &JWT_KEYS,
+        )
+        .unwrap();
+    }
 }
 
 impl TestApp {
+    /// Emails "sent" by the app so far, oldest first
+    pub fn sent_emails(&self) -> Vec<Email> {
+        let mut paths = match std::fs::read_dir(&self.email_directory) {
+            Ok(entries) => entries
+                .map(|entry| entry.unwrap().path())
+                .collect::<Vec<PathBuf>>(),
+            Err(_) => return vec![],
+        };
+        paths.sort();
+        paths
+            .into_iter()
+            .map(|path| serde_json::from_slice(&std::fs::read(path).unwrap()).unwrap())
+            .collect()
+    }
+
+    /// Extract the single link from an email's text body
+    pub fn get_link(&self, email: &Email) -> reqwest::Url {
+        let links: Vec<_> = linkify::LinkFinder::new()
+            .links(&email.text_content)
+            .filter(|l| *l.kind() == linkify::LinkKind::Url)
+            .collect();
+        assert_eq!(links.len(), 1);
+        reqwest::Url::parse(links[0].as_str()).unwrap()
+    }
+
+    pub async fn confirm_email(&self, token: &str) -> reqwest::Response {
+        let client = reqwest::Client::new();
+        let url = format!("{}/users/confirm", &self.address);
+        client
+            .get(&url)
+            .query(&[("token", token)])
+            .send()
+            .await
+            .unwrap()
+    }
+
+    pub async fn resend_confirmation_email(&self, bearer: &str) -> reqwest::Response {
+        let client = reqwest::Client::new();
+        let url = format!("{}/users/confirm/resend", &self.address);
+        client.post(&url).bearer_auth(bearer).send().await.unwrap()
+    }
+
+    pub async fn forgot_password(&self, email: &str) -> reqwest::Response {
+        let client = reqwest::Client::new();
+        let url = format!("{}/users/password/forgot", &self.address);
+        client
+            .post(&url)
+            .json(&serde_json::json!({ "email": email }))
+            .send()
+            .await
+            .unwrap()
+    }
+
+    /// Opens a link from an email, which points at the configured base url without a port
+    pub async fn get_link_page(&self, link: &reqwest::Url) -> reqwest::Response {
+        let mut link = link.clone();
+        link.set_port(Some(self.port)).unwrap();
+        reqwest::Client::new().get(link).send().await.unwrap()
+    }
+
+    pub async fn submit_password_reset_form(
+        &self,
+        token: &str,
+        password: &str,
+    ) -> reqwest::Response {
+        let client = reqwest::Client::new();
+        let url = format!("{}/users/password/reset", &self.address);
+        client
+            .post(&url)
+            .form(&[("token", token), ("password", password)])
+            .send()
+            .await
+            .unwrap()
+    }
+
+    pub async fn reset_password(&self, token: &str, password: &str) -> reqwest::Response {
+        let client = reqwest::Client::new();
+        let url = format!("{}/users/password/reset", &self.address);
+        client
+            .post(&url)
+            .json(&serde_json::json!({ "token": token, "password": password }))
+            .send()
+            .await
+            .unwrap()
+    }
+
     pub async fn post_user(&self, body: serde_json::Value) -> reqwest::Response {
         let client = reqwest::Client::new();
         let url = format!("{}/users", &self.address);
         client.post(&url).json(&body).send().await.unwrap()
     }
 
+    pub async fn login(&self, email: &str, password: &str) -> reqwest::Response {
+        let client = reqwest::Client::new();
+        let url = format!("{}/users/login", &self.address);
+        client
+            .post(&url)
+            .json(&serde_json::json!({ "email": email, "password": password }))
+            .send()
+            .await
+            .unwrap()
+    }
+
+    pub async fn login_mfa(&self, mfa_token: &str, code: &str) -> reqwest::Response {
+        let client = reqwest::Client::new();
+        let url = format!("{}/users/login/mfa", &self.address);
+        client
+            .post(&url)
+            .json(&serde_json::json!({ "mfa_token": mfa_token, "code": code }))
+            .send()
+            .await
+            .unwrap()
+    }
+
+    pub async fn enroll_two_factor(&self, bearer: &str) -> reqwest::Response {
+        let client = reqwest::Client::new();
+        let url = format!("{}/users/me/2fa", &self.address);
+        client.post(&url).bearer_auth(bearer).send().await.unwrap()
+    }
+
+    pub async fn confirm_two_factor(&self, code: &str, bearer: &str) -> reqwest::Response {
+        let client = reqwest::Client::new();
+        let url = format!("{}/users/me/2fa/confirm", &self.address);
+        client
+            .post(&url)
+            .bearer_auth(bearer)
+            .json(&serde_json::json!({ "code": code }))
+            .send()
+            .await
+            .unwrap()
+    }
+
+    pub async fn disable_two_factor(&self, code: &str, bearer: &str) -> reqwest::Response {
+        let client = reqwest::Client::new();
+        let url = format!("{}/users/me/2fa", &self.address);
+        client
+            .delete(&url)
+            .bearer_auth(bearer)
+            .json(&serde_json::json!({ "code": code }))
+            .send()
+            .await
+            .unwrap()
+    }
+
+    pub async fn start_oidc_login(&self, provider: &str) -> reqwest::Response {
+        // Keep the redirect to the provider instead of following it
+        let client = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .unwrap();
+        let url = format!("{}/users/oidc/{}/authorize", &self.address, provider);
+        client.get(&url).send().await.unwrap()
+    }
+
+    pub async fn finish_oidc_login(
+        &self,
+        provider: &str,
+        state: &str,
+        code: &str,
+    ) -> reqwest::Response {
+        let client = reqwest::Client::new();
+        let url = format!("{}/users/oidc/{}/callback", &self.address, provider);
+        client
+            .get(&url)
+            .query(&[("state", state), ("code", code)])
+            .send()
+            .await
+            .unwrap()
+    }
+
+    pub async fn refresh_token(&self, refresh_token: &str) -> reqwest::Response {
+        let client = reqwest::Client::new();
+        let url = format!("{}/users/token/refresh", &self.address);
+        client
+            .post(&url)
+            .json(&serde_json::json!({ "refresh_token": refresh_token }))
+            .send()
+            .await
+            .unwrap()
+    }
+
+    pub async fn get_me(&self, bearer: &str) -> reqwest::Response {
+        let client = reqwest::Client::new();
+        let url = format!("{}/users/me", &self.address);
+        client.get(&url).bearer_auth(bearer).send().await.unwrap()
+    }
+
+    /// Uploads an avatar or cover, `kind` being either "avatar" or "cover"
+    pub async fn upload_profile_image(
+        &self,
+        kind: &str,
+        image: Vec<u8>,
+        bearer: &str,
+    ) -> reqwest::Response {
+        let client = reqwest::Client::new();
+        let url = format!("{}/users/me/{}", &self.address, kind);
+        let form = reqwest::multipart::Form::new().part(
+            "image",
+            reqwest::multipart::Part::bytes(image).file_name("image.png"),
+        );
+        client
+            .put(&url)
+            .bearer_auth(bearer)
+            .multipart(form)
+            .send()
+            .await
+            .unwrap()
+    }
+
+    pub async fn remove_profile_image(&self, kind: &str, bearer: &str) -> reqwest::Response {
+        let client = reqwest::Client::new();
+        let url = format!("{}/users/me/{}", &self.address, kind);
+        client
+            .delete(&url)
+            .bearer_auth(bearer)
+            .send()
+            .await
+            .unwrap()
+    }
+
+    /// Fetches an image by the URL path returned in a user's profile
+    pub async fn get_media(&self, path: &str) -> reqwest::Response {
+        let client = reqwest::Client::new();
+        let url = format!("{}{}", &self.address, path);
+        client.get(&url).send().await.unwrap()
+    }
+
+    pub async fn update_profile(&self, body: serde_json::Value, bearer: &str) -> reqwest::Response {
+        let client = reqwest::Client::new();
+        let url = format!("{}/users/me", &self.address);
+        client
+            .patch(&url)
+            .bearer_auth(bearer)
+            .json(&body)
+            .send()
+            .await
+            .unwrap()
+    }
+
+    pub async fn delete_account(&self, password: &str, bearer: &str) -> reqwest::Response {
+        let client = reqwest::Client::new();
+        let url = format!("{}/users/me", &self.address);
+        client
+            .delete(&url)
+            .bearer_auth(bearer)
+            .json(&serde_json::json!({ "password": password }))
+            .send()
+            .await
+            .unwrap()
+    }
+
+    pub async fn start_export(&self, bearer: &str) -> reqwest::Response {
+        let client = reqwest::Client::new();
+        let url = format!("{}/users/me/export", &self.address);
+        client.post(&url).bearer_auth(bearer).send().await.unwrap()
+    }
+
+    pub async fn get_export(&self, export_id: &str, bearer: &str) -> reqwest::Response {
+        let client = reqwest::Client::new();
+        let url = format!("{}/users/me/export/{}", &self.address, export_id);
+        client.get(&url).bearer_auth(bearer).send().await.unwrap()
+    }
+
+    pub async fn change_password(
+        &self,
+        current_password: &str,
+        new_password: &str,
+        bearer: &str,
+    ) -> reqwest::Response {
+        let client = reqwest::Client::new();
+        let url = format!("{}/users/me/password", &self.address);
+        client
+            .post(&url)
+            .bearer_auth(bearer)
+            .json(&serde_json::json!({
+                "current_password": current_password,
+                "new_password": new_password
+            }))
+            .send()
+            .await
+            .unwrap()
+    }
+
+    pub async fn change_username(&self, username: &str, bearer: &str) -> reqwest::Response {
+        let client = reqwest::Client::new();
+        let url = format!("{}/users/me/username", &self.address);
+        client
+            .post(&url)
+            .bearer_auth(bearer)
+            .json(&serde_json::json!({ "username": username }))
+            .send()
+            .await
+            .unwrap()
+    }
+
+    pub async fn get_user_by_username(&self, username: &str) -> reqwest::Response {
+        // Keep redirects for renamed users instead of following them
+        let client = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .unwrap();
+        let url = format!("{}/users/by-username/{}", &self.address, username);
+        client.get(&url).send().await.unwrap()
+    }
+
+    pub async fn logout(&self, bearer: &str) -> reqwest::Response {
+        let client = reqwest::Client::new();
+        let url = format!("{}/users/logout", &self.address);
+        client.post(&url).bearer_auth(bearer).send().await.unwrap()
+    }
+
+    pub async fn logout_all(&self, bearer: &str) -> reqwest::Response {
+        let client = reqwest::Client::new();
+        let url = format!("{}/users/logout-all", &self.address);
+        client.post(&url).bearer_auth(bearer).send().await.unwrap()
+    }
+
+    pub async fn get_sessions(&self, bearer: &str) -> reqwest::Response {
+        let client = reqwest::Client::new();
+        let url = format!("{}/users/me/sessions", &self.address);
+        client.get(&url).bearer_auth(bearer).send().await.unwrap()
+    }
+
+    pub async fn revoke_session(&self, session_id: &str, bearer: &str) -> reqwest::Response {
+        let client = reqwest::Client::new();
+        let url = format!("{}/users/me/sessions/{}", &self.address, session_id);
+        client
+            .delete(&url)
+            .bearer_auth(bearer)
+            .send()
+            .await
+            .unwrap()
+    }
+
+    pub async fn get_lockout_events(&self, bearer: &str) -> reqwest::Response {
+        let client = reqwest::Client::new();
+        let url = format!("{}/admin/lockouts", &self.address);
+        client.get(&url).bearer_auth(bearer).send().await.unwrap()
+    }
+
+    pub async fn get_user_roles(&self, user_id: &str, bearer: &str) -> reqwest::Response {
+        let client = reqwest::Client::new();
+        let url = format!("{}/admin/users/{}/roles", &self.address, user_id);
+        client.get(&url).bearer_auth(bearer).send().await.unwrap()
+    }
+
+    pub async fn grant_role(&self, user_id: &str, role: &str, bearer: &str) -> reqwest::Response {
+        let client = reqwest::Client::new();
+        let url = format!("{}/admin/users/{}/roles/{}", &self.address, user_id, role);
+        client.put(&url).bearer_auth(bearer).send().await.unwrap()
+    }
+
+    pub async fn revoke_role(&self, user_id: &str, role: &str, bearer: &str) -> reqwest::Response {
+        let client = reqwest::Client::new();
+        let url = format!("{}/admin/users/{}/roles/{}", &self.address, user_id, role);
+        client
+            .delete(&url)
+            .bearer_auth(bearer)
+            .send()
+            .await
+            .unwrap()
+    }
+
+    pub async fn create_api_key(&self, body: serde_json::Value, bearer: &str) -> reqwest::Response {
+        let client = reqwest::Client::new();
+        let url = format!("{}/users/me/api-keys", &self.address);
+        client
+            .post(&url)
+            .bearer_auth(bearer)
+            .json(&body)
+            .send()
+            .await
+            .unwrap()
+    }
+
+    pub async fn get_api_keys(&self, bearer: &str) -> reqwest::Response {
+        let client = reqwest::Client::new();
+        let url = format!("{}/users/me/api-keys", &self.address);
+        client.get(&url).bearer_auth(bearer).send().await.unwrap()
+    }
+
+    pub async fn revoke_api_key(&self, key_id: &str, bearer: &str) -> reqwest::Response {
+        let client = reqwest::Client::new();
+        let url = format!("{}/users/me/api-keys/{}", &self.address, key_id);
+        client
+            .delete(&url)
+            .bearer_auth(bearer)
+            .send()
+            .await
+            .unwrap()
+    }
+
+    pub async fn create_post_with_api_key(
+        &self,
+        body: serde_json::Value,
+        api_key: &str,
+    ) -> reqwest::Response {
+        let client = reqwest::Client::new();
+        let url = format!("{}/users/post", &self.address);
+        client
+            .post(&url)
+            .header("X-Api-Key", api_key)
+            .json(&body)
+            .send()
+            .await
+            .unwrap()
+    }
+
+    pub async fn get_user_feed_with_api_key(&self, api_key: &str) -> reqwest::Response {
+        let client = reqwest::Client::new();
+        let url = format!("{}/feed", &self.address);
+        client
+            .get(&url)
+            .header("X-Api-Key", api_key)
+            .send()
+            .await
+            .unwrap()
+    }
+
     pub async fn create_post(&self, body: serde_json::Value, bearer: &str) -> reqwest::Response {
         let client = reqwest::Client::new();
         let url = format!("{}/users/post", &self.address);
@@ -103,6 +627,83 @@ impl TestApp {
             .unwrap()
     }
 
+    /// Creates the post `test_post_body` makes from `fields` and returns its id
+    pub async fn create_test_post(&self, fields: serde_json::Value, bearer: &str) -> String {
+        let res = self.create_post(test_post_body(fields), bearer).await;
+        assert_eq!(res.status().as_u16(), 201);
+        res.json::<serde_json::Value>().await.unwrap()["post_id"]
+            .as_str()
+            .unwrap()
+            .to_string()
+    }
+
+    pub async fn upload_post_media(&self, image: Vec<u8>, bearer: &str) -> reqwest::Response {
+        let client = reqwest::Client::new();
+        let url = format!("{}/post/media", &self.address);
+        let form = reqwest::multipart::Form::new().part(
+            "image",
+            reqwest::multipart::Part::bytes(image).file_name("image.png"),
+        );
+        client
+            .post(&url)
+            .bearer_auth(bearer)
+            .multipart(form)
+            .send()
+            .await
+            .unwrap()
+    }
+
+    pub async fn get_post(&self, post_id: &str) -> reqwest::Response {
+        let client = reqwest::Client::new();
+        let url = format!("{}/post/{}", &self.address, post_id);
+        client.get(&url).send().await.unwrap()
+    }
+
+    pub async fn update_post(
+        &self,
+        post_id: &str,
+        body: serde_json::Value,
+        bearer: &str,
+    ) -> reqwest::Response {
+        let client = reqwest::Client::new();
+        let url = format!("{}/post/{}", &self.address, post_id);
+        client
+            .patch(&url)
+            .bearer_auth(bearer)
+            .json(&body)
+            .send()
+            .await
+            .unwrap()
+    }
+
+    pub async fn delete_post(&self, post_id: &str, bearer: &str) -> reqwest::Response {
+        let client = reqwest::Client::new();
+        let url = format!("{}/post/{}", &self.address, post_id);
+        client
+            .delete(&url)
+            .bearer_auth(bearer)
+            .send()
+            .await
+            .unwrap()
+    }
+
+    pub async fn remove_post_mention(&self, post_id: &str, bearer: &str) -> reqwest::Response {
+        let client = reqwest::Client::new();
+        let url = format!("{}/post/{}/mentions/me", &self.address, post_id);
+        client
+            .delete(&url)
+            .bearer_auth(bearer)
+            .send()
+            .await
+            .unwrap()
+    }
+
+    pub async fn get_post_history(&self, post_id: &str) -> reqwest::Response {
+        let client = reqwest::Client::new();
+        let url = format!("{}/post/{}/history", &self.address, post_id);
+        client.get(&url).send().await.unwrap()
+    }
+
     pub async fn get_user_posts(&self, user_id: &str, bearer: &str) -> reqwest::Response {
         let client = reqwest::Client::new();
         let url = format!("{}/users/{}/posts", &self.address, user_id);
@@ -121,6 +722,26 @@ impl TestApp {
         client.get(&url).send().await.unwrap()
     }
 
+    pub async fn get_follow_requests(&self, bearer: &str) -> reqwest::Response {
+        let client = reqwest::Client::new();
+        let url = format!("{}/users/me/follow-requests", &self.address);
+        client.get(&url).bearer_auth(bearer).send().await.unwrap()
+    }
+
+    pub async fn answer_follow_request(
+        &self,
+        follower_id: &str,
+        answer: &str,
+        bearer: &str,
+    ) -> reqwest::Response {
+        let client = reqwest::Client::new();
+        let url = format!(
+            "{}/users/me/follow-requests/{}/{}",
+            &self.address, follower_id, answer
+        );
+        client.post(&url).bearer_auth(bearer).send().await.unwrap()
+    }
+
     pub async fn get_following(&self, user_id: &str) -> reqwest::Response {
         let client = reqwest::Client::new();
         let url = format!("{}/users/{}/following", &self.address, user_id);
@@ -147,12 +768,130 @@ impl TestApp {
         client.get(&url).send().await.unwrap()
     }
 
+    pub async fn search_users_as(&self, query: &str, bearer: &str) -> reqwest::Response {
+        let client = reqwest::Client::new();
+        let url = format!("{}/users?query={}", &self.address, query);
+        client.get(&url).bearer_auth(bearer).send().await.unwrap()
+    }
+
+    pub async fn block_user(&self, user_id: &str, bearer: &str) -> reqwest::Response {
+        let client = reqwest::Client::new();
+        let url = format!("{}/users/{}/block", &self.address, user_id);
+        client.post(&url).bearer_auth(bearer).send().await.unwrap()
+    }
+
+    pub async fn unblock_user(&self, user_id: &str, bearer: &str) -> reqwest::Response {
+        let client = reqwest::Client::new();
+        let url = format!("{}/users/{}/block", &self.address, user_id);
+        client
+            .delete(&url)
+            .bearer_auth(bearer)
+            .send()
+            .await
+            .unwrap()
+    }
+
+    pub async fn mute_user(&self, user_id: &str, bearer: &str) -> reqwest::Response {
+        let client = reqwest::Client::new();
+        let url = format!("{}/users/{}/mute", &self.address, user_id);
+        client.post(&url).bearer_auth(bearer).send().await.unwrap()
+    }
+
+    pub async fn unmute_user(&self, user_id: &str, bearer: &str) -> reqwest::Response {
+        let client = reqwest::Client::new();
+        let url = format!("{}/users/{}/mute", &self.address, user_id);
+        client
+            .delete(&url)
+            .bearer_auth(bearer)
+            .send()
+            .await
+            .unwrap()
+    }
+
     pub async fn get_user_feed(&self, token: &str) -> reqwest::Response {
         let client = reqwest::Client::new();
         let url = format!("{}/feed", &self.address,);
         client.get(&url).bearer_auth(token).send().await.unwrap()
     }
 
+    /// `query` is the raw query string, e.g. `page=2&per_page=5`
+    pub async fn get_tag_posts(&self, tag: &str, query: &str, bearer: &str) -> reqwest::Response {
+        let client = reqwest::Client::new();
+        let url = format!("{}/tags/{}/posts?{}", &self.address, tag, query);
+        client.get(&url).bearer_auth(bearer).send().await.unwrap()
+    }
+
+    pub async fn get_nearby_posts(&self, query: &str) -> reqwest::Response {
+        let client = reqwest::Client::new();
+        let url = format!("{}/posts/nearby?{}", &self.address, query);
+        client.get(&url).send().await.unwrap()
+    }
+
+    pub async fn get_posts_within(&self, query: &str) -> reqwest::Response {
+        let client = reqwest::Client::new();
+        let url = format!("{}/posts/within?{}", &self.address, query);
+        client.get(&url).send().await.unwrap()
+    }
+
+    pub async fn create_place(&self, body: serde_json::Value, bearer: &str) -> reqwest::Response {
+        let client = reqwest::Client::new();
+        let url = format!("{}/places", &self.address);
+        client
+            .post(&url)
+            .bearer_auth(bearer)
+            .json(&body)
+            .send()
+            .await
+            .unwrap()
+    }
+
+    pub async fn get_place(&self, place_id: &str) -> reqwest::Response {
+        let client = reqwest::Client::new();
+        let url = format!("{}/places/{}", &self.address, place_id);
+        client.get(&url).send().await.unwrap()
+    }
+
+    pub async fn get_place_posts(&self, place_id: &str, query: &str) -> reqwest::Response {
+        let client = reqwest::Client::new();
+        let url = format!("{}/places/{}/posts?{}", &self.address, place_id, query);
+        client.get(&url).send().await.unwrap()
+    }
+
+    pub async fn search_places(&self, query: &str) -> reqwest::Response {
+        let client = reqwest::Client::new();
+        let url = format!("{}/places/search?{}", &self.address, query);
+        client.get(&url).send().await.unwrap()
+    }
+
+    pub async fn get_trending_tags(&self, query: &str) -> reqwest::Response {
+        let client = reqwest::Client::new();
+        let url = format!("{}/tags/trending?{}", &self.address, query);
+        client.get(&url).send().await.unwrap()
+    }
+
+    pub async fn follow_tag(&self, tag: &str, bearer: &str) -> reqwest::Response {
+        let client = reqwest::Client::new();
+        let url = format!("{}/tags/{}/follow", &self.address, tag);
+        client.post(&url).bearer_auth(bearer).send().await.unwrap()
+    }
+
+    pub async fn unfollow_tag(&self, tag: &str, bearer: &str) -> reqwest::Response {
+        let client = reqwest::Client::new();
+        let url = format!("{}/tags/{}/follow", &self.address, tag);
+        client
+            .delete(&url)
+            .bearer_auth(bearer)
+            .send()
+            .await
+            .unwrap()
+    }
+
+    pub async fn get_followed_tags(&self, bearer: &str) -> reqwest::Response {
+        let client = reqwest::Client::new();
+        let url = format!("{}/users/me/tags", &self.address);
+        client.get(&url).bearer_auth(bearer).send().await.unwrap()
+    }
+
     pub async fn create_comment(
         &self,
         post_id: &str,
@@ -176,6 +915,12 @@ impl TestApp {
         client.get(&url).send().await.unwrap()
     }
 
+    pub async fn get_comments_as(&self, post_id: &str, bearer: &str) -> reqwest::Response {
+        let client = reqwest::Client::new();
+        let url = format!("{}/post/{}/comment", &self.address, post_id);
+        client.get(&url).bearer_auth(bearer).send().await.unwrap()
+    }
+
     pub async fn delete_comment(
         &self,
         post_id: &str,
@@ -222,7 +967,7 @@ impl TestApp {
 
     pub async fn get_user(&self, user_id: &str) -> reqwest::Response {
         let client = reqwest::Client::new();
-        let url = format!("{}/user/{}", &self.address, user_id);
+        let url = format!("{}/users/{}", &self.address, user_id);
         client.get(&url).send().await.unwrap()
     }
 
@@ -234,6 +979,11 @@ impl TestApp {
 }
 
 pub async fn spawn_app() -> TestApp {
+    spawn_app_with(|_| {}).await
+}
+
+/// Spawn the app after letting the test adjust the configuration
+pub async fn spawn_app_with(configure: impl FnOnce(&mut Settings)) -> TestApp {
     Lazy::force(&TRACING);
 
     let configuration = {
@@ -242,10 +992,35 @@ pub async fn spawn_app() -> TestApp {
         c.database.database_name = Uuid::new_v4().to_string();
         // use a random OS port
         c.application.port = 0;
+        // Write emails to a directory owned by this test case
+        c.email.transport = EmailTransport::File {
+            directory: std::env::temp_dir()
+                .join(format!("voyage-atlas-emails-{}", Uuid::new_v4()))
+                .to_string_lossy()
+                .to_string(),
+        };
+        // Keep uploaded images in a directory owned by this test case
+        c.media.storage = BlobStorage::Local {
+            directory: std::env::temp_dir()
+                .join(format!("voyage-atlas-media-{}", Uuid::new_v4()))
+                .to_string_lossy()
+                .to_string(),
+        };
+        // Production hashing costs make every login noticeably slow in a debug build
+        c.auth.password_hashing = PasswordHashingSettings {
+            memory_cost_kib: 4096,
+            iterations: 1,
+            parallelism: 1,
+        };
+        configure(&mut c);
         c
     };
 
     configure_database(&configuration.database).await;
+    let email_directory = match &configuration.email.transport {
+        EmailTransport::File { directory } => PathBuf::from(directory),
+        EmailTransport::Smtp { .. } => PathBuf::new(),
+    };
 
     // Launch the app
     let application = Application::build(configuration.clone())
@@ -253,13 +1028,15 @@ pub async fn spawn_app() -> TestApp {
         .expect("Failed to create app");
     let application_port = application.port();
     let address = format!("http://127.0.0.1:{}", application.port());
-    let _ = tokio::spawn(application.run_until_stopped());
+    tokio::spawn(application.run_until_stopped());
 
     let test_app = TestApp {
         address,
         port: application_port,
         db_pool: get_connection_pool(&configuration.database),
         auth_info: TestAuthInfo::generate(),
+        email_directory,
+        blob_store: get_blob_store(&configuration.media).expect("Failed to create blob store"),
     };
 
     // Create a user
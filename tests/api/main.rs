@@ -1,5 +1,15 @@
+pub mod admin;
+pub mod api_keys;
+pub mod blocks;
 pub mod comments;
+pub mod export;
 pub mod health_check;
 pub mod helpers;
+pub mod media;
+pub mod mentions;
+pub mod nearby;
+pub mod oidc;
+pub mod places;
 pub mod posts;
+pub mod tags;
 pub mod users;
@@ -0,0 +1,302 @@
+use serde_json::{json, Value};
+use voyage_atlas_api::api::models::{AuthUser, Post, PostMedia, ProfileImage, PublicUser};
+
+use crate::helpers::{png_image, spawn_app, spawn_app_with, test_post_body, TestApp, TestAuthInfo};
+
+/// Dimensions of the JPEG served at `path`
+async fn image_dimensions(test_app: &TestApp, path: &str) -> (u32, u32) {
+    let res = test_app.get_media(path).await;
+    assert_eq!(res.status().as_u16(), 200);
+    assert_eq!(res.headers()["content-type"], "image/jpeg");
+    let bytes = res.bytes().await.unwrap();
+    let image = image::load_from_memory_with_format(&bytes, image::ImageFormat::Jpeg).unwrap();
+    (image.width(), image.height())
+}
+
+#[tokio::test]
+async fn test_avatar_is_resized_and_shown_on_the_profile() {
+    let test_app = spawn_app().await;
+
+    let res = test_app
+        .upload_profile_image("avatar", png_image(800, 600), &test_app.auth_info.bearer)
+        .await;
+    assert_eq!(res.status().as_u16(), 200);
+    let me = res.json::<AuthUser>().await.unwrap();
+    let avatar = me.avatar.unwrap();
+    assert!(me.cover.is_none());
+
+    assert_eq!(image_dimensions(&test_app, &avatar.small).await, (64, 64));
+    assert_eq!(
+        image_dimensions(&test_app, &avatar.medium).await,
+        (256, 256)
+    );
+    assert_eq!(image_dimensions(&test_app, &avatar.large).await, (512, 512));
+
+    let res = test_app.get_user(&test_app.auth_info.user.id).await;
+    let user = res.json::<PublicUser>().await.unwrap();
+    assert_eq!(user.avatar, Some(avatar));
+}
+
+#[tokio::test]
+async fn test_cover_is_resized_to_banner_variants() {
+    let test_app = spawn_app().await;
+
+    let res = test_app
+        .upload_profile_image("cover", png_image(400, 400), &test_app.auth_info.bearer)
+        .await;
+    assert_eq!(res.status().as_u16(), 200);
+    let cover = res.json::<AuthUser>().await.unwrap().cover.unwrap();
+
+    assert_eq!(image_dimensions(&test_app, &cover.small).await, (600, 200));
+    assert_eq!(
+        image_dimensions(&test_app, &cover.medium).await,
+        (1200, 400)
+    );
+    assert_eq!(image_dimensions(&test_app, &cover.large).await, (1500, 500));
+}
+
+#[tokio::test]
+async fn test_served_images_are_cached_forever() {
+    let test_app = spawn_app().await;
+    let res = test_app
+        .upload_profile_image("avatar", png_image(100, 100), &test_app.auth_info.bearer)
+        .await;
+    let avatar = res.json::<AuthUser>().await.unwrap().avatar.unwrap();
+
+    let res = test_app.get_media(&avatar.small).await;
+
+    assert_eq!(
+        res.headers()["cache-control"],
+        "public, max-age=31536000, immutable"
+    );
+}
+
+#[tokio::test]
+async fn test_uploads_that_are_not_images_are_rejected() {
+    let test_app = spawn_app().await;
+
+    let uploads = [
+        b"definitely not an image".to_vec(),
+        // A GIF header, which is an image but not a supported one
+        b"GIF89a\x01\x00\x01\x00\x00\x00\x00;".to_vec(),
+        // A PNG signature followed by garbage
+        b"\x89PNG\r\n\x1a\nnot really a png".to_vec(),
+    ];
+    for upload in uploads {
+        let res = test_app
+            .upload_profile_image("avatar", upload, &test_app.auth_info.bearer)
+            .await;
+        assert_eq!(res.status().as_u16(), 400);
+    }
+
+    let res = test_app.get_me(&test_app.auth_info.bearer).await;
+    assert!(res.json::<AuthUser>().await.unwrap().avatar.is_none());
+}
+
+#[tokio::test]
+async fn test_uploads_over_the_size_limit_are_rejected() {
+    let test_app = spawn_app_with(|c| c.media.max_upload_bytes = 64).await;
+
+    let res = test_app
+        .upload_profile_image("avatar", png_image(800, 600), &test_app.auth_info.bearer)
+        .await;
+
+    assert_eq!(res.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn test_uploading_requires_authentication() {
+    let test_app = spawn_app().await;
+
+    let res = test_app
+        .upload_profile_image("avatar", png_image(100, 100), "not-a-token")
+        .await;
+
+    assert_eq!(res.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn test_replacing_an_avatar_deletes_the_previous_one() {
+    let test_app = spawn_app().await;
+    let res = test_app
+        .upload_profile_image("avatar", png_image(100, 100), &test_app.auth_info.bearer)
+        .await;
+    let previous = res.json::<AuthUser>().await.unwrap().avatar.unwrap();
+
+    let res = test_app
+        .upload_profile_image("avatar", png_image(200, 200), &test_app.auth_info.bearer)
+        .await;
+    let current = res.json::<AuthUser>().await.unwrap().avatar.unwrap();
+
+    assert_ne!(previous, current);
+    for path in [&previous.small, &previous.medium, &previous.large] {
+        assert_eq!(test_app.get_media(path).await.status().as_u16(), 404);
+    }
+    assert_eq!(
+        test_app.get_media(&current.small).await.status().as_u16(),
+        200
+    );
+}
+
+#[tokio::test]
+async fn test_removing_an_avatar_clears_it() {
+    let test_app = spawn_app().await;
+    let res = test_app
+        .upload_profile_image("avatar", png_image(100, 100), &test_app.auth_info.bearer)
+        .await;
+    let avatar: ProfileImage = res.json::<AuthUser>().await.unwrap().avatar.unwrap();
+
+    let res = test_app
+        .remove_profile_image("avatar", &test_app.auth_info.bearer)
+        .await;
+
+    assert_eq!(res.status().as_u16(), 200);
+    assert!(res.json::<AuthUser>().await.unwrap().avatar.is_none());
+    assert_eq!(
+        test_app.get_media(&avatar.small).await.status().as_u16(),
+        404
+    );
+}
+
+#[tokio::test]
+async fn test_unknown_media_paths_are_not_found() {
+    let test_app = spawn_app().await;
+
+    let paths = [
+        format!("/media/avatars/{}/small.jpg", uuid::Uuid::new_v4()),
+        format!("/media/avatars/{}/huge.jpg", uuid::Uuid::new_v4()),
+        format!("/media/banners/{}/small.jpg", uuid::Uuid::new_v4()),
+        "/media/avatars/not-an-id/small.jpg".to_string(),
+    ];
+    for path in paths {
+        assert_eq!(test_app.get_media(&path).await.status().as_u16(), 404);
+    }
+}
+
+async fn upload_post_media(test_app: &TestApp, width: u32, height: u32, bearer: &str) -> PostMedia {
+    let res = test_app
+        .upload_post_media(png_image(width, height), bearer)
+        .await;
+    assert_eq!(res.status().as_u16(), 201);
+    res.json::<PostMedia>().await.unwrap()
+}
+
+#[tokio::test]
+async fn test_photos_are_attached_to_posts_in_order() {
+    let test_app = spawn_app().await;
+    let follower = TestAuthInfo::generate();
+    follower.store(&test_app.db_pool).await;
+    test_app
+        .follow_user(&test_app.auth_info.user.id, &follower.bearer)
+        .await;
+    let first = upload_post_media(&test_app, 800, 600, &test_app.auth_info.bearer).await;
+    let second = upload_post_media(&test_app, 300, 500, &test_app.auth_info.bearer).await;
+    assert_eq!((first.width, first.height), (800, 600));
+
+    test_app
+        .create_test_post(
+            json!({ "media": [
+                { "id": second.id, "alt_text": "A lighthouse" },
+                { "id": first.id }
+            ] }),
+            &test_app.auth_info.bearer,
+        )
+        .await;
+
+    let res = test_app.get_user_feed(&follower.bearer).await;
+    let feed = res.json::<Vec<Post>>().await.unwrap();
+    let media = &feed[0].media;
+    assert_eq!(media.len(), 2);
+    assert_eq!(media[0].id, second.id);
+    assert_eq!(media[0].alt_text.as_deref(), Some("A lighthouse"));
+    assert_eq!(media[1].id, first.id);
+    assert_eq!(media[1].alt_text, None);
+
+    let res = test_app
+        .get_user_posts(&test_app.auth_info.user.id, &test_app.auth_info.bearer)
+        .await;
+    let posts = res.json::<Vec<Post>>().await.unwrap();
+    assert_eq!(posts[0].media, feed[0].media);
+
+    assert_eq!(image_dimensions(&test_app, &media[0].url).await, (300, 500));
+    assert_eq!(
+        image_dimensions(&test_app, &media[0].thumbnail_url).await,
+        (320, 320)
+    );
+}
+
+#[tokio::test]
+async fn test_only_your_own_unattached_photos_can_be_attached() {
+    let test_app = spawn_app().await;
+    let other_user = TestAuthInfo::generate();
+    other_user.store(&test_app.db_pool).await;
+    let theirs = upload_post_media(&test_app, 100, 100, &other_user.bearer).await;
+    let mine = upload_post_media(&test_app, 100, 100, &test_app.auth_info.bearer).await;
+    test_app
+        .create_test_post(
+            json!({ "media": [{ "id": mine.id }] }),
+            &test_app.auth_info.bearer,
+        )
+        .await;
+
+    let attachments = [
+        json!([{ "id": theirs.id }]),
+        json!([{ "id": mine.id }]),
+        json!([{ "id": uuid::Uuid::new_v4() }]),
+        json!([{ "id": "not-an-id" }]),
+    ];
+    for media in attachments {
+        let res = test_app
+            .create_post(
+                test_post_body(json!({ "media": media })),
+                &test_app.auth_info.bearer,
+            )
+            .await;
+        assert_eq!(res.status().as_u16(), 400);
+    }
+
+    // None of the rejected posts were created
+    let res = test_app
+        .get_user_posts(&test_app.auth_info.user.id, &test_app.auth_info.bearer)
+        .await;
+    assert_eq!(res.json::<Vec<Post>>().await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_posts_have_at_most_ten_photos() {
+    let test_app = spawn_app().await;
+    let media = (0..11)
+        .map(|_| json!({ "id": uuid::Uuid::new_v4() }))
+        .collect::<Vec<Value>>();
+
+    let res = test_app
+        .create_post(
+            test_post_body(json!({ "media": media })),
+            &test_app.auth_info.bearer,
+        )
+        .await;
+
+    assert_eq!(res.status().as_u16(), 400);
+    let error = res.json::<Value>().await.unwrap();
+    assert_eq!(error["error"], "Invalid fields: media");
+}
+
+#[tokio::test]
+async fn test_deleting_a_post_deletes_its_photos() {
+    let test_app = spawn_app().await;
+    let media = upload_post_media(&test_app, 100, 100, &test_app.auth_info.bearer).await;
+    let post_id = test_app
+        .create_test_post(
+            json!({ "media": [{ "id": media.id }] }),
+            &test_app.auth_info.bearer,
+        )
+        .await;
+
+    test_app
+        .delete_post(&post_id, &test_app.auth_info.bearer)
+        .await;
+
+    for path in [&media.url, &media.thumbnail_url] {
+        assert_eq!(test_app.get_media(path).await.status().as_u16(), 404);
+    }
+}
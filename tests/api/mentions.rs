@@ -0,0 +1,150 @@
+use serde_json::json;
+use voyage_atlas_api::api::models::{Comment, CreateComment, Mention, Post};
+
+use crate::helpers::{spawn_app, TestApp, TestAuthInfo};
+
+async fn post_mentions(test_app: &TestApp, post_id: &str) -> Vec<Mention> {
+    let res = test_app.get_post(post_id).await;
+    assert_eq!(res.status().as_u16(), 200);
+    res.json::<Post>().await.unwrap().mentions
+}
+
+async fn stored_user(test_app: &TestApp) -> TestAuthInfo {
+    let user = TestAuthInfo::generate();
+    user.store(&test_app.db_pool).await;
+    user
+}
+
+#[tokio::test]
+async fn test_mentions_in_posts_are_resolved_with_their_spans() {
+    let test_app = spawn_app().await;
+    let friend = stored_user(&test_app).await;
+    let username = &friend.user.username;
+    let content = format!(
+        "Hiking with @{}! Also @nobody, mail me@example.com",
+        username
+    );
+
+    let post_id = test_app
+        .create_test_post(json!({ "content": &content }), &test_app.auth_info.bearer)
+        .await;
+
+    let mentions = post_mentions(&test_app, &post_id).await;
+    assert_eq!(
+        mentions,
+        [Mention {
+            user_id: friend.user.id.clone(),
+            username: username.clone(),
+            start: 12,
+            end: 13 + username.chars().count() as u32,
+        }]
+    );
+    let mentioned = content
+        .chars()
+        .skip(mentions[0].start as usize)
+        .take((mentions[0].end - mentions[0].start) as usize)
+        .collect::<String>();
+    assert_eq!(mentioned, format!("@{}", username));
+}
+
+#[tokio::test]
+async fn test_mentions_ignore_the_case_of_the_username() {
+    let test_app = spawn_app().await;
+    let friend = stored_user(&test_app).await;
+    let content = format!("Hiking with @{}", friend.user.username.to_uppercase());
+
+    let post_id = test_app
+        .create_test_post(json!({ "content": &content }), &test_app.auth_info.bearer)
+        .await;
+
+    let mentions = post_mentions(&test_app, &post_id).await;
+    assert_eq!(mentions.len(), 1);
+    assert_eq!(mentions[0].user_id, friend.user.id);
+    assert_eq!(mentions[0].username, friend.user.username);
+}
+
+#[tokio::test]
+async fn test_mentions_in_comments_are_resolved() {
+    let test_app = spawn_app().await;
+    let friend = stored_user(&test_app).await;
+    let post_id = test_app
+        .create_test_post(
+            json!({ "content": "A day out" }),
+            &test_app.auth_info.bearer,
+        )
+        .await;
+
+    let comment = CreateComment {
+        comment: format!("Look @{}.", friend.user.username),
+    };
+    let res = test_app
+        .create_comment(&post_id, comment, &test_app.auth_info.bearer)
+        .await;
+    assert_eq!(res.status().as_u16(), 201);
+
+    let res = test_app.get_comments(&post_id).await;
+    let comments = res.json::<Vec<Comment>>().await.unwrap();
+    let mentions = &comments[0].mentions;
+    assert_eq!(mentions.len(), 1);
+    assert_eq!(mentions[0].user_id, friend.user.id);
+    // The full stop ends the sentence, it isn't part of the username
+    assert_eq!(
+        (mentions[0].start, mentions[0].end),
+        (5, 6 + friend.user.username.chars().count() as u32)
+    );
+}
+
+#[tokio::test]
+async fn test_a_tagged_user_can_remove_themselves_for_good() {
+    let test_app = spawn_app().await;
+    let friend = stored_user(&test_app).await;
+    let other_friend = stored_user(&test_app).await;
+    let content = format!(
+        "With @{} and @{}",
+        friend.user.username, other_friend.user.username
+    );
+    let post_id = test_app
+        .create_test_post(json!({ "content": &content }), &test_app.auth_info.bearer)
+        .await;
+    assert_eq!(post_mentions(&test_app, &post_id).await.len(), 2);
+
+    let res = test_app.remove_post_mention(&post_id, &friend.bearer).await;
+    assert_eq!(res.status().as_u16(), 204);
+    let mentions = post_mentions(&test_app, &post_id).await;
+    assert_eq!(mentions.len(), 1);
+    assert_eq!(mentions[0].user_id, other_friend.user.id);
+
+    // Editing the post doesn't tag them again
+    let res = test_app
+        .update_post(
+            &post_id,
+            json!({ "content": format!("{}!", content) }),
+            &test_app.auth_info.bearer,
+        )
+        .await;
+    assert_eq!(res.status().as_u16(), 200);
+    let mentions = res.json::<Post>().await.unwrap().mentions;
+    assert_eq!(mentions.len(), 1);
+    assert_eq!(mentions[0].user_id, other_friend.user.id);
+
+    let res = test_app.remove_post_mention(&post_id, &friend.bearer).await;
+    assert_eq!(res.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn test_users_who_blocked_the_author_are_not_mentioned() {
+    let test_app = spawn_app().await;
+    let blocker = stored_user(&test_app).await;
+    test_app
+        .block_user(&test_app.auth_info.user.id, &blocker.bearer)
+        .await;
+
+    let post_id = test_app
+        .create_test_post(
+            json!({ "content": &format!("Hi @{}", blocker.user.username) }),
+            &test_app.auth_info.bearer,
+        )
+        .await;
+
+    assert!(post_mentions(&test_app, &post_id).await.is_empty());
+}
@@ -0,0 +1,193 @@
+use serde_json::{json, Value};
+use voyage_atlas_api::api::models::{NearbyPost, Page, Post};
+
+use crate::helpers::{spawn_app, TestApp};
+
+async fn posts_within(test_app: &TestApp, query: &str) -> Vec<String> {
+    let res = test_app.get_posts_within(query).await;
+    assert_eq!(res.status().as_u16(), 200);
+    res.json::<Page<Post>>()
+        .await
+        .unwrap()
+        .items
+        .into_iter()
+        .map(|post| post.id)
+        .collect()
+}
+
+#[tokio::test]
+async fn test_nearby_posts_are_ordered_by_distance() {
+    let test_app = spawn_app().await;
+    let sintra = test_app
+        .create_test_post(
+            json!({ "location": "Sintra", "latitude": 38.8029, "longitude": -9.3817 }),
+            &test_app.auth_info.bearer,
+        )
+        .await;
+    let lisbon = test_app
+        .create_test_post(
+            json!({ "location": "Lisbon", "latitude": 38.7223, "longitude": -9.1393 }),
+            &test_app.auth_info.bearer,
+        )
+        .await;
+    test_app
+        .create_test_post(
+            json!({ "location": "Porto", "latitude": 41.1579, "longitude": -8.6291 }),
+            &test_app.auth_info.bearer,
+        )
+        .await;
+    test_app
+        .create_post(
+            json!({ "title": "Somewhere", "location": "Lisbon", "content": "content" }),
+            &test_app.auth_info.bearer,
+        )
+        .await;
+
+    let res = test_app
+        .get_nearby_posts("lat=38.7223&lon=-9.1393&radius_km=50")
+        .await;
+    assert_eq!(res.status().as_u16(), 200);
+    let nearby = res.json::<Page<NearbyPost>>().await.unwrap().items;
+
+    let ids = nearby
+        .iter()
+        .map(|nearby| nearby.post.id.as_str())
+        .collect::<Vec<&str>>();
+    assert_eq!(ids, [lisbon.as_str(), sintra.as_str()]);
+    assert!(nearby[0].distance_km < 0.01);
+    assert!((nearby[1].distance_km - 22.5).abs() < 1.0);
+    assert_eq!(nearby[1].post.latitude, Some(38.8029));
+}
+
+#[tokio::test]
+async fn test_nearby_search_crosses_the_antimeridian() {
+    let test_app = spawn_app().await;
+    let post_id = test_app
+        .create_test_post(
+            json!({ "location": "Taveuni", "latitude": -16.8, "longitude": 179.95 }),
+            &test_app.auth_info.bearer,
+        )
+        .await;
+
+    let res = test_app
+        .get_nearby_posts("lat=-16.8&lon=-179.95&radius_km=20")
+        .await;
+    let nearby = res.json::<Page<NearbyPost>>().await.unwrap().items;
+
+    assert_eq!(nearby.len(), 1);
+    assert_eq!(nearby[0].post.id, post_id);
+}
+
+#[tokio::test]
+async fn test_nearby_search_is_validated() {
+    let test_app = spawn_app().await;
+
+    for query in [
+        "lat=91&lon=0",
+        "lat=0&lon=-181",
+        "lat=0&lon=0&radius_km=0",
+        "lat=0&lon=0&radius_km=10000",
+        "lon=0",
+    ] {
+        let res = test_app.get_nearby_posts(query).await;
+        assert_eq!(res.status().as_u16(), 400, "{}", query);
+    }
+}
+
+#[tokio::test]
+async fn test_post_coordinates_are_validated() {
+    let test_app = spawn_app().await;
+    let post = |fields: Value| {
+        let mut post =
+            json!({ "title": "My first post", "location": "Lisbon", "content": "content" });
+        post.as_object_mut()
+            .unwrap()
+            .extend(fields.as_object().unwrap().clone());
+        post
+    };
+
+    for fields in [
+        json!({ "latitude": 90.5, "longitude": 0.0 }),
+        json!({ "latitude": 0.0, "longitude": 180.5 }),
+        json!({ "latitude": 38.7 }),
+        json!({ "country_code": "PRT" }),
+    ] {
+        let res = test_app
+            .create_post(post(fields.clone()), &test_app.auth_info.bearer)
+            .await;
+        assert_eq!(res.status().as_u16(), 400, "{}", fields);
+    }
+
+    let res = test_app
+        .create_post(
+            post(json!({ "latitude": 38.7, "longitude": -9.1, "country_code": "pt" })),
+            &test_app.auth_info.bearer,
+        )
+        .await;
+    assert_eq!(res.status().as_u16(), 201);
+    let post_id = res.json::<Value>().await.unwrap()["post_id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let post = test_app
+        .get_post(&post_id)
+        .await
+        .json::<Post>()
+        .await
+        .unwrap();
+    assert_eq!(post.country_code.as_deref(), Some("PT"));
+}
+
+#[tokio::test]
+async fn test_posts_within_a_map_viewport() {
+    let test_app = spawn_app().await;
+    let lisbon = test_app
+        .create_test_post(
+            json!({ "location": "Lisbon", "latitude": 38.7223, "longitude": -9.1393 }),
+            &test_app.auth_info.bearer,
+        )
+        .await;
+    let porto = test_app
+        .create_test_post(
+            json!({ "location": "Porto", "latitude": 41.1579, "longitude": -8.6291 }),
+            &test_app.auth_info.bearer,
+        )
+        .await;
+    test_app
+        .create_test_post(
+            json!({ "location": "Madrid", "latitude": 40.4168, "longitude": -3.7038 }),
+            &test_app.auth_info.bearer,
+        )
+        .await;
+    let fiji = test_app
+        .create_test_post(
+            json!({ "location": "Fiji", "latitude": -17.7, "longitude": 178.0 }),
+            &test_app.auth_info.bearer,
+        )
+        .await;
+    let samoa = test_app
+        .create_test_post(
+            json!({ "location": "Samoa", "latitude": -13.8, "longitude": -172.1 }),
+            &test_app.auth_info.bearer,
+        )
+        .await;
+
+    assert_eq!(
+        posts_within(&test_app, "min_lat=36&max_lat=42.5&min_lon=-10&max_lon=-6").await,
+        [porto.as_str(), lisbon.as_str()]
+    );
+    // A viewport across the antimeridian
+    assert_eq!(
+        posts_within(
+            &test_app,
+            "min_lat=-20&max_lat=-10&min_lon=170&max_lon=-170"
+        )
+        .await,
+        [samoa.as_str(), fiji.as_str()]
+    );
+
+    let res = test_app
+        .get_posts_within("min_lat=42&max_lat=36&min_lon=-10&max_lon=-6")
+        .await;
+    assert_eq!(res.status().as_u16(), 400);
+}
@@ -0,0 +1,280 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use jsonwebtoken::{EncodingKey, Header};
+use secrecy::Secret;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use voyage_atlas_api::api::{configuration::OidcProviderSettings, models::AuthInfo};
+use wiremock::{
+    matchers::{method, path},
+    Mock, MockServer, ResponseTemplate,
+};
+
+use crate::helpers::{spawn_app_with, TestApp};
+
+const CLIENT_ID: &str = "voyage-atlas";
+
+/// An identity provider serving discovery and JWKS documents, signing ID tokens with
+/// the key in `fixtures/`
+async fn spawn_stub_issuer() -> MockServer {
+    let issuer = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/.well-known/openid-configuration"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "issuer": issuer.uri(),
+            "authorization_endpoint": format!("{}/authorize", issuer.uri()),
+            "token_endpoint": format!("{}/token", issuer.uri()),
+            "jwks_uri": format!("{}/jwks", issuer.uri()),
+        })))
+        .mount(&issuer)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/jwks"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_raw(include_str!("fixtures/oidc_jwks.json"), "application/json"),
+        )
+        .mount(&issuer)
+        .await;
+    issuer
+}
+
+async fn spawn_app_with_issuer(issuer: &MockServer) -> TestApp {
+    let issuer_url = issuer.uri();
+    spawn_app_with(|c| {
+        c.auth.oidc_providers = vec![OidcProviderSettings {
+            name: "stub".to_string(),
+            issuer_url,
+            client_id: CLIENT_ID.to_string(),
+            client_secret: Secret::new("client-secret".to_string()),
+            redirect_url: "http://localhost/users/oidc/stub/callback".to_string(),
+        }]
+    })
+    .await
+}
+
+struct Authorization {
+    state: String,
+    nonce: String,
+    code_challenge: String,
+}
+
+async fn authorize(test_app: &TestApp, issuer: &MockServer) -> Authorization {
+    let res = test_app.start_oidc_login("stub").await;
+    assert_eq!(res.status().as_u16(), 302);
+    let location =
+        reqwest::Url::parse(res.headers().get("Location").unwrap().to_str().unwrap()).unwrap();
+    assert!(location
+        .as_str()
+        .starts_with(&format!("{}/authorize", issuer.uri())));
+
+    let param = |name: &str| {
+        location
+            .query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.to_string())
+            .unwrap()
+    };
+    assert_eq!(param("client_id"), CLIENT_ID);
+    assert_eq!(param("code_challenge_method"), "S256");
+    Authorization {
+        state: param("state"),
+        nonce: param("nonce"),
+        code_challenge: param("code_challenge"),
+    }
+}
+
+/// Answers the next code exchange with an ID token carrying the given claims
+async fn mount_id_token(issuer: &MockServer, claims: Value) {
+    let mut header = Header::new(jsonwebtoken::Algorithm::RS256);
+    header.kid = Some("test-key".to_string());
+    let mut claims = claims;
+    claims["iss"] = json!(issuer.uri());
+    claims["aud"] = json!(CLIENT_ID);
+    claims["exp"] = json!(chrono::Utc::now().timestamp() + 300);
+    let id_token = jsonwebtoken::encode(
+        &header,
+        &claims,
+        &EncodingKey::from_rsa_pem(include_bytes!("fixtures/oidc_private_key.pem")).unwrap(),
+    )
+    .unwrap();
+
+    Mock::given(method("POST"))
+        .and(path("/token"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "access_token": "access-token",
+            "token_type": "Bearer",
+            "id_token": id_token,
+        })))
+        .up_to_n_times(1)
+        .mount(issuer)
+        .await;
+}
+
+async fn login(
+    test_app: &TestApp,
+    issuer: &MockServer,
+    sub: &str,
+    email: &str,
+    email_verified: bool,
+) -> reqwest::Response {
+    let authorization = authorize(test_app, issuer).await;
+    mount_id_token(
+        issuer,
+        json!({
+            "sub": sub,
+            "nonce": authorization.nonce,
+            "email": email,
+            "email_verified": email_verified,
+            "given_name": "Jane",
+            "family_name": "Doe",
+        }),
+    )
+    .await;
+    test_app
+        .finish_oidc_login("stub", &authorization.state, "authorization-code")
+        .await
+}
+
+#[tokio::test]
+async fn test_oidc_login_creates_account_on_first_login() {
+    let issuer = spawn_stub_issuer().await;
+    let test_app = spawn_app_with_issuer(&issuer).await;
+
+    let authorization = authorize(&test_app, &issuer).await;
+    mount_id_token(
+        &issuer,
+        json!({
+            "sub": "external-user",
+            "nonce": authorization.nonce,
+            "email": "Jane@Example.com",
+            "email_verified": true,
+            "name": "Jane Doe",
+        }),
+    )
+    .await;
+    let res = test_app
+        .finish_oidc_login("stub", &authorization.state, "authorization-code")
+        .await;
+    assert_eq!(res.status().as_u16(), 200);
+    let auth_info = res.json::<AuthInfo>().await.unwrap();
+    assert_eq!(auth_info.user.email, "jane@example.com");
+    assert_eq!(auth_info.user.name, "Jane Doe");
+    let res = test_app.get_sessions(&auth_info.bearer).await;
+    assert_eq!(res.status().as_u16(), 200);
+
+    // The code was exchanged with the verifier behind the challenge
+    let requests = issuer.received_requests().await.unwrap();
+    let token_request = requests
+        .iter()
+        .find(|request| request.url.path() == "/token")
+        .unwrap();
+    let form = reqwest::Url::parse(&format!(
+        "http://localhost/?{}",
+        String::from_utf8_lossy(&token_request.body)
+    ))
+    .unwrap();
+    let code_verifier = form
+        .query_pairs()
+        .find(|(key, _)| key == "code_verifier")
+        .unwrap()
+        .1
+        .to_string();
+    assert_eq!(
+        URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes())),
+        authorization.code_challenge
+    );
+
+    let user = sqlx::query!(
+        "SELECT email_verified_at FROM users WHERE id = $1::text::uuid",
+        auth_info.user.id
+    )
+    .fetch_one(&test_app.db_pool)
+    .await
+    .unwrap();
+    assert!(user.email_verified_at.is_some());
+
+    // Signing in again finds the same account
+    let res = login(
+        &test_app,
+        &issuer,
+        "external-user",
+        "jane@example.com",
+        true,
+    )
+    .await;
+    assert_eq!(res.status().as_u16(), 200);
+    let second_login = res.json::<AuthInfo>().await.unwrap();
+    assert_eq!(second_login.user.id, auth_info.user.id);
+}
+
+#[tokio::test]
+async fn test_oidc_login_links_existing_account_with_verified_email() {
+    let issuer = spawn_stub_issuer().await;
+    let test_app = spawn_app_with_issuer(&issuer).await;
+    let email = test_app.auth_info.user.email.clone();
+
+    let res = login(&test_app, &issuer, "external-user", &email, true).await;
+    assert_eq!(res.status().as_u16(), 200);
+    let auth_info = res.json::<AuthInfo>().await.unwrap();
+    assert_eq!(auth_info.user.id, test_app.auth_info.user.id);
+
+    let identities = sqlx::query!("SELECT provider, subject FROM user_identities")
+        .fetch_all(&test_app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(identities.len(), 1);
+    assert_eq!(identities[0].provider, "stub");
+    assert_eq!(identities[0].subject, "external-user");
+}
+
+#[tokio::test]
+async fn test_oidc_login_fails_unverified_email_of_existing_account() {
+    let issuer = spawn_stub_issuer().await;
+    let test_app = spawn_app_with_issuer(&issuer).await;
+    let email = test_app.auth_info.user.email.clone();
+
+    let res = login(&test_app, &issuer, "external-user", &email, false).await;
+    assert_eq!(res.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn test_oidc_login_fails_invalid_state_or_nonce() {
+    let issuer = spawn_stub_issuer().await;
+    let test_app = spawn_app_with_issuer(&issuer).await;
+
+    let res = test_app
+        .finish_oidc_login("stub", "unknown-state", "authorization-code")
+        .await;
+    assert_eq!(res.status().as_u16(), 400);
+
+    let authorization = authorize(&test_app, &issuer).await;
+    mount_id_token(
+        &issuer,
+        json!({
+            "sub": "external-user",
+            "nonce": "some-other-nonce",
+            "email": "jane@example.com",
+            "email_verified": true,
+        }),
+    )
+    .await;
+    let res = test_app
+        .finish_oidc_login("stub", &authorization.state, "authorization-code")
+        .await;
+    assert_eq!(res.status().as_u16(), 401);
+
+    // Each state can only be used once
+    let res = test_app
+        .finish_oidc_login("stub", &authorization.state, "authorization-code")
+        .await;
+    assert_eq!(res.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn test_oidc_login_fails_unknown_provider() {
+    let issuer = spawn_stub_issuer().await;
+    let test_app = spawn_app_with_issuer(&issuer).await;
+
+    let res = test_app.start_oidc_login("unknown").await;
+    assert_eq!(res.status().as_u16(), 404);
+}
@@ -0,0 +1,255 @@
+use serde_json::json;
+use voyage_atlas_api::api::models::{Page, Place, PlaceCategory, PlaceDetails, Post};
+
+use crate::helpers::{spawn_app, TestApp, TestAuthInfo};
+
+/// The place the post was filed under
+async fn place_of(test_app: &TestApp, post_id: &str) -> String {
+    let res = test_app.get_post(post_id).await;
+    res.json::<Post>().await.unwrap().place_id.unwrap()
+}
+
+async fn search_names(test_app: &TestApp, query: &str) -> Vec<String> {
+    let res = test_app.search_places(query).await;
+    assert_eq!(res.status().as_u16(), 200);
+    res.json::<Vec<Place>>()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|place| place.name)
+        .collect()
+}
+
+#[tokio::test]
+async fn test_posts_from_the_same_location_share_a_place() {
+    let test_app = spawn_app().await;
+    let bearer = &test_app.auth_info.bearer;
+    let first = test_app
+        .create_test_post(json!({ "location": "Lisbon,  Portugal" }), bearer)
+        .await;
+    let second = test_app
+        .create_test_post(json!({ "location": " lisbon, portugal" }), bearer)
+        .await;
+    let other = test_app
+        .create_test_post(json!({ "location": "Porto" }), bearer)
+        .await;
+
+    let place_id = place_of(&test_app, &first).await;
+    assert_eq!(place_of(&test_app, &second).await, place_id);
+    assert_ne!(place_of(&test_app, &other).await, place_id);
+
+    let res = test_app.get_place(&place_id).await;
+    assert_eq!(res.status().as_u16(), 200);
+    let place = res.json::<PlaceDetails>().await.unwrap();
+    // The name is kept as it was first written, apart from the extra space
+    assert_eq!(place.place.name, "Lisbon, Portugal");
+    assert_eq!(place.place.num_posts, 2);
+    assert_eq!(place.place.category, None);
+    let recent = place
+        .recent_posts
+        .iter()
+        .map(|post| post.id.as_str())
+        .collect::<Vec<&str>>();
+    assert_eq!(recent, [second.as_str(), first.as_str()]);
+}
+
+#[tokio::test]
+async fn test_place_page_shows_visitors_and_top_contributors() {
+    let test_app = spawn_app().await;
+    let regular = TestAuthInfo::generate();
+    regular.store(&test_app.db_pool).await;
+    let post_id = test_app
+        .create_test_post(json!({ "location": "Sintra" }), &test_app.auth_info.bearer)
+        .await;
+    let place_id = place_of(&test_app, &post_id).await;
+    for _ in 0..2 {
+        test_app
+            .create_test_post(json!({ "location": "Sintra" }), &regular.bearer)
+            .await;
+    }
+
+    let res = test_app.get_place(&place_id).await;
+    let place = res.json::<PlaceDetails>().await.unwrap();
+    assert_eq!(place.num_visitors, 2);
+    assert_eq!(place.place.num_posts, 3);
+    let contributors = place
+        .top_contributors
+        .iter()
+        .map(|contributor| (contributor.user.id.as_str(), contributor.num_posts))
+        .collect::<Vec<(&str, u32)>>();
+    assert_eq!(
+        contributors,
+        [
+            (regular.user.id.as_str(), 2),
+            (test_app.auth_info.user.id.as_str(), 1)
+        ]
+    );
+
+    let res = test_app.get_place_posts(&place_id, "per_page=2").await;
+    assert_eq!(res.status().as_u16(), 200);
+    let page = res.json::<Page<Post>>().await.unwrap();
+    assert_eq!(page.items.len(), 2);
+    assert!(page.has_more);
+
+    let res = test_app
+        .get_place("00000000-0000-0000-0000-000000000000")
+        .await;
+    assert_eq!(res.status().as_u16(), 404);
+    let res = test_app.get_place("not-a-uuid").await;
+    assert_eq!(res.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn test_private_accounts_are_left_out_of_a_place_page() {
+    let test_app = spawn_app().await;
+    let regular = TestAuthInfo::generate();
+    regular.store(&test_app.db_pool).await;
+    let res = test_app
+        .update_profile(json!({ "is_private": true }), &test_app.auth_info.bearer)
+        .await;
+    assert_eq!(res.status().as_u16(), 200);
+    test_app
+        .create_test_post(json!({ "location": "Sintra" }), &test_app.auth_info.bearer)
+        .await;
+    let post_id = test_app
+        .create_test_post(json!({ "location": "Sintra" }), &regular.bearer)
+        .await;
+    let place_id = place_of(&test_app, &post_id).await;
+
+    let res = test_app.get_place(&place_id).await;
+    assert_eq!(res.status().as_u16(), 200);
+    let place = res.json::<PlaceDetails>().await.unwrap();
+    assert_eq!(place.num_visitors, 1);
+    assert_eq!(place.place.num_posts, 1);
+    let contributors = place
+        .top_contributors
+        .iter()
+        .map(|contributor| contributor.user.id.as_str())
+        .collect::<Vec<&str>>();
+    assert_eq!(contributors, [regular.user.id.as_str()]);
+
+    let res = test_app.search_places("query=sintra").await;
+    let places = res.json::<Vec<Place>>().await.unwrap();
+    assert_eq!(places[0].num_posts, 1);
+}
+
+#[tokio::test]
+async fn test_place_search_puts_names_starting_with_the_query_first() {
+    let test_app = spawn_app().await;
+    let bearer = &test_app.auth_info.bearer;
+    let locations = [
+        "Old Town of Porto",
+        "Portimao",
+        "Portimao",
+        "Porto",
+        "Lisbon",
+    ];
+    for location in locations {
+        test_app
+            .create_test_post(json!({ "location": location }), bearer)
+            .await;
+    }
+
+    assert_eq!(
+        search_names(&test_app, "query=port").await,
+        ["Portimao", "Porto", "Old Town of Porto"]
+    );
+    assert_eq!(
+        search_names(&test_app, "query=PORT&limit=1").await,
+        ["Portimao"]
+    );
+    assert_eq!(
+        search_names(&test_app, "query=town%20%20of").await,
+        ["Old Town of Porto"]
+    );
+    // Wildcards are matched literally
+    assert!(search_names(&test_app, "query=%25").await.is_empty());
+
+    let res = test_app.search_places("query=port&limit=0").await;
+    assert_eq!(res.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn test_places_can_be_created_and_posted_from() {
+    let test_app = spawn_app().await;
+    let bearer = &test_app.auth_info.bearer;
+    let res = test_app
+        .create_place(
+            json!({
+                "name": "Praia da Marinha",
+                "country_code": "pt",
+                "latitude": 37.09,
+                "longitude": -8.41,
+                "category": "beach"
+            }),
+            bearer,
+        )
+        .await;
+    assert_eq!(res.status().as_u16(), 201);
+    let place = res.json::<Place>().await.unwrap();
+    assert_eq!(place.country_code.as_deref(), Some("PT"));
+    assert_eq!(place.category, Some(PlaceCategory::Beach));
+    assert_eq!(place.num_posts, 0);
+
+    let res = test_app
+        .create_place(
+            json!({ "name": "praia da  marinha", "country_code": "PT" }),
+            bearer,
+        )
+        .await;
+    assert_eq!(res.status().as_u16(), 400);
+    let res = test_app
+        .create_place(
+            json!({ "name": "Somewhere", "category": "volcano" }),
+            bearer,
+        )
+        .await;
+    assert_eq!(res.status().as_u16(), 400);
+
+    let post_id = test_app
+        .create_test_post(
+            json!({ "location": "The beach", "place_id": place.id }),
+            bearer,
+        )
+        .await;
+    assert_eq!(place_of(&test_app, &post_id).await, place.id);
+
+    let res = test_app
+        .create_post(
+            json!({
+                "title": "My first post",
+                "location": "The beach",
+                "place_id": "00000000-0000-0000-0000-000000000000",
+                "content": "content"
+            }),
+            bearer,
+        )
+        .await;
+    assert_eq!(res.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn test_editing_the_location_moves_the_post_to_another_place() {
+    let test_app = spawn_app().await;
+    let bearer = &test_app.auth_info.bearer;
+    let post_id = test_app
+        .create_test_post(json!({ "location": "Lisbon" }), bearer)
+        .await;
+    let porto_id = test_app
+        .create_test_post(json!({ "location": "Porto" }), bearer)
+        .await;
+    let porto = place_of(&test_app, &porto_id).await;
+
+    let res = test_app
+        .update_post(&post_id, json!({ "location": "porto" }), bearer)
+        .await;
+    assert_eq!(res.status().as_u16(), 200);
+    let post = res.json::<Post>().await.unwrap();
+    assert_eq!(post.place_id, Some(porto.clone()));
+
+    let res = test_app
+        .update_post(&post_id, json!({ "content": "new content" }), bearer)
+        .await;
+    let post = res.json::<Post>().await.unwrap();
+    assert_eq!(post.place_id, Some(porto));
+}
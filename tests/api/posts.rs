@@ -1,13 +1,8 @@
-/*
-    TODO: Write tests for
-    - Creating a post
-    - Gettting a post
-*/
-
 use std::str::FromStr;
 
+use serde_json::json;
 use uuid::Uuid;
-use voyage_atlas_api::api::models::{Like, Post};
+use voyage_atlas_api::api::models::{CreateComment, Like, Post, PostEdit};
 
 use crate::helpers::{spawn_app, TestAuthInfo};
 
@@ -37,6 +32,19 @@ async fn test_creating_a_post() {
     assert!(post.is_some());
 }
 
+#[tokio::test]
+async fn test_creating_a_post_with_a_long_title() {
+    let test_app = spawn_app().await;
+    let title = "A week around the Portuguese coast";
+
+    let post_id = test_app
+        .create_test_post(json!({ "title": title }), &test_app.auth_info.bearer)
+        .await;
+
+    let res = test_app.get_post(&post_id).await;
+    assert_eq!(res.json::<Post>().await.unwrap().title, title);
+}
+
 #[tokio::test]
 async fn test_get_user_posts() {
     let test_app = spawn_app().await;
@@ -312,3 +320,154 @@ async fn test_get_likes_for_a_post() {
     assert_eq!(likes.len(), 1);
     assert_eq!(likes[0].user.id, test_app.auth_info.user.id);
 }
+
+#[tokio::test]
+async fn test_getting_a_single_post() {
+    let test_app = spawn_app().await;
+    let post_id = test_app
+        .create_test_post(json!({}), &test_app.auth_info.bearer)
+        .await;
+
+    let res = test_app.get_post(&post_id).await;
+
+    assert_eq!(res.status().as_u16(), 200);
+    let post = res.json::<Post>().await.unwrap();
+    assert_eq!(post.id, post_id);
+    assert_eq!(post.title, "My first post");
+    assert_eq!(post.author, test_app.auth_info.user.id);
+    assert!(post.edited_at.is_none());
+
+    let res = test_app.get_post(&Uuid::new_v4().to_string()).await;
+    assert_eq!(res.status().as_u16(), 404);
+    let res = test_app.get_post("not-an-id").await;
+    assert_eq!(res.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn test_editing_a_post_keeps_its_history() {
+    let test_app = spawn_app().await;
+    let post_id = test_app
+        .create_test_post(json!({}), &test_app.auth_info.bearer)
+        .await;
+
+    let res = test_app
+        .update_post(
+            &post_id,
+            json!({ "title": "Second title" }),
+            &test_app.auth_info.bearer,
+        )
+        .await;
+    assert_eq!(res.status().as_u16(), 200);
+    let post = res.json::<Post>().await.unwrap();
+    assert_eq!(post.title, "Second title");
+    assert_eq!(post.content, "content");
+    assert!(post.edited_at.is_some());
+
+    test_app
+        .update_post(
+            &post_id,
+            json!({ "title": "Third title", "content": "New content" }),
+            &test_app.auth_info.bearer,
+        )
+        .await;
+
+    let res = test_app.get_post(&post_id).await;
+    let post = res.json::<Post>().await.unwrap();
+    assert_eq!(post.title, "Third title");
+    assert_eq!(post.content, "New content");
+
+    let res = test_app.get_post_history(&post_id).await;
+    assert_eq!(res.status().as_u16(), 200);
+    let history = res.json::<Vec<PostEdit>>().await.unwrap();
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[0].title, "Second title");
+    assert_eq!(history[1].title, "My first post");
+    assert_eq!(history[1].content, "content");
+}
+
+#[tokio::test]
+async fn test_editing_a_post_is_validated() {
+    let test_app = spawn_app().await;
+    let post_id = test_app
+        .create_test_post(json!({}), &test_app.auth_info.bearer)
+        .await;
+
+    let res = test_app
+        .update_post(
+            &post_id,
+            json!({ "title": "a" }),
+            &test_app.auth_info.bearer,
+        )
+        .await;
+
+    assert_eq!(res.status().as_u16(), 400);
+    let res = test_app.get_post_history(&post_id).await;
+    assert!(res.json::<Vec<PostEdit>>().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_only_the_author_can_edit_or_delete_a_post() {
+    let test_app = spawn_app().await;
+    let post_id = test_app
+        .create_test_post(json!({}), &test_app.auth_info.bearer)
+        .await;
+    let other_user = TestAuthInfo::generate();
+    other_user.store(&test_app.db_pool).await;
+
+    let res = test_app
+        .update_post(
+            &post_id,
+            json!({ "title": "Not my post" }),
+            &other_user.bearer,
+        )
+        .await;
+    assert_eq!(res.status().as_u16(), 403);
+
+    let res = test_app.delete_post(&post_id, &other_user.bearer).await;
+    assert_eq!(res.status().as_u16(), 403);
+
+    let res = test_app.get_post(&post_id).await;
+    assert_eq!(res.json::<Post>().await.unwrap().title, "My first post");
+}
+
+#[tokio::test]
+async fn test_deleting_a_post_removes_its_comments_and_likes() {
+    let test_app = spawn_app().await;
+    let post_id = test_app
+        .create_test_post(json!({}), &test_app.auth_info.bearer)
+        .await;
+    let other_user = TestAuthInfo::generate();
+    other_user.store(&test_app.db_pool).await;
+    test_app
+        .create_comment(
+            &post_id,
+            CreateComment {
+                comment: "Nice".to_string(),
+            },
+            &other_user.bearer,
+        )
+        .await;
+    test_app.like_a_post(&post_id, &other_user.bearer).await;
+
+    let res = test_app
+        .delete_post(&post_id, &test_app.auth_info.bearer)
+        .await;
+
+    assert_eq!(res.status().as_u16(), 204);
+    let res = test_app.get_post(&post_id).await;
+    assert_eq!(res.status().as_u16(), 404);
+    let post_id = Uuid::from_str(&post_id).unwrap();
+    let remaining = sqlx::query!(
+        r#"
+        SELECT
+            (SELECT COUNT(*) FROM comments WHERE post_id = $1) AS "comments!",
+            (SELECT COUNT(*) FROM likes WHERE post_id = $1) AS "likes!"
+        "#,
+        post_id
+    )
+    .fetch_one(&test_app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(remaining.comments, 0);
+    assert_eq!(remaining.likes, 0);
+}
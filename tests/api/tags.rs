@@ -0,0 +1,257 @@
+use serde_json::json;
+use voyage_atlas_api::api::models::{FollowedTag, Page, Post, TrendingTag};
+
+use crate::helpers::{spawn_app, TestApp, TestAuthInfo};
+
+async fn tag_post_ids(test_app: &TestApp, tag: &str, bearer: &str) -> Vec<String> {
+    let res = test_app.get_tag_posts(tag, "", bearer).await;
+    assert_eq!(res.status().as_u16(), 200);
+    res.json::<Page<Post>>()
+        .await
+        .unwrap()
+        .items
+        .into_iter()
+        .map(|post| post.id)
+        .collect()
+}
+
+#[tokio::test]
+async fn test_hashtags_are_parsed_from_the_title_and_content() {
+    let test_app = spawn_app().await;
+    let bearer = &test_app.auth_info.bearer;
+    // Lowercased, the last one is longer than a tag can be
+    let content = format!(
+        "Another #beach day in #Lisbon_2023! Not tags: C# page#top #123 #{}",
+        "İ".repeat(30)
+    );
+    let post_id = test_app
+        .create_test_post(
+            json!({ "title": "Sunset at the #Beach", "content": content }),
+            bearer,
+        )
+        .await;
+
+    assert_eq!(
+        tag_post_ids(&test_app, "beach", bearer).await,
+        [post_id.as_str()]
+    );
+    assert_eq!(
+        tag_post_ids(&test_app, "BEACH", bearer).await,
+        [post_id.as_str()]
+    );
+    assert_eq!(
+        tag_post_ids(&test_app, "lisbon_2023", bearer).await,
+        [post_id.as_str()]
+    );
+    assert!(tag_post_ids(&test_app, "top", bearer).await.is_empty());
+
+    let res = test_app.get_tag_posts("123", "", bearer).await;
+    assert_eq!(res.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn test_editing_a_post_replaces_its_hashtags() {
+    let test_app = spawn_app().await;
+    let bearer = &test_app.auth_info.bearer;
+    let post_id = test_app
+        .create_test_post(
+            json!({ "title": "#Portugal trip", "content": "Day one in #porto" }),
+            bearer,
+        )
+        .await;
+
+    let res = test_app
+        .update_post(&post_id, json!({ "content": "Day one in #lisbon" }), bearer)
+        .await;
+    assert_eq!(res.status().as_u16(), 200);
+
+    assert!(tag_post_ids(&test_app, "porto", bearer).await.is_empty());
+    assert_eq!(
+        tag_post_ids(&test_app, "lisbon", bearer).await,
+        [post_id.as_str()]
+    );
+    // The title wasn't edited, so its tag stays
+    assert_eq!(
+        tag_post_ids(&test_app, "portugal", bearer).await,
+        [post_id.as_str()]
+    );
+}
+
+#[tokio::test]
+async fn test_tag_pages_are_paginated_newest_first() {
+    let test_app = spawn_app().await;
+    let bearer = &test_app.auth_info.bearer;
+    let mut post_ids = Vec::new();
+    for i in 0..3 {
+        post_ids.push(
+            test_app
+                .create_test_post(
+                    json!({ "title": &format!("Post {}", i), "content": "#travel" }),
+                    bearer,
+                )
+                .await,
+        );
+    }
+
+    let res = test_app.get_tag_posts("travel", "per_page=2", bearer).await;
+    let first_page = res.json::<Page<Post>>().await.unwrap();
+    let res = test_app
+        .get_tag_posts("travel", "page=2&per_page=2", bearer)
+        .await;
+    let second_page = res.json::<Page<Post>>().await.unwrap();
+
+    assert!(first_page.has_more);
+    assert!(!second_page.has_more);
+    let ids = first_page
+        .items
+        .iter()
+        .chain(second_page.items.iter())
+        .map(|post| &post.id)
+        .collect::<Vec<&String>>();
+    assert_eq!(ids, post_ids.iter().rev().collect::<Vec<&String>>());
+
+    let res = test_app.get_tag_posts("travel", "per_page=0", bearer).await;
+    assert_eq!(res.status().as_u16(), 400);
+    let res = test_app
+        .get_tag_posts("travel", "page=9223372036854775807&per_page=100", bearer)
+        .await;
+    assert_eq!(res.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn test_trending_tags_only_count_posts_within_the_window() {
+    let test_app = spawn_app().await;
+    let bearer = &test_app.auth_info.bearer;
+    for _ in 0..3 {
+        test_app
+            .create_test_post(json!({ "title": "Hiking", "content": "#hiking" }), bearer)
+            .await;
+    }
+    test_app
+        .create_test_post(
+            json!({ "title": "Sailing", "content": "#sailing #hiking" }),
+            bearer,
+        )
+        .await;
+    for _ in 0..5 {
+        test_app
+            .create_test_post(json!({ "title": "Skiing", "content": "#skiing" }), bearer)
+            .await;
+    }
+    sqlx::query!(
+        r#"
+        UPDATE post_tags SET created_at = NOW() - INTERVAL '2 days'
+        WHERE tag_id = (SELECT id FROM tags WHERE name = 'skiing')
+        "#
+    )
+    .execute(&test_app.db_pool)
+    .await
+    .unwrap();
+
+    let res = test_app.get_trending_tags("").await;
+    assert_eq!(res.status().as_u16(), 200);
+    let trending = res.json::<Vec<TrendingTag>>().await.unwrap();
+    let trending = trending
+        .iter()
+        .map(|tag| (tag.name.as_str(), tag.num_posts))
+        .collect::<Vec<(&str, u32)>>();
+    assert_eq!(trending, [("hiking", 4), ("sailing", 1)]);
+
+    let res = test_app.get_trending_tags("window_hours=72&limit=1").await;
+    let trending = res.json::<Vec<TrendingTag>>().await.unwrap();
+    assert_eq!(trending.len(), 1);
+    assert_eq!(trending[0].name, "skiing");
+
+    let res = test_app.get_trending_tags("window_hours=0").await;
+    assert_eq!(res.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn test_trending_tags_leave_out_private_accounts() {
+    let test_app = spawn_app().await;
+    let private = TestAuthInfo::generate();
+    private.store(&test_app.db_pool).await;
+    let res = test_app
+        .update_profile(json!({ "is_private": true }), &private.bearer)
+        .await;
+    assert_eq!(res.status().as_u16(), 200);
+    for _ in 0..2 {
+        test_app
+            .create_test_post(json!({ "content": "#surprise_party" }), &private.bearer)
+            .await;
+    }
+    test_app
+        .create_test_post(json!({ "content": "#hiking" }), &test_app.auth_info.bearer)
+        .await;
+
+    let res = test_app.get_trending_tags("").await;
+    assert_eq!(res.status().as_u16(), 200);
+    let trending = res.json::<Vec<TrendingTag>>().await.unwrap();
+    let names = trending
+        .iter()
+        .map(|tag| tag.name.as_str())
+        .collect::<Vec<&str>>();
+    assert_eq!(names, ["hiking"]);
+}
+
+#[tokio::test]
+async fn test_posts_with_followed_tags_appear_in_the_feed() {
+    let test_app = spawn_app().await;
+    let follower = TestAuthInfo::generate();
+    follower.store(&test_app.db_pool).await;
+    let post_id = test_app
+        .create_test_post(
+            json!({ "title": "Up the mountain", "content": "#hiking" }),
+            &test_app.auth_info.bearer,
+        )
+        .await;
+
+    let res = test_app.follow_tag("%23Hiking", &follower.bearer).await;
+    assert_eq!(res.status().as_u16(), 201);
+    let res = test_app.get_followed_tags(&follower.bearer).await;
+    let followed = res.json::<Vec<FollowedTag>>().await.unwrap();
+    assert_eq!(followed.len(), 1);
+    assert_eq!(followed[0].name, "hiking");
+
+    let res = test_app.get_user_feed(&follower.bearer).await;
+    let feed = res.json::<Vec<Post>>().await.unwrap();
+    assert_eq!(feed.len(), 1);
+    assert_eq!(feed[0].id, post_id);
+
+    let res = test_app.unfollow_tag("hiking", &follower.bearer).await;
+    assert_eq!(res.status().as_u16(), 204);
+    let res = test_app.get_user_feed(&follower.bearer).await;
+    assert!(res.json::<Vec<Post>>().await.unwrap().is_empty());
+
+    let res = test_app.unfollow_tag("hiking", &follower.bearer).await;
+    assert_eq!(res.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn test_posts_of_private_accounts_stay_hidden_from_tag_followers() {
+    let test_app = spawn_app().await;
+    let stranger = TestAuthInfo::generate();
+    stranger.store(&test_app.db_pool).await;
+    let res = test_app
+        .update_profile(json!({ "is_private": true }), &test_app.auth_info.bearer)
+        .await;
+    assert_eq!(res.status().as_u16(), 200);
+    let post_id = test_app
+        .create_test_post(
+            json!({ "title": "Up the mountain", "content": "#hiking" }),
+            &test_app.auth_info.bearer,
+        )
+        .await;
+    test_app.follow_tag("hiking", &stranger.bearer).await;
+
+    assert!(tag_post_ids(&test_app, "hiking", &stranger.bearer)
+        .await
+        .is_empty());
+    let res = test_app.get_user_feed(&stranger.bearer).await;
+    assert!(res.json::<Vec<Post>>().await.unwrap().is_empty());
+
+    assert_eq!(
+        tag_post_ids(&test_app, "hiking", &test_app.auth_info.bearer).await,
+        [post_id.as_str()]
+    );
+}
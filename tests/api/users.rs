@@ -1,7 +1,19 @@
-use crate::helpers::{spawn_app, TestAuthInfo};
+use crate::helpers::{
+    png_image, spawn_app, spawn_app_with, test_post_body, totp_code, TestApp, TestAuthInfo,
+};
+use argon2::{password_hash::SaltString, Argon2, PasswordHasher};
+use secrecy::Secret;
 use serde_json::{json, Value};
 use std::str::FromStr;
 use uuid::Uuid;
+use voyage_atlas_api::api::{
+    configuration::JwtKeySettings,
+    controller,
+    models::{
+        AuthInfo, AuthUser, Comment, CreateComment, FollowRequest, PostMedia, PublicUser,
+        RecoveryCodes, SessionInfo, TotpEnrollment,
+    },
+};
 
 #[tokio::test]
 async fn create_user() {
@@ -10,7 +22,10 @@ async fn create_user() {
         .post_user(json!({
             "username": "testuser",
             "password": "Password123!",
-            "email": "email123@email.com"
+            "email": "email123@email.com",
+            "first_name": "Test",
+            "last_name": "User",
+            "description": "Test Description"
         }))
         .await;
 
@@ -28,7 +43,10 @@ async fn create_user_fails_invalid_data() {
             json!({
                 "username": "testuser",
                 "password": "Password123!",
-                "email": "email"
+                "email": "email",
+                "first_name": "Test",
+                "last_name": "User",
+                "description": "Test Description"
             }),
             vec!["email"],
         ),
@@ -36,7 +54,10 @@ async fn create_user_fails_invalid_data() {
             json!({
                 "username": "testuser",
                 "password": "Password123",
-                "email": "email"
+                "email": "email",
+                "first_name": "Test",
+                "last_name": "User",
+                "description": "Test Description"
             }),
             vec!["email", "password"],
         ),
@@ -44,7 +65,10 @@ async fn create_user_fails_invalid_data() {
             json!({
                 "username": "testuser",
                 "password": "Password123",
-                "email": "email@email.com"
+                "email": "email@email.com",
+                "first_name": "Test",
+                "last_name": "User",
+                "description": "Test Description"
             }),
             vec!["password"],
         ),
@@ -107,6 +131,13 @@ async fn test_login_fails() {
             }),
             "Password was wrong",
         ),
+        (
+            json!({
+                "email": format!("{}@123.com", "a".repeat(300)),
+                "password": "Password123!"
+            }),
+            "Email was too long",
+        ),
     ];
     for (data, reason) in test_data {
         let res = reqwest::Client::new()
@@ -319,3 +350,1385 @@ async fn test_get_a_user() {
     let user = res.json::<Value>().await.unwrap();
     assert_eq!(user["username"], new_user.user.username);
 }
+
+#[tokio::test]
+async fn test_token_signed_with_unknown_key_is_rejected() {
+    let test_app = spawn_app().await;
+    let forged = jsonwebtoken::encode(
+        &jsonwebtoken::Header::default(),
+        &json!({
+            "user_id": test_app.auth_info.user.id,
+            "iss": chrono::Utc::now().timestamp(),
+            "exp": (chrono::Utc::now() + chrono::Duration::days(1)).timestamp(),
+        }),
+        &jsonwebtoken::EncodingKey::from_secret("Secret".as_bytes()),
+    )
+    .unwrap();
+
+    let res = test_app.get_user_feed(&forged).await;
+    assert_eq!(res.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn test_tokens_from_previous_key_are_accepted_after_rotation() {
+    let test_app = spawn_app_with(|c| {
+        let previous_key = c.auth.signing_key.clone();
+        c.auth.signing_key = JwtKeySettings {
+            kid: "rotated".into(),
+            algorithm: jsonwebtoken::Algorithm::HS512,
+            secret: Some(Secret::new("rotated-secret".into())),
+            private_key_path: None,
+            public_key_path: None,
+        };
+        c.auth.verification_keys = vec![previous_key];
+    })
+    .await;
+
+    // The bearer was signed with the previous key
+    let res = test_app.get_user_feed(&test_app.auth_info.bearer).await;
+    assert_eq!(res.status().as_u16(), 200);
+
+    // New tokens are signed with the current key
+    let res = reqwest::Client::new()
+        .post(format!("{}/users/login", test_app.address))
+        .json(&json!({
+            "email": test_app.auth_info.user.email,
+            "password": "Password123!"
+        }))
+        .send()
+        .await
+        .expect("failed to send request");
+    assert_eq!(res.status().as_u16(), 200);
+    let body = res.json::<Value>().await.unwrap();
+    let header = jsonwebtoken::decode_header(body["bearer"].as_str().unwrap()).unwrap();
+    assert_eq!(header.kid.as_deref(), Some("rotated"));
+    assert_eq!(header.alg, jsonwebtoken::Algorithm::HS512);
+}
+
+#[tokio::test]
+async fn test_refresh_token_rotates_tokens() {
+    let test_app = spawn_app().await;
+    let res = test_app
+        .login(&test_app.auth_info.user.email, "Password123!")
+        .await;
+    assert_eq!(res.status().as_u16(), 200);
+    let auth_info = res.json::<Value>().await.unwrap();
+    let refresh_token = auth_info["refresh_token"].as_str().unwrap();
+
+    let res = test_app.refresh_token(refresh_token).await;
+    assert_eq!(res.status().as_u16(), 200);
+    let tokens = res.json::<Value>().await.unwrap();
+    assert_ne!(tokens["refresh_token"].as_str().unwrap(), refresh_token);
+
+    // The new bearer is usable
+    let res = test_app
+        .get_user_feed(tokens["bearer"].as_str().unwrap())
+        .await;
+    assert_eq!(res.status().as_u16(), 200);
+
+    // And the new refresh token can be rotated again
+    let res = test_app
+        .refresh_token(tokens["refresh_token"].as_str().unwrap())
+        .await;
+    assert_eq!(res.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn test_refresh_token_reuse_revokes_session() {
+    let test_app = spawn_app().await;
+    let res = test_app
+        .login(&test_app.auth_info.user.email, "Password123!")
+        .await;
+    let auth_info = res.json::<Value>().await.unwrap();
+    let refresh_token = auth_info["refresh_token"].as_str().unwrap();
+
+    let res = test_app.refresh_token(refresh_token).await;
+    assert_eq!(res.status().as_u16(), 200);
+    let tokens = res.json::<Value>().await.unwrap();
+
+    // Replaying the rotated token is rejected
+    let res = test_app.refresh_token(refresh_token).await;
+    assert_eq!(res.status().as_u16(), 401);
+    let body = res.json::<Value>().await.unwrap();
+    assert_eq!(body["error"], "Refresh token has already been used");
+
+    // And the whole session is revoked
+    let res = test_app
+        .get_user_feed(tokens["bearer"].as_str().unwrap())
+        .await;
+    assert_eq!(res.status().as_u16(), 401);
+    let res = test_app
+        .refresh_token(tokens["refresh_token"].as_str().unwrap())
+        .await;
+    assert_eq!(res.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn test_refresh_token_fails_invalid_token() {
+    let test_app = spawn_app().await;
+    let res = test_app.refresh_token("not-a-refresh-token").await;
+    assert_eq!(res.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn test_logout_revokes_session() {
+    let test_app = spawn_app().await;
+    let res = test_app
+        .login(&test_app.auth_info.user.email, "Password123!")
+        .await;
+    let auth_info = res.json::<Value>().await.unwrap();
+    let bearer = auth_info["bearer"].as_str().unwrap();
+
+    let res = test_app.logout(bearer).await;
+    assert_eq!(res.status().as_u16(), 204);
+
+    let res = test_app.get_user_feed(bearer).await;
+    assert_eq!(res.status().as_u16(), 401);
+    let res = test_app
+        .refresh_token(auth_info["refresh_token"].as_str().unwrap())
+        .await;
+    assert_eq!(res.status().as_u16(), 401);
+    // Other sessions are untouched
+    let res = test_app.get_user_feed(&test_app.auth_info.bearer).await;
+    assert_eq!(res.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn test_logout_all_revokes_every_session() {
+    let test_app = spawn_app().await;
+    let res = test_app
+        .login(&test_app.auth_info.user.email, "Password123!")
+        .await;
+    let auth_info = res.json::<Value>().await.unwrap();
+    let bearer = auth_info["bearer"].as_str().unwrap();
+
+    let res = test_app.logout_all(bearer).await;
+    assert_eq!(res.status().as_u16(), 204);
+
+    let res = test_app.get_user_feed(bearer).await;
+    assert_eq!(res.status().as_u16(), 401);
+    let res = test_app.get_user_feed(&test_app.auth_info.bearer).await;
+    assert_eq!(res.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn test_get_sessions() {
+    let test_app = spawn_app().await;
+    let res = reqwest::Client::new()
+        .post(format!("{}/users/login", test_app.address))
+        .header("User-Agent", "VoyageAtlas iOS")
+        .json(&json!({
+            "email": test_app.auth_info.user.email,
+            "password": "Password123!"
+        }))
+        .send()
+        .await
+        .expect("failed to send request");
+    assert_eq!(res.status().as_u16(), 200);
+    let auth_info = res.json::<Value>().await.unwrap();
+
+    let res = test_app
+        .get_sessions(auth_info["bearer"].as_str().unwrap())
+        .await;
+    assert_eq!(res.status().as_u16(), 200);
+    let sessions = res.json::<Vec<SessionInfo>>().await.unwrap();
+    assert_eq!(sessions.len(), 2);
+    let current = sessions.iter().find(|s| s.current).unwrap();
+    assert_eq!(current.user_agent.as_deref(), Some("VoyageAtlas iOS"));
+    assert_eq!(current.ip_address.as_deref(), Some("127.0.0.1"));
+    assert!(sessions
+        .iter()
+        .any(|s| !s.current && s.id == test_app.auth_info.session_id));
+}
+
+#[tokio::test]
+async fn test_revoke_session() {
+    let test_app = spawn_app().await;
+    let res = test_app
+        .login(&test_app.auth_info.user.email, "Password123!")
+        .await;
+    let auth_info = res.json::<Value>().await.unwrap();
+    let bearer = auth_info["bearer"].as_str().unwrap();
+
+    // Sign out the other device
+    let res = test_app
+        .revoke_session(&test_app.auth_info.session_id, bearer)
+        .await;
+    assert_eq!(res.status().as_u16(), 204);
+
+    let res = test_app.get_user_feed(&test_app.auth_info.bearer).await;
+    assert_eq!(res.status().as_u16(), 401);
+    let res = test_app.get_sessions(bearer).await;
+    let sessions = res.json::<Vec<SessionInfo>>().await.unwrap();
+    assert_eq!(sessions.len(), 1);
+    assert!(sessions[0].current);
+}
+
+#[tokio::test]
+async fn test_revoke_session_fails_not_owner() {
+    let test_app = spawn_app().await;
+    let other_user = TestAuthInfo::generate();
+    other_user.store(&test_app.db_pool).await;
+
+    let res = test_app
+        .revoke_session(&other_user.session_id, &test_app.auth_info.bearer)
+        .await;
+    assert_eq!(res.status().as_u16(), 404);
+    let body = res.json::<Value>().await.unwrap();
+    assert_eq!(body["error"], "Session does not exist");
+
+    let res = test_app.get_user_feed(&other_user.bearer).await;
+    assert_eq!(res.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn test_password_reset() {
+    let test_app = spawn_app().await;
+    let res = test_app
+        .forgot_password(&test_app.auth_info.user.email)
+        .await;
+    assert_eq!(res.status().as_u16(), 202);
+
+    let emails = test_app.sent_emails();
+    assert_eq!(emails.len(), 1);
+    assert_eq!(emails[0].recipient, test_app.auth_info.user.email);
+    let link = test_app.get_link(&emails[0]);
+    let token = link
+        .query_pairs()
+        .find(|(key, _)| key == "token")
+        .map(|(_, value)| value.to_string())
+        .unwrap();
+
+    let res = test_app.reset_password(&token, "NewPassword123!").await;
+    assert_eq!(res.status().as_u16(), 204);
+
+    // Only the new password works
+    let res = test_app
+        .login(&test_app.auth_info.user.email, "Password123!")
+        .await;
+    assert_eq!(res.status().as_u16(), 400);
+    let res = test_app
+        .login(&test_app.auth_info.user.email, "NewPassword123!")
+        .await;
+    assert_eq!(res.status().as_u16(), 200);
+
+    // Existing sessions are logged out and the token is single-use
+    let res = test_app.get_user_feed(&test_app.auth_info.bearer).await;
+    assert_eq!(res.status().as_u16(), 401);
+    let res = test_app.reset_password(&token, "OtherPassword123!").await;
+    assert_eq!(res.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn test_password_reset_link_opens_a_form() {
+    let test_app = spawn_app().await;
+    test_app
+        .forgot_password(&test_app.auth_info.user.email)
+        .await;
+    let link = test_app.get_link(&test_app.sent_emails()[0]);
+    let token = link
+        .query_pairs()
+        .find(|(key, _)| key == "token")
+        .map(|(_, value)| value.to_string())
+        .unwrap();
+
+    let res = test_app.get_link_page(&link).await;
+    assert_eq!(res.status().as_u16(), 200);
+    let page = res.text().await.unwrap();
+    assert!(page.contains(&format!("value=\"{}\"", token)));
+
+    let res = test_app
+        .submit_password_reset_form(&token, "NewPassword123!")
+        .await;
+    assert_eq!(res.status().as_u16(), 204);
+    let res = test_app
+        .login(&test_app.auth_info.user.email, "NewPassword123!")
+        .await;
+    assert_eq!(res.status().as_u16(), 200);
+
+    let mut link = link;
+    link.set_query(Some("token=%22%3E%3Cscript%3E"));
+    let res = test_app.get_link_page(&link).await;
+    assert_eq!(res.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn test_forgot_password_unknown_email_sends_nothing() {
+    let test_app = spawn_app().await;
+    let res = test_app.forgot_password("nobody@email.com").await;
+    assert_eq!(res.status().as_u16(), 202);
+    assert!(test_app.sent_emails().is_empty());
+}
+
+#[tokio::test]
+async fn test_reset_password_fails() {
+    let test_app = spawn_app().await;
+    test_app
+        .forgot_password(&test_app.auth_info.user.email)
+        .await;
+    let link = test_app.get_link(&test_app.sent_emails()[0]);
+    let token = link
+        .query_pairs()
+        .find(|(key, _)| key == "token")
+        .map(|(_, value)| value.to_string())
+        .unwrap();
+
+    let res = test_app.reset_password(&token, "password").await;
+    assert_eq!(res.status().as_u16(), 400);
+    let body = res.json::<Value>().await.unwrap();
+    assert!(body["error"].as_str().unwrap().contains("password"));
+
+    let res = test_app
+        .reset_password("not-a-token", "NewPassword123!")
+        .await;
+    assert_eq!(res.status().as_u16(), 400);
+    let body = res.json::<Value>().await.unwrap();
+    assert_eq!(body["error"], "Invalid or expired password reset token");
+}
+
+fn new_user_body() -> Value {
+    json!({
+        "username": "unverified",
+        "password": "Password123!",
+        "email": "unverified@email.com",
+        "first_name": "Test",
+        "last_name": "User",
+        "description": "Test Description"
+    })
+}
+
+#[tokio::test]
+async fn test_register_requires_email_confirmation() {
+    let test_app = spawn_app().await;
+    let res = test_app.post_user(new_user_body()).await;
+    assert_eq!(res.status().as_u16(), 201);
+    let auth_info = res.json::<Value>().await.unwrap();
+    let bearer = auth_info["bearer"].as_str().unwrap();
+
+    let emails = test_app.sent_emails();
+    assert_eq!(emails.len(), 1);
+    assert_eq!(emails[0].recipient, "unverified@email.com");
+
+    // Unverified accounts can't post or comment by default
+    let res = test_app
+        .create_post(test_post_body(json!({})), bearer)
+        .await;
+    assert_eq!(res.status().as_u16(), 403);
+    let post_id = test_app
+        .create_test_post(json!({}), &test_app.auth_info.bearer)
+        .await;
+    let res = test_app
+        .create_comment(
+            &post_id,
+            CreateComment {
+                comment: "Nice post".into(),
+            },
+            bearer,
+        )
+        .await;
+    assert_eq!(res.status().as_u16(), 403);
+
+    let link = test_app.get_link(&emails[0]);
+    assert_eq!(link.path(), "/users/confirm");
+    let token = link
+        .query_pairs()
+        .find(|(key, _)| key == "token")
+        .map(|(_, value)| value.to_string())
+        .unwrap();
+    let res = test_app.confirm_email(&token).await;
+    assert_eq!(res.status().as_u16(), 200);
+
+    test_app.create_test_post(json!({}), bearer).await;
+
+    // The link is single-use and there's nothing left to confirm
+    let res = test_app.confirm_email(&token).await;
+    assert_eq!(res.status().as_u16(), 400);
+    let res = test_app.resend_confirmation_email(bearer).await;
+    assert_eq!(res.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn test_resend_confirmation_email() {
+    let test_app = spawn_app().await;
+    let res = test_app.post_user(new_user_body()).await;
+    let auth_info = res.json::<Value>().await.unwrap();
+    let bearer = auth_info["bearer"].as_str().unwrap();
+
+    let res = test_app.resend_confirmation_email(bearer).await;
+    assert_eq!(res.status().as_u16(), 202);
+
+    let emails = test_app.sent_emails();
+    assert_eq!(emails.len(), 2);
+    let token = test_app
+        .get_link(&emails[1])
+        .query_pairs()
+        .find(|(key, _)| key == "token")
+        .map(|(_, value)| value.to_string())
+        .unwrap();
+    let res = test_app.confirm_email(&token).await;
+    assert_eq!(res.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn test_confirm_email_fails_invalid_token() {
+    let test_app = spawn_app().await;
+    let res = test_app.confirm_email("not-a-real-token").await;
+    assert_eq!(res.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn test_unverified_accounts_policy_is_configurable() {
+    let test_app = spawn_app_with(|c| c.auth.unverified_accounts.can_post = true).await;
+    let res = test_app.post_user(new_user_body()).await;
+    let auth_info = res.json::<Value>().await.unwrap();
+    let bearer = auth_info["bearer"].as_str().unwrap();
+
+    test_app.create_test_post(json!({}), bearer).await;
+}
+
+// Enables 2FA for the test user, returning the TOTP secret and the recovery codes
+async fn enable_two_factor(test_app: &TestApp) -> (String, Vec<String>) {
+    let bearer = &test_app.auth_info.bearer;
+    let res = test_app.enroll_two_factor(bearer).await;
+    assert_eq!(res.status().as_u16(), 200);
+    let enrollment = res.json::<TotpEnrollment>().await.unwrap();
+    assert!(enrollment.provisioning_uri.starts_with("otpauth://totp/"));
+
+    let res = test_app
+        .confirm_two_factor(&totp_code(&enrollment.secret, 0), bearer)
+        .await;
+    assert_eq!(res.status().as_u16(), 200);
+    let recovery_codes = res.json::<RecoveryCodes>().await.unwrap().recovery_codes;
+    assert_eq!(recovery_codes.len(), 10);
+
+    (enrollment.secret, recovery_codes)
+}
+
+async fn login_mfa_token(test_app: &TestApp) -> String {
+    let res = test_app
+        .login(&test_app.auth_info.user.email, "Password123!")
+        .await;
+    assert_eq!(res.status().as_u16(), 200);
+    let body = res.json::<Value>().await.unwrap();
+    assert!(body.get("bearer").is_none());
+    body["mfa_token"].as_str().unwrap().to_string()
+}
+
+#[tokio::test]
+async fn test_two_factor_login() {
+    let test_app = spawn_app().await;
+    let (secret, _) = enable_two_factor(&test_app).await;
+
+    let mfa_token = login_mfa_token(&test_app).await;
+    // The pending token is not a bearer token
+    let res = test_app.get_sessions(&mfa_token).await;
+    assert_eq!(res.status().as_u16(), 401);
+
+    let res = test_app.login_mfa(&mfa_token, "000000").await;
+    assert_eq!(res.status().as_u16(), 400);
+
+    // The confirmation code was already used, so use the next one
+    let code = totp_code(&secret, 1);
+    let res = test_app.login_mfa(&mfa_token, &code).await;
+    assert_eq!(res.status().as_u16(), 200);
+    let auth_info = res.json::<AuthInfo>().await.unwrap();
+    let res = test_app.get_sessions(&auth_info.bearer).await;
+    assert_eq!(res.status().as_u16(), 200);
+
+    // Codes can't be replayed
+    let res = test_app.login_mfa(&mfa_token, &code).await;
+    assert_eq!(res.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn test_two_factor_login_with_recovery_code() {
+    let test_app = spawn_app().await;
+    let (_, recovery_codes) = enable_two_factor(&test_app).await;
+
+    let mfa_token = login_mfa_token(&test_app).await;
+    let res = test_app.login_mfa(&mfa_token, &recovery_codes[0]).await;
+    assert_eq!(res.status().as_u16(), 200);
+
+    // Each recovery code works once
+    let res = test_app.login_mfa(&mfa_token, &recovery_codes[0]).await;
+    assert_eq!(res.status().as_u16(), 400);
+    let res = test_app.login_mfa(&mfa_token, &recovery_codes[1]).await;
+    assert_eq!(res.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn test_confirm_two_factor_fails_invalid_code() {
+    let test_app = spawn_app().await;
+    let bearer = &test_app.auth_info.bearer;
+    let res = test_app.confirm_two_factor("123456", bearer).await;
+    assert_eq!(res.status().as_u16(), 400);
+
+    test_app.enroll_two_factor(bearer).await;
+    let res = test_app.confirm_two_factor("not-a-code", bearer).await;
+    assert_eq!(res.status().as_u16(), 400);
+
+    // 2FA was never enabled, so the password is enough
+    let res = test_app
+        .login(&test_app.auth_info.user.email, "Password123!")
+        .await;
+    let body = res.json::<Value>().await.unwrap();
+    assert!(body.get("bearer").is_some());
+}
+
+#[tokio::test]
+async fn test_disable_two_factor() {
+    let test_app = spawn_app().await;
+    let (secret, _) = enable_two_factor(&test_app).await;
+    let bearer = &test_app.auth_info.bearer;
+
+    let res = test_app.enroll_two_factor(bearer).await;
+    assert_eq!(res.status().as_u16(), 400);
+    let res = test_app.disable_two_factor("000000", bearer).await;
+    assert_eq!(res.status().as_u16(), 400);
+    let res = test_app
+        .disable_two_factor(&totp_code(&secret, 1), bearer)
+        .await;
+    assert_eq!(res.status().as_u16(), 204);
+
+    let res = test_app
+        .login(&test_app.auth_info.user.email, "Password123!")
+        .await;
+    let body = res.json::<Value>().await.unwrap();
+    assert!(body.get("bearer").is_some());
+}
+
+async fn stored_password_hash(test_app: &TestApp, email: &str) -> String {
+    sqlx::query!("SELECT password FROM users WHERE email = $1", email)
+        .fetch_one(&test_app.db_pool)
+        .await
+        .unwrap()
+        .password
+}
+
+#[tokio::test]
+async fn test_new_passwords_are_hashed_with_argon2id() {
+    let test_app = spawn_app().await;
+    let res = test_app.post_user(new_user_body()).await;
+    assert_eq!(res.status().as_u16(), 201);
+
+    let hash = stored_password_hash(&test_app, "unverified@email.com").await;
+    assert!(hash.starts_with("$argon2id$v=19$m=4096,t=1,p=1$"));
+    let res = test_app.login("unverified@email.com", "Password123!").await;
+    assert_eq!(res.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn test_login_rehashes_legacy_bcrypt_password() {
+    let test_app = spawn_app().await;
+    let email = &test_app.auth_info.user.email;
+    assert!(stored_password_hash(&test_app, email)
+        .await
+        .starts_with("$2"));
+
+    let res = test_app.login(email, "Password123!").await;
+    assert_eq!(res.status().as_u16(), 200);
+    assert!(stored_password_hash(&test_app, email)
+        .await
+        .starts_with("$argon2id$"));
+
+    // The upgraded hash keeps working, and a wrong password still doesn't
+    let res = test_app.login(email, "Password123!").await;
+    assert_eq!(res.status().as_u16(), 200);
+    let res = test_app.login(email, "WrongPassword123!").await;
+    assert_eq!(res.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn test_login_rehashes_password_with_outdated_parameters() {
+    let test_app = spawn_app().await;
+    let email = &test_app.auth_info.user.email;
+    let outdated_hash = Argon2::new(
+        argon2::Algorithm::Argon2id,
+        argon2::Version::V0x13,
+        argon2::Params::new(2048, 1, 1, None).unwrap(),
+    )
+    .hash_password(
+        b"Password123!",
+        &SaltString::generate(&mut rand::thread_rng()),
+    )
+    .unwrap()
+    .to_string();
+    sqlx::query!(
+        "UPDATE users SET password = $1 WHERE email = $2",
+        outdated_hash,
+        email
+    )
+    .execute(&test_app.db_pool)
+    .await
+    .unwrap();
+
+    let res = test_app.login(email, "Password123!").await;
+    assert_eq!(res.status().as_u16(), 200);
+    assert!(stored_password_hash(&test_app, email)
+        .await
+        .starts_with("$argon2id$v=19$m=4096,t=1,p=1$"));
+}
+
+fn retry_after(res: &reqwest::Response) -> i64 {
+    res.headers()
+        .get("Retry-After")
+        .expect("missing Retry-After header")
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_login_locks_account_after_repeated_failures() {
+    let test_app = spawn_app_with(|c| {
+        c.auth.login_throttling.account_free_attempts = 3;
+        c.auth.login_throttling.base_lockout_seconds = 60;
+    })
+    .await;
+    let email = &test_app.auth_info.user.email;
+
+    for _ in 0..3 {
+        let res = test_app.login(email, "WrongPassword123!").await;
+        assert_eq!(res.status().as_u16(), 400);
+    }
+
+    // Even the right password is turned away until the lockout is over
+    let res = test_app.login(email, "Password123!").await;
+    assert_eq!(res.status().as_u16(), 429);
+    let retry_after = retry_after(&res);
+    assert!(retry_after > 0 && retry_after <= 60);
+
+    let events = sqlx::query!("SELECT scope, identifier FROM lockout_events")
+        .fetch_all(&test_app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].scope, "account");
+    assert_eq!(&events[0].identifier, email);
+}
+
+#[tokio::test]
+async fn test_login_lockout_grows_exponentially() {
+    let test_app = spawn_app_with(|c| {
+        c.auth.login_throttling.account_free_attempts = 2;
+        c.auth.login_throttling.base_lockout_seconds = 10;
+    })
+    .await;
+    let email = &test_app.auth_info.user.email;
+
+    test_app.login(email, "WrongPassword123!").await;
+    test_app.login(email, "WrongPassword123!").await;
+    let res = test_app.login(email, "WrongPassword123!").await;
+    assert!(retry_after(&res) <= 10);
+
+    // Let the first lockout run out, the next failure locks for twice as long
+    sqlx::query!("UPDATE login_throttles SET locked_until = NOW()")
+        .execute(&test_app.db_pool)
+        .await
+        .unwrap();
+    test_app.login(email, "WrongPassword123!").await;
+    let res = test_app.login(email, "WrongPassword123!").await;
+    assert_eq!(res.status().as_u16(), 429);
+    let retry_after = retry_after(&res);
+    assert!(retry_after > 10 && retry_after <= 20);
+}
+
+#[tokio::test]
+async fn test_login_locks_ip_address_after_repeated_failures() {
+    let test_app = spawn_app_with(|c| c.auth.login_throttling.ip_free_attempts = 3).await;
+
+    for i in 0..3 {
+        let res = test_app
+            .login(&format!("nobody{}@email.com", i), "Password123!")
+            .await;
+        assert_eq!(res.status().as_u16(), 400);
+    }
+
+    let res = test_app
+        .login(&test_app.auth_info.user.email, "Password123!")
+        .await;
+    assert_eq!(res.status().as_u16(), 429);
+}
+
+#[tokio::test]
+async fn test_successful_login_resets_failed_attempts() {
+    let test_app = spawn_app_with(|c| c.auth.login_throttling.account_free_attempts = 3).await;
+    let email = &test_app.auth_info.user.email;
+
+    for _ in 0..2 {
+        test_app.login(email, "WrongPassword123!").await;
+        test_app.login(email, "WrongPassword123!").await;
+        let res = test_app.login(email, "Password123!").await;
+        assert_eq!(res.status().as_u16(), 200);
+    }
+}
+
+#[tokio::test]
+async fn test_update_profile_changes_only_given_fields() {
+    let test_app = spawn_app().await;
+    let user_id = Uuid::from_str(&test_app.auth_info.user.id).unwrap();
+
+    let res = test_app
+        .update_profile(
+            json!({ "first_name": "Updated", "description": "Now travelling" }),
+            &test_app.auth_info.bearer,
+        )
+        .await;
+    assert_eq!(res.status().as_u16(), 200);
+    let user = res.json::<Value>().await.unwrap();
+    assert_eq!(user["name"], "Updated User");
+    assert_eq!(user["description"], "Now travelling");
+    assert_eq!(
+        user["email"].as_str().unwrap(),
+        test_app.auth_info.user.email
+    );
+
+    let stored = sqlx::query!(
+        "SELECT email_verified_at, created_at, updated_at FROM users WHERE id = $1",
+        user_id
+    )
+    .fetch_one(&test_app.db_pool)
+    .await
+    .unwrap();
+    assert!(stored.email_verified_at.is_some());
+    assert!(stored.updated_at > stored.created_at);
+    assert!(test_app.sent_emails().is_empty());
+}
+
+#[tokio::test]
+async fn test_update_profile_email_requires_verification() {
+    let test_app = spawn_app().await;
+    let old_email = test_app.auth_info.user.email.clone();
+
+    let res = test_app
+        .update_profile(
+            json!({ "email": "New.Address@email.com" }),
+            &test_app.auth_info.bearer,
+        )
+        .await;
+    assert_eq!(res.status().as_u16(), 200);
+    let user = res.json::<Value>().await.unwrap();
+    assert_eq!(user["email"], "new.address@email.com");
+
+    let user_id = Uuid::from_str(&test_app.auth_info.user.id).unwrap();
+    let verified_at = sqlx::query!("SELECT email_verified_at FROM users WHERE id = $1", user_id)
+        .fetch_one(&test_app.db_pool)
+        .await
+        .unwrap()
+        .email_verified_at;
+    assert!(verified_at.is_none());
+
+    // The new address gets a confirmation link, the old one a heads-up
+    let emails = test_app.sent_emails();
+    assert_eq!(emails.len(), 2);
+    assert!(emails.iter().any(|email| email.recipient == old_email));
+    let confirmation = emails
+        .iter()
+        .find(|email| email.recipient == "new.address@email.com")
+        .unwrap();
+    let link = test_app.get_link(confirmation);
+    let token = link
+        .query_pairs()
+        .find(|(key, _)| key == "token")
+        .map(|(_, value)| value.to_string())
+        .unwrap();
+    let res = test_app.confirm_email(&token).await;
+    assert_eq!(res.status().as_u16(), 200);
+
+    let res = test_app
+        .login("new.address@email.com", "Password123!")
+        .await;
+    assert_eq!(res.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn test_update_profile_rejects_taken_or_invalid_email() {
+    let test_app = spawn_app().await;
+    let other_user = TestAuthInfo::generate();
+    other_user.store(&test_app.db_pool).await;
+
+    let res = test_app
+        .update_profile(
+            json!({ "email": other_user.user.email, "first_name": "Taken" }),
+            &test_app.auth_info.bearer,
+        )
+        .await;
+    assert_eq!(res.status().as_u16(), 400);
+    let body = res.json::<Value>().await.unwrap();
+    assert_eq!(body["error"], "Email already taken.");
+
+    let res = test_app
+        .update_profile(
+            json!({ "email": "not-an-email" }),
+            &test_app.auth_info.bearer,
+        )
+        .await;
+    assert_eq!(res.status().as_u16(), 400);
+
+    // Nothing was saved
+    let res = test_app.get_user(&test_app.auth_info.user.id).await;
+    let user = res.json::<Value>().await.unwrap();
+    assert_eq!(user["name"], "Test User");
+    assert!(test_app.sent_emails().is_empty());
+
+    let res = reqwest::Client::new()
+        .patch(format!("{}/users/me", &test_app.address))
+        .json(&json!({ "first_name": "Anonymous" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn test_public_user_views_hide_email() {
+    let test_app = spawn_app().await;
+    let follower = TestAuthInfo::generate();
+    follower.store(&test_app.db_pool).await;
+    test_app
+        .follow_user(&test_app.auth_info.user.id, &follower.bearer)
+        .await;
+    let post_id = test_app
+        .create_test_post(json!({}), &test_app.auth_info.bearer)
+        .await;
+    test_app
+        .create_comment(
+            &post_id,
+            CreateComment {
+                comment: "Nice".to_string(),
+            },
+            &follower.bearer,
+        )
+        .await;
+    test_app.like_a_post(&post_id, &follower.bearer).await;
+
+    let views = [
+        test_app.get_followers(&test_app.auth_info.user.id).await,
+        test_app.get_following(&follower.user.id).await,
+        test_app.get_all_users(None).await,
+        test_app.get_likes_for_a_post(&post_id).await,
+    ];
+    for res in views {
+        let users = res.json::<Value>().await.unwrap();
+        let body = users.to_string();
+        assert!(!body.contains(&follower.user.email));
+        assert!(!body.contains(&test_app.auth_info.user.email));
+    }
+    let comments = test_app.get_comments(&post_id).await;
+    assert!(!comments
+        .text()
+        .await
+        .unwrap()
+        .contains(&follower.user.email));
+    let user: PublicUser = test_app
+        .get_user(&follower.user.id)
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(user.email, None);
+
+    // The owner still sees their own email
+    let me: AuthUser = test_app
+        .get_me(&follower.bearer)
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(me.email, follower.user.email);
+    assert!(!me.show_email);
+}
+
+#[tokio::test]
+async fn test_users_can_opt_in_to_showing_email() {
+    let test_app = spawn_app().await;
+
+    let res = test_app
+        .update_profile(json!({ "show_email": true }), &test_app.auth_info.bearer)
+        .await;
+    assert_eq!(res.status().as_u16(), 200);
+    let me: AuthUser = res.json().await.unwrap();
+    assert!(me.show_email);
+
+    let user: PublicUser = test_app
+        .get_user(&test_app.auth_info.user.id)
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(user.email, Some(test_app.auth_info.user.email.clone()));
+}
+
+async fn make_private(test_app: &TestApp, is_private: bool) {
+    let res = test_app
+        .update_profile(
+            json!({ "is_private": is_private }),
+            &test_app.auth_info.bearer,
+        )
+        .await;
+    assert_eq!(res.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn test_private_account_approves_followers() {
+    let test_app = spawn_app().await;
+    let owner_id = &test_app.auth_info.user.id;
+    make_private(&test_app, true).await;
+    let follower = TestAuthInfo::generate();
+    follower.store(&test_app.db_pool).await;
+
+    let res = test_app.follow_user(owner_id, &follower.bearer).await;
+    assert_eq!(res.status().as_u16(), 202);
+    let res = test_app.follow_user(owner_id, &follower.bearer).await;
+    assert_eq!(res.status().as_u16(), 400);
+
+    // Nothing is visible until the request is approved
+    let res = test_app.get_user_posts(owner_id, &follower.bearer).await;
+    assert_eq!(res.status().as_u16(), 403);
+    assert_eq!(
+        test_app.get_followers(owner_id).await.status().as_u16(),
+        403
+    );
+    assert_eq!(
+        test_app.get_following(owner_id).await.status().as_u16(),
+        403
+    );
+    let res = test_app
+        .get_user_posts(owner_id, &test_app.auth_info.bearer)
+        .await;
+    assert_eq!(res.status().as_u16(), 200);
+
+    let requests: Vec<FollowRequest> = test_app
+        .get_follow_requests(&test_app.auth_info.bearer)
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].user.id, follower.user.id);
+
+    let res = test_app
+        .answer_follow_request(&follower.user.id, "approve", &test_app.auth_info.bearer)
+        .await;
+    assert_eq!(res.status().as_u16(), 204);
+
+    let res = test_app.get_user_posts(owner_id, &follower.bearer).await;
+    assert_eq!(res.status().as_u16(), 200);
+    let res = test_app
+        .get_follow_requests(&test_app.auth_info.bearer)
+        .await;
+    assert_eq!(res.json::<Vec<FollowRequest>>().await.unwrap().len(), 0);
+}
+
+#[tokio::test]
+async fn test_rejected_follow_request_stays_hidden() {
+    let test_app = spawn_app().await;
+    let owner_id = &test_app.auth_info.user.id;
+    make_private(&test_app, true).await;
+    let follower = TestAuthInfo::generate();
+    follower.store(&test_app.db_pool).await;
+    test_app.follow_user(owner_id, &follower.bearer).await;
+
+    let res = test_app
+        .answer_follow_request(&follower.user.id, "reject", &test_app.auth_info.bearer)
+        .await;
+    assert_eq!(res.status().as_u16(), 204);
+    let res = test_app
+        .answer_follow_request(&follower.user.id, "approve", &test_app.auth_info.bearer)
+        .await;
+    assert_eq!(res.status().as_u16(), 404);
+
+    let res = test_app.get_user_posts(owner_id, &follower.bearer).await;
+    assert_eq!(res.status().as_u16(), 403);
+}
+
+#[tokio::test]
+async fn test_private_account_hides_comments_and_likes_of_its_posts() {
+    let test_app = spawn_app().await;
+    make_private(&test_app, true).await;
+    let stranger = TestAuthInfo::generate();
+    stranger.store(&test_app.db_pool).await;
+    let post_id = test_app
+        .create_test_post(json!({}), &test_app.auth_info.bearer)
+        .await;
+    let comment = || CreateComment {
+        comment: "Lovely place".to_string(),
+    };
+
+    let res = test_app
+        .create_comment(&post_id, comment(), &stranger.bearer)
+        .await;
+    assert_eq!(res.status().as_u16(), 403);
+    let res = test_app.like_a_post(&post_id, &stranger.bearer).await;
+    assert_eq!(res.status().as_u16(), 403);
+    let res = test_app.get_comments_as(&post_id, &stranger.bearer).await;
+    assert_eq!(res.status().as_u16(), 403);
+    let res = test_app.get_likes_for_a_post(&post_id).await;
+    assert_eq!(res.status().as_u16(), 403);
+
+    // The owner still can
+    let res = test_app
+        .create_comment(&post_id, comment(), &test_app.auth_info.bearer)
+        .await;
+    assert_eq!(res.status().as_u16(), 201);
+    let res = test_app
+        .get_comments_as(&post_id, &test_app.auth_info.bearer)
+        .await;
+    assert_eq!(res.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn test_going_public_approves_pending_follow_requests() {
+    let test_app = spawn_app().await;
+    let owner_id = &test_app.auth_info.user.id;
+    make_private(&test_app, true).await;
+    let follower = TestAuthInfo::generate();
+    follower.store(&test_app.db_pool).await;
+    test_app.follow_user(owner_id, &follower.bearer).await;
+
+    make_private(&test_app, false).await;
+
+    let followers: Vec<PublicUser> = test_app.get_followers(owner_id).await.json().await.unwrap();
+    assert_eq!(followers.len(), 1);
+    assert_eq!(followers[0].id, follower.user.id);
+}
+
+#[tokio::test]
+async fn test_change_password_signs_out_other_sessions() {
+    let test_app = spawn_app().await;
+    let email = &test_app.auth_info.user.email;
+    let res = test_app.login(email, "Password123!").await;
+    let auth_info = res.json::<Value>().await.unwrap();
+    let bearer = auth_info["bearer"].as_str().unwrap();
+
+    let res = test_app
+        .change_password("Password123!", "NewPassword123!", bearer)
+        .await;
+    assert_eq!(res.status().as_u16(), 204);
+
+    // The session that made the change stays, the others are signed out
+    let res = test_app.get_user_feed(bearer).await;
+    assert_eq!(res.status().as_u16(), 200);
+    let res = test_app.get_user_feed(&test_app.auth_info.bearer).await;
+    assert_eq!(res.status().as_u16(), 401);
+
+    let res = test_app.login(email, "Password123!").await;
+    assert_eq!(res.status().as_u16(), 400);
+    let res = test_app.login(email, "NewPassword123!").await;
+    assert_eq!(res.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn test_change_password_fails() {
+    let test_app = spawn_app().await;
+    let bearer = &test_app.auth_info.bearer;
+
+    let res = test_app
+        .change_password("WrongPassword123!", "NewPassword123!", bearer)
+        .await;
+    assert_eq!(res.status().as_u16(), 400);
+    let body = res.json::<Value>().await.unwrap();
+    assert_eq!(body["error"], "Current password is incorrect");
+
+    let res = test_app
+        .change_password("Password123!", "password", bearer)
+        .await;
+    assert_eq!(res.status().as_u16(), 400);
+    let body = res.json::<Value>().await.unwrap();
+    assert!(body["error"].as_str().unwrap().contains("new_password"));
+
+    // Nothing changed and the session is still valid
+    let res = test_app.get_user_feed(bearer).await;
+    assert_eq!(res.status().as_u16(), 200);
+    let res = test_app
+        .login(&test_app.auth_info.user.email, "Password123!")
+        .await;
+    assert_eq!(res.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn test_change_username_redirects_old_profile_links() {
+    let test_app = spawn_app().await;
+    let old_username = test_app.auth_info.user.username.clone();
+
+    let res = test_app
+        .change_username("NewName", &test_app.auth_info.bearer)
+        .await;
+    assert_eq!(res.status().as_u16(), 200);
+    let user = res.json::<Value>().await.unwrap();
+    assert_eq!(user["username"], "newname");
+
+    let res = test_app.get_user_by_username("newname").await;
+    assert_eq!(res.status().as_u16(), 200);
+    let user = res.json::<Value>().await.unwrap();
+    assert_eq!(user["id"].as_str().unwrap(), test_app.auth_info.user.id);
+
+    let res = test_app.get_user_by_username(&old_username).await;
+    assert_eq!(res.status().as_u16(), 301);
+    assert_eq!(
+        res.headers().get("Location").unwrap(),
+        "/users/by-username/newname"
+    );
+
+    let res = test_app.get_user_by_username("nobody-by-that-name").await;
+    assert_eq!(res.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn test_old_username_is_reserved_for_its_owner() {
+    let test_app = spawn_app().await;
+    let other_user = TestAuthInfo::new("otheruser");
+    other_user.store(&test_app.db_pool).await;
+
+    let res = test_app
+        .change_username("firstrename", &other_user.bearer)
+        .await;
+    assert_eq!(res.status().as_u16(), 200);
+
+    // Nobody else can take the old username during the cooldown
+    let res = test_app
+        .change_username("otheruser", &test_app.auth_info.bearer)
+        .await;
+    assert_eq!(res.status().as_u16(), 400);
+    let res = test_app
+        .post_user(json!({
+            "username": "otheruser",
+            "password": "Password123!",
+            "email": "squatter@email.com",
+            "first_name": "Test",
+            "last_name": "User",
+            "description": "Test description"
+        }))
+        .await;
+    assert_eq!(res.status().as_u16(), 400);
+
+    // Nor a username somebody is using
+    let res = test_app
+        .change_username("firstrename", &test_app.auth_info.bearer)
+        .await;
+    assert_eq!(res.status().as_u16(), 400);
+
+    // But the previous owner can take it back, and keeps their sessions
+    let res = test_app
+        .change_username("otheruser", &other_user.bearer)
+        .await;
+    assert_eq!(res.status().as_u16(), 200);
+    let res = test_app.get_user_feed(&other_user.bearer).await;
+    assert_eq!(res.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn test_delete_account_requires_password() {
+    let test_app = spawn_app().await;
+    let bearer = &test_app.auth_info.bearer;
+
+    let res = test_app.delete_account("WrongPassword123!", bearer).await;
+    assert_eq!(res.status().as_u16(), 400);
+
+    let res = test_app.get_user_feed(bearer).await;
+    assert_eq!(res.status().as_u16(), 200);
+    let res = test_app.get_user(&test_app.auth_info.user.id).await;
+    assert_eq!(res.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn test_deleted_account_can_be_restored_during_grace_period() {
+    let test_app = spawn_app().await;
+    let user = &test_app.auth_info.user;
+
+    let res = test_app
+        .delete_account("Password123!", &test_app.auth_info.bearer)
+        .await;
+    assert_eq!(res.status().as_u16(), 202);
+
+    // The account is signed out and hidden
+    let res = test_app.get_user_feed(&test_app.auth_info.bearer).await;
+    assert_eq!(res.status().as_u16(), 401);
+    let res = test_app.get_user(&user.id).await;
+    assert_eq!(res.status().as_u16(), 404);
+    let res = test_app.get_all_users(None).await;
+    let users = res.json::<Vec<Value>>().await.unwrap();
+    assert!(users.iter().all(|u| u["id"].as_str().unwrap() != user.id));
+
+    // Signing in again cancels the deletion
+    let res = test_app.login(&user.email, "Password123!").await;
+    assert_eq!(res.status().as_u16(), 200);
+    let res = test_app.get_user(&user.id).await;
+    assert_eq!(res.status().as_u16(), 200);
+    let user_id = Uuid::from_str(&user.id).unwrap();
+    let stored = sqlx::query!("SELECT purge_after FROM users WHERE id = $1", user_id)
+        .fetch_one(&test_app.db_pool)
+        .await
+        .unwrap();
+    assert!(stored.purge_after.is_none());
+}
+
+#[tokio::test]
+async fn test_purge_removes_deleted_account() {
+    let test_app = spawn_app().await;
+    let user = &test_app.auth_info;
+    let user_id = Uuid::from_str(&user.user.id).unwrap();
+    let other_user = TestAuthInfo::generate();
+    other_user.store(&test_app.db_pool).await;
+
+    // The user's own post, with a comment from someone else
+    let own_post_id = test_app.create_test_post(json!({}), &user.bearer).await;
+    test_app
+        .create_comment(
+            &own_post_id,
+            CreateComment {
+                comment: "Nice".into(),
+            },
+            &other_user.bearer,
+        )
+        .await;
+
+    // Activity on someone else's post: a comment that got a reply, one that didn't, a like
+    let other_post_id = test_app
+        .create_test_post(json!({}), &other_user.bearer)
+        .await;
+    let res = test_app
+        .create_comment(
+            &other_post_id,
+            CreateComment {
+                comment: "Replied to".into(),
+            },
+            &user.bearer,
+        )
+        .await;
+    let replied_id = res.json::<Value>().await.unwrap()["comment_id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    test_app
+        .create_reply_comment(
+            &other_post_id,
+            &replied_id,
+            CreateComment {
+                comment: "Reply".into(),
+            },
+            &other_user.bearer,
+        )
+        .await;
+    test_app
+        .create_comment(
+            &other_post_id,
+            CreateComment {
+                comment: "Unanswered".into(),
+            },
+            &user.bearer,
+        )
+        .await;
+    test_app.like_a_post(&other_post_id, &user.bearer).await;
+    test_app
+        .follow_user(&other_user.user.id, &user.bearer)
+        .await;
+    test_app
+        .follow_user(&user.user.id, &other_user.bearer)
+        .await;
+
+    let res = test_app.delete_account("Password123!", &user.bearer).await;
+    assert_eq!(res.status().as_u16(), 202);
+
+    // Nothing happens before the grace period is over
+    let purged = controller::user::purge_deactivated_accounts(
+        test_app.blob_store.as_ref(),
+        &test_app.db_pool,
+    )
+    .await
+    .unwrap();
+    assert_eq!(purged, 0);
+
+    sqlx::query!(
+        "UPDATE users SET purge_after = NOW() - INTERVAL '1 second' WHERE id = $1",
+        user_id
+    )
+    .execute(&test_app.db_pool)
+    .await
+    .unwrap();
+    let purged = controller::user::purge_deactivated_accounts(
+        test_app.blob_store.as_ref(),
+        &test_app.db_pool,
+    )
+    .await
+    .unwrap();
+    assert_eq!(purged, 1);
+
+    let res = test_app.login(&user.user.email, "Password123!").await;
+    assert_eq!(res.status().as_u16(), 400);
+    let res = test_app.get_comments(&own_post_id).await;
+    assert_eq!(res.status().as_u16(), 404);
+
+    // Only the replied-to comment is left, without its author or text
+    let res = test_app.get_comments(&other_post_id).await;
+    let comments = res.json::<Vec<Comment>>().await.unwrap();
+    assert_eq!(comments.len(), 2);
+    let replied = comments.iter().find(|c| c.id == replied_id).unwrap();
+    assert!(replied.user.is_none());
+    assert_eq!(replied.comment, "[deleted]");
+
+    let res = test_app.get_likes_for_a_post(&other_post_id).await;
+    assert!(res.json::<Vec<Value>>().await.unwrap().is_empty());
+    let res = test_app.get_followers(&other_user.user.id).await;
+    assert!(res.json::<Vec<Value>>().await.unwrap().is_empty());
+    let res = test_app.get_following(&other_user.user.id).await;
+    assert!(res.json::<Vec<Value>>().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_purge_deletes_the_users_images() {
+    let test_app = spawn_app().await;
+    let user = &test_app.auth_info;
+    let res = test_app
+        .upload_profile_image("avatar", png_image(100, 100), &user.bearer)
+        .await;
+    let avatar = res.json::<AuthUser>().await.unwrap().avatar.unwrap();
+    let res = test_app
+        .upload_profile_image("cover", png_image(400, 400), &user.bearer)
+        .await;
+    let cover = res.json::<AuthUser>().await.unwrap().cover.unwrap();
+    let res = test_app
+        .upload_post_media(png_image(100, 100), &user.bearer)
+        .await;
+    let attached = res.json::<PostMedia>().await.unwrap();
+    let res = test_app
+        .upload_post_media(png_image(100, 100), &user.bearer)
+        .await;
+    let unattached = res.json::<PostMedia>().await.unwrap();
+    test_app
+        .create_test_post(json!({ "media": [{ "id": attached.id }] }), &user.bearer)
+        .await;
+
+    let res = test_app.delete_account("Password123!", &user.bearer).await;
+    assert_eq!(res.status().as_u16(), 202);
+    sqlx::query!(
+        "UPDATE users SET purge_after = NOW() - INTERVAL '1 second' WHERE id = $1",
+        Uuid::from_str(&user.user.id).unwrap()
+    )
+    .execute(&test_app.db_pool)
+    .await
+    .unwrap();
+    let purged = controller::user::purge_deactivated_accounts(
+        test_app.blob_store.as_ref(),
+        &test_app.db_pool,
+    )
+    .await
+    .unwrap();
+    assert_eq!(purged, 1);
+
+    for path in [
+        &avatar.small,
+        &avatar.medium,
+        &avatar.large,
+        &cover.small,
+        &cover.medium,
+        &cover.large,
+        &attached.url,
+        &attached.thumbnail_url,
+        &unattached.url,
+        &unattached.thumbnail_url,
+    ] {
+        assert_eq!(test_app.get_media(path).await.status().as_u16(), 404);
+    }
+}